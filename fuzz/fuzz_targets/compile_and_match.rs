@@ -0,0 +1,16 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate luther_cli;
+
+fuzz_target!(|data: &[u8]| {
+    luther_cli::fuzz::compile_and_match(data);
+});
@@ -0,0 +1,34 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate luther;
+#[macro_use]
+extern crate luther_derive;
+
+use luther::Lexer;
+
+#[derive(Lexer, Debug)]
+enum Token {
+    #[luther(regex = "[a-zA-Z_][a-zA-Z0-9_]*", keywords(If = "if", While = "while"))]
+    Ident,
+    If,
+    While,
+    #[luther(regex = "[0-9]+")] Number(u32),
+    #[luther(regex = "\"([^\"\\\\]|\\\\.)*\"")] Str,
+    #[luther(regex = "[-+*/=<>(){};]")] Punct,
+    #[luther(regex = "[ \t\r\n]+", skip)] WhiteSpace,
+    #[luther(error)] Garbage,
+}
+
+fuzz_target!(|data: &[u8]| {
+    let input = data.iter().map(|&b| Ok(b));
+    for _ in Token::byte_lexer(input) {}
+});
@@ -0,0 +1,1040 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, VecDeque};
+use std::mem;
+use std::result::Result as StdResult;
+
+use failure::Error;
+use redfa::{self, Regex};
+use redfa::dfa::Normalize;
+
+use error::{CompileError, LengthError, ParseError};
+use limits::Limits;
+use simplify::{simplify, Effort};
+use tokens::{TokenDef, DEFAULT_MODE};
+
+/// A dfa whose accepting states have the index of the token that they accept.
+pub type Dfa = redfa::Dfa<char, Option<usize>>;
+
+/// The meaning of the `^` and `$` anchors in a token regex.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnchorSemantics {
+    /// `^` matches at the start of the input or after a `'\n'` and `$` matches at the end of
+    /// the input or before a `'\n'` (as in flex).
+    Line,
+
+    /// `^` matches only at the start of the input and `$` only at the end of the input.
+    Input,
+}
+
+impl Default for AnchorSemantics {
+    fn default() -> AnchorSemantics {
+        AnchorSemantics::Line
+    }
+}
+
+/// A set of the `char`'s on which a state takes the same transition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateClass {
+    /// The state that the transition goes to.
+    pub to: usize,
+
+    /// The ranges of the listed `char`'s in the class, in order.
+    pub ranges: Vec<(char, char)>,
+
+    /// Whether the class also has the `char`'s that are not listed (that take the default
+    /// transition).
+    pub other: bool,
+}
+
+/// The multi-pattern dfa for a list of token definitions.
+///
+/// The dfa is built with the same rules as `luther-derive` uses for the variants of an `enum`.
+/// It has a start state for each lexer mode. The lexer modes are named by the tokens, with
+/// the default mode first.
+///
+/// A token regex may start with a `^` anchor (the token only matches at the start of a line)
+/// or end with a `$` anchor (the token only matches at the end of a line). Each mode has a
+/// second start state, for the start of a line, that also matches the `^` tokens. A `$` token
+/// (or a token with `word_boundary`) is checked by looking at the `char` after the match,
+/// falling back to the best token in that state that has no such condition. The anchors and
+/// word boundaries are only supported by the matching in this module (the exported formats
+/// reject them, see `binary::check`).
+///
+/// The numbering of the states depends only on the token definitions, so that generated code
+/// and golden files are reproducible. `redfa` numbers the states in the order in which it
+/// discovers them (following the derivatives of each state in order of their `char`) and
+/// nothing in the construction depends on the iteration order of a hash map.
+pub struct Automaton {
+    pub tokens: Vec<TokenDef>,
+
+    /// The normalized regexes for the tokens in the default mode (with `Regex::Null` for the
+    /// tokens in other modes).
+    pub regexs: Vec<Regex<char>>,
+    pub dfa: Dfa,
+    pub modes: Vec<String>,
+    pub start_states: Vec<usize>,
+
+    /// The start state of each mode at the start of a line.
+    pub line_start_states: Vec<usize>,
+
+    /// Whether each token ends with a `$` anchor.
+    pub end_anchored: Vec<bool>,
+
+    /// The best token accepted by each state that has no condition on the `char` after it
+    /// (neither a `$` anchor nor `word_boundary`).
+    pub unconditional_accepts: Vec<Option<usize>>,
+
+    /// The tokens that contributed (a derivative that is not `Regex::Null`) to each state.
+    pub contributors: Vec<Vec<usize>>,
+
+    /// Whether the regex for each token is a simple string (which wins a tie in priority).
+    pub simple_strings: Vec<bool>,
+
+    /// The meaning of the anchors for the matching methods.
+    pub anchors: AnchorSemantics,
+
+    /// The start state of the default mode.
+    pub start_state: usize,
+    pub error_state: usize,
+}
+
+impl Automaton {
+    /// Builds the automaton for `tokens`.
+    ///
+    /// It is an error if the regex for any token cannot be parsed or matches the empty string,
+    /// or if an accepting state matches more than one token of the same priority.
+    pub fn new(tokens: Vec<TokenDef>) -> StdResult<Automaton, CompileError> {
+        Automaton::with_limits(tokens, &Limits::unlimited())
+    }
+
+    /// Builds the automaton for `tokens`, which may come from an untrusted source.
+    ///
+    /// This is like `new` except that it is also an error if the regex for any token is larger
+    /// than `limits` allow.
+    pub fn with_limits(
+        tokens: Vec<TokenDef>,
+        limits: &Limits,
+    ) -> StdResult<Automaton, CompileError> {
+        Automaton::build(tokens, limits, None)
+    }
+
+    /// Builds the automaton for `tokens` after simplifying their regexes with `effort`.
+    ///
+    /// This is like `with_limits` but it may be faster to build for a large generated set of
+    /// token definitions (see `simplify::simplify`). The dfa matches the same strings, though
+    /// its `regexs` (and so the numbering of its states) may differ.
+    pub fn with_simplify(
+        tokens: Vec<TokenDef>,
+        limits: &Limits,
+        effort: Effort,
+    ) -> StdResult<Automaton, CompileError> {
+        Automaton::build(tokens, limits, Some(effort))
+    }
+
+    fn build(
+        tokens: Vec<TokenDef>,
+        limits: &Limits,
+        effort: Option<Effort>,
+    ) -> StdResult<Automaton, CompileError> {
+        let mut regexs = Vec::new();
+        for token in tokens.iter() {
+            let limit_exceeded = |e| CompileError::LimitExceeded(token.name.clone(), e);
+            limits.check_depth(&token.regex).map_err(limit_exceeded)?;
+            let regex = parse(token)?;
+            limits.check_nodes(&regex).map_err(limit_exceeded)?;
+            regexs.push(regex);
+        }
+        let mut regexs = regexs.normalize();
+        if let Some(effort) = effort {
+            regexs = regexs.iter().map(|re| simplify(re, effort)).collect();
+        }
+
+        let simple_strings: Vec<_> = regexs.iter().map(is_simple_string).collect();
+        let start_anchored: Vec<_> = tokens
+            .iter()
+            .map(|token| split_anchors(&token.regex).0)
+            .collect();
+        let end_anchored: Vec<_> = tokens
+            .iter()
+            .map(|token| split_anchors(&token.regex).2)
+            .collect();
+
+        if let Some(token) = regexs
+            .iter()
+            .position(|re| re.nullable())
+            .map(|i| &tokens[i])
+        {
+            return Err(CompileError::NullableRegex(
+                token.name.clone(),
+                token.regex.clone(),
+            ));
+        }
+
+        let modes = make_modes(&tokens);
+        let mode_regexs = |line_start: bool| -> Vec<Vec<_>> {
+            modes
+                .iter()
+                .map(|mode| {
+                    regexs
+                        .iter()
+                        .zip(tokens.iter())
+                        .zip(start_anchored.iter())
+                        .map(|((re, token), &anchored)| {
+                            if token.mode_name() == mode && (line_start || !anchored) {
+                                re.clone()
+                            } else {
+                                Regex::Null
+                            }
+                        })
+                        .collect()
+                })
+                .collect()
+        };
+        let line_regexs = mode_regexs(true);
+        let mode_regexs = mode_regexs(false);
+
+        let error = vec![Regex::Null; regexs.len()];
+        if line_regexs[0] == error {
+            return Err(CompileError::NoDefaultModeTokens);
+        }
+
+        let mut start = mode_regexs.clone();
+        start.extend(line_regexs.iter().cloned());
+        start.push(error.clone());
+        let (dfa, map) = redfa::Dfa::from_derivatives(start);
+        let start_states: Vec<_> = mode_regexs.iter().map(|res| map[res] as usize).collect();
+        let line_start_states = line_regexs.iter().map(|res| map[res] as usize).collect();
+        let start_state = start_states[0];
+        let error_state = map[&error] as usize;
+        let regexs = mode_regexs.into_iter().next().unwrap();
+
+        let unconditional = |res: &Vec<Regex<char>>| -> Vec<Regex<char>> {
+            res.iter()
+                .enumerate()
+                .map(|(i, re)| {
+                    if end_anchored[i] || tokens[i].word_boundary {
+                        Regex::Null
+                    } else {
+                        re.clone()
+                    }
+                })
+                .collect()
+        };
+        let mut unconditional_accepts = Vec::new();
+        for state in dfa.states.iter() {
+            let accepts = accepting_token(&state.value, &tokens, &simple_strings).and_then(|_| {
+                accepting_token(&unconditional(&state.value), &tokens, &simple_strings)
+            });
+            match accepts {
+                Ok(token) => unconditional_accepts.push(token),
+                Err((first, second)) => {
+                    return Err(CompileError::AmbiguousTokens(
+                        tokens[first].name.clone(),
+                        tokens[second].name.clone(),
+                    ))
+                }
+            }
+        }
+
+        let contributors = dfa
+            .states
+            .iter()
+            .map(|state| {
+                state
+                    .value
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, re)| *re != Regex::Null)
+                    .map(|(token, _)| token)
+                    .collect()
+            })
+            .collect();
+        let dfa = dfa.map(|res| accepting_token(&res, &tokens, &simple_strings).unwrap_or(None));
+
+        Ok(Automaton {
+            tokens,
+            regexs,
+            dfa,
+            modes,
+            start_states,
+            line_start_states,
+            end_anchored,
+            unconditional_accepts,
+            contributors,
+            simple_strings,
+            anchors: AnchorSemantics::default(),
+            start_state,
+            error_state,
+        })
+    }
+
+    /// The number of transitions that do not go to the error state.
+    pub fn transition_count(&self) -> usize {
+        self.dfa
+            .states
+            .iter()
+            .map(|state| {
+                let by_char = state
+                    .by_char
+                    .values()
+                    .filter(|&&to| to as usize != self.error_state)
+                    .count();
+                by_char + if state.default as usize == self.error_state { 0 } else { 1 }
+            })
+            .sum()
+    }
+
+    /// The number of accepting states.
+    pub fn accepting_count(&self) -> usize {
+        self.dfa
+            .states
+            .iter()
+            .filter(|state| state.value.is_some())
+            .count()
+    }
+
+    /// The token and length (in bytes) of the longest prefix of `input` that is accepted.
+    ///
+    /// This is the match that a lexer would produce at the start of `input` (which is the
+    /// start of a line for the `^` anchor).
+    pub fn longest_match(&self, input: &str) -> Option<(usize, usize)> {
+        self.longest_match_at(input, 0)
+    }
+
+    /// The token and length (in bytes) of the longest match at `offset` in `input` that is
+    /// within the `min_len` and `max_len` of its token.
+    ///
+    /// This is `longest_match_at` for a lexer that enforces the length limits of its tokens.
+    /// The limits are checked at each accepting state as the input is scanned, so the match is
+    /// the longest one that is within the limits of its token (such as the first 255 `char`'s
+    /// of a longer identifier). If every match at `offset` breaks the limits of its token, the
+    /// longest of them is an error with the span of the match.
+    pub fn scan_at(
+        &self,
+        input: &str,
+        offset: usize,
+    ) -> StdResult<Option<(usize, usize)>, LengthError> {
+        let mut state = self.start_at(input, offset);
+        let rest = &input[offset..];
+        let mut longest = None;
+        let mut broken = None;
+
+        for (chars, (i, c)) in rest.char_indices().enumerate() {
+            state = self.transition(state, c);
+            if state == self.error_state {
+                break;
+            }
+            let end = i + c.len_utf8();
+            let token = match self.accept_before(state, &rest[end..]) {
+                Some(token) => token,
+                None => continue,
+            };
+
+            let def = &self.tokens[token];
+            let len = chars + 1;
+            if def.min_len.map_or(false, |min_len| len < min_len)
+                || def.max_len.map_or(false, |max_len| len > max_len)
+            {
+                broken = Some((token, end, len));
+            } else {
+                longest = Some((token, end));
+            }
+        }
+
+        match (longest, broken) {
+            (Some(found), _) => Ok(Some(found)),
+            (None, Some((token, end, len))) => {
+                Err(self.length_error(token, offset, offset + end, len))
+            }
+            (None, None) => Ok(None),
+        }
+    }
+
+    // The error for a match of token from start to end (in bytes) that is len chars long and
+    // breaks the length limits of token.
+    fn length_error(&self, token: usize, start: usize, end: usize, len: usize) -> LengthError {
+        let def = &self.tokens[token];
+        match (def.min_len, def.max_len) {
+            (Some(min_len), _) if len < min_len => LengthError::TooShort {
+                token: def.name.clone(),
+                start,
+                end,
+                len,
+                min_len,
+            },
+            (_, Some(max_len)) => LengthError::TooLong {
+                token: def.name.clone(),
+                start,
+                end,
+                len,
+                max_len,
+            },
+            _ => unreachable!("the match is within the length limits of its token"),
+        }
+    }
+
+    /// The token and length (in bytes) of the longest prefix of `&input[offset..]` that is
+    /// accepted.
+    ///
+    /// This is the match that a lexer would produce at `offset` in `input`, with the anchors
+    /// checked against the `char`'s before and after the match.
+    pub fn longest_match_at(&self, input: &str, offset: usize) -> Option<(usize, usize)> {
+        let mut state = self.start_at(input, offset);
+        let rest = &input[offset..];
+        let mut longest = None;
+
+        for (i, c) in rest.char_indices() {
+            state = self.transition(state, c);
+            if state == self.error_state {
+                break;
+            }
+            let end = i + c.len_utf8();
+            if let Some(token) = self.accept_before(state, &rest[end..]) {
+                longest = Some((token, end));
+            }
+        }
+
+        longest
+    }
+
+    // The start state (of the default mode) for a match at offset in input.
+    fn start_at(&self, input: &str, offset: usize) -> usize {
+        let line_start = offset == 0
+            || (self.anchors == AnchorSemantics::Line && input[..offset].ends_with('\n'));
+        if line_start {
+            self.line_start_states[0]
+        } else {
+            self.start_state
+        }
+    }
+
+    // The token accepted by state for a match that is followed by rest.
+    fn accept_before(&self, state: usize, rest: &str) -> Option<usize> {
+        match self.dfa.states[state].value {
+            Some(token) if !self.can_end(token, rest) => self.unconditional_accepts[state],
+            token => token,
+        }
+    }
+
+    // Whether token can end before rest (checking its $ anchor and word boundary).
+    fn can_end(&self, token: usize, rest: &str) -> bool {
+        let line_end =
+            rest.is_empty() || (self.anchors == AnchorSemantics::Line && rest.starts_with('\n'));
+        let word_end = !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_');
+
+        (line_end || !self.end_anchored[token]) && (word_end || !self.tokens[token].word_boundary)
+    }
+
+    /// The state reached from `state` on `c`.
+    pub fn transition(&self, state: usize, c: char) -> usize {
+        let state = &self.dfa.states[state];
+        state
+            .by_char
+            .get(&c)
+            .map_or(state.default as usize, |&to| to as usize)
+    }
+
+    /// The partition of the alphabet that `state` distinguishes: a class for each state that it
+    /// has a transition to (including the error state), in order of the target state.
+    pub fn state_classes(&self, state: usize) -> Vec<StateClass> {
+        let dfa_state = &self.dfa.states[state];
+        let mut classes: BTreeMap<usize, StateClass> = BTreeMap::new();
+
+        for (&c, &to) in dfa_state.by_char.iter() {
+            let class = classes.entry(to as usize).or_insert_with(|| StateClass {
+                to: to as usize,
+                ranges: Vec::new(),
+                other: false,
+            });
+            match class.ranges.last_mut() {
+                Some(&mut (_, ref mut last)) if *last as u32 + 1 == c as u32 => *last = c,
+                _ => class.ranges.push((c, c)),
+            }
+        }
+        let default = dfa_state.default as usize;
+        classes
+            .entry(default)
+            .or_insert_with(|| StateClass {
+                to: default,
+                ranges: Vec::new(),
+                other: false,
+            })
+            .other = true;
+
+        classes.into_iter().map(|(_, class)| class).collect()
+    }
+
+    /// The states that are reachable from `state` (including `state` itself).
+    pub fn accessible_from(&self, state: usize) -> Vec<bool> {
+        let mut reached = vec![false; self.dfa.states.len()];
+        let mut stack = vec![state];
+        reached[state] = true;
+
+        while let Some(state) = stack.pop() {
+            let dfa_state = &self.dfa.states[state];
+            for &to in dfa_state.by_char.values().chain(Some(&dfa_state.default)) {
+                if !reached[to as usize] {
+                    reached[to as usize] = true;
+                    stack.push(to as usize);
+                }
+            }
+        }
+
+        reached
+    }
+
+    /// The states from which `state` is reachable (including `state` itself).
+    pub fn reaching(&self, state: usize) -> Vec<bool> {
+        let mut predecessors = vec![Vec::new(); self.dfa.states.len()];
+        for (from, dfa_state) in self.dfa.states.iter().enumerate() {
+            for &to in dfa_state.by_char.values().chain(Some(&dfa_state.default)) {
+                predecessors[to as usize].push(from);
+            }
+        }
+
+        let mut reached = vec![false; self.dfa.states.len()];
+        let mut stack = vec![state];
+        reached[state] = true;
+        while let Some(state) = stack.pop() {
+            for &from in predecessors[state].iter() {
+                if !reached[from] {
+                    reached[from] = true;
+                    stack.push(from);
+                }
+            }
+        }
+
+        reached
+    }
+
+    /// Whether some extension of `prefix` is accepted (in the default mode) as `token`.
+    pub fn can_complete(&self, prefix: &str, token: usize) -> bool {
+        let state = prefix
+            .chars()
+            .fold(self.start_state, |state, c| self.transition(state, c));
+
+        self.accessible_from(state)
+            .iter()
+            .zip(self.dfa.states.iter())
+            .any(|(&reached, dfa_state)| reached && dfa_state.value == Some(token))
+    }
+
+    /// The states (other than the error state) that are reachable from the start states, in
+    /// breadth first order.
+    ///
+    /// The search starts from the start states in the order of their modes (followed by the
+    /// start states at the start of a line) and follows the
+    /// transitions of each state in order of their `char` (with the default transition last).
+    pub fn bfs_order(&self) -> Vec<usize> {
+        let mut order = Vec::new();
+        let mut seen = vec![false; self.dfa.states.len()];
+        let mut queue: VecDeque<usize> = VecDeque::new();
+
+        seen[self.error_state] = true;
+        let starts = self.start_states.iter().chain(self.line_start_states.iter());
+        for &start in starts {
+            if !seen[start] {
+                seen[start] = true;
+                queue.push_back(start);
+            }
+        }
+
+        while let Some(state) = queue.pop_front() {
+            order.push(state);
+
+            let dfa_state = &self.dfa.states[state];
+            let targets = dfa_state
+                .by_char
+                .values()
+                .chain(Some(&dfa_state.default))
+                .map(|&to| to as usize);
+            for to in targets {
+                if !seen[to] {
+                    seen[to] = true;
+                    queue.push_back(to);
+                }
+            }
+        }
+
+        order
+    }
+
+    /// Renumbers the states so that the states that are likely to be visited most often have
+    /// the lowest numbers.
+    ///
+    /// The likelihood of a visit is estimated by the distance from a start state, so the
+    /// states are numbered in breadth first order (see `bfs_order`) with the error state and
+    /// any unreachable states last. This packs the rows for the states near the start states
+    /// together in a transition table.
+    pub fn renumber_hot_first(&mut self) {
+        let order = self.bfs_order();
+        self.renumber(&order);
+    }
+
+    /// Renumbers the states so that the states in `order` have the lowest numbers (in the
+    /// order given), followed by the error state and then any other states.
+    pub fn renumber(&mut self, order: &[usize]) {
+        let mut order = order.to_vec();
+        let mut numbers = vec![None; self.dfa.states.len()];
+        for (number, &state) in order.iter().enumerate() {
+            numbers[state] = Some(number);
+        }
+        if numbers[self.error_state].is_none() {
+            numbers[self.error_state] = Some(order.len());
+            order.push(self.error_state);
+        }
+        for state in 0..numbers.len() {
+            if numbers[state].is_none() {
+                numbers[state] = Some(order.len());
+                order.push(state);
+            }
+        }
+        let number = |state: u32| numbers[state as usize].unwrap() as u32;
+
+        let mut old: Vec<_> = mem::replace(&mut self.dfa.states, Vec::new())
+            .into_iter()
+            .map(Some)
+            .collect();
+        self.unconditional_accepts = order
+            .iter()
+            .map(|&state| self.unconditional_accepts[state])
+            .collect();
+        self.contributors = order
+            .iter()
+            .map(|&state| mem::replace(&mut self.contributors[state], Vec::new()))
+            .collect();
+        for &state in order.iter() {
+            let mut dfa_state = old[state].take().unwrap();
+            for to in dfa_state.by_char.values_mut() {
+                *to = number(*to);
+            }
+            dfa_state.default = number(dfa_state.default);
+            self.dfa.states.push(dfa_state);
+        }
+
+        for start in self.start_states.iter_mut() {
+            *start = number(*start as u32) as usize;
+        }
+        for start in self.line_start_states.iter_mut() {
+            *start = number(*start as u32) as usize;
+        }
+        self.start_state = self.start_states[0];
+        self.error_state = number(self.error_state as u32) as usize;
+    }
+}
+
+/// Parses the regex for `token` (without its anchors) with the syntax options of the token.
+pub fn parse(token: &TokenDef) -> StdResult<Regex<char>, ParseError> {
+    let error = |reason: String| ParseError {
+        token: token.name.clone(),
+        regex: token.regex.clone(),
+        reason,
+    };
+
+    token
+        .syntax
+        .translate(split_anchors(&token.regex).1)
+        .map_err(&error)?
+        .parse::<Regex<char>>()
+        .map_err(|e| error(e.to_string()))
+}
+
+/// Parses `regex` (with the default syntax options and no anchors) into its normal form.
+pub fn parse_regex(regex: &str) -> StdResult<Regex<char>, Error> {
+    let parsed = regex
+        .parse::<Regex<char>>()
+        .map_err(|e| format_err!("invalid regex \"{}\": {}", regex, e))?;
+
+    Ok(vec![parsed].normalize().remove(0))
+}
+
+/// Splits `regex` into whether it starts with a `^` anchor, the regex without its anchors, and
+/// whether it ends with a (not escaped) `$` anchor.
+pub fn split_anchors(regex: &str) -> (bool, &str, bool) {
+    let start = regex.starts_with('^');
+    let regex = if start { &regex[1..] } else { regex };
+
+    let end = regex.ends_with('$')
+        && regex[..regex.len() - 1]
+            .chars()
+            .rev()
+            .take_while(|&c| c == '\\')
+            .count() % 2 == 0;
+    let regex = if end { &regex[..regex.len() - 1] } else { regex };
+
+    (start, regex, end)
+}
+
+// The token accepted by the derivative state res, or the pair of ambiguous tokens.
+fn accepting_token(
+    res: &Vec<Regex<char>>,
+    tokens: &[TokenDef],
+    simple: &[bool],
+) -> StdResult<Option<usize>, (usize, usize)> {
+    let nullable: Vec<_> = res.iter()
+        .enumerate()
+        .filter(|&(_, re)| re.nullable())
+        .map(|(i, _)| i)
+        .collect();
+    best_token(&nullable, tokens, simple)
+}
+
+/// The best of the `candidates` tokens (by priority group and then preferring simple strings),
+/// or the first two tokens that tie for the best.
+///
+/// `simple` is whether the regex of each token is a simple string (see
+/// `Automaton::simple_strings`).
+pub fn best_token(
+    candidates: &[usize],
+    tokens: &[TokenDef],
+    simple: &[bool],
+) -> StdResult<Option<usize>, (usize, usize)> {
+    let mut best: Option<usize> = None;
+    let mut tied = None;
+
+    for &i in candidates {
+        match best {
+            None => best = Some(i),
+            Some(b) => match compare(i, b, tokens, simple) {
+                Ordering::Less => {
+                    best = Some(i);
+                    tied = None;
+                }
+                Ordering::Equal => tied = Some((b, i)),
+                Ordering::Greater => {}
+            },
+        }
+    }
+
+    match tied {
+        Some(pair) => Err(pair),
+        None => Ok(best),
+    }
+}
+
+// The lexer modes named by tokens, starting with the default mode.
+fn make_modes(tokens: &[TokenDef]) -> Vec<String> {
+    let mut modes = vec![DEFAULT_MODE.to_string()];
+    for token in tokens {
+        if !modes.iter().any(|mode| mode == token.mode_name()) {
+            modes.push(token.mode_name().to_string());
+        }
+    }
+    modes
+}
+
+fn compare(lhs: usize, rhs: usize, tokens: &[TokenDef], simple: &[bool]) -> Ordering {
+    tokens[lhs]
+        .priority_group
+        .cmp(&tokens[rhs].priority_group)
+        .then_with(|| simple[rhs].cmp(&simple[lhs]))
+}
+
+fn is_simple_string(regex: &Regex<char>) -> bool {
+    match regex {
+        &Regex::Cat(ref regexs) => regexs.iter().all(|re| match re {
+            &Regex::Alt(ref ts, ref res) => ts.len() == 1 && res.len() == 0,
+            _ => false,
+        }),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use error::LimitError;
+    use failure::Fail;
+
+    fn run(automaton: &Automaton, input: &str) -> Option<usize> {
+        let state = input
+            .chars()
+            .fold(automaton.start_state, |state, c| automaton.transition(state, c));
+        automaton.dfa.states[state].value
+    }
+
+    #[test]
+    fn automaton_prefers_simple_strings() {
+        let tokens = vec![TokenDef::new("Ident", "[a-z]+"), TokenDef::new("If", "if")];
+
+        let sut = Automaton::new(tokens).expect("unexpected error building the automaton");
+
+        assert_eq!(run(&sut, "if"), Some(1));
+        assert_eq!(run(&sut, "iff"), Some(0));
+    }
+
+    #[test]
+    fn automaton_state_numbering_is_reproducible() {
+        let tokens = || {
+            let mut text = TokenDef::new("Text", "[a-z]+");
+            text.mode = Some("STRING".to_string());
+            vec![
+                TokenDef::new("Ident", "[a-z][a-z0-9_]*"),
+                TokenDef::new("If", "if"),
+                TokenDef::new("Comment", "#[^\n]*"),
+                TokenDef::new("Number", "[0-9]+(\\.[0-9]+)?"),
+                text,
+            ]
+        };
+
+        let first = Automaton::new(tokens()).expect("unexpected error building the automaton");
+        let second = Automaton::new(tokens()).expect("unexpected error building the automaton");
+
+        assert_eq!(first.start_states, second.start_states);
+        assert_eq!(first.line_start_states, second.line_start_states);
+        assert_eq!(first.error_state, second.error_state);
+        assert_eq!(first.contributors, second.contributors);
+        assert_eq!(
+            ::binary::to_bytes(&first).unwrap(),
+            ::binary::to_bytes(&second).unwrap()
+        );
+    }
+
+    #[test]
+    fn automaton_with_simplify_lexes_the_same() {
+        let tokens = || {
+            let mut ident = TokenDef::new("Ident", "[a-z_][a-z0-9_]*|[a-z]+");
+            ident.priority_group = 2;
+            vec![
+                TokenDef::new("Keyword", "if|else|elif|end|while|when"),
+                ident,
+                TokenDef::new("Number", "0x[0-9a-f]+|0b[01]+|[0-9][0-9]*[0-9]*"),
+            ]
+        };
+
+        let plain = Automaton::new(tokens()).expect("unexpected error building the automaton");
+        let simplified = Automaton::with_simplify(tokens(), &Limits::unlimited(), Effort::High)
+            .expect("unexpected error building the automaton");
+
+        assert_eq!(::equiv::distinguishing_input(&plain, &simplified), None);
+    }
+
+    #[test]
+    fn automaton_uses_syntax_options_of_tokens() {
+        let mut line = TokenDef::new("Line", "#.*");
+        line.syntax.dot_matches_newline = false;
+        let tokens = vec![line, TokenDef::new("Any", "%.*")];
+
+        let sut = Automaton::new(tokens).expect("unexpected error building the automaton");
+
+        assert_eq!(sut.longest_match("# a\nb"), Some((0, 3)));
+        assert_eq!(sut.longest_match("% a\nb"), Some((1, 5)));
+    }
+
+    #[test]
+    fn scan_at_checks_length_limits() {
+        let mut ident = TokenDef::new("Ident", "[a-z]+");
+        ident.max_len = Some(3);
+        let mut number = TokenDef::new("Number", "[0-9]+");
+        number.min_len = Some(2);
+        let mut char_lit = TokenDef::new("Char", "'[^']+'");
+        char_lit.max_len = Some(3);
+        let sut = Automaton::new(vec![ident, number, char_lit]).unwrap();
+
+        assert_eq!(sut.scan_at("x abc", 2), Ok(Some((0, 3))));
+        assert_eq!(sut.scan_at("x abcd", 2), Ok(Some((0, 3))));
+        assert_eq!(sut.scan_at("789", 0), Ok(Some((1, 3))));
+        assert_eq!(
+            sut.scan_at("x 'ab'", 2),
+            Err(LengthError::TooLong {
+                token: "Char".to_string(),
+                start: 2,
+                end: 6,
+                len: 4,
+                max_len: 3,
+            })
+        );
+        assert_eq!(
+            sut.scan_at("7", 0),
+            Err(LengthError::TooShort {
+                token: "Number".to_string(),
+                start: 0,
+                end: 1,
+                len: 1,
+                min_len: 2,
+            })
+        );
+        assert_eq!(sut.scan_at("-", 0), Ok(None));
+    }
+
+    #[test]
+    fn automaton_has_start_state_for_each_mode() {
+        let mut text = TokenDef::new("Text", "[a-z]+");
+        text.mode = Some("STRING".to_string());
+        let tokens = vec![TokenDef::new("Quote", "\""), text];
+
+        let sut = Automaton::new(tokens).expect("unexpected error building the automaton");
+
+        assert_eq!(sut.modes, vec!["INITIAL".to_string(), "STRING".to_string()]);
+        assert_eq!(sut.longest_match("abc"), None);
+        assert_ne!(sut.start_states[1], sut.start_state);
+    }
+
+    #[test]
+    fn split_anchors_finds_unescaped_anchors() {
+        assert_eq!(split_anchors("^#.*$"), (true, "#.*", true));
+        assert_eq!(split_anchors("a\\$"), (false, "a\\$", false));
+        assert_eq!(split_anchors("a\\\\$"), (false, "a\\\\", true));
+        assert_eq!(split_anchors("^"), (true, "", false));
+    }
+
+    #[test]
+    fn automaton_checks_anchors() {
+        let mut hash = TokenDef::new("Hash", "#");
+        hash.priority_group = 2;
+        let tokens = vec![
+            TokenDef::new("Comment", "^#[a-z]*"),
+            hash,
+            TokenDef::new("Label", "[a-z]+:$"),
+            TokenDef::new("Ident", "[a-z]+"),
+            TokenDef::new("Colon", ":"),
+        ];
+
+        let mut sut = Automaton::new(tokens).expect("unexpected error building the automaton");
+
+        assert_eq!(sut.longest_match("#ab"), Some((0, 3)));
+        assert_eq!(sut.longest_match_at("x #ab", 2), Some((1, 1)));
+        assert_eq!(sut.longest_match_at("x\n#ab", 2), Some((0, 3)));
+        assert_eq!(sut.longest_match("ab:\nc"), Some((2, 3)));
+        assert_eq!(sut.longest_match("ab: c"), Some((3, 2)));
+
+        sut.anchors = AnchorSemantics::Input;
+        assert_eq!(sut.longest_match_at("x\n#ab", 2), Some((1, 1)));
+        assert_eq!(sut.longest_match("ab:\nc"), Some((3, 2)));
+        assert_eq!(sut.longest_match("ab:"), Some((2, 3)));
+    }
+
+    #[test]
+    fn automaton_checks_word_boundaries() {
+        let mut keyword = TokenDef::new("If", "if");
+        keyword.word_boundary = true;
+        let tokens = vec![keyword, TokenDef::new("I", "i"), TokenDef::new("F", "f")];
+
+        let sut = Automaton::new(tokens).expect("unexpected error building the automaton");
+
+        assert_eq!(sut.longest_match("if x"), Some((0, 2)));
+        assert_eq!(sut.longest_match("if"), Some((0, 2)));
+        assert_eq!(sut.longest_match("if("), Some((0, 2)));
+        assert_eq!(sut.longest_match("ifx"), Some((1, 1)));
+        assert_eq!(sut.longest_match("if_"), Some((1, 1)));
+    }
+
+    #[test]
+    fn state_classes_partition_the_alphabet() {
+        let tokens = vec![
+            TokenDef::new("Ident", "[a-z][a-z0-9]*"),
+            TokenDef::new("Other", "[^a-z]"),
+        ];
+
+        let sut = Automaton::new(tokens).expect("unexpected error building the automaton");
+        let classes = sut.state_classes(sut.start_state);
+
+        assert_eq!(classes.len(), 2);
+        assert_eq!(classes.iter().filter(|class| class.other).count(), 1);
+        let ident = classes
+            .iter()
+            .find(|class| class.ranges == vec![('a', 'z')])
+            .expect("expected a class for the first char of Ident");
+        assert!(!ident.other);
+        assert_eq!(sut.dfa.states[ident.to].value, Some(0));
+    }
+
+    #[test]
+    fn reachability_queries_follow_transitions() {
+        let tokens = vec![TokenDef::new("Ident", "[a-z]+"), TokenDef::new("Arrow", "->")];
+
+        let sut = Automaton::new(tokens).expect("unexpected error building the automaton");
+        let dash = sut.transition(sut.start_state, '-');
+
+        let accessible = sut.accessible_from(dash);
+        assert!(accessible[dash]);
+        assert!(!accessible[sut.start_state]);
+        assert!(accessible[sut.error_state]);
+        assert!(sut.reaching(dash)[sut.start_state]);
+        assert!(!sut.reaching(sut.start_state)[dash]);
+
+        assert!(sut.can_complete("-", 1));
+        assert!(!sut.can_complete("-", 0));
+        assert!(sut.can_complete("", 0));
+    }
+
+    #[test]
+    fn renumbered_automaton_matches_the_same() {
+        let tokens = vec![
+            TokenDef::new("Ident", "[a-z][a-z0-9]*"),
+            TokenDef::new("If", "if"),
+            TokenDef::new("Number", "[0-9]+"),
+        ];
+        let original = Automaton::new(tokens).expect("unexpected error building the automaton");
+        let mut sut = Automaton::new(original.tokens.clone()).unwrap();
+
+        sut.renumber_hot_first();
+
+        assert_eq!(sut.start_state, 0);
+        assert_eq!(sut.error_state, sut.dfa.states.len() - 1);
+        for input in &["if", "iffy", "x1", "42", "-"] {
+            assert_eq!(run(&sut, input), run(&original, input));
+        }
+    }
+
+    #[test]
+    fn automaton_rejects_nullable_regex() {
+        let tokens = vec![TokenDef::new("Digits", "[0-9]*")];
+
+        let result = Automaton::new(tokens);
+
+        assert_eq!(
+            result.err(),
+            Some(CompileError::NullableRegex("Digits".into(), "[0-9]*".into()))
+        );
+    }
+
+    #[test]
+    fn automaton_rejects_ambiguous_tokens() {
+        let tokens = vec![TokenDef::new("Lower", "[a-z]+"), TokenDef::new("Word", "[a-z]+")];
+
+        let result = Automaton::new(tokens);
+
+        assert_eq!(
+            result.err(),
+            Some(CompileError::AmbiguousTokens("Lower".into(), "Word".into()))
+        );
+    }
+
+    #[test]
+    fn automaton_rejects_regex_over_limits() {
+        let tokens = vec![TokenDef::new("Nested", "((((a))))")];
+        let limits = Limits {
+            max_depth: 3,
+            ..Limits::default()
+        };
+
+        let result = Automaton::with_limits(tokens, &limits);
+
+        assert_eq!(
+            result.err(),
+            Some(CompileError::LimitExceeded(
+                "Nested".into(),
+                LimitError::Depth(4, 3)
+            ))
+        );
+    }
+
+    #[test]
+    fn automaton_reports_parse_error_as_cause() {
+        let tokens = vec![TokenDef::new("Open", "(a")];
+
+        let error = Automaton::new(tokens).err().expect("expected an error");
+
+        let cause = error.cause().and_then(|cause| cause.downcast_ref::<ParseError>());
+        assert_eq!(cause.map(|cause| cause.token.as_str()), Some("Open"));
+    }
+}
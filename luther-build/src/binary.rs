@@ -0,0 +1,242 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! The binary encoding of a compiled automaton.
+//!
+//! The encoding is the one read by `luther::table::TableDfa::from_bytes`.
+//!
+//! The encoding has only the states and transitions of the dfa, so `to_bytes` (and the other
+//! exported formats, through `check`) rejects a token that needs more than the dfa to be
+//! matched.
+
+use std::collections::HashMap;
+use std::result::Result as StdResult;
+
+use luther::table::{MAGIC, NO_TOKEN, VERSION};
+
+use automaton::{split_anchors, Automaton};
+use error::ExportError;
+
+/// Checks that the tokens of `automaton` can be exported.
+///
+/// It is an error if the regex for any token has a `^` or `$` anchor or if any token has
+/// `word_boundary`, `min_len`, or `max_len`: the start state for the start of a line, the check
+/// of the `char` after a match, and the length limits are not part of the dfa.
+pub fn check(automaton: &Automaton) -> StdResult<(), ExportError> {
+    for token in automaton.tokens.iter() {
+        let (start, _, end) = split_anchors(&token.regex);
+        if start || end {
+            return Err(ExportError::Anchored(token.name.clone()));
+        }
+        if token.word_boundary {
+            return Err(ExportError::WordBoundary(token.name.clone()));
+        }
+        if token.min_len.is_some() || token.max_len.is_some() {
+            return Err(ExportError::LengthLimit(token.name.clone()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Encodes `automaton` in the binary format.
+///
+/// The `char`'s on which every state has the same transitions are grouped into an alphabet
+/// class, so the transition table has a column for each distinct set of transitions rather than
+/// for each `char`. Class 0 is the `char`'s that take the default transition of every state.
+///
+/// The lexer modes share the tables, with the start state of each mode after the default mode
+/// in the section after the accept map. The names of the tokens are in the last section, so
+/// that a program that loads the dfa can report a token by name.
+///
+/// It is an error if a token cannot be exported (see `check`).
+pub fn to_bytes(automaton: &Automaton) -> StdResult<Vec<u8>, ExportError> {
+    check(automaton)?;
+    let states = &automaton.dfa.states;
+
+    let defaults: Vec<u32> = states.iter().map(|state| state.default).collect();
+    let mut chars: Vec<char> = states
+        .iter()
+        .flat_map(|state| state.by_char.keys().cloned())
+        .collect();
+    chars.sort();
+    chars.dedup();
+
+    let mut columns = vec![defaults.clone()];
+    let mut classes: HashMap<Vec<u32>, u32> = HashMap::new();
+    classes.insert(defaults, 0);
+    let mut char_classes = Vec::new();
+    for c in chars {
+        let column: Vec<u32> = (0..states.len())
+            .map(|state| automaton.transition(state, c) as u32)
+            .collect();
+        let next = columns.len() as u32;
+        let class = *classes.entry(column.clone()).or_insert(next);
+        if class == next {
+            columns.push(column);
+        }
+        char_classes.push((c, class));
+    }
+
+    let mut bytes = MAGIC.to_vec();
+    let header = [
+        VERSION,
+        states.len() as u32,
+        columns.len() as u32,
+        char_classes.len() as u32,
+        automaton.start_state as u32,
+        automaton.error_state as u32,
+    ];
+    for &value in header.iter() {
+        push_u32(&mut bytes, value);
+    }
+    for &(c, class) in char_classes.iter() {
+        push_u32(&mut bytes, c as u32);
+        push_u32(&mut bytes, class);
+    }
+    for state in 0..states.len() {
+        for column in columns.iter() {
+            push_u32(&mut bytes, column[state]);
+        }
+    }
+    for state in states.iter() {
+        push_u32(&mut bytes, state.value.map_or(NO_TOKEN, |token| token as u32));
+    }
+    push_u32(&mut bytes, automaton.start_states.len() as u32 - 1);
+    for &start in automaton.start_states[1..].iter() {
+        push_u32(&mut bytes, start as u32);
+    }
+
+    push_u32(&mut bytes, automaton.tokens.len() as u32);
+    for token in automaton.tokens.iter() {
+        push_u32(&mut bytes, token.name.len() as u32);
+    }
+    for token in automaton.tokens.iter() {
+        bytes.extend_from_slice(token.name.as_bytes());
+    }
+    while bytes.len() % 4 != 0 {
+        bytes.push(0);
+    }
+
+    Ok(bytes)
+}
+
+fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&[
+        value as u8,
+        (value >> 8) as u8,
+        (value >> 16) as u8,
+        (value >> 24) as u8,
+    ]);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use luther::table::{ModeSet, TableDfa};
+    use tokens::TokenDef;
+
+    #[test]
+    fn table_dfa_matches_like_automaton() {
+        let automaton = Automaton::new(vec![
+            TokenDef::new("If", "if"),
+            TokenDef::new("Ident", "[a-z][a-z0-9]*"),
+            TokenDef::new("Number", "[0-9]+"),
+        ]).unwrap();
+
+        let bytes = to_bytes(&automaton).unwrap();
+        let dfa = TableDfa::from_bytes(&bytes).expect("unexpected error loading the dfa");
+
+        for input in &["if", "iffy", "x12 y", "42", "-"] {
+            let expected = automaton
+                .longest_match(input)
+                .map(|(token, len)| (token as u32, len));
+            assert_eq!(dfa.longest_match(input), expected, "input {:?}", input);
+        }
+    }
+
+    #[test]
+    fn table_dfa_has_start_state_for_each_mode() {
+        let mut string = TokenDef::new("StringBody", "[^\"]+");
+        string.mode = Some("STRING".to_string());
+        let automaton = Automaton::new(vec![TokenDef::new("Quote", "\""), string]).unwrap();
+
+        let bytes = to_bytes(&automaton).unwrap();
+        let modes = ModeSet::new(TableDfa::from_bytes(&bytes).unwrap());
+
+        assert_eq!(modes.mode_count(), 2);
+        assert_eq!(modes.longest_match(0, "ab\""), None);
+        assert_eq!(modes.longest_match(1, "ab\""), Some((1, 2)));
+    }
+
+    #[test]
+    fn table_dfa_has_token_names() {
+        let automaton = Automaton::new(vec![
+            TokenDef::new("StringLit", "\"[a-z]*\""),
+            TokenDef::new("Ident", "[a-z]+"),
+        ]).unwrap();
+
+        let bytes = to_bytes(&automaton).unwrap();
+        let dfa = TableDfa::from_bytes(&bytes).unwrap();
+
+        assert_eq!(dfa.token_count(), 2);
+        assert_eq!(dfa.token_name(0), Some("StringLit"));
+        assert_eq!(dfa.token_name(1), Some("Ident"));
+    }
+
+    #[test]
+    fn to_bytes_rejects_anchored_tokens() {
+        for &regex in &["^#[a-z]*", "[a-z]+:$"] {
+            let automaton = Automaton::new(vec![
+                TokenDef::new("Anchored", regex),
+                TokenDef::new("Ident", "[a-z]+"),
+            ]).unwrap();
+
+            let result = to_bytes(&automaton);
+
+            assert_eq!(result, Err(ExportError::Anchored("Anchored".to_string())));
+        }
+    }
+
+    #[test]
+    fn to_bytes_rejects_word_boundary_tokens() {
+        let mut keyword = TokenDef::new("If", "if");
+        keyword.word_boundary = true;
+        let automaton = Automaton::new(vec![keyword, TokenDef::new("Ident", "[a-z]+")]).unwrap();
+
+        let result = to_bytes(&automaton);
+
+        assert_eq!(result, Err(ExportError::WordBoundary("If".to_string())));
+    }
+
+    #[test]
+    fn to_bytes_rejects_length_limits() {
+        let mut ident = TokenDef::new("Ident", "[a-z]+");
+        ident.max_len = Some(255);
+        let automaton = Automaton::new(vec![ident]).unwrap();
+
+        let result = to_bytes(&automaton);
+
+        assert_eq!(result, Err(ExportError::LengthLimit("Ident".to_string())));
+    }
+
+    #[test]
+    fn table_dfa_matches_escaped_anchors() {
+        let automaton = Automaton::new(vec![
+            TokenDef::new("Caret", "\\^[a-z]*"),
+            TokenDef::new("Dollar", "[a-z]*\\$"),
+        ]).unwrap();
+
+        let bytes = to_bytes(&automaton).expect("unexpected error encoding the dfa");
+        let dfa = TableDfa::from_bytes(&bytes).unwrap();
+
+        assert_eq!(dfa.longest_match("^ab"), Some((0, 3)));
+        assert_eq!(dfa.longest_match("ab$"), Some((1, 3)));
+        assert_eq!(dfa.longest_match("ab"), None);
+    }
+}
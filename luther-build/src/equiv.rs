@@ -0,0 +1,203 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Equivalence checking of regexes and of token definitions.
+//!
+//! Both checks search the product of two automata breadth first, following the transitions of
+//! each state in order of their `char`, so a distinguishing string that they find is the
+//! smallest one: the shortest one and, of those, the least in lexicographic order (by `char`).
+//! The result is therefore the same for the same regexes (or tokens) on every run.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::char;
+
+use redfa::{self, Regex};
+
+use automaton::Automaton;
+
+/// The smallest string that is matched by exactly one of `lhs` and `rhs`, or `None` if they
+/// match the same language.
+pub fn distinguishing_string(lhs: &Regex<char>, rhs: &Regex<char>) -> Option<String> {
+    let start = vec![lhs.clone(), rhs.clone()];
+    let (dfa, map) = redfa::Dfa::from_derivatives(vec![start.clone()]);
+    let dfa = dfa.map(|res| (res[0].nullable(), res[1].nullable()));
+
+    search(map[&start] as usize, |state| {
+        let state = &dfa.states[state];
+        let (lhs, rhs) = state.value;
+        let mut next: Vec<_> = state
+            .by_char
+            .iter()
+            .map(|(&c, &to)| (c, to as usize))
+            .collect();
+        if let Some(c) = unused_char(&state.by_char, None) {
+            next.push((c, state.default as usize));
+        }
+        next.sort();
+        (lhs != rhs, next)
+    })
+}
+
+/// The smallest string for which the longest match of `lhs` and `rhs` differ, or `None` if
+/// they accept the same strings as the same tokens.
+///
+/// Tokens are compared by name, so the tokens can be in a different order or have different
+/// regexes and still be equivalent.
+pub fn distinguishing_input(lhs: &Automaton, rhs: &Automaton) -> Option<String> {
+    let mut states = Vec::new();
+    let mut numbers = HashMap::new();
+    let start = (lhs.start_state, rhs.start_state);
+    numbers.insert(start, 0);
+    states.push(start);
+
+    search(0, |number| {
+        let (l, r) = states[number];
+        let lhs_state = &lhs.dfa.states[l];
+        let rhs_state = &rhs.dfa.states[r];
+        let lhs_token = lhs_state.value.map(|token| &lhs.tokens[token].name);
+        let rhs_token = rhs_state.value.map(|token| &rhs.tokens[token].name);
+
+        let chars = lhs_state.by_char.keys().chain(rhs_state.by_char.keys());
+        let mut labels: Vec<Option<char>> = chars.cloned().map(Some).collect();
+        labels.sort();
+        labels.dedup();
+        labels.push(None);
+
+        let mut next = Vec::new();
+        for label in labels {
+            let c = match label {
+                Some(c) => c,
+                None => match unused_char(&lhs_state.by_char, Some(&rhs_state.by_char)) {
+                    Some(c) => c,
+                    None => continue,
+                },
+            };
+            let to = (lhs.transition(l, c), rhs.transition(r, c));
+            if to == (lhs.error_state, rhs.error_state) {
+                continue;
+            }
+            let count = states.len();
+            let to_number = *numbers.entry(to).or_insert(count);
+            if to_number == count {
+                states.push(to);
+            }
+            next.push((c, to_number));
+        }
+        next.sort();
+
+        (lhs_token != rhs_token, next)
+    })
+}
+
+/// Searches breadth first from `start` for a state that is distinguishing and returns the string
+/// that reaches it.
+///
+/// The `visit` closure returns whether a state is distinguishing and its transitions (in the
+/// order in which to follow them), so the string found is the smallest one in that order.
+pub fn search<F>(start: usize, mut visit: F) -> Option<String>
+where
+    F: FnMut(usize) -> (bool, Vec<(char, usize)>),
+{
+    let mut paths: HashMap<usize, (usize, char)> = HashMap::new();
+    let mut seen = BTreeSet::new();
+    let mut queue = VecDeque::new();
+
+    seen.insert(start);
+    queue.push_back(start);
+    while let Some(state) = queue.pop_front() {
+        let (distinguishing, next) = visit(state);
+        if distinguishing {
+            let mut path = Vec::new();
+            let mut current = state;
+            while let Some(&(from, c)) = paths.get(&current) {
+                path.push(c);
+                current = from;
+            }
+            return Some(path.into_iter().rev().collect());
+        }
+
+        for (c, to) in next {
+            if seen.insert(to) {
+                paths.insert(to, (state, c));
+                queue.push_back(to);
+            }
+        }
+    }
+
+    None
+}
+
+/// The least `char` that is in neither `by_char` nor `other`, as the label of a default
+/// transition.
+pub fn unused_char(
+    by_char: &BTreeMap<char, u32>,
+    other: Option<&BTreeMap<char, u32>>,
+) -> Option<char> {
+    (0..0x11_0000)
+        .filter_map(char::from_u32)
+        .find(|c| !by_char.contains_key(c) && !other.map_or(false, |o| o.contains_key(c)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use automaton::parse_regex;
+    use tokens::TokenDef;
+
+    #[test]
+    fn equivalent_regexs_have_no_distinguishing_string() {
+        let lhs = parse_regex("(a|b)*").unwrap();
+        let rhs = parse_regex("(a*b*)*").unwrap();
+
+        assert_eq!(distinguishing_string(&lhs, &rhs), None);
+    }
+
+    #[test]
+    fn different_regexs_have_shortest_distinguishing_string() {
+        let lhs = parse_regex("a[a-z]*").unwrap();
+        let rhs = parse_regex("a[a-y]*").unwrap();
+
+        assert_eq!(distinguishing_string(&lhs, &rhs), Some("az".to_string()));
+    }
+
+    #[test]
+    fn distinguishing_string_is_least_of_shortest() {
+        let lhs = parse_regex("c|b|aa").unwrap();
+        let rhs = parse_regex("d|c|ab").unwrap();
+
+        assert_eq!(distinguishing_string(&lhs, &rhs), Some("b".to_string()));
+        assert_eq!(
+            distinguishing_string(&parse_regex("[^b]").unwrap(), &parse_regex("a").unwrap()),
+            Some("\0".to_string())
+        );
+    }
+
+    #[test]
+    fn distinguishing_input_is_least_of_shortest() {
+        let tokens = |b: &str| {
+            let mut a = TokenDef::new("A", "[a-z]");
+            a.priority_group = 2;
+            vec![a, TokenDef::new("B", b)]
+        };
+        let lhs = Automaton::new(tokens("y"));
+        let rhs = Automaton::new(tokens("x"));
+
+        assert_eq!(
+            distinguishing_input(&lhs.unwrap(), &rhs.unwrap()),
+            Some("x".to_string())
+        );
+    }
+
+    #[test]
+    fn reordered_tokens_are_equivalent() {
+        let lhs = Automaton::new(vec![TokenDef::new("A", "a+"), TokenDef::new("B", "b")]);
+        let rhs = Automaton::new(vec![TokenDef::new("B", "b"), TokenDef::new("A", "aa*")]);
+
+        assert_eq!(distinguishing_input(&lhs.unwrap(), &rhs.unwrap()), None);
+    }
+}
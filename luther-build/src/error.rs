@@ -0,0 +1,140 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! The errors for the phases of compiling token definitions.
+//!
+//! Compiling token definitions has two phases: parsing the regex of each token (which fails
+//! with a `ParseError`) and building the dfa for the tokens (which fails with a
+//! `CompileError`). The errors from the third phase, scanning an input with the compiled dfa,
+//! are `luther::LexError` (and a `LengthError` for a token that breaks the length limits of its
+//! definition). Exporting the dfa fails with an `ExportError` for a token that the exported
+//! formats cannot express.
+
+/// The regex for a token could not be parsed.
+#[derive(Debug, Fail, Clone, PartialEq)]
+#[fail(display = "invalid regex \"{}\" for token {}: {}", regex, token, reason)]
+pub struct ParseError {
+    /// The name of the token.
+    pub token: String,
+
+    /// The regex that could not be parsed.
+    pub regex: String,
+
+    /// Why the regex could not be parsed.
+    pub reason: String,
+}
+
+/// A regex is larger than the `limits::Limits` allow.
+#[derive(Debug, Fail, Clone, PartialEq)]
+pub enum LimitError {
+    /// The regex nests groups and classes deeper (the first field) than the limit (the second
+    /// field).
+    #[fail(display = "its nesting depth {} is more than the limit of {}", _0, _1)]
+    Depth(usize, usize),
+
+    /// The regex has more nodes (the first field) than the limit (the second field).
+    #[fail(display = "it has {} nodes, more than the limit of {}", _0, _1)]
+    Nodes(usize, usize),
+}
+
+/// The dfa for a list of token definitions could not be built.
+#[derive(Debug, Fail, Clone, PartialEq)]
+pub enum CompileError {
+    /// The regex for a token could not be parsed.
+    #[fail(display = "{}", _0)]
+    Parse(#[cause] ParseError),
+
+    /// The regex (the second field) for a token (the first field) matches the empty string.
+    #[fail(display = "regex \"{}\" for token {} matches the empty string", _1, _0)]
+    NullableRegex(String, String),
+
+    /// The regex for a token (the first field) is larger than the limits allow.
+    #[fail(display = "the regex for token {} is too large: {}", _0, _1)]
+    LimitExceeded(String, #[cause] LimitError),
+
+    /// Two tokens of the same priority match the same input.
+    #[fail(display = "tokens {} and {} match the same input with the same priority", _0, _1)]
+    AmbiguousTokens(String, String),
+
+    /// None of the tokens are in the default mode (`tokens::DEFAULT_MODE`).
+    #[fail(display = "there are no tokens in the default mode (INITIAL)")]
+    NoDefaultModeTokens,
+}
+
+/// A token uses a feature that the exported formats of a dfa cannot express.
+///
+/// The exported formats (the binary encoding and the formats of the `luther` command line
+/// tool) have only the states and transitions of the dfa, so a token that needs more than the
+/// dfa to be matched would be silently matched as if it did not.
+#[derive(Debug, Fail, Clone, PartialEq)]
+pub enum ExportError {
+    /// The regex for a token (the first field) has a `^` or `$` anchor.
+    #[fail(display = "token {} has an anchor, which the exported dfa cannot check", _0)]
+    Anchored(String),
+
+    /// A token (the first field) has `word_boundary`.
+    #[fail(display = "token {} has a word boundary, which the exported dfa cannot check", _0)]
+    WordBoundary(String),
+
+    /// A token (the first field) has a `min_len` or a `max_len`.
+    #[fail(display = "token {} has a length limit, which the exported dfa cannot check", _0)]
+    LengthLimit(String),
+}
+
+/// A match of a token is shorter or longer (in `char`'s) than its definition allows.
+///
+/// The `start` and `end` are the byte offsets of the match (with `end` just past its end).
+#[derive(Debug, Fail, Clone, PartialEq)]
+pub enum LengthError {
+    /// The match is shorter than the `min_len` of the token.
+    #[fail(display = "{} at {}..{} is {} chars long, less than its minimum of {}", token, start,
+           end, len, min_len)]
+    TooShort {
+        token: String,
+        start: usize,
+        end: usize,
+        len: usize,
+        min_len: usize,
+    },
+
+    /// The match is longer than the `max_len` of the token.
+    #[fail(display = "{} at {}..{} is {} chars long, more than its maximum of {}", token, start,
+           end, len, max_len)]
+    TooLong {
+        token: String,
+        start: usize,
+        end: usize,
+        len: usize,
+        max_len: usize,
+    },
+}
+
+impl LengthError {
+    /// The name of the token.
+    pub fn token(&self) -> &str {
+        match self {
+            &LengthError::TooShort { ref token, .. } | &LengthError::TooLong { ref token, .. } => {
+                token
+            }
+        }
+    }
+
+    /// The byte offsets of the start and (just past) the end of the match.
+    pub fn span(&self) -> (usize, usize) {
+        match self {
+            &LengthError::TooShort { start, end, .. }
+            | &LengthError::TooLong { start, end, .. } => (start, end),
+        }
+    }
+}
+
+impl From<ParseError> for CompileError {
+    fn from(error: ParseError) -> CompileError {
+        CompileError::Parse(error)
+    }
+}
@@ -0,0 +1,350 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Build script support for the Luther lexer generator.
+//!
+//! This crate compiles token definitions in a build script rather than through the
+//! `luther-derive` proc macro. It is meant for token definitions that live outside of the Rust
+//! source (or are shared with other tools) and for crates that would rather not use a proc
+//! macro.
+//!
+//! The token definitions are a TOML file with a `[[token]]` table for each token or a JSON
+//! file with a `token` array. Each token has a `name`, a `regex`, and optionally a
+//! `priority_group`, a `mode`, and a `skip` flag (see `tokens::TokenFile`), and the tokens are
+//! compiled into a dfa by the same rules that `luther-derive` uses for the variants of an
+//! `enum`. The `.dfa` file has the start state of every lexer mode (see
+//! `luther::table::ModeSet`) and the names of the tokens. It is an error for a token to have a
+//! `^` or `$` anchor, `word_boundary`, `min_len`, or `max_len` (which the `.dfa` file cannot
+//! check).
+//!
+//! The modules of the crate are the compiler behind `compile`, which the `luther` command line
+//! tool (in `tools/luther-cli`) shares:
+//!
+//! - `tokens` and `syntax`: the token definitions file and the syntax options of its regexes
+//! - `automaton`: the multi-pattern dfa for a list of token definitions
+//! - `binary`: the encoding of the dfa read by `luther::table::TableDfa`
+//! - `error`: the errors for parsing and compiling token definitions
+//! - `limits` and `simplify`: the size limits on regexes and the rewrites that shrink them
+//! - `equiv`: the equivalence of regexes and of token definitions
+//!
+//! # Example
+//! In `build.rs`:
+//!
+//! ```ignore
+//! extern crate luther_build;
+//!
+//! fn main() {
+//!     luther_build::compile("src/tokens.toml", std::env::var("OUT_DIR").unwrap()).unwrap();
+//! }
+//! ```
+//!
+//! In the crate:
+//!
+//! ```ignore
+//! extern crate luther;
+//!
+//! include!(concat!(env!("OUT_DIR"), "/tokens.rs"));
+//!
+//! fn main() {
+//!     assert_eq!(Token::longest_match("if x"), Some((Token::If, 2)));
+//! }
+//! ```
+//!
+//! # Generated source
+//! `compile` writes two files to the output directory, both named for the token definitions
+//! file: the dfa in the binary format read by `luther::table::TableDfa` (with a `.dfa`
+//! extension) and Rust source (with a `.rs` extension). The source defines a `Token` enum with
+//! a unit variant for each token and these methods:
+//!
+//! - `ALL`: the tokens in the order they are defined
+//! - `name`: the name of a token
+//! - `mode`: the lexer mode in which a token is recognized
+//! - `skip`: whether a token is recognized but should not be produced
+//! - `dfa`: the `TableDfa` for the tokens
+//! - `longest_match`: the token and length (in bytes) of the longest prefix of a `&str` that
+//! is a token
+//!
+//! `compile_tables` writes only the `.dfa` file, for crates that load the dfa themselves.
+//!
+//! # Feature groups
+//! A token with `groups` (such as `groups = ["edition2021"]`) is compiled only when one of its
+//! groups is enabled, so that one token definitions file can serve several dialects of a
+//! language. `compile` and `compile_tables` enable no groups, so they compile the tokens that
+//! are in no group. `compile_with_groups` and `compile_tables_with_groups` enable the given
+//! groups, for example from the features of the crate:
+//!
+//! ```ignore
+//! let mut groups = Vec::new();
+//! if std::env::var_os("CARGO_FEATURE_EXTENSIONS").is_some() {
+//!     groups.push("extensions");
+//! }
+//! luther_build::compile_with_groups("src/tokens.toml", out_dir, &groups).unwrap();
+//! ```
+
+#[macro_use]
+extern crate failure;
+extern crate luther;
+extern crate redfa;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate toml;
+
+pub mod automaton;
+pub mod binary;
+pub mod equiv;
+pub mod error;
+pub mod limits;
+pub mod simplify;
+pub mod syntax;
+pub mod tokens;
+
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use failure::Error;
+use automaton::Automaton;
+
+/// Compiles the token definitions in `tokens` into a `.dfa` file and a `.rs` file in `out_dir`.
+///
+/// The `.rs` file defines a `Token` enum for the token definitions (see the crate level
+/// documentation). Returns the path of the `.rs` file.
+pub fn compile<P: AsRef<Path>, Q: AsRef<Path>>(
+    tokens: P,
+    out_dir: Q,
+) -> Result<PathBuf, Error> {
+    compile_with_groups(tokens, out_dir, &[])
+}
+
+/// Compiles the token definitions in `tokens` for the enabled feature `groups` into a `.dfa`
+/// file and a `.rs` file in `out_dir`.
+///
+/// This is `compile` for the tokens that are in no group or in at least one of `groups`.
+/// Returns the path of the `.rs` file.
+pub fn compile_with_groups<P: AsRef<Path>, Q: AsRef<Path>>(
+    tokens: P,
+    out_dir: Q,
+    groups: &[&str],
+) -> Result<PathBuf, Error> {
+    let tokens = tokens.as_ref();
+    let automaton = load(tokens, groups)?;
+
+    for token in automaton.tokens.iter() {
+        if !is_identifier(&token.name) {
+            bail!(
+                "the token name \"{}\" in {} is not a valid Rust identifier",
+                token.name,
+                tokens.display()
+            );
+        }
+    }
+
+    let dfa_path = write_tables(tokens, out_dir.as_ref(), &automaton)?;
+    let source_path = dfa_path.with_extension("rs");
+    fs::write(&source_path, to_source(&automaton, tokens, &dfa_path))?;
+
+    Ok(source_path)
+}
+
+/// Compiles the token definitions in `tokens` into a `.dfa` file in `out_dir`.
+///
+/// The `.dfa` file can be loaded with `luther::table::TableDfa::from_bytes`. Returns the path
+/// of the `.dfa` file.
+pub fn compile_tables<P: AsRef<Path>, Q: AsRef<Path>>(
+    tokens: P,
+    out_dir: Q,
+) -> Result<PathBuf, Error> {
+    compile_tables_with_groups(tokens, out_dir, &[])
+}
+
+/// Compiles the token definitions in `tokens` for the enabled feature `groups` into a `.dfa`
+/// file in `out_dir`.
+///
+/// This is `compile_tables` for the tokens that are in no group or in at least one of
+/// `groups`. Returns the path of the `.dfa` file.
+pub fn compile_tables_with_groups<P: AsRef<Path>, Q: AsRef<Path>>(
+    tokens: P,
+    out_dir: Q,
+    groups: &[&str],
+) -> Result<PathBuf, Error> {
+    let tokens = tokens.as_ref();
+    let automaton = load(tokens, groups)?;
+    write_tables(tokens, out_dir.as_ref(), &automaton)
+}
+
+fn load(tokens: &Path, groups: &[&str]) -> Result<Automaton, Error> {
+    println!("cargo:rerun-if-changed={}", tokens.display());
+    let groups: Vec<_> = groups.iter().map(|group| group.to_string()).collect();
+    Ok(Automaton::new(tokens::select(tokens::load(tokens)?, &groups)?)?)
+}
+
+fn write_tables(tokens: &Path, out_dir: &Path, automaton: &Automaton) -> Result<PathBuf, Error> {
+    let stem = tokens
+        .file_stem()
+        .ok_or_else(|| format_err!("{} is not a file", tokens.display()))?;
+    let path = out_dir.join(stem).with_extension("dfa");
+
+    fs::create_dir_all(out_dir)?;
+    fs::write(&path, binary::to_bytes(automaton)?)?;
+
+    Ok(path)
+}
+
+fn to_source(automaton: &Automaton, tokens: &Path, dfa_path: &Path) -> String {
+    let mut source = String::new();
+    let names: Vec<_> = automaton.tokens.iter().map(|t| t.name.as_str()).collect();
+
+    writeln!(source, "// Generated by luther-build from {}. Do not edit.", tokens.display())
+        .unwrap();
+    writeln!(source).unwrap();
+    writeln!(source, "/// The tokens defined in {}.", tokens.display()).unwrap();
+    writeln!(source, "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]").unwrap();
+    writeln!(source, "pub enum Token {{").unwrap();
+    for name in names.iter() {
+        writeln!(source, "    {},", name).unwrap();
+    }
+    writeln!(source, "}}").unwrap();
+    writeln!(source).unwrap();
+
+    writeln!(source, "impl Token {{").unwrap();
+    writeln!(source, "    /// The tokens in the order they are defined.").unwrap();
+    writeln!(source, "    pub const ALL: &'static [Token] = &[").unwrap();
+    for name in names.iter() {
+        writeln!(source, "        Token::{},", name).unwrap();
+    }
+    writeln!(source, "    ];").unwrap();
+    writeln!(source).unwrap();
+    writeln!(source, "    /// The name of the token.").unwrap();
+    writeln!(source, "    pub fn name(&self) -> &'static str {{").unwrap();
+    writeln!(source, "        match *self {{").unwrap();
+    for name in names.iter() {
+        writeln!(source, "            Token::{} => {:?},", name, name).unwrap();
+    }
+    writeln!(source, "        }}").unwrap();
+    writeln!(source, "    }}").unwrap();
+    writeln!(source).unwrap();
+    writeln!(source, "    /// The lexer mode in which the token is recognized.").unwrap();
+    writeln!(source, "    pub fn mode(&self) -> &'static str {{").unwrap();
+    writeln!(source, "        match *self {{").unwrap();
+    for token in automaton.tokens.iter() {
+        writeln!(source, "            Token::{} => {:?},", token.name, token.mode_name()).unwrap();
+    }
+    writeln!(source, "        }}").unwrap();
+    writeln!(source, "    }}").unwrap();
+    writeln!(source).unwrap();
+    writeln!(source, "    /// Whether the token is recognized but should not be produced.").unwrap();
+    writeln!(source, "    pub fn skip(&self) -> bool {{").unwrap();
+    writeln!(source, "        match *self {{").unwrap();
+    for token in automaton.tokens.iter() {
+        writeln!(source, "            Token::{} => {},", token.name, token.skip).unwrap();
+    }
+    writeln!(source, "        }}").unwrap();
+    writeln!(source, "    }}").unwrap();
+    writeln!(source).unwrap();
+    source.push_str(
+        r#"    /// The dfa for the tokens.
+    pub fn dfa() -> ::luther::table::TableDfa<'static> {
+        ::luther::table::TableDfa::from_bytes(TOKEN_DFA).expect("luther-build: invalid dfa")
+    }
+
+    /// The token and length (in bytes) of the longest prefix of `input` that is a token.
+    pub fn longest_match(input: &str) -> Option<(Token, usize)> {
+        Token::dfa()
+            .longest_match(input)
+            .map(|(token, len)| (Token::ALL[token as usize], len))
+    }
+}
+
+"#,
+    );
+    writeln!(
+        source,
+        "static TOKEN_DFA: &'static [u8] = include_bytes!({:?});",
+        dfa_path.display().to_string()
+    ).unwrap();
+
+    source
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    name != "_" && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+
+    fn out_dir(name: &str) -> PathBuf {
+        env::temp_dir().join("luther-build-test").join(name)
+    }
+
+    fn write_tokens(dir: &Path, contents: &str) -> PathBuf {
+        fs::create_dir_all(dir).unwrap();
+        let path = dir.join("tokens.toml");
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn compile_writes_source_and_tables() {
+        let dir = out_dir("compile");
+        let tokens = write_tokens(
+            &dir,
+            "[[token]]\nname = \"Ident\"\nregex = \"[a-z]+\"\n\n\
+             [[token]]\nname = \"If\"\nregex = \"if\"\n",
+        );
+
+        let source = compile(&tokens, &dir).expect("unexpected error compiling");
+
+        let contents = fs::read_to_string(&source).unwrap();
+        assert!(contents.contains("pub enum Token {\n    Ident,\n    If,\n}"));
+        assert!(dir.join("tokens.dfa").exists());
+    }
+
+    #[test]
+    fn compile_with_groups_compiles_enabled_groups() {
+        let dir = out_dir("groups");
+        let tokens = write_tokens(
+            &dir,
+            "[[token]]\nname = \"Ident\"\nregex = \"[a-z]+\"\n\n\
+             [[token]]\nname = \"Async\"\nregex = \"async\"\ngroups = [\"edition2018\"]\n\n\
+             [[token]]\nname = \"Try\"\nregex = \"try\"\ngroups = [\"extensions\"]\n",
+        );
+
+        let source = compile_with_groups(&tokens, &dir, &["edition2018"])
+            .expect("unexpected error compiling");
+
+        let contents = fs::read_to_string(&source).unwrap();
+        assert!(contents.contains("pub enum Token {\n    Ident,\n    Async,\n}"));
+    }
+
+    #[test]
+    fn compile_rejects_invalid_token_names() {
+        let dir = out_dir("invalid-name");
+        let tokens = write_tokens(&dir, "[[token]]\nname = \"left paren\"\nregex = \"[(]\"\n");
+
+        assert!(compile(&tokens, &dir).is_err());
+    }
+
+    #[test]
+    fn compile_rejects_anchored_tokens() {
+        let dir = out_dir("anchored");
+        let tokens = write_tokens(&dir, "[[token]]\nname = \"Comment\"\nregex = \"^#.*\"\n");
+
+        assert!(compile(&tokens, &dir).is_err());
+        assert!(!dir.join("tokens.dfa").exists());
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Limits on the size of the regexes in token definitions.
+//!
+//! The regexes are parsed recursively and the number of states of a dfa grows with the size of
+//! its regexes, so a service that compiles token definitions from untrusted users should limit
+//! both the nesting depth of a regex (which is checked on its text, before it is parsed) and the
+//! number of nodes in the parsed regex (which is checked before the dfa is built).
+
+use redfa::Regex;
+
+use error::LimitError;
+
+/// The limits on the size of a regex.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// The most groups and classes that a regex may have nested inside each other.
+    pub max_depth: usize,
+
+    /// The most nodes that a parsed regex may have (counting each `char` of a class).
+    pub max_nodes: usize,
+}
+
+impl Limits {
+    /// No limits.
+    pub fn unlimited() -> Limits {
+        Limits {
+            max_depth: usize::max_value(),
+            max_nodes: usize::max_value(),
+        }
+    }
+
+    /// Checks the nesting depth of the text of `regex`.
+    pub fn check_depth(&self, regex: &str) -> Result<(), LimitError> {
+        let depth = nesting_depth(regex);
+        if depth > self.max_depth {
+            return Err(LimitError::Depth(depth, self.max_depth));
+        }
+        Ok(())
+    }
+
+    /// Checks the number of nodes of the parsed `regex`.
+    pub fn check_nodes(&self, regex: &Regex<char>) -> Result<(), LimitError> {
+        let nodes = node_count(regex);
+        if nodes > self.max_nodes {
+            return Err(LimitError::Nodes(nodes, self.max_nodes));
+        }
+        Ok(())
+    }
+}
+
+impl Default for Limits {
+    /// Limits that allow any reasonable hand written token definition.
+    fn default() -> Limits {
+        Limits {
+            max_depth: 32,
+            max_nodes: 10_000,
+        }
+    }
+}
+
+/// The deepest nesting of groups and classes in the text of a regex.
+pub fn nesting_depth(regex: &str) -> usize {
+    let mut depth = 0usize;
+    let mut max_depth = 0;
+    let mut in_class = false;
+    let mut chars = regex.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            ']' if in_class => {
+                in_class = false;
+                depth -= 1;
+            }
+            _ if in_class => {}
+            '[' => {
+                in_class = true;
+                depth += 1;
+            }
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        max_depth = max_depth.max(depth);
+    }
+
+    max_depth
+}
+
+/// The number of nodes in `regex` (counting each `char` of a class as a node).
+pub fn node_count(regex: &Regex<char>) -> usize {
+    match regex {
+        &Regex::Null | &Regex::Empty => 1,
+        &Regex::Except(ref ts) => 1 + ts.len(),
+        &Regex::Alt(ref ts, ref res) => 1 + ts.len() + res.iter().map(node_count).sum::<usize>(),
+        &Regex::Cat(ref res) | &Regex::And(ref res) => {
+            1 + res.iter().map(node_count).sum::<usize>()
+        }
+        &Regex::Not(ref re) | &Regex::Kleene(ref re) => 1 + node_count(re),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use automaton::parse_regex;
+
+    #[test]
+    fn nesting_depth_counts_groups_and_classes() {
+        assert_eq!(nesting_depth("abc"), 0);
+        assert_eq!(nesting_depth("(a(b[c)]))"), 3);
+        assert_eq!(nesting_depth("\\(\\(a"), 0);
+        assert_eq!(nesting_depth("[(]"), 1);
+    }
+
+    #[test]
+    fn limits_reject_large_regexes() {
+        let limits = Limits {
+            max_depth: 2,
+            max_nodes: 8,
+        };
+
+        assert_eq!(limits.check_depth("((a))"), Ok(()));
+        assert_eq!(limits.check_depth("(((a)))"), Err(LimitError::Depth(3, 2)));
+        assert_eq!(limits.check_nodes(&parse_regex("ab").unwrap()), Ok(()));
+        assert_matches_nodes(limits.check_nodes(&parse_regex("abcdefghij").unwrap()));
+    }
+
+    fn assert_matches_nodes(result: Result<(), LimitError>) {
+        match result {
+            Err(LimitError::Nodes(nodes, 8)) => assert!(nodes > 8),
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+}
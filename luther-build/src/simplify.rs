@@ -0,0 +1,381 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Rewrites of regexes that go beyond the normalization done by `redfa`.
+//!
+//! `redfa` normalizes regexes only up to similarity (the rules that the construction of a dfa
+//! by derivatives needs in order to terminate). The regexes of a large generated set of token
+//! definitions are often much larger than they need to be, and the construction of the dfa is
+//! faster (and may find fewer states) when they are simplified first.
+//! `Automaton::with_simplify` simplifies each regex before it builds the dfa.
+//!
+//! Every rewrite matches exactly the same strings as the regex it replaces.
+
+use redfa::Regex;
+use redfa::dfa::Normalize;
+
+use limits::node_count;
+
+/// How hard `simplify` tries to shrink a regex.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Effort {
+    /// Rewrites repetitions: `(r*)*` and `(r|s*)*` to `(r|s)*`, and `r*r*` to `r*`.
+    Low,
+
+    /// Also removes the alternatives that another alternative already matches, such as `r`
+    /// in `r|rs*` or in `r|r*`.
+    Medium,
+
+    /// Also factors the alternatives that share a first part (`ab|ac` to `a(b|c)`) or that
+    /// differ only in a first `char` (`ac|bc` to `[ab]c`).
+    High,
+}
+
+/// Simplifies `regex` with the rewrites for `effort`.
+///
+/// The rewrites are repeated for as long as they make the regex smaller (in the number of its
+/// nodes). The result is normalized.
+pub fn simplify(regex: &Regex<char>, effort: Effort) -> Regex<char> {
+    let mut current = normalize(regex.clone());
+    loop {
+        let next = normalize(rewrite(&current, effort));
+        if node_count(&next) >= node_count(&current) {
+            return current;
+        }
+        current = next;
+    }
+}
+
+fn normalize(regex: Regex<char>) -> Regex<char> {
+    vec![regex].normalize().remove(0)
+}
+
+// Rewrites regex from the bottom up.
+fn rewrite(regex: &Regex<char>, effort: Effort) -> Regex<char> {
+    let all = |res: &[Regex<char>]| -> Vec<Regex<char>> {
+        res.iter().map(|re| rewrite(re, effort)).collect()
+    };
+    match regex {
+        &Regex::Null | &Regex::Empty | &Regex::Except(_) => regex.clone(),
+        &Regex::Alt(ref ts, ref res) => rewrite_alt(ts.clone(), all(res), effort),
+        &Regex::Cat(ref res) => rewrite_cat(all(res)),
+        &Regex::And(ref res) => Regex::And(all(res)),
+        &Regex::Not(ref re) => Regex::Not(Box::new(rewrite(re, effort))),
+        &Regex::Kleene(ref re) => rewrite_star(rewrite(re, effort)),
+    }
+}
+
+fn rewrite_star(regex: Regex<char>) -> Regex<char> {
+    if regex == Regex::Null || is_empty(&regex) {
+        return empty();
+    }
+
+    match regex {
+        // (r*)* = r*
+        Regex::Kleene(re) => Regex::Kleene(re),
+
+        // (r|s*|())* = (r|s)*
+        Regex::Alt(ts, res) => {
+            let res = res.into_iter()
+                .filter(|re| !is_empty(re))
+                .map(unstar)
+                .collect();
+            Regex::Kleene(Box::new(Regex::Alt(ts, res)))
+        }
+
+        // (r*s*)* = (r|s)*
+        Regex::Cat(res) => {
+            if res.iter().all(is_star) {
+                let res = res.into_iter().map(unstar).collect();
+                Regex::Kleene(Box::new(Regex::Alt(Vec::new(), res)))
+            } else {
+                Regex::Kleene(Box::new(Regex::Cat(res)))
+            }
+        }
+
+        re => Regex::Kleene(Box::new(re)),
+    }
+}
+
+fn rewrite_cat(res: Vec<Regex<char>>) -> Regex<char> {
+    let mut parts: Vec<Regex<char>> = Vec::new();
+    for re in res.into_iter().flat_map(into_parts) {
+        // r*r* = r*
+        if is_star(&re) && parts.last() == Some(&re) {
+            continue;
+        }
+        parts.push(re);
+    }
+    cat(parts)
+}
+
+fn rewrite_alt(ts: Vec<char>, res: Vec<Regex<char>>, effort: Effort) -> Regex<char> {
+    if effort == Effort::Low {
+        return Regex::Alt(ts, res);
+    }
+
+    let (mut ts, mut res) = flatten_alt(ts, res);
+
+    // r|rs* = rs* and r|r* = r*
+    let subsumed: Vec<_> = (0..res.len())
+        .map(|i| (0..res.len()).any(|j| i != j && subsumes(&res[j], &res[i])))
+        .collect();
+    res = res.into_iter()
+        .zip(subsumed)
+        .filter(|&(_, subsumed)| !subsumed)
+        .map(|(re, _)| re)
+        .collect();
+    ts.retain(|&c| !res.iter().any(|re| subsumes_char(re, c)));
+
+    if effort == Effort::High {
+        res = merge_first_chars(res);
+        res = factor_first_parts(res, effort);
+    }
+
+    Regex::Alt(ts, res)
+}
+
+// Merges nested alternations into one.
+fn flatten_alt(ts: Vec<char>, res: Vec<Regex<char>>) -> (Vec<char>, Vec<Regex<char>>) {
+    let mut chars = ts;
+    let mut alternatives = Vec::new();
+    for re in res {
+        match re {
+            Regex::Alt(ts, res) => {
+                let (ts, res) = flatten_alt(ts, res);
+                chars.extend(ts);
+                alternatives.extend(res);
+            }
+            re => alternatives.push(re),
+        }
+    }
+    (chars, alternatives)
+}
+
+// Whether sup matches every string that sub matches (by the form of the two regexes).
+fn subsumes(sup: &Regex<char>, sub: &Regex<char>) -> bool {
+    if let &Regex::Kleene(ref re) = sup {
+        if **re == *sub {
+            return true;
+        }
+    }
+    if is_empty(sub) {
+        return !is_empty(sup) && sup.nullable();
+    }
+
+    let (sup, sub) = (parts(sup), parts(sub));
+    let last = sup.len().saturating_sub(sub.len());
+    sup.len() > sub.len()
+        && (padded_by_nullable(&sup, &sub, 0) || padded_by_nullable(&sup, &sub, last))
+}
+
+// Whether sup is sub with only nullable parts before index start and after sub.
+fn padded_by_nullable(sup: &[Regex<char>], sub: &[Regex<char>], start: usize) -> bool {
+    let end = start + sub.len();
+    sup[start..end] == *sub
+        && sup[..start]
+            .iter()
+            .chain(sup[end..].iter())
+            .all(|re| re.nullable())
+}
+
+// Whether sup matches the single char c (by the form of sup).
+fn subsumes_char(sup: &Regex<char>, c: char) -> bool {
+    let class_has = |re: &Regex<char>| match re {
+        &Regex::Alt(ref ts, _) => ts.contains(&c),
+        &Regex::Except(ref ts) => !ts.contains(&c),
+        _ => false,
+    };
+    let split = parts(sup);
+
+    match sup {
+        &Regex::Kleene(ref re) => class_has(re),
+        _ => (0..split.len()).any(|i| {
+            class_has(&split[i])
+                && split[..i]
+                    .iter()
+                    .chain(split[i + 1..].iter())
+                    .all(|re| re.nullable())
+        }),
+    }
+}
+
+// ac|bc = [ab]c
+fn merge_first_chars(res: Vec<Regex<char>>) -> Vec<Regex<char>> {
+    let mut groups: Vec<(Vec<Regex<char>>, Vec<char>)> = Vec::new();
+    let mut others = Vec::new();
+
+    for re in res {
+        let mut split = parts(&re);
+        let chars = match split.first() {
+            Some(&Regex::Alt(ref ts, ref res)) if split.len() > 1 && res.is_empty() => ts.clone(),
+            _ => {
+                others.push(re);
+                continue;
+            }
+        };
+        let rest = split.split_off(1);
+        match groups.iter().position(|&(ref other, _)| *other == rest) {
+            Some(i) => groups[i].1.extend(chars),
+            None => groups.push((rest, chars)),
+        }
+    }
+
+    let mut merged: Vec<_> = groups
+        .into_iter()
+        .map(|(rest, chars)| {
+            let mut parts = vec![Regex::Alt(chars, Vec::new())];
+            parts.extend(rest);
+            cat(parts)
+        })
+        .collect();
+    merged.extend(others);
+    merged
+}
+
+// ab|ac = a(b|c)
+fn factor_first_parts(res: Vec<Regex<char>>, effort: Effort) -> Vec<Regex<char>> {
+    let mut groups: Vec<(Regex<char>, Vec<Regex<char>>)> = Vec::new();
+    let mut others = Vec::new();
+
+    for re in res {
+        let mut split = parts(&re);
+        if split.len() < 2 {
+            others.push(re);
+            continue;
+        }
+        let rest = cat(split.split_off(1));
+        let first = split.remove(0);
+        match groups.iter().position(|&(ref other, _)| *other == first) {
+            Some(i) => groups[i].1.push(rest),
+            None => groups.push((first, vec![rest])),
+        }
+    }
+
+    let mut factored: Vec<_> = groups
+        .into_iter()
+        .map(|(first, mut rests)| {
+            if rests.len() == 1 {
+                let mut parts = vec![first];
+                parts.extend(into_parts(rests.remove(0)));
+                cat(parts)
+            } else {
+                cat(vec![first, rewrite_alt(Vec::new(), rests, effort)])
+            }
+        })
+        .collect();
+    factored.extend(others);
+    factored
+}
+
+// The regex that matches only the empty string.
+fn empty() -> Regex<char> {
+    Regex::Empty
+}
+
+fn is_empty(regex: &Regex<char>) -> bool {
+    match regex {
+        &Regex::Empty => true,
+        &Regex::Cat(ref res) => res.is_empty(),
+        _ => false,
+    }
+}
+
+fn is_star(regex: &Regex<char>) -> bool {
+    match regex {
+        &Regex::Kleene(_) => true,
+        _ => false,
+    }
+}
+
+fn unstar(regex: Regex<char>) -> Regex<char> {
+    match regex {
+        Regex::Kleene(re) => *re,
+        re => re,
+    }
+}
+
+// The parts of regex as a concatenation.
+fn parts(regex: &Regex<char>) -> Vec<Regex<char>> {
+    into_parts(regex.clone())
+}
+
+fn into_parts(regex: Regex<char>) -> Vec<Regex<char>> {
+    match regex {
+        Regex::Cat(res) => res,
+        re => vec![re],
+    }
+}
+
+fn cat(mut parts: Vec<Regex<char>>) -> Regex<char> {
+    if parts.len() == 1 {
+        parts.remove(0)
+    } else {
+        Regex::Cat(parts)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use automaton::parse_regex;
+    use equiv::distinguishing_string;
+
+    fn simplified(regex: &str, effort: Effort) -> Regex<char> {
+        simplify(&parse_regex(regex).unwrap(), effort)
+    }
+
+    #[test]
+    fn low_effort_simplifies_repetitions() {
+        let cases = [("a*a*", "a*"), ("(a|b*)*", "(a|b)*"), ("(a*b*)*", "(a|b)*")];
+
+        for &(regex, expected) in cases.iter() {
+            assert_eq!(simplified(regex, Effort::Low), parse_regex(expected).unwrap());
+        }
+    }
+
+    #[test]
+    fn medium_effort_removes_subsumed_alternatives() {
+        let cases = [("a|ab*", "ab*"), ("ab|(ab)*", "(ab)*"), ("xy|xy(z|w)*", "xy(z|w)*")];
+
+        for &(regex, expected) in cases.iter() {
+            assert_eq!(simplified(regex, Effort::Medium), parse_regex(expected).unwrap());
+            assert_eq!(simplified(regex, Effort::Low), parse_regex(regex).unwrap());
+        }
+    }
+
+    #[test]
+    fn high_effort_factors_alternatives() {
+        let cases = [("ab|ac", "a(b|c)"), ("ac|bc", "[ab]c"), ("abc|abd|xe", "ab[cd]|xe")];
+
+        for &(regex, expected) in cases.iter() {
+            let regex = simplified(regex, Effort::High);
+            assert_eq!(distinguishing_string(&regex, &parse_regex(expected).unwrap()), None);
+            assert!(node_count(&regex) <= node_count(&parse_regex(expected).unwrap()));
+        }
+    }
+
+    #[test]
+    fn simplify_matches_the_same_strings() {
+        let regexs = [
+            "if|else|elif|end",
+            "[a-z_][a-z0-9_]*|[a-z]+",
+            "(a|b)*abb|a*|(ab)*a",
+            "0x[0-9a-f]+|0b[01]+|0|[1-9][0-9]*",
+            "\"([^\"\\\\]|\\\\.)*\"",
+        ];
+
+        for regex in regexs.iter() {
+            let original = parse_regex(regex).unwrap();
+            let simplified = simplify(&original, Effort::High);
+
+            assert_eq!(distinguishing_string(&original, &simplified), None, "{}", regex);
+            assert!(node_count(&simplified) <= node_count(&original), "{}", regex);
+        }
+    }
+}
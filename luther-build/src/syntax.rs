@@ -0,0 +1,422 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! The luther extensions to the regex syntax of `redfa`.
+//!
+//! A token regex is translated into the syntax that the `redfa` parser accepts before it is
+//! parsed. The translation is controlled by the `Syntax` options of a token definitions file
+//! (its `[syntax]` table), so that a file states which conventions its regexes were written
+//! for instead of silently getting those of the parser.
+//!
+//! The translation also handles these escapes (in and out of character classes) whatever the
+//! options: `\xNN` (two hex digits), `\u{XXXX}` (one to six hex digits), `\0`, and the named
+//! escapes `\n`, `\r`, `\t`, `\f`, `\v`, `\a`, and `\e`. Any other escaped `char` is a
+//! literal, except for the shorthand classes `\d` (ASCII digits), `\w` (ASCII word `char`'s),
+//! and `\s` (ASCII whitespace) and their negations `\D`, `\W`, and `\S`.
+//!
+//! A character class may contain nested classes and the set operations `&&` (intersection)
+//! and `--` (difference), as in `[[a-z]&&[^aeiou]]` or `[\w--\d]`. An operator must be
+//! followed by a nested class or a shorthand class (otherwise `&&` and `--` are literal
+//! `char`'s and ranges, as in `[+--]`).
+
+use std::char;
+use std::result::Result as StdResult;
+use std::str::Chars;
+
+/// The line terminators that `.` does not match unless `dot_matches_newline` is set.
+pub const LINE_TERMINATORS: &[char] = &['\n', '\r', '\u{85}', '\u{2028}', '\u{2029}'];
+
+/// The options for the syntax of the regexes in a token definitions file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Syntax {
+    /// Whether `.` (outside of a character class) matches a line terminator.
+    ///
+    /// This is true by default (as for the `luther` attribute). Many other regex dialects
+    /// (and flex) use false, where `.` matches any `char` except one of `LINE_TERMINATORS`.
+    pub dot_matches_newline: bool,
+
+    /// Whether whitespace (outside of a character class) is ignored and `#` starts a comment
+    /// that runs to the end of the line, so that a long regex can be laid out over several
+    /// lines. An escaped whitespace `char` or `#` is a literal.
+    ///
+    /// A regex that starts with `(?x)` (after any `^` anchor) is free spacing whatever this
+    /// option is.
+    pub free_spacing: bool,
+}
+
+impl Default for Syntax {
+    fn default() -> Syntax {
+        Syntax {
+            dot_matches_newline: true,
+            free_spacing: false,
+        }
+    }
+}
+
+impl Syntax {
+    /// Tests for these being the default options.
+    pub fn is_default(&self) -> bool {
+        *self == Syntax::default()
+    }
+
+    /// Translates `regex` into the syntax of the `redfa` parser.
+    ///
+    /// The error is a description of the part of `regex` that could not be translated.
+    pub fn translate(&self, regex: &str) -> StdResult<String, String> {
+        let mut translated = String::with_capacity(regex.len());
+        let flagged = regex.starts_with("(?x)");
+        let free_spacing = self.free_spacing || flagged;
+        let mut chars = if flagged { &regex[4..] } else { regex }.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                c if free_spacing && c.is_whitespace() => {}
+                '#' if free_spacing => while chars.next().map_or(false, |c| c != '\n') {},
+                '\\' => match shorthand(&chars) {
+                    Some((class, negated)) => {
+                        chars.next();
+                        translated.push_str(&Operand::shorthand(class, negated).render());
+                    }
+                    None => match escape(&mut chars)? {
+                        Some(c) => push_literal(&mut translated, c, false),
+                        None => {
+                            translated.push(c);
+                            translated.extend(chars.next());
+                        }
+                    },
+                },
+                '[' => translated.push_str(&class(&mut chars)?),
+                '.' if !self.dot_matches_newline => {
+                    translated.push_str("[^");
+                    for &t in LINE_TERMINATORS {
+                        match t {
+                            '\n' => translated.push_str("\\n"),
+                            '\r' => translated.push_str("\\r"),
+                            t => translated.push(t),
+                        }
+                    }
+                    translated.push(']');
+                }
+                c => translated.push(c),
+            }
+        }
+
+        Ok(translated)
+    }
+}
+
+// The chars in each shorthand class (\d, \w, and \s) as the body of a redfa class.
+const DIGITS: &str = "0-9";
+const WORD: &str = "0-9A-Z_a-z";
+const SPACE: &str = " \t\n\r\x0b\x0c";
+
+// The body of the shorthand class after a '\\' (if the next char names one) and whether it is
+// negated (\D, \W, and \S).
+fn shorthand(chars: &Chars) -> Option<(&'static str, bool)> {
+    match chars.clone().next() {
+        Some('d') => Some((DIGITS, false)),
+        Some('w') => Some((WORD, false)),
+        Some('s') => Some((SPACE, false)),
+        Some('D') => Some((DIGITS, true)),
+        Some('W') => Some((WORD, true)),
+        Some('S') => Some((SPACE, true)),
+        _ => None,
+    }
+}
+
+// The operation that combines two operands of a character class.
+#[derive(Clone, Copy)]
+enum ClassOp {
+    Intersection,
+    Difference,
+}
+
+// An operand of a character class: the body of a redfa class for its ranges and the
+// translations of its nested classes (which are unioned with the ranges).
+#[derive(Default)]
+struct Operand {
+    body: String,
+    nested: Vec<String>,
+}
+
+impl Operand {
+    fn shorthand(body: &str, negated: bool) -> Operand {
+        let mut operand = Operand::default();
+        if negated {
+            operand.nested.push(format!("[^{}]", body));
+        } else {
+            operand.body.push_str(body);
+        }
+        operand
+    }
+
+    fn render(&self) -> String {
+        let mut alternatives = Vec::new();
+        if !self.body.is_empty() || self.nested.is_empty() {
+            alternatives.push(format!("[{}]", self.body));
+        }
+        alternatives.extend(self.nested.iter().cloned());
+
+        if alternatives.len() == 1 {
+            alternatives.pop().unwrap()
+        } else {
+            format!("({})", alternatives.join("|"))
+        }
+    }
+}
+
+// Translates the character class after a '[' (up to and including its ']').
+//
+// A class that has no nested classes or operators is translated as is (apart from escapes).
+// Otherwise it is lowered onto the and (&) and not (~) operators of redfa, as "(A&B)" for the
+// intersection "A&&B" of two operands and "(A&~B)" for the difference "A--B".
+fn class(chars: &mut Chars) -> StdResult<String, String> {
+    let negated = chars.clone().next() == Some('^');
+    if negated {
+        chars.next();
+    }
+
+    let mut operands = vec![Operand::default()];
+    let mut ops = Vec::new();
+
+    // A ']' at the start of a class is a literal.
+    if chars.clone().next() == Some(']') {
+        chars.next();
+        operands[0].body.push_str("\\]");
+    }
+
+    loop {
+        let c = chars.next().ok_or("the regex has an unclosed character class")?;
+        let mut rest = chars.clone();
+        let next = rest.next();
+
+        match (c, next) {
+            (']', _) => break,
+            ('&', Some('&')) | ('-', Some('-')) if next_starts_operand(&rest) => {
+                chars.next();
+                ops.push(if c == '&' {
+                    ClassOp::Intersection
+                } else {
+                    ClassOp::Difference
+                });
+                operands.push(Operand::default());
+            }
+            ('[', _) => {
+                let nested = class(chars)?;
+                operands.last_mut().unwrap().nested.push(nested);
+            }
+            ('\\', _) => {
+                let operand = operands.last_mut().unwrap();
+                match shorthand(chars) {
+                    Some((body, negated)) => {
+                        chars.next();
+                        let shorthand = Operand::shorthand(body, negated);
+                        operand.body.push_str(&shorthand.body);
+                        operand.nested.extend(shorthand.nested);
+                    }
+                    None => match escape(chars)? {
+                        Some(c) => push_literal(&mut operand.body, c, true),
+                        None => {
+                            operand.body.push(c);
+                            operand.body.extend(chars.next());
+                        }
+                    },
+                }
+            }
+            (c, _) => operands.last_mut().unwrap().body.push(c),
+        }
+    }
+
+    if ops.is_empty() && operands[0].nested.is_empty() {
+        let operand = &operands[0];
+        return Ok(format!("[{}{}]", if negated { "^" } else { "" }, operand.body));
+    }
+
+    let mut operands = operands.iter().map(Operand::render);
+    let mut translated = operands.next().unwrap();
+    for (op, operand) in ops.into_iter().zip(operands) {
+        translated = match op {
+            ClassOp::Intersection => format!("({}&{})", translated, operand),
+            ClassOp::Difference => format!("({}&~{})", translated, operand),
+        };
+    }
+
+    if negated {
+        translated = format!("(.&~{})", translated);
+    }
+    Ok(translated)
+}
+
+// Tests for the rest of a class after "&&" or "--" starting an operand (rather than being
+// literal chars, as in "[+--]").
+fn next_starts_operand(rest: &Chars) -> bool {
+    match rest.clone().next() {
+        Some('[') | Some('\\') => true,
+        _ => false,
+    }
+}
+
+// Reads the escape after a '\\' and returns the char that it stands for, or None if the
+// escaped char is passed through to the redfa parser as is.
+fn escape(chars: &mut Chars) -> StdResult<Option<char>, String> {
+    let c = match chars.clone().next() {
+        Some('x') => {
+            chars.next();
+            let digits: String = chars.by_ref().take(2).collect();
+            if digits.len() != 2 {
+                return Err(format!("invalid escape `\\x{}`", digits));
+            }
+            return hex(&digits, "\\x").map(Some);
+        }
+        Some('u') => {
+            chars.next();
+            if chars.next() != Some('{') {
+                return Err("expected `{` after `\\u`".to_string());
+            }
+            let mut digits = String::new();
+            let mut closed = false;
+            for c in chars.by_ref() {
+                if c == '}' {
+                    closed = true;
+                    break;
+                }
+                digits.push(c);
+            }
+            if !closed || digits.is_empty() || digits.len() > 6 {
+                return Err(format!("invalid escape `\\u{{{}}}`", digits));
+            }
+            return hex(&digits, "\\u").map(Some);
+        }
+        Some('0') => '\0',
+        Some('n') => '\n',
+        Some('r') => '\r',
+        Some('t') => '\t',
+        Some('f') => '\x0c',
+        Some('v') => '\x0b',
+        Some('a') => '\x07',
+        Some('e') => '\x1b',
+        Some(_) => return Ok(None),
+        None => return Err("the regex ends with an unfinished escape".to_string()),
+    };
+    chars.next();
+    Ok(Some(c))
+}
+
+fn hex(digits: &str, escape: &str) -> StdResult<char, String> {
+    u32::from_str_radix(digits, 16)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or_else(|| format!("invalid escape `{}{}`", escape, digits))
+}
+
+// Pushes c so that the redfa parser reads it as a literal (in or out of a character class).
+fn push_literal(translated: &mut String, c: char, in_class: bool) {
+    let special = if in_class {
+        "\\]^-["
+    } else {
+        "\\|&~*+?()[].{}^$"
+    };
+    if special.contains(c) {
+        translated.push('\\');
+    }
+    translated.push(c);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn dot_matches_newline_by_default() {
+        let sut = Syntax::default();
+
+        assert_eq!(sut.translate("a.b"), Ok("a.b".to_string()));
+    }
+
+    #[test]
+    fn dot_excludes_line_terminators_when_configured() {
+        let sut = Syntax {
+            dot_matches_newline: false,
+            ..Syntax::default()
+        };
+
+        assert_eq!(
+            sut.translate("a.[.]\\."),
+            Ok("a[^\\n\\r\u{85}\u{2028}\u{2029}][.]\\.".to_string())
+        );
+    }
+
+    #[test]
+    fn escapes_are_translated_to_literals() {
+        let sut = Syntax::default();
+
+        assert_eq!(
+            sut.translate("\\x41\\u{1F600}\\0\\e\\x2a"),
+            Ok("A\u{1f600}\0\x1b\\*".to_string())
+        );
+        assert_eq!(sut.translate("[\\x00-\\x1f\\u{5d}]"), Ok("[\0-\x1f\\]]".to_string()));
+        assert_eq!(sut.translate("\\."), Ok("\\.".to_string()));
+    }
+
+    #[test]
+    fn shorthand_classes_are_expanded() {
+        let sut = Syntax::default();
+
+        assert_eq!(sut.translate("\\d+"), Ok("[0-9]+".to_string()));
+        assert_eq!(sut.translate("[\\w.]"), Ok("[0-9A-Z_a-z.]".to_string()));
+        assert_eq!(sut.translate("\\S"), Ok("[^ \t\n\r\x0b\x0c]".to_string()));
+    }
+
+    #[test]
+    fn class_operations_are_lowered_to_and_and_not() {
+        let sut = Syntax::default();
+
+        assert_eq!(
+            sut.translate("[[a-z]&&[^aeiou]]"),
+            Ok("([a-z]&[^aeiou])".to_string())
+        );
+        assert_eq!(sut.translate("[\\w--\\d]"), Ok("([0-9A-Z_a-z]&~[0-9])".to_string()));
+        assert_eq!(sut.translate("[^_[0-9]]"), Ok("(.&~([_]|[0-9]))".to_string()));
+        assert_eq!(sut.translate("[+--]"), Ok("[+--]".to_string()));
+    }
+
+    #[test]
+    fn class_operations_match_the_expected_chars() {
+        let token = ::tokens::TokenDef::new("Consonant", "[[a-z]&&[^aeiou]]+");
+        let automaton = ::automaton::Automaton::new(vec![token])
+            .expect("unexpected error building the automaton");
+
+        assert_eq!(automaton.longest_match("bcda"), Some((0, 3)));
+        assert_eq!(automaton.longest_match("a"), None);
+    }
+
+    #[test]
+    fn free_spacing_ignores_whitespace_and_comments() {
+        let regex = "(?x)
+            [0-9]+      # the integer part
+            ( \\. [0-9]+ )?  # the fraction
+            \\ [ ]\\#";
+
+        assert_eq!(
+            Syntax::default().translate(regex),
+            Ok("[0-9]+(\\.[0-9]+)?\\ [ ]\\#".to_string())
+        );
+    }
+
+    #[test]
+    fn invalid_escapes_are_errors() {
+        let sut = Syntax::default();
+
+        assert!(sut.translate("\\x4").is_err());
+        assert!(sut.translate("\\u{110000}").is_err());
+        assert!(sut.translate("\\u41").is_err());
+        assert!(sut.translate("\\u{41").is_err());
+        assert!(sut.translate("a\\").is_err());
+    }
+}
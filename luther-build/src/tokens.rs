@@ -0,0 +1,216 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+use std::fs;
+use std::path::Path;
+
+use failure::Error;
+use serde_json;
+use toml;
+
+use syntax::Syntax;
+
+/// The name of the mode for tokens that do not name one.
+pub const DEFAULT_MODE: &str = "INITIAL";
+
+/// The definition of a single token: its name and the regular expression that it matches.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenDef {
+    pub name: String,
+    pub regex: String,
+
+    /// The priority group of the token (a smaller number is a higher priority).
+    #[serde(default = "default_priority_group")]
+    pub priority_group: u32,
+
+    /// The lexer mode in which the token is recognized (the default mode if `None`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+
+    /// Whether the token is recognized but not produced (like whitespace or comments).
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub skip: bool,
+
+    /// Whether the token must be followed by a `char` that is not a word `char` (an
+    /// alphanumeric `char` or `_`) or by the end of the input.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub word_boundary: bool,
+
+    /// The fewest `char`'s that a match of the token may have (checked while scanning).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_len: Option<usize>,
+
+    /// The most `char`'s that a match of the token may have (checked while scanning).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_len: Option<usize>,
+
+    /// The feature groups of the token (such as a dialect of the language). A token in no
+    /// group is in every subset of the tokens (see `select`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+
+    /// The syntax options for the regex (from the file that defines the token).
+    #[serde(skip)]
+    pub syntax: Syntax,
+}
+
+impl TokenDef {
+    pub fn new<N: Into<String>, R: Into<String>>(name: N, regex: R) -> TokenDef {
+        TokenDef {
+            name: name.into(),
+            regex: regex.into(),
+            priority_group: default_priority_group(),
+            mode: None,
+            skip: false,
+            word_boundary: false,
+            min_len: None,
+            max_len: None,
+            groups: Vec::new(),
+            syntax: Syntax::default(),
+        }
+    }
+
+    /// The name of the lexer mode in which the token is recognized.
+    pub fn mode_name(&self) -> &str {
+        self.mode.as_ref().map_or(DEFAULT_MODE, |mode| mode.as_str())
+    }
+
+    /// Whether the token is in the subset of the tokens for the enabled feature `groups`.
+    pub fn is_enabled(&self, groups: &[String]) -> bool {
+        self.groups.is_empty() || self.groups.iter().any(|group| groups.contains(group))
+    }
+}
+
+fn default_priority_group() -> u32 {
+    1
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// The contents of a token definitions file.
+///
+/// In TOML this is a list of `[[token]]` tables. In JSON this is an object with a
+/// `token` array. Each token has these fields:
+///
+/// - `name` (required): the name of the token
+/// - `regex` (required): the regular expression that the token matches
+/// - `priority_group` (default 1): the priority group of the token, where a smaller number is a
+/// higher priority
+/// - `mode` (default `"INITIAL"`): the lexer mode in which the token is recognized
+/// - `skip` (default false): whether the token is recognized but not produced
+/// - `word_boundary` (default false): whether the token must be followed by a `char` that is
+/// not alphanumeric or `_` (or by the end of the input), such as for a keyword
+/// - `min_len` and `max_len` (default none): the fewest and the most `char`'s that a match of
+/// the token may have (checked as the input is scanned, see `automaton::Automaton::scan_at`)
+/// - `groups` (default none): the feature groups of the token, such as `["edition2021"]`; a
+/// token in one or more groups is compiled only when one of them is enabled (see `select`)
+///
+/// These have the same meaning as the `regex`, `priority_group`, `mode`, and `skip` options of
+/// the `luther` attribute, except that a `regex` may also start with a `^` anchor or end with a
+/// `$` anchor (see `automaton::Automaton`). A literal `^` or `$` there must be escaped. The
+/// anchors, `word_boundary`, and the length limits are checked only by the matching in
+/// `automaton`, so exporting the dfa for a token that uses them is an error (see
+/// `binary::check`).
+///
+/// The optional `[syntax]` table has the options for the syntax of all of the regexes in the
+/// file (see `syntax::Syntax`):
+///
+/// - `dot_matches_newline` (default true): whether `.` matches a line terminator
+/// - `free_spacing` (default false): whether whitespace is ignored and `#` starts a comment
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TokenFile {
+    #[serde(default, skip_serializing_if = "Syntax::is_default")]
+    pub syntax: Syntax,
+
+    #[serde(default)]
+    pub token: Vec<TokenDef>,
+}
+
+/// Loads the token definitions from `path`.
+///
+/// The format of the file is determined by its extension, either `.toml` or `.json`.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<TokenDef>, Error> {
+    let path = path.as_ref();
+    let contents = fs::read_to_string(path)?;
+
+    let file: TokenFile = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => toml::from_str(&contents)?,
+        Some("json") => serde_json::from_str(&contents)?,
+        _ => bail!(
+            "unable to determine the format of {} (expected a .toml or .json file)",
+            path.display()
+        ),
+    };
+
+    if file.token.is_empty() {
+        bail!("{} does not define any tokens", path.display());
+    }
+
+    let syntax = file.syntax;
+    Ok(file.token
+        .into_iter()
+        .map(|token| TokenDef {
+            syntax: syntax.clone(),
+            ..token
+        })
+        .collect())
+}
+
+/// Selects the subset of `tokens` for the enabled feature `groups`.
+///
+/// This keeps the tokens that are in no group and the tokens that are in at least one of
+/// `groups`, so that one token definitions file can serve several dialects of a language.
+/// It is an error for a group to be enabled that no token is in, since that is most likely a
+/// misspelling.
+pub fn select(tokens: Vec<TokenDef>, groups: &[String]) -> Result<Vec<TokenDef>, Error> {
+    for group in groups {
+        if !tokens.iter().any(|token| token.groups.contains(group)) {
+            bail!("no token is in the feature group \"{}\"", group);
+        }
+    }
+
+    Ok(tokens
+        .into_iter()
+        .filter(|token| token.is_enabled(groups))
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn grouped(name: &str, groups: &[&str]) -> TokenDef {
+        TokenDef {
+            groups: groups.iter().map(|group| group.to_string()).collect(),
+            ..TokenDef::new(name, "a")
+        }
+    }
+
+    #[test]
+    fn select_keeps_ungrouped_tokens_and_enabled_groups() {
+        let tokens = vec![
+            grouped("Base", &[]),
+            grouped("Async", &["edition2018", "edition2021"]),
+            grouped("Ext", &["extensions"]),
+        ];
+
+        let result = select(tokens, &["edition2021".to_string()]).expect("unexpected error");
+
+        let names: Vec<_> = result.iter().map(|token| token.name.as_str()).collect();
+        assert_eq!(names, vec!["Base", "Async"]);
+    }
+
+    #[test]
+    fn select_rejects_unknown_group() {
+        let tokens = vec![grouped("Base", &[])];
+
+        assert!(select(tokens, &["edition2021".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Benchmarks of the derived lexers for some standard corpora.
+//!
+//! Each corpus is lexed by lexers generated with the `direct` and the `tables` codegen
+//! strategies from `char` input, and by the `direct` lexer from utf8 encoded bytes.
+
+#[macro_use]
+extern crate criterion;
+extern crate luther;
+#[macro_use]
+extern crate luther_derive;
+
+use criterion::Criterion;
+use luther::Lexer;
+use luther::spanned::StrExt;
+
+macro_rules! json_token {
+    ($name:ident, $codegen:tt) => {
+        #[derive(Lexer, Debug)]
+        #[luther(codegen = $codegen)]
+        enum $name {
+            #[luther(regex = "\\{")] LBrace,
+            #[luther(regex = "\\}")] RBrace,
+            #[luther(regex = "\\[")] LBracket,
+            #[luther(regex = "\\]")] RBracket,
+            #[luther(regex = ":")] Colon,
+            #[luther(regex = ",")] Comma,
+            #[luther(regex = "true")] True,
+            #[luther(regex = "false")] False,
+            #[luther(regex = "null")] Null,
+            #[luther(regex = "-?(0|[1-9][0-9]*)(\\.[0-9]+)?([eE][+-]?[0-9]+)?")] Number,
+            #[luther(regex = "\"([^\"\\\\]|\\\\.)*\"")] Str,
+            #[luther(regex = "[ \t\r\n]+", skip)] WhiteSpace,
+        }
+    };
+}
+
+json_token!(JsonDirect, "direct");
+json_token!(JsonTables, "tables");
+
+macro_rules! c_token {
+    ($name:ident, $codegen:tt) => {
+        #[derive(Lexer, Debug)]
+        #[luther(codegen = $codegen)]
+        enum $name {
+            #[luther(regex = "[a-zA-Z_][a-zA-Z0-9_]*",
+                     keywords(If = "if", Else = "else", While = "while", Return = "return",
+                              Int = "int", Char = "char", Void = "void"))]
+            Ident,
+            If,
+            Else,
+            While,
+            Return,
+            Int,
+            Char,
+            Void,
+            #[luther(regex = "[0-9]+")] Constant,
+            #[luther(regex = "\"([^\"\\\\\n]|\\\\.)*\"")] StringLiteral,
+            #[luther(regex = "[-+*/%=<>!&|^~]|==|!=|<=|>=|\\&\\&|\\|\\||\\+\\+|--|->")] Operator,
+            #[luther(regex = "[(){}\\[\\];,.]")] Punctuation,
+            #[luther(regex = "//[^\n]*", skip)] Comment,
+            #[luther(regex = "[ \t\r\n]+", skip)] WhiteSpace,
+        }
+    };
+}
+
+c_token!(CDirect, "direct");
+c_token!(CTables, "tables");
+
+macro_rules! log_token {
+    ($name:ident, $codegen:tt) => {
+        #[derive(Lexer, Debug)]
+        #[luther(codegen = $codegen)]
+        enum $name {
+            #[luther(regex = "[0-9][0-9][0-9][0-9]-[0-9][0-9]-[0-9][0-9]T[0-9:.]+Z")] Timestamp,
+            #[luther(regex = "INFO|WARN|ERROR|DEBUG")] Level,
+            #[luther(regex = "\\[[a-z:_]+\\]")] Module,
+            #[luther(regex = "[a-z_]+=[^ \n]*")] Field,
+            #[luther(regex = "[^ \n]+", priority_group = "2")] Word,
+            #[luther(regex = "[ \n]+", skip)] WhiteSpace,
+        }
+    };
+}
+
+log_token!(LogDirect, "direct");
+log_token!(LogTables, "tables");
+
+const JSON_SAMPLE: &str = r#"{"id": 1234, "name": "luther", "tags": ["lexer", "dfa"],
+"ratio": -12.5e-3, "active": true, "parent": null, "nested": {"a": [1, 2, 3]}}
+"#;
+
+const C_SAMPLE: &str = r#"int main(void) {
+    // count the characters
+    int count = 0;
+    while (count < 100 && input[count] != 0) {
+        count++;
+    }
+    if (count > 10) return count; else return -1;
+}
+"#;
+
+const LOG_SAMPLE: &str = "2018-03-04T12:34:56.789Z INFO [server::http] request method=GET \
+path=/index.html status=200 took=12ms\n2018-03-04T12:34:57.001Z WARN [server::db] slow query \
+table=users rows=1024\n";
+
+fn corpus(sample: &str) -> String {
+    sample.repeat(200)
+}
+
+macro_rules! bench_corpus {
+    ($fn_name:ident, $sample:expr, $direct:ident, $tables:ident, $label:expr) => {
+        fn $fn_name(c: &mut Criterion) {
+            c.bench_function(concat!($label, " direct chars"), |b| {
+                let input = corpus($sample);
+                b.iter(|| $direct::lexer(input.spanned_chars()).count())
+            });
+            c.bench_function(concat!($label, " tables chars"), |b| {
+                let input = corpus($sample);
+                b.iter(|| $tables::lexer(input.spanned_chars()).count())
+            });
+            c.bench_function(concat!($label, " direct bytes"), |b| {
+                let input = corpus($sample);
+                b.iter(|| $direct::byte_lexer(input.bytes().map(Ok)).count())
+            });
+        }
+    };
+}
+
+bench_corpus!(bench_json, JSON_SAMPLE, JsonDirect, JsonTables, "json");
+bench_corpus!(bench_c, C_SAMPLE, CDirect, CTables, "c");
+bench_corpus!(bench_log, LOG_SAMPLE, LogDirect, LogTables, "log");
+
+criterion_group!(benches, bench_json, bench_c, bench_log);
+criterion_main!(benches);
@@ -0,0 +1,188 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use proc_macro2::TokenStream;
+
+use enum_info::EnumInfo;
+use dump::{manifest_relative, DUMP_DIR_VAR};
+
+/// The environment variable naming the directory in which to cache the generated lexers.
+const CACHE_DIR_VAR: &str = "LUTHER_CACHE_DIR";
+
+/// A lexer generated by an earlier build.
+pub struct Cached {
+    /// The warnings reported when the lexer was generated.
+    pub warnings: Vec<String>,
+
+    /// The source of the generated lexer.
+    pub source: String,
+}
+
+/// `Cache` is the entry in the cache for an `enum` for which `Lexer` is being derived.
+///
+/// The entry is a file named for the dfa and a hash of the inputs of the generated lexer: the
+/// source of the `enum` (including its `luther` attributes), the contents of the files named by
+/// its `regex_file` and `fragment_file` options, and the version number of luther-derive. A
+/// change to any of them is a different entry. A change to the code generation of luther-derive
+/// that keeps its version number (such as in a git or path dependency) is not, so such an entry
+/// is stale until the cache directory is deleted.
+pub struct Cache {
+    path: PathBuf,
+}
+
+impl Cache {
+    /// The entry for the `enum` with `info` and `source`, if caching is enabled.
+    ///
+    /// The cache is in the directory named by the `LUTHER_CACHE_DIR` environment variable
+    /// (relative to the directory of the crate being compiled). Caching is disabled if
+    /// `LUTHER_CACHE_DIR` is not set or is empty, or if the dfa or the source are to be dumped
+    /// (since that needs them to be generated).
+    pub fn new(info: &EnumInfo, source: &str) -> Option<Cache> {
+        if info.dump_dfa.is_some() || info.dump_source.is_some()
+            || env::var_os(DUMP_DIR_VAR).is_some()
+        {
+            return None;
+        }
+
+        let dir = match env::var_os(CACHE_DIR_VAR) {
+            Some(ref dir) if !dir.is_empty() => manifest_relative(dir.into()),
+            _ => return None,
+        };
+
+        let mut hash = Fnv::new();
+        hash.write(env!("CARGO_PKG_VERSION").as_bytes());
+        hash.write(source.as_bytes());
+        for path in info.regex_files.iter() {
+            hash.write(path.to_string_lossy().as_bytes());
+            hash.write(&fs::read(path).unwrap_or_default());
+        }
+
+        Some(Cache {
+            path: dir.join(format!("{}-{:016x}.rs", info.dfa_name, hash.finish())),
+        })
+    }
+
+    /// Reads the lexer from the entry, if an earlier build wrote it.
+    ///
+    /// An entry whose source is not valid Rust tokens (such as one that was truncated or
+    /// edited) is discarded so that the lexer is generated (and stored) again.
+    pub fn load(&self) -> Option<Cached> {
+        let contents = fs::read_to_string(&self.path).ok()?;
+
+        let mut warnings = Vec::new();
+        let mut rest = contents.as_str();
+        while rest.starts_with("// ") {
+            let end = rest.find('\n')?;
+            warnings.push(rest[3..end].to_string());
+            rest = &rest[end + 1..];
+        }
+
+        if rest.parse::<TokenStream>().is_err() {
+            self.discard();
+            return None;
+        }
+
+        Some(Cached {
+            warnings,
+            source: rest.to_string(),
+        })
+    }
+
+    /// Removes the entry (ignoring any error, as for `store`).
+    pub fn discard(&self) {
+        let _ = fs::remove_file(&self.path);
+    }
+
+    /// Writes the lexer to the entry.
+    ///
+    /// The cache is only an optimization so any error writing it is ignored. The entry is
+    /// written to a temporary file first so that a concurrent build never reads part of it.
+    pub fn store(&self, warnings: &[String], source: &str) {
+        let mut contents = String::new();
+        for warning in warnings {
+            contents.push_str("// ");
+            contents.push_str(warning);
+            contents.push('\n');
+        }
+        contents.push_str(source);
+
+        let temp = self.path.with_extension(format!("{}.tmp", process::id()));
+        let _ = self.path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&temp, contents))
+            .and_then(|_| fs::rename(&temp, &self.path));
+        let _ = fs::remove_file(&temp);
+    }
+}
+
+// The 64 bit FNV-1a hash (which, unlike the hasher in the standard library, is the same on
+// every version of Rust).
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Fnv {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+        // separate the parts so that moving bytes between them changes the hash
+        self.0 ^= 0xff;
+        self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(name: &str, contents: &str) -> Cache {
+        let path = env::temp_dir().join(format!(
+            "luther-cache-{}-{}.rs",
+            name,
+            process::id()
+        ));
+        fs::write(&path, contents).expect("could not write the cache entry");
+        Cache { path }
+    }
+
+    #[test]
+    fn load_reads_warnings_and_source() {
+        let sut = entry("valid", "// a warning\nimpl Foo {}");
+
+        let result = sut.load();
+        sut.discard();
+
+        let cached = result.expect("expected a cached lexer");
+        assert_eq!(cached.warnings, vec!["a warning".to_string()]);
+        assert_eq!(cached.source, "impl Foo {}");
+    }
+
+    #[test]
+    fn load_discards_corrupt_entry() {
+        let sut = entry("corrupt", "// a warning\nimpl Foo { fn transition(");
+
+        let result = sut.load();
+
+        assert!(result.is_none());
+        assert!(!sut.path.exists());
+    }
+}
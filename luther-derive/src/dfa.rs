@@ -9,13 +9,27 @@
 use std::cmp::Ordering;
 
 use enum_info::{EnumInfo, VariantInfo};
+use error::RegexError;
+use minimize::merge_states;
 use super::Dfa;
 use redfa::{self, Regex};
 use redfa::dfa::Normalize;
 
+/// The Dfa for a lexer together with the indexes of its distinguished states.
+pub struct LexerDfa<'info, 'ast: 'info> {
+    pub dfa: Dfa<'info, 'ast>,
+    pub error_state: usize,
+
+    /// The start state for each lexer mode (indexed by mode number).
+    pub start_states: Vec<usize>,
+
+    /// The normalized (and case folded) regex for each variant.
+    pub regexs: Vec<Regex<char>>,
+}
+
 /// build_dfa builds a Dfa from the information contained in the EnumInfo passed in.
 ///
-/// The return type is the Dfa and the index of the error state in that Dfa. The value
+/// The return type is the Dfa and the indexes of its error state and start states. The value
 /// attached to each state of the Dfa is an Option<VariantInfo>. It is None for non-accepting
 /// states and Some(vi) for accepting states where vi is the VariantInfo that corresponds
 /// to the variant matched by this state.
@@ -28,44 +42,101 @@ use redfa::dfa::Normalize;
 ///
 /// It is also an error if any of the regular expresions corresponding to one of variants matches
 /// the empty string since this would prevent the generated lexer from making progress.
-pub fn build_dfa<'info, 'ast: 'info>(info: &'info EnumInfo<'ast>) -> (Dfa<'info, 'ast>, usize) {
+///
+/// The regular expressions for variants marked as `case_insensitive` are case folded before
+/// the Dfa is built. The equivalent states of the Dfa are then merged according to the
+/// `minimize` option of the `enum`.
+///
+/// A regular expression that cannot be parsed or that matches the empty string is returned
+/// as a `RegexError`.
+///
+/// There is one start state for each lexer mode. The start state for a mode is the derivative
+/// state for the regular expressions of the variants in that mode (with Regex::Null in place
+/// of the variants in other modes). This allows all of the modes to share a single Dfa.
+pub fn build_dfa<'info, 'ast: 'info>(
+    info: &'info EnumInfo<'ast>,
+) -> Result<LexerDfa<'info, 'ast>, RegexError> {
     // parse the regex for the variants
-    let regexs: Result<Vec<Regex<char>>, _> = info.variants
+    let regexs: Vec<Regex<char>> = info.variants
+        .iter()
+        .map(|vi| {
+            vi.regex
+                .parse()
+                .map_err(|e| RegexError::invalid(vi, e))
+        })
+        .collect::<Result<_, _>>()?;
+
+    // find all the simple strings (before case folding so that case insensitive
+    // keywords are still preferred over identifiers)
+    let simple_strings: Vec<_> = regexs
+        .clone()
+        .normalize()
         .iter()
-        .map(|vi| vi.regex.parse().or_else(|e| Err((&vi.regex, e))))
+        .map(|re| is_simple_string(re))
         .collect();
+
+    // case fold the case insensitive regexs
     let regexs = regexs
-        .unwrap_or_else(|(re, e)| panic!("luther: invalid regex \"{}\":{}", re, e))
+        .into_iter()
+        .zip(info.variants.iter())
+        .map(|(re, vi)| if vi.case_insensitive { fold_case(re) } else { re })
+        .collect::<Vec<_>>()
         .normalize();
 
     // check for nullable regex
-    match regexs
+    if let Some(vi) = regexs
         .iter()
         .position(|re| re.nullable())
-        .map(|i| &info.variants[i].regex)
+        .map(|i| &info.variants[i])
     {
-        Some(re) => panic!("luther: regex \"{}\" matches the empty string", re),
-        _ => {}
+        return Err(RegexError::nullable(vi));
     }
 
-    // find all the simple strings
-    let simple_strings: Vec<_> = regexs.iter().map(|re| is_simple_string(re)).collect();
+    // create the start state for each mode
+    let starts: Vec<Vec<Regex<char>>> = (0..info.modes.len())
+        .map(|mode| {
+            regexs
+                .iter()
+                .zip(info.variants.iter())
+                .map(|(re, vi)| {
+                    if info.mode_index(&vi.mode) == mode {
+                        re.clone()
+                    } else {
+                        Regex::Null
+                    }
+                })
+                .collect()
+        })
+        .collect();
 
     // create the error state
     let error = vec![Regex::Null; regexs.len()];
 
     // create the dfa
-    let (dfa, map) = redfa::Dfa::from_derivatives(vec![regexs, error.clone()]);
+    let mut initial = starts.clone();
+    initial.push(error.clone());
+    let (dfa, map) = redfa::Dfa::from_derivatives(initial);
 
-    // find the error state
+    // find the error and start states
     let error_state = map[&error] as usize;
+    let start_states: Vec<_> = starts.iter().map(|start| map[start] as usize).collect();
 
     // map the states to accepting states
     let dfa = dfa.map(|re| {
         map_accepting_state(re.as_ref(), info.variants.as_ref(), simple_strings.as_ref())
     });
 
-    (dfa, error_state)
+    // merge the equivalent states
+    let (dfa, numbers) = merge_states(dfa, info.minimize);
+    let error_state = numbers[error_state];
+    let start_states = start_states.into_iter().map(|state| numbers[state]).collect();
+
+    Ok(LexerDfa {
+        dfa,
+        error_state,
+        start_states,
+        regexs,
+    })
 }
 
 fn map_accepting_state<'re, 'info, 'ast: 'info>(
@@ -149,3 +220,41 @@ fn is_simple_string(regex: &Regex<char>) -> bool {
         _ => false,
     }
 }
+
+fn fold_case(regex: Regex<char>) -> Regex<char> {
+    match regex {
+        Regex::Except(ts) => Regex::Except(fold_case_chars(ts)),
+        Regex::Alt(ts, res) => Regex::Alt(
+            fold_case_chars(ts),
+            res.into_iter().map(fold_case).collect(),
+        ),
+        Regex::And(res) => Regex::And(res.into_iter().map(fold_case).collect()),
+        Regex::Cat(res) => Regex::Cat(res.into_iter().map(fold_case).collect()),
+        Regex::Not(re) => Regex::Not(Box::new(fold_case(*re))),
+        Regex::Kleene(re) => Regex::Kleene(Box::new(fold_case(*re))),
+        Regex::Null => Regex::Null,
+        Regex::Empty => Regex::Empty,
+    }
+}
+
+// Adds the simple (single char) upper and lower case mappings of each char in ts.
+fn fold_case_chars(ts: Vec<char>) -> Vec<char> {
+    let mut folded: Vec<char> = ts.iter()
+        .flat_map(|&c| {
+            let lower = single_char(c.to_lowercase());
+            let upper = single_char(c.to_uppercase());
+            Some(c).into_iter().chain(lower).chain(upper)
+        })
+        .collect();
+
+    folded.sort();
+    folded.dedup();
+    folded
+}
+
+fn single_char<I: Iterator<Item = char>>(mut iter: I) -> Option<char> {
+    match (iter.next(), iter.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
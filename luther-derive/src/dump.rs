@@ -0,0 +1,175 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+
+use quote;
+
+use enum_info::EnumInfo;
+use dfa::LexerDfa;
+
+/// The environment variable naming a directory into which to dump every derived dfa.
+pub const DUMP_DIR_VAR: &str = "LUTHER_DUMP_DFA";
+
+/// Writes the dfa in the DOT format if requested by either the `dump_dfa` option on the
+/// `luther` attribute or the `LUTHER_DUMP_DFA` environment variable.
+///
+/// The `dump_dfa` option names the file to write. The environment variable names a directory
+/// into which a file named for the dfa (with a `.dot` extension) is written. Relative paths
+/// are relative to the directory of the crate being compiled.
+pub fn dump_dfa(info: &EnumInfo, dfa: &LexerDfa) {
+    let mut paths = Vec::new();
+    if let Some(ref path) = info.dump_dfa {
+        paths.push(manifest_relative(path.into()));
+    }
+    if let Some(dir) = env::var_os(DUMP_DIR_VAR) {
+        paths.push(manifest_relative(PathBuf::from(dir).join(format!("{}.dot", info.dfa_name))));
+    }
+
+    if paths.is_empty() {
+        return;
+    }
+
+    let dot = to_dot(info, dfa);
+    for path in paths {
+        write_file(&path, &dot);
+    }
+}
+
+/// Writes the generated source for the lexer if requested by the `dump_source` option on the
+/// `luther` attribute.
+///
+/// A relative path is relative to `OUT_DIR` if it is set (i.e. if the crate being compiled has
+/// a build script) and to the directory of the crate being compiled otherwise. The file is
+/// formatted with `rustfmt` if it is available.
+pub fn dump_source(info: &EnumInfo, source: &quote::Tokens) {
+    let path = match info.dump_source {
+        Some(ref path) => match env::var_os("OUT_DIR") {
+            Some(dir) => PathBuf::from(dir).join(path),
+            None => manifest_relative(path.into()),
+        },
+        None => return,
+    };
+
+    write_file(&path, &source.to_string());
+    let _ = Command::new("rustfmt").arg(&path).status();
+}
+
+/// Resolves `path` relative to the directory of the crate being compiled.
+pub fn manifest_relative(path: PathBuf) -> PathBuf {
+    match env::var_os("CARGO_MANIFEST_DIR") {
+        Some(dir) => PathBuf::from(dir).join(path),
+        None => path,
+    }
+}
+
+/// Writes `contents` to `path`, creating any missing parent directories.
+pub fn write_file(path: &PathBuf, contents: &str) {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).unwrap_or_else(|e| {
+            panic!("luther: unable to create directory {}: {}", parent.display(), e)
+        });
+    }
+
+    fs::File::create(path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .unwrap_or_else(|e| panic!("luther: unable to write {}: {}", path.display(), e));
+}
+
+fn to_dot(info: &EnumInfo, lexer_dfa: &LexerDfa) -> String {
+    let dfa = &lexer_dfa.dfa;
+    let error_state = lexer_dfa.error_state;
+    let mut dot = String::new();
+    let transitions: usize = dfa.states
+        .iter()
+        .map(|state| {
+            let by_char = state
+                .by_char
+                .values()
+                .filter(|&&to| to as usize != error_state)
+                .count();
+            by_char + if state.default as usize == error_state { 0 } else { 1 }
+        })
+        .sum();
+    let accepting = dfa.states.iter().filter(|s| s.value.is_some()).count();
+
+    writeln!(
+        dot,
+        "// {}: {} states, {} transitions, {} accepting states",
+        info.dfa_name,
+        dfa.states.len(),
+        transitions,
+        accepting
+    ).unwrap();
+    writeln!(dot, "digraph {} {{", info.dfa_name).unwrap();
+    writeln!(dot, "    rankdir=LR;").unwrap();
+
+    for (mode, &start) in lexer_dfa.start_states.iter().enumerate() {
+        writeln!(dot, "    start{} [shape=point];", mode).unwrap();
+        writeln!(
+            dot,
+            "    start{} -> State{} [label=\"{}\"];",
+            mode, start, info.modes[mode]
+        ).unwrap();
+    }
+
+    for (num, state) in dfa.states.iter().enumerate() {
+        if num == error_state {
+            continue;
+        }
+
+        let result = match state.value {
+            Some(vi) => writeln!(
+                dot,
+                "    State{} [shape=doublecircle, label=\"State{}\\n{}\"];",
+                num, num, vi.name
+            ),
+            None => writeln!(dot, "    State{} [shape=circle];", num),
+        };
+        result.unwrap();
+    }
+
+    for (num, state) in dfa.states.iter().enumerate() {
+        let mut labels: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+        for (c, &to) in state.by_char.iter() {
+            if to as usize != error_state {
+                labels.entry(to).or_insert_with(Vec::new).push(escape(*c));
+            }
+        }
+        if state.default as usize != error_state {
+            labels
+                .entry(state.default)
+                .or_insert_with(Vec::new)
+                .push("other".to_string());
+        }
+
+        for (to, label) in labels {
+            writeln!(
+                dot,
+                "    State{} -> State{} [label=\"{}\"];",
+                num,
+                to,
+                label.join(" ")
+            ).unwrap();
+        }
+    }
+
+    writeln!(dot, "}}").unwrap();
+    dot
+}
+
+fn escape(c: char) -> String {
+    let escaped: String = c.escape_default().collect();
+    format!("'{}'", escaped.replace('\\', "\\\\").replace('"', "\\\""))
+}
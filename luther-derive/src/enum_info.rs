@@ -7,17 +7,117 @@
 // except according to those terms
 
 use std::fmt;
+use std::fs;
+use std::path::PathBuf;
 
+use proc_macro2::Span;
 use syn::{self, visit};
 
+use dump::manifest_relative;
+use fragment::{expand_fragments, parse_fragments, Fragment};
+use posix;
+
 /// `EnumInfo` gathers the relevant information about an `enum`
 /// for which `Lexer` is being derived. The main way of constructing
 /// an `EnumInfo` is through its `From<syn::DeriveInput>` implementation.
 pub struct EnumInfo<'ast> {
     pub name: &'ast syn::Ident,
     pub dfa_name: String,
+    pub dump_dfa: Option<String>,
+    pub dump_source: Option<String>,
+
+    /// The name of the type alias for the lexer over utf8 encoded bytes.
+    pub byte_lexer: Option<String>,
+    pub codegen: Codegen,
+    pub minimize: Minimize,
     pub vis: &'ast syn::Visibility,
+    pub generics: &'ast syn::Generics,
     pub variants: Vec<VariantInfo<'ast>>,
+
+    /// The names of the lexer modes (indexed by mode number). The default mode is first.
+    pub modes: Vec<String>,
+
+    /// The variant for input that the lexer cannot match.
+    pub error_variant: Option<ErrorVariant<'ast>>,
+
+    /// The files from which the `regex_file` and `fragment_file` options were read.
+    pub regex_files: Vec<PathBuf>,
+
+    /// Whether to generate `name()` and `Display` for the `enum`.
+    pub display: bool,
+
+    /// The human readable name of every variant of the `enum`.
+    pub names: Vec<TokenName<'ast>>,
+
+    /// Whether to generate `kind_code()` and `from_code()` for the `enum`.
+    pub kind_codes: bool,
+
+    /// The numeric code of every variant of the `enum`.
+    pub codes: Vec<KindCode<'ast>>,
+}
+
+/// `TokenName` is the human readable name of a variant.
+pub struct TokenName<'ast> {
+    pub variant: &'ast syn::Ident,
+    pub has_field: bool,
+    pub name: String,
+}
+
+/// `KindCode` is the stable numeric code of a variant.
+pub struct KindCode<'ast> {
+    pub variant: &'ast syn::Ident,
+    pub has_field: bool,
+    pub code: u32,
+}
+
+/// `ErrorVariant` is the variant marked with the `error` option of the `luther` attribute.
+pub struct ErrorVariant<'ast> {
+    pub name: &'ast syn::Ident,
+    pub field: Option<&'ast syn::Field>,
+    pub with: Option<syn::Path>,
+}
+
+/// The name of the default lexer mode.
+pub const DEFAULT_MODE: &str = "INITIAL";
+
+impl<'ast> EnumInfo<'ast> {
+    /// The mode number for the mode named `mode`.
+    pub fn mode_index(&self, mode: &str) -> usize {
+        self.modes
+            .iter()
+            .position(|m| m == mode)
+            .unwrap_or_else(|| panic!("luther: {} is not a lexer mode", mode))
+    }
+}
+
+/// `ModeSwitch` describes the change to the lexer mode after matching a variant.
+pub enum ModeSwitch {
+    Push(String),
+    Pop,
+    Set(String),
+}
+
+/// `Codegen` is the strategy for generating the transition function of the dfa.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Codegen {
+    /// A `match` on the current state and the next `char`.
+    Direct,
+
+    /// A lookup in static tables of `char` ranges.
+    Tables,
+}
+
+/// `Minimize` is how the equivalent states of the dfa are merged.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Minimize {
+    /// The states are not merged.
+    None,
+
+    /// The states with the same transitions and accepted variant are merged.
+    Fast,
+
+    /// The dfa is minimized.
+    Full,
 }
 
 /// `VariantInfo` gathers the relevant information a variant of an `enum`
@@ -27,8 +127,24 @@ pub struct EnumInfo<'ast> {
 pub struct VariantInfo<'ast> {
     pub name: &'ast syn::Ident,
     pub regex: String,
+    pub regex_span: Span,
     pub priority_group: u32,
+    pub case_insensitive: bool,
     pub field: Option<&'ast syn::Field>,
+    pub with: Option<syn::Path>,
+    pub mode: String,
+    pub mode_switch: Option<ModeSwitch>,
+    pub skip: bool,
+    pub action: Option<syn::Path>,
+
+    /// The keywords that are recognized as other (unit) variants when matched by this variant.
+    pub keywords: Vec<Keyword>,
+}
+
+/// `Keyword` is an entry in the `keywords` option of the `luther` attribute.
+pub struct Keyword {
+    pub variant: syn::Ident,
+    pub keyword: String,
 }
 
 impl<'ast> From<&'ast syn::DeriveInput> for EnumInfo<'ast> {
@@ -38,14 +154,107 @@ impl<'ast> From<&'ast syn::DeriveInput> for EnumInfo<'ast> {
 
         let name = builder.name;
         let dfa_name = builder.dfa_name.unwrap_or_else(|| make_dfa_name(name));
+        let fragments = builder.fragments;
+        let posix = builder.syntax.map_or(false, |s| convert_syntax(&s));
+        let variants: Vec<VariantInfo> = builder
+            .variants
+            .into_iter()
+            .map(|vi| {
+                let regex = expand_fragments(&vi.regex, &fragments);
+                let regex = if posix {
+                    posix::translate(&regex).unwrap_or_else(|e| {
+                        panic!("luther: invalid POSIX regex for variant {}: {}", vi.name, e)
+                    })
+                } else {
+                    regex
+                };
+                VariantInfo { regex, ..vi }
+            })
+            .collect();
+
+        let modes = make_modes(&variants);
 
         EnumInfo {
             name,
             dfa_name,
+            dump_dfa: builder.dump_dfa,
+            dump_source: builder.dump_source,
+            byte_lexer: builder.byte_lexer,
+            codegen: builder.codegen.map_or(Codegen::Direct, |s| convert_codegen(&s)),
+            minimize: builder.minimize.map_or(Minimize::None, |s| convert_minimize(&s)),
             vis: builder.vis,
-            variants: builder.variants,
+            generics: &input.generics,
+            variants,
+            modes,
+            error_variant: builder.error_variant,
+            regex_files: builder.regex_files,
+            display: builder.display,
+            names: builder.names,
+            kind_codes: builder.kind_codes,
+            codes: builder.codes,
+        }
+    }
+}
+
+fn make_modes(variants: &[VariantInfo]) -> Vec<String> {
+    let mut modes = vec![DEFAULT_MODE.to_string()];
+    for vi in variants {
+        if !modes.contains(&vi.mode) {
+            modes.push(vi.mode.clone());
+        }
+    }
+
+    for vi in variants {
+        match vi.mode_switch {
+            Some(ModeSwitch::Push(ref mode)) | Some(ModeSwitch::Set(ref mode)) => {
+                if !modes.contains(mode) {
+                    panic!(
+                        "luther: variant {} switches to mode {} which has no variants",
+                        vi.name, mode
+                    );
+                }
+            }
+            _ => {}
         }
     }
+
+    modes
+}
+
+fn convert_codegen(s: &str) -> Codegen {
+    match s {
+        "direct" => Codegen::Direct,
+        "tables" => Codegen::Tables,
+        s => panic!(
+            "luther: {} is not a valid codegen option (expected \"direct\" or \"tables\")",
+            s
+        ),
+    }
+}
+
+fn convert_minimize(s: &str) -> Minimize {
+    match s {
+        "none" => Minimize::None,
+        "fast" => Minimize::Fast,
+        "full" => Minimize::Full,
+        s => panic!(
+            "luther: {} is not a valid minimize option \
+             (expected \"none\", \"fast\", or \"full\")",
+            s
+        ),
+    }
+}
+
+// Whether s names the POSIX syntax (rather than the default syntax).
+fn convert_syntax(s: &str) -> bool {
+    match s {
+        "luther" => false,
+        "posix" => true,
+        s => panic!(
+            "luther: {} is not a valid syntax option (expected \"luther\" or \"posix\")",
+            s
+        ),
+    }
 }
 
 fn make_dfa_name(name: &syn::Ident) -> String {
@@ -58,7 +267,20 @@ struct EnumInfoBuilder<'ast> {
     name: &'ast syn::Ident,
     vis: &'ast syn::Visibility,
     dfa_name: Option<String>,
+    dump_dfa: Option<String>,
+    dump_source: Option<String>,
+    byte_lexer: Option<String>,
+    codegen: Option<String>,
+    minimize: Option<String>,
+    syntax: Option<String>,
+    fragments: Vec<Fragment>,
     variants: Vec<VariantInfo<'ast>>,
+    error_variant: Option<ErrorVariant<'ast>>,
+    regex_files: Vec<PathBuf>,
+    display: bool,
+    names: Vec<TokenName<'ast>>,
+    kind_codes: bool,
+    codes: Vec<KindCode<'ast>>,
 }
 
 impl<'ast> EnumInfoBuilder<'ast> {
@@ -67,7 +289,20 @@ impl<'ast> EnumInfoBuilder<'ast> {
             name,
             vis,
             dfa_name: None,
+            dump_dfa: None,
+            dump_source: None,
+            byte_lexer: None,
+            codegen: None,
+            minimize: None,
+            syntax: None,
+            fragments: Vec::new(),
             variants: Vec::new(),
+            error_variant: None,
+            regex_files: Vec::new(),
+            display: false,
+            names: Vec::new(),
+            kind_codes: false,
+            codes: Vec::new(),
         } // COV_EXCL_LINE
     }
 }
@@ -93,7 +328,35 @@ impl<'ast> visit::Visit<'ast> for EnumInfoBuilder<'ast> {
                 let mut builder = LutherAttrBuilder::new();
                 visit::visit_meta(&mut builder, &m);
                 validate_luther_attr_for_enum(&builder);
-                self.dfa_name = builder.dfa_name;
+                if builder.dfa_name.is_some() {
+                    self.dfa_name = builder.dfa_name;
+                }
+                if builder.dump_dfa.is_some() {
+                    self.dump_dfa = builder.dump_dfa;
+                }
+                if builder.dump_source.is_some() {
+                    self.dump_source = builder.dump_source;
+                }
+                if builder.byte_lexer.is_some() {
+                    self.byte_lexer = builder.byte_lexer;
+                }
+                if builder.codegen.is_some() {
+                    self.codegen = builder.codegen;
+                }
+                if builder.minimize.is_some() {
+                    self.minimize = builder.minimize;
+                }
+                if builder.syntax.is_some() {
+                    self.syntax = builder.syntax;
+                }
+                self.fragments.extend(builder.fragments);
+                for file in builder.fragment_files {
+                    let path = manifest_relative(file.into());
+                    self.fragments.extend(parse_fragments(&read_file(&path)));
+                    self.regex_files.push(path);
+                }
+                self.display |= builder.display;
+                self.kind_codes |= builder.kind_codes;
             }
         }
     }
@@ -102,28 +365,131 @@ impl<'ast> visit::Visit<'ast> for EnumInfoBuilder<'ast> {
         let mut builder = VariantInfoBuilder::new(&i.ident);
         visit::visit_variant(&mut builder, i);
 
-        if builder.regex.is_none() {
+        self.names.push(TokenName {
+            variant: builder.name,
+            has_field: builder.field.is_some(),
+            name: builder
+                .display_name
+                .take()
+                .unwrap_or_else(|| builder.name.to_string()),
+        });
+        self.push_code(builder.name, builder.field.is_some(), builder.code.take());
+
+        if builder.error {
+            if builder.regex.is_some() || builder.regex_file.is_some() {
+                panic!("luther: error option not valid on a variant with the regex option");
+            }
+            if self.error_variant.is_some() {
+                panic!("luther: error option valid on only one variant");
+            }
+
+            let field = builder.field;
+            self.error_variant = Some(ErrorVariant {
+                name: builder.name,
+                field,
+                with: builder.with.map(|s| convert_with(s, field)),
+            });
             return;
         }
 
+        let regex = match (builder.regex, builder.regex_file) {
+            (Some(_), Some(_)) => {
+                panic!("luther: only one of regex or regex_file is allowed on a variant")
+            }
+            (Some(regex), None) => regex,
+            (None, Some(file)) => {
+                let path = manifest_relative(file.into());
+                let regex = read_regex_file(&path);
+                self.regex_files.push(path);
+                regex
+            }
+            (None, None) => return,
+        };
+
+        let field = builder.field;
         let info = VariantInfo {
             name: builder.name,
-            regex: builder.regex.unwrap(),
+            regex,
+            regex_span: builder.regex_span.unwrap_or_else(Span::call_site),
             priority_group: builder
                 .priority_group
                 .map_or(0, |s| convert_priority_group(s)),
-            field: builder.field,
+            case_insensitive: builder.case_insensitive,
+            field,
+            with: builder.with.map(|s| convert_with(s, field)),
+            mode: builder.mode.unwrap_or_else(|| DEFAULT_MODE.to_string()),
+            mode_switch: builder.mode_switch,
+            skip: builder.skip,
+            action: builder.action.map(|s| convert_action(&s)),
+            keywords: builder.keywords,
         }; // COV_EXCL_LINE
 
         self.variants.push(info);
     }
 }
 
+impl<'ast> EnumInfoBuilder<'ast> {
+    // Gives the variant its code from the code option, or else the code after the code of the
+    // variant before it (as for the discriminants of an enum).
+    fn push_code(&mut self, variant: &'ast syn::Ident, has_field: bool, code: Option<String>) {
+        let code = match code {
+            Some(code) => convert_code(&code),
+            None => self.codes.last().map_or(0, |prev| {
+                prev.code.checked_add(1).unwrap_or_else(|| {
+                    panic!("luther: the kind code of variant {} overflows u32", variant)
+                })
+            }),
+        };
+
+        if let Some(other) = self.codes.iter().find(|other| other.code == code) {
+            panic!(
+                "luther: variants {} and {} have the same kind code {}",
+                other.variant, variant, code
+            );
+        }
+
+        self.codes.push(KindCode {
+            variant,
+            has_field,
+            code,
+        });
+    }
+}
+
+// Reads the regex in path, ignoring any trailing newline.
+fn read_regex_file(path: &PathBuf) -> String {
+    read_file(path)
+        .trim_right_matches(|c| c == '\n' || c == '\r')
+        .to_string()
+}
+
+fn read_file(path: &PathBuf) -> String {
+    fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("luther: unable to read {}: {}", path.display(), e))
+}
+
 fn convert_priority_group(s: String) -> u32 {
     s.parse()
         .expect("luther: priority_group option on luther attribute must be an unsigned interger")
 }
 
+fn convert_code(s: &str) -> u32 {
+    s.parse()
+        .expect("luther: code option on luther attribute must be an unsigned interger")
+}
+
+fn convert_with(s: String, field: Option<&syn::Field>) -> syn::Path {
+    if field.is_none() {
+        panic!("luther: with option on luther attribute requires a tuple style variant");
+    }
+
+    syn::parse_str(&s).expect("luther: with option on luther attribute must be a function path")
+}
+
+fn convert_action(s: &str) -> syn::Path {
+    syn::parse_str(s).expect("luther: action option on luther attribute must be a function path")
+}
+
 fn is_luther_path(path: &syn::Path) -> bool {
     !path.global() && path.segments.len() == 1 && if let Some(pair) = path.segments.first() {
         pair.value().ident == "luther"
@@ -136,21 +502,96 @@ fn validate_luther_attr_for_enum(attr: &LutherAttrBuilder) {
     if attr.regex.is_some() {
         panic!("luther: regex option not valid on luther attribute for enum");
     }
+    if attr.regex_file.is_some() {
+        panic!("luther: regex_file option not valid on luther attribute for enum");
+    }
     if attr.priority_group.is_some() {
         panic!("luther: priority_group option not valid on luther attribute for enum");
     }
+    if attr.case_insensitive {
+        panic!("luther: case_insensitive option not valid on luther attribute for enum");
+    }
+    if attr.with.is_some() {
+        panic!("luther: with option not valid on luther attribute for enum");
+    }
+    if attr.mode.is_some() {
+        panic!("luther: mode option not valid on luther attribute for enum");
+    }
+    if attr.mode_switch.is_some() {
+        panic!("luther: mode switching options not valid on luther attribute for enum");
+    }
+    if attr.error {
+        panic!("luther: error option not valid on luther attribute for enum");
+    }
+    if attr.skip {
+        panic!("luther: skip option not valid on luther attribute for enum");
+    }
+    if attr.action.is_some() {
+        panic!("luther: action option not valid on luther attribute for enum");
+    }
+    if !attr.keywords.is_empty() {
+        panic!("luther: keywords option not valid on luther attribute for enum");
+    }
+    if attr.display_name.is_some() {
+        panic!("luther: display option with a value not valid on luther attribute for enum");
+    }
+    if attr.code.is_some() {
+        panic!("luther: code option not valid on luther attribute for enum");
+    }
 }
 
 fn validate_luther_attr_for_variant(attr: &LutherAttrBuilder) {
     if attr.dfa_name.is_some() {
         panic!("luther: dfa_name option not valid on luther attribute for variants");
     }
+    if !attr.fragments.is_empty() {
+        panic!("luther: fragment option not valid on luther attribute for variants");
+    }
+    if !attr.fragment_files.is_empty() {
+        panic!("luther: fragment_file option not valid on luther attribute for variants");
+    }
+    if attr.dump_dfa.is_some() {
+        panic!("luther: dump_dfa option not valid on luther attribute for variants");
+    }
+    if attr.dump_source.is_some() {
+        panic!("luther: dump_source option not valid on luther attribute for variants");
+    }
+    if attr.byte_lexer.is_some() {
+        panic!("luther: byte_lexer option not valid on luther attribute for variants");
+    }
+    if attr.display {
+        panic!("luther: display option requires a value on luther attribute for variants");
+    }
+    if attr.codegen.is_some() {
+        panic!("luther: codegen option not valid on luther attribute for variants");
+    }
+    if attr.minimize.is_some() {
+        panic!("luther: minimize option not valid on luther attribute for variants");
+    }
+    if attr.syntax.is_some() {
+        panic!("luther: syntax option not valid on luther attribute for variants");
+    }
+    if attr.kind_codes {
+        panic!("luther: kind_codes option not valid on luther attribute for variants");
+    }
 }
 
 struct VariantInfoBuilder<'ast> {
     name: &'ast syn::Ident,
     regex: Option<String>,
+    regex_span: Option<Span>,
+    regex_file: Option<String>,
     priority_group: Option<String>,
+    case_insensitive: bool,
+    with: Option<String>,
+    mode: Option<String>,
+    mode_switch: Option<ModeSwitch>,
+    error: bool,
+    skip: bool,
+    action: Option<String>,
+    keywords: Vec<Keyword>,
+    display_name: Option<String>,
+    code: Option<String>,
     field: Option<&'ast syn::Field>,
 }
 
@@ -159,7 +600,19 @@ impl<'ast> VariantInfoBuilder<'ast> {
         VariantInfoBuilder {
             name,
             regex: None,
+            regex_span: None,
+            regex_file: None,
             priority_group: None,
+            case_insensitive: false,
+            with: None,
+            mode: None,
+            mode_switch: None,
+            error: false,
+            skip: false,
+            action: None,
+            keywords: Vec::new(),
+            display_name: None,
+            code: None,
             field: None,
         }
     }
@@ -178,7 +631,19 @@ impl<'ast> visit::Visit<'ast> for VariantInfoBuilder<'ast> {
                 visit::visit_meta(&mut builder, &m);
                 validate_luther_attr_for_variant(&builder);
                 self.regex = builder.regex;
+                self.regex_span = builder.regex_span;
+                self.regex_file = builder.regex_file;
                 self.priority_group = builder.priority_group;
+                self.case_insensitive = builder.case_insensitive;
+                self.with = builder.with;
+                self.mode = builder.mode;
+                self.mode_switch = builder.mode_switch;
+                self.error = builder.error;
+                self.skip = builder.skip;
+                self.action = builder.action;
+                self.keywords = builder.keywords;
+                self.display_name = builder.display_name;
+                self.code = builder.code;
             }
         }
     }
@@ -198,8 +663,30 @@ impl<'ast> visit::Visit<'ast> for VariantInfoBuilder<'ast> {
 
 struct LutherAttrBuilder {
     dfa_name: Option<String>,
+    dump_dfa: Option<String>,
+    dump_source: Option<String>,
+    byte_lexer: Option<String>,
+    codegen: Option<String>,
+    minimize: Option<String>,
+    syntax: Option<String>,
     regex: Option<String>,
+    regex_span: Option<Span>,
+    regex_file: Option<String>,
     priority_group: Option<String>,
+    case_insensitive: bool,
+    with: Option<String>,
+    mode: Option<String>,
+    mode_switch: Option<ModeSwitch>,
+    error: bool,
+    skip: bool,
+    action: Option<String>,
+    keywords: Vec<Keyword>,
+    display: bool,
+    display_name: Option<String>,
+    kind_codes: bool,
+    code: Option<String>,
+    fragments: Vec<Fragment>,
+    fragment_files: Vec<String>,
     nested: bool,
 }
 
@@ -207,15 +694,100 @@ impl LutherAttrBuilder {
     fn new() -> Self {
         LutherAttrBuilder {
             dfa_name: None,
+            dump_dfa: None,
+            dump_source: None,
+            byte_lexer: None,
+            codegen: None,
+            minimize: None,
+            syntax: None,
             regex: None,
+            regex_span: None,
+            regex_file: None,
             priority_group: None,
+            case_insensitive: false,
+            with: None,
+            mode: None,
+            mode_switch: None,
+            error: false,
+            skip: false,
+            action: None,
+            keywords: Vec::new(),
+            display: false,
+            display_name: None,
+            kind_codes: false,
+            code: None,
+            fragments: Vec::new(),
+            fragment_files: Vec::new(),
             nested: false,
         }
     }
+
+    fn set_mode_switch(&mut self, switch: ModeSwitch) {
+        if self.mode_switch.is_some() {
+            panic!("luther: only one of push_mode, pop_mode, or set_mode is allowed");
+        }
+
+        self.mode_switch = Some(switch);
+    }
 }
 
 impl<'meta> visit::Visit<'meta> for LutherAttrBuilder {
+    fn visit_meta(&mut self, meta: &'meta syn::Meta) {
+        match meta {
+            &syn::Meta::Word(ref ident) => {
+                if !self.nested {
+                    panic!("luther: unrecognized form of luther attribute (word)");
+                }
+
+                match LutherAttrOption::from(ident.as_ref()) {
+                    LutherAttrOption::CaseInsensitive => self.case_insensitive = true,
+                    LutherAttrOption::PopMode => self.set_mode_switch(ModeSwitch::Pop),
+                    LutherAttrOption::Error => self.error = true,
+                    LutherAttrOption::Skip => self.skip = true,
+                    LutherAttrOption::Display => self.display = true,
+                    LutherAttrOption::KindCodes => self.kind_codes = true,
+                    key => panic!("luther: luther attribute option {} requires a value", key),
+                }
+            }
+            _ => visit::visit_meta(self, meta),
+        }
+    }
+
     fn visit_meta_list(&mut self, meta: &'meta syn::MetaList) {
+        if self.nested && meta.ident == "fragment" {
+            let fragments = meta.nested.iter().map(|nested| match nested {
+                &syn::NestedMeta::Meta(syn::Meta::NameValue(ref nv)) => {
+                    let mut option = LutherAttrOptionBuilder::new(LutherAttrOption::Fragment);
+                    visit::visit_lit(&mut option, &nv.lit);
+                    Fragment {
+                        name: nv.ident.to_string(),
+                        regex: option.value.expect("luther: fragment must be a string"),
+                    }
+                }
+                _ => panic!("luther: fragment option must be a list of name = \"regex\" pairs"),
+            });
+            self.fragments.extend(fragments);
+            return;
+        }
+
+        if self.nested && meta.ident == "keywords" {
+            let keywords = meta.nested.iter().map(|nested| match nested {
+                &syn::NestedMeta::Meta(syn::Meta::NameValue(ref nv)) => {
+                    let mut option = LutherAttrOptionBuilder::new(LutherAttrOption::Keywords);
+                    visit::visit_lit(&mut option, &nv.lit);
+                    Keyword {
+                        variant: nv.ident,
+                        keyword: option.value.expect("luther: keyword must be a string"),
+                    }
+                }
+                _ => panic!(
+                    "luther: keywords option must be a list of Variant = \"keyword\" pairs"
+                ),
+            });
+            self.keywords.extend(keywords);
+            return;
+        }
+
         if self.nested {
             panic!(
                 "luther: unregcognized form of luther attribute (meta_list); {}",
@@ -248,8 +820,34 @@ impl<'meta> visit::Visit<'meta> for LutherAttrBuilder {
 
         match option.key {
             LutherAttrOption::Dfa => self.dfa_name = option.value,
-            LutherAttrOption::Regex => self.regex = option.value,
+            LutherAttrOption::Regex => {
+                self.regex = option.value;
+                self.regex_span = option.span;
+            }
+            LutherAttrOption::RegexFile => {
+                self.regex_file = option.value;
+                self.regex_span = option.span;
+            }
             LutherAttrOption::PriorityGroup => self.priority_group = option.value,
+            LutherAttrOption::With => self.with = option.value,
+            LutherAttrOption::Action => self.action = option.value,
+            LutherAttrOption::Display => self.display_name = option.value,
+            LutherAttrOption::Code => self.code = option.value,
+            LutherAttrOption::DumpDfa => self.dump_dfa = option.value,
+            LutherAttrOption::DumpSource => self.dump_source = option.value,
+            LutherAttrOption::FragmentFile => self.fragment_files.extend(option.value),
+            LutherAttrOption::ByteLexer => self.byte_lexer = option.value,
+            LutherAttrOption::Codegen => self.codegen = option.value,
+            LutherAttrOption::Minimize => self.minimize = option.value,
+            LutherAttrOption::Syntax => self.syntax = option.value,
+            LutherAttrOption::Mode => self.mode = option.value,
+            LutherAttrOption::PushMode => {
+                self.set_mode_switch(ModeSwitch::Push(option.value.unwrap_or_default()))
+            }
+            LutherAttrOption::SetMode => {
+                self.set_mode_switch(ModeSwitch::Set(option.value.unwrap_or_default()))
+            }
+            key => panic!("luther: luther attribute option {} does not take a value", key),
         };
     }
 }
@@ -258,7 +856,29 @@ impl<'meta> visit::Visit<'meta> for LutherAttrBuilder {
 enum LutherAttrOption {
     Dfa,
     Regex,
+    RegexFile,
     PriorityGroup,
+    CaseInsensitive,
+    Fragment,
+    FragmentFile,
+    With,
+    DumpDfa,
+    DumpSource,
+    ByteLexer,
+    Codegen,
+    Minimize,
+    Syntax,
+    Mode,
+    PushMode,
+    PopMode,
+    SetMode,
+    Error,
+    Skip,
+    Action,
+    Keywords,
+    Display,
+    KindCodes,
+    Code,
 }
 
 impl<'a> From<&'a str> for LutherAttrOption {
@@ -268,7 +888,29 @@ impl<'a> From<&'a str> for LutherAttrOption {
         match value {
             "dfa" => Dfa,
             "regex" => Regex,
+            "regex_file" => RegexFile,
             "priority_group" => PriorityGroup,
+            "case_insensitive" => CaseInsensitive,
+            "fragment" => Fragment,
+            "fragment_file" => FragmentFile,
+            "with" => With,
+            "dump_dfa" => DumpDfa,
+            "dump_source" => DumpSource,
+            "byte_lexer" => ByteLexer,
+            "codegen" => Codegen,
+            "minimize" => Minimize,
+            "syntax" => Syntax,
+            "mode" => Mode,
+            "push_mode" => PushMode,
+            "pop_mode" => PopMode,
+            "set_mode" => SetMode,
+            "error" => Error,
+            "skip" => Skip,
+            "action" => Action,
+            "keywords" => Keywords,
+            "display" => Display,
+            "kind_codes" => KindCodes,
+            "code" => Code,
             s => panic!("luther: {} is not a valid luther attribute option", s),
         }
     }
@@ -281,7 +923,29 @@ impl fmt::Display for LutherAttrOption {
         let s = match self {
             &Dfa => "dfa",
             &Regex => "regex",
+            &RegexFile => "regex_file",
             &PriorityGroup => "priority_group", // COV_EXCL_LINE
+            &CaseInsensitive => "case_insensitive",
+            &Fragment => "fragment",
+            &FragmentFile => "fragment_file",
+            &With => "with",
+            &DumpDfa => "dump_dfa",
+            &DumpSource => "dump_source",
+            &ByteLexer => "byte_lexer",
+            &Codegen => "codegen",
+            &Minimize => "minimize",
+            &Syntax => "syntax",
+            &Mode => "mode",
+            &PushMode => "push_mode",
+            &PopMode => "pop_mode",
+            &SetMode => "set_mode",
+            &Error => "error",
+            &Skip => "skip",
+            &Action => "action",
+            &Keywords => "keywords",
+            &Display => "display",
+            &KindCodes => "kind_codes",
+            &Code => "code",
         };
 
         f.write_str(s)
@@ -291,17 +955,23 @@ impl fmt::Display for LutherAttrOption {
 struct LutherAttrOptionBuilder {
     key: LutherAttrOption,
     value: Option<String>,
+    span: Option<Span>,
 }
 
 impl LutherAttrOptionBuilder {
     fn new(key: LutherAttrOption) -> Self {
-        LutherAttrOptionBuilder { key, value: None }
+        LutherAttrOptionBuilder {
+            key,
+            value: None,
+            span: None,
+        }
     }
 }
 
 impl<'meta> visit::Visit<'meta> for LutherAttrOptionBuilder {
     fn visit_lit_str(&mut self, lit: &'meta syn::LitStr) {
         self.value = Some(lit.value());
+        self.span = Some(lit.span);
     }
 
     fn visit_lit_bool(&mut self, lit: &'meta syn::LitBool) {
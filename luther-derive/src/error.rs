@@ -0,0 +1,122 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+use std::fmt::Display;
+
+use proc_macro2::Span;
+use quote;
+
+use enum_info::VariantInfo;
+use syntax;
+
+/// An error in the regular expression for a variant.
+///
+/// Unlike most other errors in `luther_derive` (which panic), a `RegexError` is reported
+/// through a `compile_error!` invocation so that the error is attributed to the regular
+/// expression literal in the `luther` attribute.
+///
+/// The span of the error is the whole literal: `proc_macro2` has no way to make a span for
+/// part of a literal, so the compiler highlights all of it. Where the column of a syntax error
+/// is known the message points to it instead, with a caret under the regular expression.
+pub struct RegexError {
+    span: Span,
+    message: String,
+}
+
+impl RegexError {
+    /// Creates a `RegexError` for the variant `vi` whose regular expression could not be parsed.
+    ///
+    /// The message will point (with a caret) to the column in the regular expression at which
+    /// the error occurred if that column can be located. The span is the whole literal.
+    pub fn invalid<E: Display>(vi: &VariantInfo, parse_error: E) -> RegexError {
+        RegexError {
+            span: vi.regex_span,
+            message: invalid_message(&vi.name.to_string(), &vi.regex, parse_error),
+        }
+    }
+
+    /// Creates a `RegexError` for the variant `vi` whose regular expression matches the
+    /// empty string.
+    ///
+    /// The lexer would never make progress on such a regular expression. The message will
+    /// suggest `+` in place of `*` if the regular expression has a `*`.
+    pub fn nullable(vi: &VariantInfo) -> RegexError {
+        let suggestion = if vi.regex.contains('*') {
+            " (consider using `+` instead of `*`)"
+        } else {
+            ""
+        };
+
+        RegexError {
+            span: vi.regex_span,
+            message: format!(
+                "luther: regex \"{}\" for variant {} matches the empty string{}",
+                vi.regex, vi.name, suggestion
+            ),
+        }
+    }
+
+    /// Generates the `compile_error!` invocation for this error (spanned at the whole regular
+    /// expression literal).
+    pub fn into_compile_error(self) -> quote::Tokens {
+        let message = self.message;
+
+        quote_spanned!{self.span=>
+            compile_error!(#message);
+        }
+    }
+}
+
+// The message for an invalid regex, with a caret under the column of the error if it is known.
+fn invalid_message<E: Display>(name: &str, regex: &str, parse_error: E) -> String {
+    match syntax::check_syntax(regex) {
+        Err(e) => format!(
+            "luther: invalid regex for variant {}: expected {}\n    {}\n    {}^",
+            name,
+            e.expected,
+            regex,
+            " ".repeat(e.column)
+        ),
+        Ok(()) => format!(
+            "luther: invalid regex \"{}\" for variant {}: {}",
+            regex, name, parse_error
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn invalid_message_has_caret_under_error_column() {
+        let message = invalid_message("Abc", "a(b|c", "unbalanced");
+
+        let lines: Vec<_> = message.lines().collect();
+        assert_eq!(lines[0], "luther: invalid regex for variant Abc: expected `)`");
+        assert_eq!(lines[1], "    a(b|c");
+        assert_eq!(lines[2], "         ^");
+    }
+
+    #[test]
+    fn invalid_message_has_caret_under_misplaced_operator() {
+        let message = invalid_message("Star", "a|*b", "bad repetition");
+
+        assert!(message.ends_with("\n    a|*b\n      ^"));
+    }
+
+    #[test]
+    fn invalid_message_has_parse_error_without_known_column() {
+        let message = invalid_message("Range", "[z-a]", "invalid range");
+
+        assert_eq!(
+            message,
+            "luther: invalid regex \"[z-a]\" for variant Range: invalid range"
+        );
+    }
+}
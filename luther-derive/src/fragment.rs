@@ -0,0 +1,98 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+/// A named regular expression fragment that can be interpolated into a variant's regex.
+pub struct Fragment {
+    pub name: String,
+    pub regex: String,
+}
+
+/// Parses the fragments in the contents of a fragment file.
+///
+/// Each line of a fragment file is either blank, a comment starting with `#`, or a fragment
+/// of the form `name = regex`. Whitespace around the name and the regex is ignored.
+///
+/// # Panics
+/// `parse_fragments` will panic if a line is not of one of these forms.
+pub fn parse_fragments(source: &str) -> Vec<Fragment> {
+    source
+        .lines()
+        .enumerate()
+        .map(|(num, line)| (num, line.trim()))
+        .filter(|&(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(num, line)| match line.find('=') {
+            Some(eq) if !line[..eq].trim().is_empty() => Fragment {
+                name: line[..eq].trim().to_string(),
+                regex: line[eq + 1..].trim().to_string(),
+            },
+            _ => panic!(
+                "luther: line {} of fragment file is not of the form name = regex",
+                num + 1
+            ),
+        })
+        .collect()
+}
+
+/// Expands each `{name}` in `regex` where `name` is the name of one of the `fragments`.
+///
+/// Each expanded fragment is wrapped in parentheses so that it acts as a single unit
+/// in the surrounding regular expression. Fragments may themselves refer to other
+/// fragments. A `{` that is escaped with a `\` or that does not start the name of
+/// a fragment is left unaltered.
+///
+/// # Panics
+/// `expand_fragments` will panic if a fragment refers (directly or indirectly) to itself.
+pub fn expand_fragments(regex: &str, fragments: &[Fragment]) -> String {
+    let mut active = Vec::new();
+    expand(regex, fragments, &mut active)
+}
+
+fn expand<'f>(regex: &str, fragments: &'f [Fragment], active: &mut Vec<&'f str>) -> String {
+    let mut expanded = String::with_capacity(regex.len());
+    let mut rest = regex;
+
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '\\' => {
+                let len = rest[1..].chars().next().map_or(1, |c| c.len_utf8() + 1);
+                expanded.push_str(&rest[..len]);
+                rest = &rest[len..];
+            }
+            '{' => match find_fragment(&rest[1..], fragments) {
+                Some(fragment) => {
+                    if active.contains(&fragment.name.as_str()) {
+                        panic!("luther: fragment {} refers to itself", fragment.name);
+                    }
+
+                    active.push(&fragment.name);
+                    expanded.push('(');
+                    expanded.push_str(&expand(&fragment.regex, fragments, active));
+                    expanded.push(')');
+                    active.pop();
+
+                    rest = &rest[fragment.name.len() + 2..];
+                }
+                None => {
+                    expanded.push(c);
+                    rest = &rest[1..];
+                }
+            },
+            c => {
+                expanded.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    expanded
+}
+
+fn find_fragment<'f>(rest: &str, fragments: &'f [Fragment]) -> Option<&'f Fragment> {
+    rest.find('}')
+        .and_then(|end| fragments.iter().find(|f| f.name == rest[..end]))
+}
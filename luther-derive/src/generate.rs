@@ -9,90 +9,181 @@
 use syn::{self, Ident};
 use quote;
 use redfa;
-use enum_info;
+use enum_info::{self, Codegen, ModeSwitch};
+use dfa::LexerDfa;
 use super::Dfa;
 
 type State<'info, 'ast: 'info> = redfa::State<char, Option<&'info enum_info::VariantInfo<'ast>>>;
 
-/// Generates a dfa enum, implements `Default` and `luther::dfa::Dfa` for that
-/// enum, and implements `luther::Lexer` for the enum described in `info`.
+/// Generates a dfa type, implements `Default` and `luther::dfa::Dfa` for that
+/// type, and implements `luther::Lexer` for the enum described in `info`.
 ///
-/// The dfa enum will be named from `info.dfa_name` and will have variants "State0",
-/// "State1", "State2", etc. State0 is the `Default` variant and `error_state`
-/// designates which state will be recognized by `is_error()`.
+/// The dfa type will be named from `info.dfa_name`. For the `Direct` codegen strategy
+/// it is an enum with variants "State0", "State1", "State2", etc. For the `Tables`
+/// codegen strategy it is a tuple struct wrapping the state number. State0 is the
+/// `Default` state and `error_state` designates which state will be recognized by
+/// `is_error()`.
+///
+/// If the enum has more than one lexer mode then start() and mode_switch() are also
+/// generated. If any variant is marked as `skip` then skip() is also generated. If any
+/// variant has an `action` then action() is also generated.
+///
+/// Any generics on the `enum` are carried over to the implementations of
+/// `luther::dfa::Dfa` and `luther::Lexer` (but not to the dfa type itself).
+///
+/// If the `display` option is given then name() and `Display` are generated for the `enum`.
+///
+/// If the `kind_codes` option is given then kind_code() and from_code() are generated for the
+/// `enum`.
+///
+/// If the `byte_lexer` option is given then a type alias of that name is generated for
+/// the `luther::dfa::LexerIter` returned by `luther::Lexer::byte_lexer`. This decodes utf8
+/// bytes for the `char` dfa; no dfa over bytes is generated.
 ///
 /// Both transition() and accept() are geneated from the `dfa.states` vector.
 /// The default transition() if nothing else is specified in `dfa.states` is
-/// to the error state.
+/// to the error state. For the `Direct` codegen strategy transition() is a `match`
+/// on the state and the `char`. For the `Tables` codegen strategy it is a lookup
+/// in a static table of `luther::dfa::TableRow`'s.
 pub fn generate_lexer_impl<'info, 'ast: 'info>(
     info: &'info enum_info::EnumInfo<'ast>,
-    dfa: &'info Dfa<'info, 'ast>,
-    error_state: usize,
+    lexer_dfa: &'info LexerDfa<'info, 'ast>,
 ) -> quote::Tokens {
+    let dfa = &lexer_dfa.dfa;
+    let error_state = lexer_dfa.error_state;
     let name = info.name;
+    let (impl_generics, ty_generics, where_clause) = info.generics.split_for_impl();
+    let token_type = quote!{#name #ty_generics};
     let dfa_name = Ident::from(&info.dfa_name as &str);
-    let dfa_enum = generate_dfa_enum(dfa_name, info.vis.clone(), dfa.states.len());
-    let dfa_default = generate_dfa_default(dfa_name);
-    let is_error_fn = generate_is_error_fn(dfa_name, error_state);
-    let transition_fn = generate_transition_fn(dfa, dfa_name, error_state);
-    let accept_fn = generate_accept_fn(dfa, dfa_name, *name);
+    let states = StateNames {
+        dfa_name,
+        codegen: info.codegen,
+    };
+    let dfa_type = generate_dfa_type(&states, info.vis.clone(), dfa.states.len());
+    let dfa_default = generate_dfa_default(&states, lexer_dfa.start_states[0]);
+    let is_error_fn = generate_is_error_fn(&states, error_state);
+    let transition_fn = match info.codegen {
+        Codegen::Direct => generate_transition_fn(dfa, &states, error_state),
+        Codegen::Tables => generate_table_transition_fn(dfa, &states),
+    };
+    let accept_fn = generate_accept_fn(dfa, &states, *name, &token_type);
+    let mode_fns = generate_mode_fns(info, lexer_dfa, &states);
+    let error_token_fn = generate_error_token_fn(info, &token_type);
+    let skip_fn = generate_skip_fn(dfa, &states);
+    let action_fn = generate_action_fn(dfa, &states, &token_type);
+    let regex_files = generate_regex_files(info, dfa_name);
+    let byte_lexer = generate_byte_lexer(info, dfa_name);
+    let display = generate_display(info);
+    let kind_codes = generate_kind_codes(info);
 
     quote!{
-        #dfa_enum
+        #dfa_type
 
         #dfa_default
 
-        impl luther::dfa::Dfa<#name> for #dfa_name {
+        impl #impl_generics luther::dfa::Dfa<#token_type> for #dfa_name #where_clause {
             #is_error_fn
 
             #transition_fn
 
             #accept_fn
+
+            #mode_fns
+
+            #error_token_fn
+
+            #skip_fn
+
+            #action_fn
         }
 
-        impl ::luther::Lexer for #name {
+        impl #impl_generics ::luther::Lexer for #token_type #where_clause {
             type Dfa = #dfa_name ;
         }
+
+        #regex_files
+
+        #byte_lexer
+
+        #display
+
+        #kind_codes
     }
 }
 
-fn generate_dfa_enum(dfa_name: Ident, vis: syn::Visibility, num_states: usize) -> quote::Tokens {
-    let state_name = (0..num_states).map(make_state_name);
+/// Names the states of the generated dfa type according to the codegen strategy.
+struct StateNames {
+    dfa_name: Ident,
+    codegen: Codegen,
+}
 
-    quote! {
-        #[derive(PartialEq, Debug, Clone, Copy)]
-        #vis enum #dfa_name {
-            #(#state_name),*
+impl StateNames {
+    /// An expression or pattern for state `state_num`.
+    fn state(&self, state_num: usize) -> quote::Tokens {
+        let dfa_name = self.dfa_name;
+
+        match self.codegen {
+            Codegen::Direct => {
+                let state_path = make_state_path(dfa_name, state_num);
+                quote!{#state_path}
+            }
+            Codegen::Tables => quote!{#dfa_name(#state_num)},
         }
     }
 }
 
-fn generate_dfa_default(dfa_name: Ident) -> quote::Tokens {
-    let state_path = make_state_path(dfa_name, 0);
+fn generate_dfa_type(
+    states: &StateNames,
+    vis: syn::Visibility,
+    num_states: usize,
+) -> quote::Tokens {
+    let dfa_name = states.dfa_name;
+
+    match states.codegen {
+        Codegen::Direct => {
+            let state_name = (0..num_states).map(make_state_name);
+
+            quote! {
+                #[derive(PartialEq, Debug, Clone, Copy)]
+                #vis enum #dfa_name {
+                    #(#state_name),*
+                }
+            }
+        }
+        Codegen::Tables => quote! {
+            #[derive(PartialEq, Debug, Clone, Copy)]
+            #vis struct #dfa_name(usize);
+        },
+    }
+}
+
+fn generate_dfa_default(states: &StateNames, start_state: usize) -> quote::Tokens {
+    let dfa_name = states.dfa_name;
+    let state = states.state(start_state);
 
     quote! {
         impl Default for #dfa_name {
             fn default() -> Self {
-                #state_path
+                #state
             }
         }
     }
 }
 
-fn generate_is_error_fn(dfa_name: Ident, error_state: usize) -> quote::Tokens {
-    let state_path = make_state_path(dfa_name, error_state);
+fn generate_is_error_fn(states: &StateNames, error_state: usize) -> quote::Tokens {
+    let state = states.state(error_state);
 
     quote! {
         fn is_error(&self) -> bool {
-            *self == #state_path
+            *self == #state
         }
     }
 }
 
-fn generate_transition_fn(dfa: &Dfa, dfa_name: Ident, error_state: usize) -> quote::Tokens {
-    let error_state_name = make_state_path(dfa_name, error_state);
+fn generate_transition_fn(dfa: &Dfa, states: &StateNames, error_state: usize) -> quote::Tokens {
+    let error_state_name = states.state(error_state);
     let state_transitions = dfa.states.iter().enumerate().map(|(state_num, state)| {
-        generate_trasitions_for_state(state, dfa_name, state_num, error_state)
+        generate_trasitions_for_state(state, states, state_num, error_state)
     });
 
     quote! {
@@ -107,26 +198,23 @@ fn generate_transition_fn(dfa: &Dfa, dfa_name: Ident, error_state: usize) -> quo
 
 fn generate_trasitions_for_state(
     state: &State,
-    dfa_name: Ident,
+    states: &StateNames,
     state_num: usize,
     error_state: usize,
 ) -> quote::Tokens {
-    let state_path = make_state_path(dfa_name, state_num);
+    let state_path = states.state(state_num);
     let default = state.default as usize;
 
     let default_transition = if default == error_state {
         quote!{}
     } else {
-        let default_state = make_state_path(dfa_name, default);
+        let default_state = states.state(default);
         quote!{ (#state_path, _) => #default_state, }
     };
 
-    let from = (0..state.by_char.len()).map(|_| make_state_path(dfa_name, state_num));
+    let from = (0..state.by_char.len()).map(|_| states.state(state_num));
     let label = state.by_char.keys();
-    let to = state
-        .by_char
-        .values()
-        .map(|&i| make_state_path(dfa_name, i as usize));
+    let to = state.by_char.values().map(|&i| states.state(i as usize));
 
     quote! {
         #((#from, #label) => #to,)*
@@ -134,14 +222,79 @@ fn generate_trasitions_for_state(
     }
 }
 
-fn generate_accept_fn(dfa: &Dfa, dfa_name: Ident, name: Ident) -> quote::Tokens {
+fn generate_table_transition_fn(dfa: &Dfa, states: &StateNames) -> quote::Tokens {
+    let dfa_name = states.dfa_name;
+    let num_states = dfa.states.len();
+    let rows = dfa.states.iter().map(generate_table_row);
+
+    quote! {
+        fn transition(&self, c: char) -> Self {
+            static TABLE: [::luther::dfa::TableRow; #num_states] = [
+                #(#rows),*
+            ];
+
+            #dfa_name(TABLE[self.0].transition(c))
+        }
+    }
+}
+
+fn generate_table_row(state: &State) -> quote::Tokens {
+    let default = state.default as usize;
+    let ranges = make_ranges(state);
+    let first = ranges.iter().map(|&(first, _, _)| first);
+    let last = ranges.iter().map(|&(_, last, _)| last);
+    let to = ranges.iter().map(|&(_, _, to)| to);
+
+    quote! {
+        ::luther::dfa::TableRow {
+            ranges: &[#((#first, #last, #to)),*],
+            default: #default,
+        }
+    }
+}
+
+// Collapses the by_char transitions of state into ranges of consecutive chars with the
+// same target, omitting those whose target is the default.
+fn make_ranges(state: &State) -> Vec<(char, char, usize)> {
+    let mut ranges: Vec<(char, char, usize)> = Vec::new();
+
+    for (&c, &to) in state.by_char.iter() {
+        let to = to as usize;
+        if to == state.default as usize {
+            continue;
+        }
+
+        if let Some(range) = ranges.last_mut() {
+            if range.2 == to && range.1 as u32 + 1 == c as u32 {
+                range.1 = c;
+                continue;
+            }
+        }
+
+        ranges.push((c, c, to));
+    }
+
+    ranges
+}
+
+fn generate_accept_fn(
+    dfa: &Dfa,
+    states: &StateNames,
+    name: Ident,
+    token_type: &quote::Tokens,
+) -> quote::Tokens {
     let state_accepts = dfa.states
         .iter()
         .enumerate()
-        .map(|(state_num, state)| generate_accept_for_state(state, dfa_name, state_num, name));
+        .map(|(state_num, state)| generate_accept_for_state(state, states, state_num, name));
 
     quote!{
-        fn accept(&self, _matched: &str) -> Option<#name> {
+        fn accept(&self, matched: &str) -> Option<#token_type> {
+            self.try_accept(matched).and_then(|r| r.ok())
+        }
+
+        fn try_accept(&self, _matched: &str)
+            -> Option<::std::result::Result<#token_type, ::std::string::String>> {
 
             match *self {
                 #(#state_accepts)*
@@ -153,21 +306,301 @@ fn generate_accept_fn(dfa: &Dfa, dfa_name: Ident, name: Ident) -> quote::Tokens
 
 fn generate_accept_for_state(
     state: &State,
-    dfa_name: Ident,
+    states: &StateNames,
     state_num: usize,
     name: Ident,
 ) -> quote::Tokens {
     state.value.as_ref().map_or(quote!{}, |variant| {
-        let state_name = make_state_path(dfa_name, state_num);
+        let state_name = states.state(state_num);
         let token_name = variant.name;
-        let field_builder = variant
-            .field
-            .map_or(quote!{}, |_| quote!{(_matched.parse().unwrap_or_default())});
-        quote!{#state_name => Some(#name::#token_name#field_builder),
+        let token = variant.field.map_or(quote!{Ok(#name::#token_name)}, |_| {
+            let convert = conversion(variant.with.as_ref());
+            quote!{
+                match #convert(_matched) {
+                    Ok(value) => Ok(#name::#token_name(value)),
+                    Err(e) => Err(::std::string::ToString::to_string(&e)),
+                }
+            }
+        });
+        let token = if variant.keywords.is_empty() {
+            token
+        } else {
+            let arms: Vec<_> = variant
+                .keywords
+                .iter()
+                .map(|kw| {
+                    let keyword = &kw.keyword;
+                    let keyword_variant = kw.variant;
+                    quote!{#keyword => Ok(#name::#keyword_variant),}
+                })
+                .collect();
+            quote!{
+                match _matched {
+                    #(#arms)*
+                    _ => #token,
+                }
+            }
+        };
+
+        quote!{#state_name => Some(#token),
         }
     })
 }
 
+// The function that converts the matched characters to the field of a variant: the with
+// option of the variant or else FromStr.
+fn conversion(with: Option<&syn::Path>) -> quote::Tokens {
+    with.map_or(quote!{::std::str::FromStr::from_str}, |with| quote!{#with})
+}
+
+fn generate_mode_fns(
+    info: &enum_info::EnumInfo,
+    lexer_dfa: &LexerDfa,
+    states: &StateNames,
+) -> quote::Tokens {
+    if lexer_dfa.start_states.len() <= 1 {
+        return quote!{};
+    }
+
+    let mode = 1..lexer_dfa.start_states.len();
+    let start = lexer_dfa.start_states[1..].iter().map(|&s| states.state(s));
+
+    let switches = lexer_dfa.dfa.states.iter().enumerate().filter_map(|(state_num, state)| {
+        state.value.and_then(|vi| vi.mode_switch.as_ref()).map(|switch| {
+            let state_name = states.state(state_num);
+            let switch = match switch {
+                &ModeSwitch::Push(ref mode) => {
+                    let mode = info.mode_index(mode);
+                    quote!{::luther::dfa::ModeSwitch::Push(#mode)}
+                }
+                &ModeSwitch::Pop => quote!{::luther::dfa::ModeSwitch::Pop},
+                &ModeSwitch::Set(ref mode) => {
+                    let mode = info.mode_index(mode);
+                    quote!{::luther::dfa::ModeSwitch::Set(#mode)}
+                }
+            };
+            quote!{#state_name => Some(#switch),}
+        })
+    });
+
+    quote!{
+        fn start(mode: usize) -> Self {
+            match mode {
+                #(#mode => #start,)*
+                _ => Self::default(),
+            }
+        }
+
+        fn mode_switch(&self) -> Option<::luther::dfa::ModeSwitch> {
+            match *self {
+                #(#switches)*
+                _ => None,
+            }
+        }
+    }
+}
+
+fn generate_error_token_fn(
+    info: &enum_info::EnumInfo,
+    token_type: &quote::Tokens,
+) -> quote::Tokens {
+    info.error_variant.as_ref().map_or(quote!{}, |variant| {
+        let name = info.name;
+        let token_name = variant.name;
+        let token = variant.field.map_or(quote!{Some(#name::#token_name)}, |_| {
+            let convert = conversion(variant.with.as_ref());
+            quote!{
+                #convert(unmatched).ok().map(#name::#token_name)
+            }
+        });
+
+        quote!{
+            fn error_token(unmatched: &str) -> Option<#token_type> {
+                #token
+            }
+        }
+    })
+}
+
+fn generate_skip_fn(dfa: &Dfa, states: &StateNames) -> quote::Tokens {
+    let skipped: Vec<_> = dfa.states
+        .iter()
+        .enumerate()
+        .filter(|&(_, state)| state.value.map_or(false, |vi| vi.skip))
+        .map(|(state_num, _)| states.state(state_num))
+        .collect();
+
+    if skipped.is_empty() {
+        return quote!{};
+    }
+
+    quote!{
+        fn skip(&self) -> bool {
+            match *self {
+                #(#skipped)|* => true,
+                _ => false,
+            }
+        }
+    }
+}
+
+fn generate_action_fn(dfa: &Dfa, states: &StateNames, token_type: &quote::Tokens) -> quote::Tokens {
+    let actions: Vec<_> = dfa.states
+        .iter()
+        .enumerate()
+        .filter_map(|(state_num, state)| {
+            state.value.and_then(|vi| vi.action.as_ref()).map(|action| {
+                let state_name = states.state(state_num);
+                quote!{#state_name => #action(matched, span),}
+            })
+        })
+        .collect();
+
+    if actions.is_empty() {
+        return quote!{};
+    }
+
+    quote!{
+        fn action(&self, matched: &str, span: &::luther::Span<#token_type>) {
+            match *self {
+                #(#actions)*
+                _ => {}
+            }
+        }
+    }
+}
+
+// Includes the files read for the regex_file options so that the compiler will rebuild
+// the lexer when one of them changes.
+fn generate_regex_files(info: &enum_info::EnumInfo, dfa_name: Ident) -> quote::Tokens {
+    if info.regex_files.is_empty() {
+        return quote!{};
+    }
+
+    let files = info.regex_files.iter().map(|path| path.display().to_string());
+
+    quote!{
+        impl #dfa_name {
+            #[allow(dead_code)]
+            const REGEX_FILES: &'static [&'static str] = &[#(include_str!(#files)),*];
+        }
+    }
+}
+
+fn generate_byte_lexer(info: &enum_info::EnumInfo, dfa_name: Ident) -> quote::Tokens {
+    info.byte_lexer.as_ref().map_or(quote!{}, |alias| {
+        let name = info.name;
+        let vis = info.vis;
+        let alias = Ident::from(alias as &str);
+        let (_, ty_generics, _) = info.generics.split_for_impl();
+        let params = info.generics.params.iter().map(|param| match param {
+            &syn::GenericParam::Lifetime(ref def) => {
+                let lifetime = &def.lifetime;
+                quote!{#lifetime}
+            }
+            &syn::GenericParam::Type(ref param) => {
+                let ident = &param.ident;
+                quote!{#ident}
+            }
+            &syn::GenericParam::Const(ref param) => {
+                let ident = &param.ident;
+                quote!{#ident}
+            }
+        });
+
+        quote!{
+            #vis type #alias<#(#params,)* I> = ::luther::dfa::LexerIter<
+                #name #ty_generics,
+                ::std::io::Error,
+                ::luther::spanned::SpannedUtf8Iter<I>,
+                #dfa_name
+            >;
+        }
+    })
+}
+
+fn generate_display(info: &enum_info::EnumInfo) -> quote::Tokens {
+    if !info.display {
+        return quote!{};
+    }
+
+    let name = info.name;
+    let (impl_generics, ty_generics, where_clause) = info.generics.split_for_impl();
+    let names = info.names.iter().map(|token_name| {
+        let variant = token_name.variant;
+        let display = &token_name.name;
+        if token_name.has_field {
+            quote!{#name::#variant(..) => #display,}
+        } else {
+            quote!{#name::#variant => #display,}
+        }
+    });
+
+    quote!{
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// The human readable name of the kind of this token.
+            pub fn name(&self) -> &'static str {
+                match *self {
+                    #(#names)*
+                }
+            }
+        }
+
+        impl #impl_generics ::std::fmt::Display for #name #ty_generics #where_clause {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                f.write_str(self.name())
+            }
+        }
+    }
+}
+
+fn generate_kind_codes(info: &enum_info::EnumInfo) -> quote::Tokens {
+    if !info.kind_codes {
+        return quote!{};
+    }
+
+    let name = info.name;
+    let (impl_generics, ty_generics, where_clause) = info.generics.split_for_impl();
+    let codes = info.codes.iter().map(|kind_code| {
+        let variant = kind_code.variant;
+        let code = kind_code.code;
+        if kind_code.has_field {
+            quote!{#name::#variant(..) => #code,}
+        } else {
+            quote!{#name::#variant => #code,}
+        }
+    });
+    let units = info.codes
+        .iter()
+        .filter(|kind_code| !kind_code.has_field)
+        .map(|kind_code| {
+            let variant = kind_code.variant;
+            let code = kind_code.code;
+            quote!{#code => Some(#name::#variant),}
+        });
+
+    quote!{
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// The stable numeric code of the kind of this token.
+            pub fn kind_code(&self) -> u32 {
+                match *self {
+                    #(#codes)*
+                }
+            }
+
+            /// The token of the unit variant with the kind code `code`.
+            ///
+            /// This is `None` if no variant has the code or if the variant has a field.
+            pub fn from_code(code: u32) -> Option<Self> {
+                match code {
+                    #(#units)*
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
 fn make_state_name(state_num: usize) -> Ident {
     format!("State{}", state_num).into()
 }
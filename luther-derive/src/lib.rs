@@ -54,12 +54,32 @@
 //! implement `str::FromStr`, but `luther_derive` cannot recognize this error. This case
 //! will likely manifest itself with a confusing error message from the compiler.
 //!
-//! For now the single type included in an enum must also implement `default::Default`,
-//! although this restriction may be lifted in the future.
+//! The conversion from the recognized characters can instead be done by a function named
+//! with the `with` option of the `luther` attribute. The function must have a signature like
+//! `fn(&str) -> Result<T, E>` where `T` is the type included in the variant:
 //!
-//! The code to capture the characters will be someting similar to
-//! `characters.parse().unwarp_or_default()` where `characters` is a `&str` of the
-//! recognized characters.
+//! ```rust
+//! # extern crate luther;
+//! # #[macro_use]
+//! # extern crate luther_derive;
+//! #[derive(Lexer)]
+//! enum Token {
+//!     #[luther(regex = "0x[0-9a-f]+", with = "parse_hex")]
+//!     Hex(u64),
+//!
+//!     #[luther(regex = "[0-9]+")]
+//!     Int(i64),
+//! }
+//!
+//! fn parse_hex(s: &str) -> Result<u64, std::num::ParseIntError> {
+//!     u64::from_str_radix(&s[2..], 16)
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! If the conversion fails (through either `str::FromStr` or the `with` function) then the
+//! lexer will produce a `luther::LexError::InvalidValue` error for that token. The error type
+//! of the conversion must implement `fmt::Display` so that it can be included in this error.
 //!
 //! # The `luther` attribute
 //! `luther_derive` recognized the `luther` attribute both on the `enum` for which
@@ -71,7 +91,389 @@
 //!
 //! * `dfa`: the name to use for the generated deterministic finite automaton [enum]
 //! * `regex`: the regular expression to recognize for particular variant [variant]
+//! * `regex_file`: a file containing the regular expression for a variant [variant]
 //! * `priority_group`: the priority group to which a variant belongs [variant]
+//! * `case_insensitive`: match the `regex` for a variant without regard to case [variant]
+//! * `fragment`: a list of named regular expression fragments [enum]
+//! * `fragment_file`: a file of named regular expression fragments [enum]
+//! * `with`: the function to convert the recognized characters for a variant [variant]
+//! * `dump_dfa`: a file into which to write the generated dfa in the DOT format [enum]
+//! * `dump_source`: a file into which to write the generated source for the lexer [enum]
+//! * `byte_lexer`: the name of a type alias for the lexer that decodes utf8 encoded bytes [enum]
+//! * `codegen`: the strategy for generating the dfa, either `"direct"` or `"tables"` [enum]
+//! * `minimize`: how to merge the states of the dfa, `"none"`, `"fast"`, or `"full"` [enum]
+//! * `syntax`: the syntax of the `regex` options, either `"luther"` or `"posix"` [enum]
+//! * `mode`: the lexer mode in which a variant is recognized [variant]
+//! * `push_mode`: the lexer mode to push after recognizing a variant [variant]
+//! * `pop_mode`: pop the current lexer mode after recognizing a variant [variant]
+//! * `set_mode`: the lexer mode to replace the current mode after recognizing a variant [variant]
+//! * `error`: the variant for input that the lexer cannot match [variant]
+//! * `keywords`: a list of unit variants recognized by the keywords a variant matches [variant]
+//! * `display`: generate `name()` and `Display` for the `enum` [enum], or the name of a
+//! variant [variant]
+//! * `kind_codes`: generate `kind_code()` and `from_code()` for the `enum` [enum]
+//! * `code`: the numeric code of the kind of a variant [variant]
+//! * `action`: a function to call whenever a variant is recognized [variant]
+//! * `skip`: recognize the `regex` for a variant but do not produce it [variant]
+//!
+//! Most options take a value but some (like `case_insensitive`) are flags which are invoked
+//! without a value like `#[luther(regex = "select", case_insensitive)]`.
+//!
+//! # Case insensitive variants
+//! The `case_insensitive` option on a variant applies simple case folding to each character
+//! (including the characters in character classes) of that variant's regular expression. A
+//! `case_insensitive` simple string is still favoured over more complicated regular expressions
+//! (see the section on priority groups below).
+//!
+//! # Regular expressions in files
+//! Long regular expressions (such as those for Unicode identifiers) can be kept in a separate
+//! file and named with the `regex_file` option in place of the `regex` option. The path is
+//! relative to the directory of the crate being compiled and a trailing newline in the file is
+//! ignored. The crate will be rebuilt if the file changes.
+//!
+//! # POSIX syntax
+//! The `syntax = "posix"` option on the `enum` reads the regular expressions of its variants
+//! (and its fragments) as POSIX extended regular expressions, so that patterns can be copied
+//! from existing POSIX tools unmodified. This adds bracket expressions with character classes
+//! like `[[:alpha:]_][[:alnum:]_]*` and interval expressions like `[0-9]{1,3}`, and makes `&`
+//! and `~` ordinary characters. Anchors (`^` and `$`) are not supported since a token always
+//! starts where the previous one ended.
+//!
+//! ```rust
+//! # extern crate luther;
+//! # #[macro_use]
+//! # extern crate luther_derive;
+//! #[derive(Lexer)]
+//! #[luther(syntax = "posix")]
+//! enum Token {
+//!     #[luther(regex = "[[:alpha:]_][[:alnum:]_]*")]
+//!     Ident,
+//!
+//!     #[luther(regex = "[0-9]{1,3}(\\.[0-9]{1,3}){3}")]
+//!     Address,
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! # Fragments
+//! The `fragment` option on the `enum` names regular expressions that can be reused in the
+//! `regex` option of any variant. A fragment is interpolated by writing its name in braces:
+//!
+//! ```rust
+//! # extern crate luther;
+//! # #[macro_use]
+//! # extern crate luther_derive;
+//! #[derive(Lexer)]
+//! #[luther(fragment(digit = "[0-9]", exp = "[eE][-+]?{digit}+"))]
+//! enum Token {
+//!     #[luther(regex = "{digit}+")]
+//!     Integer,
+//!
+//!     #[luther(regex = "{digit}+\\.{digit}*{exp}?")]
+//!     Float,
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! Each interpolated fragment is wrapped in parentheses. Fragments may refer to other fragments
+//! but not (directly or indirectly) to themselves. A `{` that does not start the name of a
+//! fragment is matched literally.
+//!
+//! Fragments that are shared by several `enum`'s (such as the lexers for different parts of a
+//! language) can be kept in a fragment file named by the `fragment_file` option on each `enum`.
+//! Each line of a fragment file is a fragment of the form `name = regex`, a comment starting
+//! with `#`, or blank. The path is relative to the directory of the crate being compiled and the
+//! crate will be rebuilt if the file changes.
+//!
+//! # Lexer modes
+//! A lexer can recognize different sets of variants depending on its current mode. This allows,
+//! for example, the contents of a string literal to be lexed differently from the code around
+//! it. A variant with the `mode` option is recognized only in the named mode and a variant
+//! without it is recognized only in the default mode (named `INITIAL`).
+//!
+//! The lexer keeps a stack of modes and starts with only the default mode on the stack. After
+//! recognizing a variant with the `push_mode` option the lexer pushes the named mode onto the
+//! stack, with the `pop_mode` option it pops the current mode from the stack, and with the
+//! `set_mode` option it replaces the current mode with the named mode.
+//!
+//! ```rust
+//! # extern crate luther;
+//! # #[macro_use]
+//! # extern crate luther_derive;
+//! #[derive(Lexer)]
+//! enum Token {
+//!     #[luther(regex = "[a-z]+")]
+//!     Ident(String),
+//!
+//!     #[luther(regex = "\"", push_mode = "STRING")]
+//!     StringStart,
+//!
+//!     #[luther(regex = "[^\"\\\\]+", mode = "STRING")]
+//!     StringText(String),
+//!
+//!     #[luther(regex = "\\\\.", mode = "STRING")]
+//!     StringEscape(String),
+//!
+//!     #[luther(regex = "\"", mode = "STRING", pop_mode)]
+//!     StringEnd,
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! # Generics
+//! The `enum` may have lifetime and type parameters. Any bounds the generated lexer needs (such
+//! as `str::FromStr` for the type of a captured field) must be given on the `enum`. The lexer
+//! builds each token from its own copy of the recognized characters so a token cannot borrow
+//! from the input, but a variant can capture an owned value into a type with a lifetime (such as
+//! `Cow<'src, str>`) through the `with` option.
+//!
+//! # The error variant
+//! By default the lexer produces a `luther::LexError::InvalidCharacter` or
+//! `luther::LexError::InvalidToken` error when it encounters input that does not match any
+//! variant. If one variant (without a `regex` option) has the `error` option then the lexer
+//! instead produces that variant for such input, so that it never fails in the middle of its
+//! input. The error variant covers either the maximal run of characters that cannot start a
+//! token or the characters of an incomplete token. If the error variant includes a type then
+//! the type is captured from the unmatched characters through its `str::FromStr`
+//! implementation or the function given by the `with` option, as for any other variant. If
+//! that conversion fails the lexer produces the error that it would without the error
+//! variant.
+//!
+//! ```rust
+//! # extern crate luther;
+//! # #[macro_use]
+//! # extern crate luther_derive;
+//! #[derive(Lexer)]
+//! enum Token {
+//!     #[luther(regex = "[a-z]+")]
+//!     Ident,
+//!
+//!     #[luther(error)]
+//!     Garbage(String),
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! # Skipped variants
+//! A variant with the `skip` option is recognized by the lexer like any other variant (so it
+//! takes part in finding the longest match) but the lexer does not produce it. This is useful for
+//! whitespace and comments. A skipped variant must still be a variant of the `enum` so it is
+//! usually a unit variant.
+//!
+//! ```rust
+//! # extern crate luther;
+//! # #[macro_use]
+//! # extern crate luther_derive;
+//! #[derive(Lexer)]
+//! enum Token {
+//!     #[luther(regex = "[a-z]+")]
+//!     Ident,
+//!
+//!     #[luther(regex = "[ \\t\\n]+", skip)]
+//!     Whitespace,
+//!
+//!     #[luther(regex = "#[^\\n]*", skip)]
+//!     Comment,
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! # Dialects
+//! The tokens of one dialect of a language (such as the keywords of a newer edition or the
+//! syntax of an extension) can be compiled into the lexer only when a feature of the crate is
+//! enabled, by giving their `luther` attribute through `cfg_attr`. The attribute is removed
+//! before the derive sees the `enum` when the feature is not enabled, and a variant without a
+//! `luther` attribute is never produced by the lexer (so its regex adds no states to the dfa).
+//! A token definitions file for `luther-build` tags such tokens with `groups` instead.
+//!
+//! ```rust
+//! # extern crate luther;
+//! # #[macro_use]
+//! # extern crate luther_derive;
+//! #[derive(Lexer)]
+//! enum Token {
+//!     #[luther(regex = "[a-z]+")]
+//!     Ident,
+//!
+//!     #[cfg_attr(feature = "extensions", luther(regex = "@[a-z]+"))]
+//!     Annotation,
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! # Keywords
+//! Adding a literal regular expression for each keyword of a language adds states to the dfa
+//! for every keyword. The `keywords` option on a variant (such as an identifier) instead lists
+//! unit variants (without a `regex` option) together with the keyword for each. When the
+//! variant matches one of the keywords, the lexer produces the keyword's variant in its place
+//! through a lookup after the match, so the keywords add no states to the dfa.
+//!
+//! ```rust
+//! # extern crate luther;
+//! # #[macro_use]
+//! # extern crate luther_derive;
+//! #[derive(Lexer)]
+//! enum Token {
+//!     #[luther(regex = "[a-z]+", keywords(If = "if", While = "while"))]
+//!     Ident(String),
+//!
+//!     If,
+//!     While,
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! # Token names
+//! The `display` option on the `enum` generates a `name()` method that returns a human readable
+//! name for the kind of a token together with an implementation of `fmt::Display` that writes
+//! that name. The name of a variant is the name of the variant itself unless it is given through
+//! the `display` option on the variant. This allows error messages such as "expected `)`".
+//!
+//! ```rust
+//! # extern crate luther;
+//! # #[macro_use]
+//! # extern crate luther_derive;
+//! #[derive(Lexer)]
+//! #[luther(display)]
+//! enum Token {
+//!     #[luther(regex = "[a-z]+", display = "identifier")]
+//!     Ident,
+//!
+//!     #[luther(regex = "\\)", display = "`)`")]
+//!     RParen,
+//! }
+//!
+//! # fn main() {
+//! assert_eq!(Token::RParen.to_string(), "`)`");
+//! # }
+//! ```
+//!
+//! # Kind codes
+//! The `kind_codes` option on the `enum` generates a `kind_code()` method that returns a `u32`
+//! for the kind of a token, and a `from_code()` function that returns the token for a code (for
+//! unit variants only, since a variant with a field needs a value). These suit serialized token
+//! streams and consumers over FFI. As with the discriminants of an `enum`, each variant has the
+//! code after that of the variant before it (starting at 0) unless it is given through the `code`
+//! option on the variant. A variant whose code is given keeps it when the variants are
+//! reordered, so give every variant a code to make the codes stable. A parser can check the
+//! `kind_code()` of the next token against a `luther::kind_set::TokenKindSet` of codes.
+//!
+//! ```rust
+//! # extern crate luther;
+//! # #[macro_use]
+//! # extern crate luther_derive;
+//! #[derive(Lexer, Debug, PartialEq)]
+//! #[luther(kind_codes)]
+//! enum Token {
+//!     #[luther(regex = "[a-z]+", code = "10")]
+//!     Ident(String),
+//!
+//!     #[luther(regex = "\\(", code = "20")]
+//!     LParen,
+//!
+//!     #[luther(regex = "\\)")]
+//!     RParen,
+//! }
+//!
+//! # fn main() {
+//! assert_eq!(Token::RParen.kind_code(), 21);
+//! assert_eq!(Token::from_code(20), Some(Token::LParen));
+//! # }
+//! ```
+//!
+//! # Actions
+//! The `action` option on a variant names a function that the lexer calls with the recognized
+//! characters and the `luther::Span` of the token each time it recognizes that variant (before
+//! the token is produced, and even if the variant is skipped). This allows for side effects
+//! such as building a table of line starts without wrapping the lexer. The function must have
+//! the signature `fn(&str, &luther::Span<Token>)` where `Token` is the `enum`.
+//!
+//! ```rust
+//! # extern crate luther;
+//! # #[macro_use]
+//! # extern crate luther_derive;
+//! #[derive(Lexer)]
+//! enum Token {
+//!     #[luther(regex = "[a-z]+")]
+//!     Ident,
+//!
+//!     #[luther(regex = "\\n", skip, action = "on_newline")]
+//!     Newline,
+//! }
+//!
+//! fn on_newline(_matched: &str, span: &luther::Span<Token>) {
+//!     println!("line ends at {:?}", span.start());
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! # Lexing bytes
+//! Every `luther::Lexer` can lex either a `char` iterator (through `lexer`) or an iterator
+//! over the bytes of utf8 encoded input (through `byte_lexer`). There is no separate dfa over
+//! bytes: `byte_lexer` is an adapter that decodes the bytes into `char`'s and runs the same
+//! `char` dfa over them, so bytes that are not valid utf8 are an `io::ErrorKind::InvalidData`
+//! error rather than input that a token can match. The `byte_lexer` option on the `enum` names a
+//! type alias for this lexer so that it can be stored without spelling out its full type.
+//! Lexing bytes needs the `io` feature of the `luther` crate (which is enabled by default).
+//!
+//! ```rust
+//! # extern crate luther;
+//! # #[macro_use]
+//! # extern crate luther_derive;
+//! #[derive(Lexer)]
+//! #[luther(byte_lexer = "TokenLexerBytes")]
+//! pub enum Token {
+//!     #[luther(regex = "[a-z]+")]
+//!     Ident,
+//! }
+//!
+//! struct Parser<I: Iterator<Item = std::io::Result<u8>>> {
+//!     tokens: TokenLexerBytes<I>,
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! # Codegen strategies
+//! By default (or with `#[luther(codegen = "direct")]`) the transition function of the generated
+//! dfa is a single `match` on the current state and the next `char`. This is fast for small
+//! lexers but can generate a great deal of code for lexers with large character classes (such
+//! as Unicode identifiers). With `#[luther(codegen = "tables")]` the transition function is
+//! instead a lookup in compact static tables of `char` ranges (see `luther::dfa::TableRow`).
+//!
+//! # Merging states
+//! The dfa is built from the derivatives of the regular expressions and may have more states
+//! than it needs. With `#[luther(minimize = "full")]` the dfa is minimized before the code for
+//! it is generated, which makes the generated code (or tables) smaller. With
+//! `#[luther(minimize = "fast")]` only the states that accept the same variant and have the
+//! same transitions are merged. This is cheaper for a large lexer (where the time to expand the
+//! derive is part of every build) and usually finds most of the states that full minimization
+//! would. The default (`"none"`) leaves the dfa as it is built.
+//!
+//! # Caching the generated lexer
+//! Building the dfa for a large lexer can take a noticeable part of each build. If the
+//! `LUTHER_CACHE_DIR` environment variable names a directory (relative to the directory of the
+//! crate being compiled), the source generated for each lexer is cached in that directory.
+//! Later builds reuse it for as long as the `enum`, its `luther` attributes, the files named by
+//! its `regex_file` and `fragment_file` options, and the version number of luther-derive stay
+//! the same. The cache is off by default and is not used for lexers whose dfa or source are
+//! dumped (see below).
+//!
+//! The version number is the only part of luther-derive itself that the cache checks, so a
+//! luther-derive from a git or path dependency whose code generation changes without a new
+//! version number reuses the lexers generated by the old code. Delete the cache directory (which
+//! can be deleted at any time) after changing luther-derive.
+//!
+//! # Inspecting the generated dfa
+//! The `dump_dfa` option on the `enum` writes the deterministic finite automaton for the lexer
+//! to the named file (relative to the directory of the crate being compiled) in the DOT format
+//! used by Graphviz. Setting the `LUTHER_DUMP_DFA` environment variable to a directory does the
+//! same for every `enum` on which `luther::Lexer` is derived, naming each file for its dfa. The
+//! first line of the file is a comment with the number of states, transitions, and accepting
+//! states. The error state and the transitions to it are omitted.
+//!
+//! The `dump_source` option on the `enum` writes the source code generated for the lexer to the
+//! named file. The path is relative to `OUT_DIR` if the crate has a build script and to the
+//! directory of the crate otherwise. The file is formatted with `rustfmt` if it is installed.
 //!
 //! # Priority groups
 //! It is possible for the regular expressions for more than one `enum` variant to match
@@ -95,18 +497,31 @@
 //!
 //! The default value for `priority_group` if it is not specified is 1.
 //!
+//! # Warnings
+//! `luther_derive` will report a warning (on standard error) at compile time when a variant can
+//! never be matched because every input it matches is matched by a variant of a higher priority,
+//! when the regular expressions for two variants match exactly the same input, or when the
+//! generated dfa has more than 1000 states.
+//!
 //! # Errors
 //! `luther_derive` will raise an error at compile time in the following circumstances (among
 //! others):
 //!
 //! * the `#[derive(Lexer)]` invocation is on a `struct` rather than an `enum`
 //! * none of the variants of the `enum` have a `luther` attribute with the `regex` specified
-//! * one of the `regex`'s specified for a variant would match the empty string
+//! * one of the `regex`'s specified for a variant would match the empty string (the error will
+//! point to the `regex` and name the variant)
 //! * a variant has included types that are not a tuple of arity 1
-//! * the value provided for the `regex` option can't be parsed as a regular expression
+//! * the value provided for the `regex` option can't be parsed as a regular expression (the
+//! error will point to the offending column of the regular expression where possible)
 //! * the value provided for the `priority_group` option can't be parsed as an integer
+//! * the `with` option is used on a variant that does not include a type
+//! * the value provided for the `with` or `action` option can't be parsed as a path
+//! * the file named by the `regex_file` option can't be read
+//! * the `push_mode` or `set_mode` option names a mode in which no variant is recognized
 
 extern crate proc_macro;
+extern crate proc_macro2;
 extern crate redfa;
 extern crate syn;
 
@@ -116,9 +531,17 @@ extern crate quote;
 #[macro_use]
 extern crate itertools;
 
+mod cache;
 mod enum_info;
+mod error;
+mod fragment;
 mod generate;
 mod dfa;
+mod dump;
+mod minimize;
+mod posix;
+mod report;
+mod syntax;
 
 use proc_macro::TokenStream;
 use syn::DeriveInput;
@@ -132,13 +555,39 @@ type Dfa<'info, 'ast: 'info> = redfa::Dfa<char, Option<&'info enum_info::Variant
 /// attribute.
 #[proc_macro_derive(Lexer, attributes(luther))]
 pub fn luther_derive(input: TokenStream) -> TokenStream {
+    let source = input.to_string();
     let ast: DeriveInput = syn::parse(input).expect("failed to parse the input token stream");
 
     let info: enum_info::EnumInfo = (&ast).into();
 
-    let (dfa, error_state) = dfa::build_dfa(&info);
+    let cache = cache::Cache::new(&info, &source);
+    if let Some(ref cache) = cache {
+        // An entry that the compiler cannot parse is discarded and the lexer generated again.
+        match cache.load().map(|cached| (cached.source.parse(), cached.warnings)) {
+            Some((Ok(tokens), warnings)) => {
+                report::report_warnings(&warnings);
+                return tokens;
+            }
+            Some((Err(_), _)) => cache.discard(),
+            None => {}
+        }
+    }
+
+    let dfa = match dfa::build_dfa(&info) {
+        Ok(dfa) => dfa,
+        Err(e) => return e.into_compile_error().into(),
+    };
+
+    let warnings = report::warnings(&info, &dfa);
+    report::report_warnings(&warnings);
+    dump::dump_dfa(&info, &dfa);
+
+    let expanded = generate::generate_lexer_impl(&info, &dfa);
 
-    let expanded = generate::generate_lexer_impl(&info, &dfa, error_state);
+    dump::dump_source(&info, &expanded);
+    if let Some(cache) = cache {
+        cache.store(&warnings, &expanded.to_string());
+    }
 
     expanded.into()
 }
@@ -0,0 +1,119 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+use std::collections::{BTreeMap, HashMap};
+
+use enum_info::{Minimize, VariantInfo};
+use super::Dfa;
+use redfa;
+
+/// merge_states merges the equivalent states of dfa as chosen by minimize.
+///
+/// The return type is the merged Dfa and the number in it of each state of dfa.
+///
+/// `Minimize::Fast` repeatedly merges the states that accept the same variant and have the
+/// same transitions (by hashing their rows) until there are no more to merge. This finds most
+/// of the equivalent states of a dfa built from derivatives (such as the states at the end of
+/// keywords), but not those that differ only in transitions to other equivalent states.
+/// `Minimize::Full` refines a partition of the states (starting with the states that accept
+/// each variant) until the states in each part have the same transitions, which gives the
+/// minimal dfa.
+///
+/// The merged states are numbered in the order of the first of their original states, so the
+/// result depends only on dfa.
+pub fn merge_states<'info, 'ast: 'info>(
+    dfa: Dfa<'info, 'ast>,
+    minimize: Minimize,
+) -> (Dfa<'info, 'ast>, Vec<usize>) {
+    let mut classes: Vec<usize> = (0..dfa.states.len()).collect();
+    match minimize {
+        Minimize::None => return (dfa, classes),
+        Minimize::Fast => loop {
+            let merged = number_rows(&dfa, |state| accepts(&dfa, state), &classes);
+            if count(&merged) == count(&classes) {
+                break;
+            }
+            classes = merged;
+        },
+        Minimize::Full => {
+            // with every state in one class the rows are all the same
+            classes = number_rows(&dfa, |state| accepts(&dfa, state), &vec![0; classes.len()]);
+            loop {
+                let refined = number_rows(&dfa, |state| classes[state], &classes);
+                if count(&refined) == count(&classes) {
+                    break;
+                }
+                classes = refined;
+            }
+        }
+    }
+
+    let mut states: Vec<Option<State>> = (0..count(&classes)).map(|_| None).collect();
+    for (state, &class) in dfa.states.iter().zip(classes.iter()) {
+        if states[class].is_none() {
+            let (default, by_char) = row(state, &classes);
+            states[class] = Some(redfa::dfa::State {
+                by_char: by_char
+                    .into_iter()
+                    .map(|(c, to)| (c, to as u32))
+                    .collect::<BTreeMap<_, _>>(),
+                default: default as u32,
+                value: state.value,
+            });
+        }
+    }
+
+    let states = states.into_iter().map(|state| state.unwrap()).collect();
+    (redfa::Dfa { states }, classes)
+}
+
+type State<'info, 'ast> = redfa::dfa::State<char, Option<&'info VariantInfo<'ast>>>;
+
+type Row = (usize, Vec<(char, usize)>);
+
+// The transitions of state to the classes of their targets, leaving out the chars that go to
+// the same class as the default transition.
+fn row(state: &State, classes: &[usize]) -> Row {
+    let default = classes[state.default as usize];
+    let by_char = state
+        .by_char
+        .iter()
+        .map(|(&c, &to)| (c, classes[to as usize]))
+        .filter(|&(_, to)| to != default)
+        .collect();
+    (default, by_char)
+}
+
+// The variant accepted by state (compared by identity).
+fn accepts(dfa: &Dfa, state: usize) -> Option<*const ()> {
+    dfa.states[state]
+        .value
+        .map(|vi| vi as *const VariantInfo as *const ())
+}
+
+// Numbers the states by key and their rows (in the order of the first state with each).
+fn number_rows<K, F>(dfa: &Dfa, key: F, classes: &[usize]) -> Vec<usize>
+where
+    K: Eq + ::std::hash::Hash,
+    F: Fn(usize) -> K,
+{
+    let mut numbers: HashMap<(K, Row), usize> = HashMap::new();
+    (0..dfa.states.len())
+        .map(|state| {
+            let next = numbers.len();
+            *numbers
+                .entry((key(state), row(&dfa.states[state], classes)))
+                .or_insert(next)
+        })
+        .collect()
+}
+
+// The number of classes in a numbering.
+fn count(classes: &[usize]) -> usize {
+    classes.iter().max().map_or(0, |&max| max + 1)
+}
@@ -0,0 +1,233 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Translates a POSIX extended regular expression into the regular expression syntax of the
+/// `regex` option.
+///
+/// The translation supports bracket expressions (including the character classes like
+/// `[[:alpha:]]`), interval expressions like `a{2,3}`, and the operators that the two syntaxes
+/// share. The `&` and `~` characters, which are operators in the `regex` syntax but not in
+/// POSIX, are escaped. A `{` that does not start an interval expression is matched literally
+/// (so that fragments can still be interpolated).
+///
+/// # Errors
+/// `translate` returns a description of the problem if `regex` uses anchors (`^` and `$`),
+/// collating elements, or equivalence classes (none of which has a translation), or if a
+/// bracket expression or an interval expression is malformed.
+pub fn translate(regex: &str) -> Result<String, String> {
+    let mut translated = String::with_capacity(regex.len());
+    let mut groups = Vec::new();
+    let mut last_atom: Option<usize> = None;
+    let mut chars = regex.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let start = translated.len();
+        match c {
+            '\\' => match chars.next() {
+                Some(escaped) => {
+                    translated.push('\\');
+                    translated.push(escaped);
+                }
+                None => return Err("the regex ends with `\\`".to_string()),
+            },
+            '[' => translated.push_str(&translate_bracket(&mut chars)?),
+            '(' => {
+                groups.push(start);
+                translated.push('(');
+                last_atom = None;
+                continue;
+            }
+            ')' => {
+                translated.push(')');
+                last_atom = groups.pop();
+                continue;
+            }
+            '{' if chars.peek().map_or(false, |c| c.is_digit(10)) => {
+                let atom = match last_atom {
+                    Some(atom) => translated.split_off(atom),
+                    None => return Err("an interval expression must follow an atom".to_string()),
+                };
+                let (min, max) = parse_interval(&mut chars)?;
+                translated.push_str(&repeat(&atom, min, max));
+                last_atom = None;
+                continue;
+            }
+            '^' | '$' => return Err(format!("the anchor `{}` is not supported", c)),
+            '&' | '~' => {
+                translated.push('\\');
+                translated.push(c);
+            }
+            '*' | '+' | '?' | '|' => {
+                translated.push(c);
+                last_atom = None;
+                continue;
+            }
+            _ => translated.push(c),
+        }
+        last_atom = Some(start);
+    }
+
+    Ok(translated)
+}
+
+// Translates a bracket expression (whose opening `[` has been consumed).
+fn translate_bracket(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    let mut translated = String::from("[");
+    if chars.peek() == Some(&'^') {
+        chars.next();
+        translated.push('^');
+    }
+
+    let mut first = true;
+    loop {
+        let c = match chars.next() {
+            Some(']') if !first => break,
+            Some(c) => c,
+            None => return Err("a bracket expression is missing its `]`".to_string()),
+        };
+        first = false;
+
+        if c == '[' {
+            match chars.peek().cloned() {
+                Some(':') => {
+                    chars.next();
+                    let name = read_until(chars, ":]")?;
+                    translated.push_str(posix_class(&name)?);
+                    continue;
+                }
+                Some(delim @ '.') | Some(delim @ '=') => {
+                    return Err(format!("`[{}` in a bracket expression is not supported", delim))
+                }
+                _ => {}
+            }
+        }
+
+        translated.push_str(&escape_class_char(c));
+        if chars.peek() == Some(&'-') {
+            chars.next();
+            match chars.peek().cloned() {
+                Some(']') | None => translated.push_str("\\-"),
+                Some(last) => {
+                    chars.next();
+                    translated.push('-');
+                    translated.push_str(&escape_class_char(last));
+                }
+            }
+        }
+    }
+
+    translated.push(']');
+    Ok(translated)
+}
+
+// Reads the chars up to (and consumes) the terminator.
+fn read_until(chars: &mut Peekable<Chars>, terminator: &str) -> Result<String, String> {
+    let mut read = String::new();
+    while !read.ends_with(terminator) {
+        match chars.next() {
+            Some(c) => read.push(c),
+            None => return Err(format!("a bracket expression is missing `{}`", terminator)),
+        }
+    }
+
+    let len = read.len() - terminator.len();
+    read.truncate(len);
+    Ok(read)
+}
+
+// The contents of a class in the `regex` syntax for the POSIX character class name.
+fn posix_class(name: &str) -> Result<&'static str, String> {
+    let class = match name {
+        "alpha" => "a-zA-Z",
+        "digit" => "0-9",
+        "alnum" => "a-zA-Z0-9",
+        "upper" => "A-Z",
+        "lower" => "a-z",
+        "xdigit" => "0-9A-Fa-f",
+        "space" => " \t\n\r\u{b}\u{c}",
+        "blank" => " \t",
+        "punct" => "!-/:-@\\[-`{-~",
+        "cntrl" => "\u{0}-\u{1f}\u{7f}",
+        "print" => " -~",
+        "graph" => "!-~",
+        name => return Err(format!("[:{}:] is not a POSIX character class", name)),
+    };
+
+    Ok(class)
+}
+
+fn escape_class_char(c: char) -> String {
+    match c {
+        '\\' | ']' | '[' | '^' | '-' => format!("\\{}", c),
+        c => c.to_string(),
+    }
+}
+
+// Parses the bounds of an interval expression (whose opening `{` has been consumed).
+fn parse_interval(chars: &mut Peekable<Chars>) -> Result<(usize, Option<usize>), String> {
+    let interval = read_interval(chars)?;
+    let parse = |s: &str| {
+        s.parse::<usize>()
+            .map_err(|_| format!("{{{}}} is not a valid interval expression", interval))
+    };
+
+    let (min, max) = match interval.find(',') {
+        None => {
+            let count = parse(&interval)?;
+            (count, Some(count))
+        }
+        Some(comma) if comma + 1 == interval.len() => (parse(&interval[..comma])?, None),
+        Some(comma) => (
+            parse(&interval[..comma])?,
+            Some(parse(&interval[comma + 1..])?),
+        ),
+    };
+
+    match max {
+        Some(max) if max < min => Err(format!("{{{}}} has its bounds out of order", interval)),
+        _ => Ok((min, max)),
+    }
+}
+
+fn read_interval(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    let mut interval = String::new();
+    loop {
+        match chars.next() {
+            Some('}') => return Ok(interval),
+            Some(c) => interval.push(c),
+            None => return Err("an interval expression is missing its `}`".to_string()),
+        }
+    }
+}
+
+// Writes out the repetitions of atom: min copies followed by either a starred copy (if there
+// is no max) or max - min optional copies.
+fn repeat(atom: &str, min: usize, max: Option<usize>) -> String {
+    let atom = format!("({})", atom);
+    let mut repeated = atom.repeat(min);
+
+    match max {
+        None => {
+            repeated.push_str(&atom);
+            repeated.push('*');
+        }
+        Some(max) => for _ in min..max {
+            repeated.push_str(&atom);
+            repeated.push('?');
+        },
+    }
+
+    if repeated.is_empty() {
+        "()".to_string()
+    } else {
+        repeated
+    }
+}
@@ -0,0 +1,63 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+use enum_info::EnumInfo;
+use dfa::LexerDfa;
+
+/// The number of states above which the size of the dfa is reported.
+const STATE_THRESHOLD: usize = 1000;
+
+/// Finds the likely problems with the dfa for a lexer.
+///
+/// There is a warning when a variant can never be matched because every input it
+/// matches is matched by a variant of a higher priority, when the regular expressions
+/// for two variants match exactly the same input, and when the dfa has more than
+/// `STATE_THRESHOLD` states.
+pub fn warnings(info: &EnumInfo, lexer_dfa: &LexerDfa) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for (i, vi) in info.variants.iter().enumerate() {
+        for (other, other_re) in info.variants.iter().zip(lexer_dfa.regexs.iter()).skip(i + 1) {
+            if vi.mode == other.mode && lexer_dfa.regexs[i] == *other_re {
+                warnings.push(format!(
+                    "variants {} and {} match exactly the same input",
+                    vi.name, other.name
+                ));
+            }
+        }
+
+        let matched = lexer_dfa
+            .dfa
+            .states
+            .iter()
+            .any(|state| state.value.map_or(false, |value| value.name == vi.name));
+        if !matched {
+            warnings.push(format!(
+                "variant {} can never be matched because it is shadowed by higher priority \
+                 variants",
+                vi.name
+            ));
+        }
+    }
+
+    let num_states = lexer_dfa.dfa.states.len();
+    if num_states > STATE_THRESHOLD {
+        warnings.push(format!(
+            "the dfa {} has {} states (consider the \"tables\" codegen strategy)",
+            info.dfa_name, num_states
+        ));
+    }
+
+    warnings
+}
+
+/// Reports the warnings on standard error.
+pub fn report_warnings(warnings: &[String]) {
+    for message in warnings {
+        eprintln!("warning: luther: {}", message);
+    }
+}
@@ -0,0 +1,90 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+/// A syntax error at a particular column (counted in `char`'s) of a regular expression.
+#[derive(Debug, PartialEq)]
+pub struct SyntaxError {
+    pub column: usize,
+    pub expected: String,
+}
+
+/// Checks `regex` for the common syntax errors that can be located precisely.
+///
+/// This is a check of the structure of the regular expression only (balanced parentheses
+/// and brackets, complete escapes, and repetition operators that follow an expression).
+/// A regular expression that passes this check may still fail to parse.
+pub fn check_syntax(regex: &str) -> Result<(), SyntaxError> {
+    let mut groups = Vec::new();
+    let mut after_expr = false;
+    let mut chars = regex.chars().enumerate();
+
+    while let Some((column, c)) = chars.next() {
+        match c {
+            '\\' => {
+                if chars.next().is_none() {
+                    return error(column + 1, "a character after `\\`");
+                }
+                after_expr = true;
+            }
+            '(' => {
+                groups.push(column);
+                after_expr = false;
+            }
+            ')' => {
+                if groups.pop().is_none() {
+                    return error(column, "an expression or `(` before `)`");
+                }
+                after_expr = true;
+            }
+            '|' | '&' | '~' => after_expr = false,
+            '*' | '+' | '?' => if !after_expr {
+                return error(column, &format!("an expression before `{}`", c));
+            },
+            '[' => {
+                check_class(column, &mut chars)?;
+                after_expr = true;
+            }
+            _ => after_expr = true,
+        }
+    }
+
+    match groups.pop() {
+        Some(_) => error(regex.chars().count(), "`)`"),
+        None => Ok(()),
+    }
+}
+
+fn check_class<I>(start: usize, chars: &mut I) -> Result<(), SyntaxError>
+where
+    I: Iterator<Item = (usize, char)>,
+{
+    let mut end = start + 1;
+    let mut first = true;
+
+    while let Some((column, c)) = chars.next() {
+        end = column + 1;
+        match c {
+            ']' if !first => return Ok(()),
+            '\\' => if chars.next().is_none() {
+                return error(column + 1, "a character after `\\`");
+            },
+            '^' if first => continue,
+            _ => {}
+        }
+        first = false;
+    }
+
+    error(end, "`]`")
+}
+
+fn error(column: usize, expected: &str) -> Result<(), SyntaxError> {
+    Err(SyntaxError {
+        column,
+        expected: expected.to_string(),
+    })
+}
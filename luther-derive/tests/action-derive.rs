@@ -0,0 +1,50 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+extern crate luther;
+
+#[macro_use]
+extern crate luther_derive;
+
+use std::cell::RefCell;
+use luther::{Lexer, Location, Span};
+use luther::spanned::StrExt;
+
+#[derive(Lexer, Debug, PartialEq)]
+enum Token {
+    #[luther(regex = "[a-z]+")] Ident(String),
+    #[luther(regex = "\n", skip, action = "on_newline")] Newline,
+}
+
+thread_local! {
+    static LINE_ENDS: RefCell<Vec<Location>> = RefCell::new(Vec::new());
+}
+
+fn on_newline(matched: &str, span: &Span<Token>) {
+    assert_eq!(matched, "\n");
+    LINE_ENDS.with(|ends| ends.borrow_mut().push(span.start()));
+}
+
+#[test]
+fn token_calls_action_for_variant() {
+    use Token::*;
+    let input = "ab\ncd\nef".spanned_chars();
+
+    let sut = Token::lexer(input).map_span(|s| s.into_inner().1);
+    let results: Result<Vec<_>, _> = sut.collect();
+
+    assert_eq!(
+        results.expect("Unexpected error in the lexer."),
+        vec![
+            Ident("ab".to_string()),
+            Ident("cd".to_string()),
+            Ident("ef".to_string()),
+        ]
+    );
+    LINE_ENDS.with(|ends| assert_eq!(*ends.borrow(), vec![2.into(), 5.into()]));
+}
@@ -0,0 +1,54 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+extern crate luther;
+
+#[macro_use]
+extern crate luther_derive;
+
+#[macro_use]
+extern crate assert_matches;
+
+use luther::{LexError, Lexer};
+use std::io::ErrorKind;
+
+#[derive(Lexer, Debug, PartialEq)]
+#[luther(byte_lexer = "TokenLexerBytes")]
+enum Token {
+    #[luther(regex = "[a-zé]+")] Ident(String),
+    #[luther(regex = " +")] WhiteSpace,
+}
+
+#[test]
+fn token_lexes_utf8_bytes_with_byte_offsets() {
+    use Token::*;
+    let input = "é ab".bytes().map(Ok);
+
+    let sut: TokenLexerBytes<_> = Token::byte_lexer(input);
+    let results: Result<Vec<_>, _> = sut.map_span(|s| s.into_inner()).collect();
+
+    assert_eq!(
+        results.expect("Unexpected error in the lexer."),
+        vec![
+            (0.into(), Ident("é".to_string()), 1.into()),
+            (2.into(), WhiteSpace, 2.into()),
+            (3.into(), Ident("ab".to_string()), 4.into()),
+        ]
+    );
+}
+
+#[test]
+fn token_byte_lexer_is_err_for_invalid_utf8() {
+    let input = vec![b'a', 0xff, b'b'].into_iter().map(Ok);
+
+    let mut sut: TokenLexerBytes<_> = Token::byte_lexer(input);
+    let result = sut.find(|result| result.is_err());
+
+    assert_matches!(result, Some(Err(LexError::InputError(ref e)))
+        if e.kind() == ErrorKind::InvalidData);
+}
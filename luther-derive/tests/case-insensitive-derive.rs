@@ -0,0 +1,58 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+extern crate luther;
+
+#[macro_use]
+extern crate luther_derive;
+
+use luther::Lexer;
+use luther::spanned::StrExt;
+
+#[derive(Lexer, Debug, PartialEq)]
+enum Token {
+    #[luther(regex = "select", case_insensitive)] Select,
+    #[luther(regex = "from", case_insensitive)] From,
+    #[luther(regex = "[a-z_][a-z0-9_]*", case_insensitive)] Ident(String),
+    #[luther(regex = " +")] WhiteSpace,
+}
+
+#[test]
+fn token_lexes_keywords_in_any_case() {
+    use Token::*;
+    let input = "SELECT a FrOm b".spanned_chars();
+
+    let sut = Token::lexer(input).map_span(|s| s.into_inner().1);
+    let results: Result<Vec<_>, _> = sut.collect();
+
+    assert_eq!(
+        results.expect("Unexpected error in the lexer."),
+        vec![
+            Select,
+            WhiteSpace,
+            Ident("a".to_string()),
+            WhiteSpace,
+            From,
+            WhiteSpace,
+            Ident("b".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn token_lexes_identifiers_in_any_case() {
+    let input = "SelectAll".spanned_chars();
+
+    let mut sut = Token::lexer(input).map_span(|s| s.into_inner().1);
+    let result = sut.next();
+
+    assert_eq!(
+        result.expect("Unexpected end of input.").expect("Unexpected error in the lexer."),
+        Token::Ident("SelectAll".to_string())
+    );
+}
@@ -0,0 +1,53 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+extern crate luther;
+
+#[macro_use]
+extern crate luther_derive;
+
+#[macro_use]
+extern crate assert_matches;
+
+use luther::{LexError, Lexer};
+use luther::spanned::StrExt;
+
+#[derive(Lexer, Debug, PartialEq)]
+enum Token {
+    #[luther(regex = "0x[0-9a-f]+", with = "parse_hex")] Hex(u8),
+    #[luther(regex = "[0-9]+")] Int(i64),
+    #[luther(regex = " +")] WhiteSpace,
+}
+
+fn parse_hex(s: &str) -> Result<u8, std::num::ParseIntError> {
+    u8::from_str_radix(&s[2..], 16)
+}
+
+#[test]
+fn token_converts_with_from_str_and_with_function() {
+    use Token::*;
+    let input = "42 0xff".spanned_chars();
+
+    let sut = Token::lexer(input).map_span(|s| s.into_inner().1);
+    let results: Result<Vec<_>, _> = sut.collect();
+
+    assert_eq!(
+        results.expect("Unexpected error in the lexer."),
+        vec![Int(42), WhiteSpace, Hex(255)]
+    );
+}
+
+#[test]
+fn token_conversion_failure_is_invalid_value() {
+    let input = "0x100".spanned_chars();
+
+    let mut sut = Token::lexer(input).map_span(|s| s.into_inner().1);
+    let result = sut.next();
+
+    assert_matches!(result, Some(Err(LexError::InvalidValue(ref s, _))) if s == "0x100");
+}
@@ -0,0 +1,31 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+extern crate luther;
+
+#[macro_use]
+extern crate luther_derive;
+
+#[derive(Lexer, Debug, PartialEq)]
+#[luther(display)]
+enum Token {
+    #[luther(regex = "[a-z]+", keywords(If = "if"))] Ident(String),
+    #[luther(regex = "\\(", display = "`(`")] LParen,
+    If,
+}
+
+#[test]
+fn token_name_defaults_to_variant_name() {
+    assert_eq!(Token::Ident("ab".to_string()).name(), "Ident");
+    assert_eq!(Token::If.name(), "If");
+}
+
+#[test]
+fn token_display_uses_display_option() {
+    assert_eq!(Token::LParen.to_string(), "`(`");
+}
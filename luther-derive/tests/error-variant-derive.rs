@@ -0,0 +1,67 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+extern crate luther;
+
+#[macro_use]
+extern crate luther_derive;
+
+use luther::Lexer;
+use luther::spanned::StrExt;
+
+#[derive(Lexer, Debug, PartialEq)]
+enum Token {
+    #[luther(regex = "[a-z]+")] Ident(String),
+    #[luther(regex = "\"[a-z]*\"")] Str(String),
+    #[luther(regex = " +")] WhiteSpace,
+    #[luther(error)] Garbage(String),
+}
+
+#[test]
+fn token_lexes_unmatched_input_as_error_variant() {
+    use Token::*;
+    let input = "ab 12$ \"cd".spanned_chars();
+
+    let sut = Token::lexer(input).map_span(|s| s.into_inner().1);
+    let results: Result<Vec<_>, _> = sut.collect();
+
+    assert_eq!(
+        results.expect("Unexpected error in the lexer."),
+        vec![
+            Ident("ab".to_string()),
+            WhiteSpace,
+            Garbage("12$".to_string()),
+            WhiteSpace,
+            Garbage("\"cd".to_string()),
+        ]
+    );
+}
+
+#[derive(Lexer, Debug, PartialEq)]
+enum CountedToken {
+    #[luther(regex = "[a-z]+")] Ident,
+    #[luther(error, with = "count_chars")] Garbage(usize),
+}
+
+fn count_chars(s: &str) -> Result<usize, String> {
+    Ok(s.chars().count())
+}
+
+#[test]
+fn token_converts_error_variant_with_function() {
+    use CountedToken::*;
+    let input = "ab12$cd".spanned_chars();
+
+    let sut = CountedToken::lexer(input).map_span(|s| s.into_inner().1);
+    let results: Result<Vec<_>, _> = sut.collect();
+
+    assert_eq!(
+        results.expect("Unexpected error in the lexer."),
+        vec![Ident, Garbage(3), Ident]
+    );
+}
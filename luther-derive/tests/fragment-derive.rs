@@ -0,0 +1,57 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+extern crate luther;
+
+#[macro_use]
+extern crate luther_derive;
+
+use luther::Lexer;
+use luther::spanned::StrExt;
+
+#[derive(Lexer, Debug, PartialEq)]
+#[luther(fragment(digit = "[0-9]", exp = "[eE][-+]?{digit}+"))]
+enum Token {
+    #[luther(regex = "{digit}+")] Integer(String),
+    #[luther(regex = r"{digit}+\.{digit}*{exp}?")] Float(String),
+    #[luther(regex = "{|}")] Brace,
+    #[luther(regex = " +")] WhiteSpace,
+}
+
+#[test]
+fn token_lexes_numbers_using_fragments() {
+    use Token::*;
+    let input = "12 3.5e-7 4.".spanned_chars();
+
+    let sut = Token::lexer(input).map_span(|s| s.into_inner().1);
+    let results: Result<Vec<_>, _> = sut.collect();
+
+    assert_eq!(
+        results.expect("Unexpected error in the lexer."),
+        vec![
+            Integer("12".to_string()),
+            WhiteSpace,
+            Float("3.5e-7".to_string()),
+            WhiteSpace,
+            Float("4.".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn token_lexes_braces_that_are_not_fragments() {
+    let input = "{".spanned_chars();
+
+    let mut sut = Token::lexer(input).map_span(|s| s.into_inner().1);
+    let result = sut.next();
+
+    assert_eq!(
+        result.expect("Unexpected end of input.").expect("Unexpected error in the lexer."),
+        Token::Brace
+    );
+}
@@ -0,0 +1,47 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+extern crate luther;
+
+#[macro_use]
+extern crate luther_derive;
+
+use luther::Lexer;
+use luther::spanned::StrExt;
+
+#[derive(Lexer, Debug, PartialEq)]
+#[luther(fragment_file = "tests/regex/common.fragments")]
+enum Number {
+    #[luther(regex = "{digit}+")] Integer(String),
+    #[luther(regex = "{digit}+\\.{digit}*{exp}?")] Float(String),
+}
+
+#[derive(Lexer, Debug, PartialEq)]
+#[luther(fragment_file = "tests/regex/common.fragments")]
+enum Version {
+    #[luther(regex = "v{digit}+")] Major(String),
+}
+
+#[test]
+fn enums_share_fragments_from_file() {
+    let numbers: Result<Vec<_>, _> = Number::lexer("1.5e3".spanned_chars())
+        .map_span(|s| s.into_inner().1)
+        .collect();
+    let versions: Result<Vec<_>, _> = Version::lexer("v12".spanned_chars())
+        .map_span(|s| s.into_inner().1)
+        .collect();
+
+    assert_eq!(
+        numbers.expect("Unexpected error in the number lexer."),
+        vec![Number::Float("1.5e3".to_string())]
+    );
+    assert_eq!(
+        versions.expect("Unexpected error in the version lexer."),
+        vec![Version::Major("v12".to_string())]
+    );
+}
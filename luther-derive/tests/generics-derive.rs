@@ -0,0 +1,64 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+extern crate luther;
+
+#[macro_use]
+extern crate luther_derive;
+
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::str::FromStr;
+use luther::Lexer;
+use luther::spanned::{Never, StrExt};
+
+#[derive(Lexer, Debug, PartialEq)]
+enum Token<'src> {
+    #[luther(regex = "[a-z]+", with = "owned")] Ident(Cow<'src, str>),
+    #[luther(regex = " +")] WhiteSpace,
+}
+
+fn owned(s: &str) -> Result<Cow<'static, str>, Never> {
+    Ok(Cow::Owned(s.to_string()))
+}
+
+#[derive(Lexer, Debug, PartialEq)]
+enum Value<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    #[luther(regex = "[0-9]+")] Num(T),
+}
+
+#[test]
+fn token_with_lifetime_lexes_owned_values() {
+    use Token::*;
+    let input = "ab cd".spanned_chars();
+
+    let sut = Token::lexer(input).map_span(|s| s.into_inner().1);
+    let results: Result<Vec<_>, _> = sut.collect();
+
+    assert_eq!(
+        results.expect("Unexpected error in the lexer."),
+        vec![Ident("ab".into()), WhiteSpace, Ident("cd".into())]
+    );
+}
+
+#[test]
+fn token_with_type_parameter_lexes_values_of_that_type() {
+    let input = "123".spanned_chars();
+
+    let sut = Value::<u16>::lexer(input).map_span(|s| s.into_inner().1);
+    let results: Result<Vec<_>, _> = sut.collect();
+
+    assert_eq!(
+        results.expect("Unexpected error in the lexer."),
+        vec![Value::Num(123)]
+    );
+}
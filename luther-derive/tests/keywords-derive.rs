@@ -0,0 +1,43 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+extern crate luther;
+
+#[macro_use]
+extern crate luther_derive;
+
+use luther::Lexer;
+use luther::spanned::StrExt;
+
+#[derive(Lexer, Debug, PartialEq)]
+enum Token {
+    #[luther(regex = "[a-z]+", keywords(If = "if", While = "while"))] Ident(String),
+    #[luther(regex = " +")] WhiteSpace,
+    If,
+    While,
+}
+
+#[test]
+fn token_lexes_keywords_through_identifier() {
+    use Token::*;
+    let input = "if iff while".spanned_chars();
+
+    let sut = Token::lexer(input).map_span(|s| s.into_inner().1);
+    let results: Result<Vec<_>, _> = sut.collect();
+
+    assert_eq!(
+        results.expect("Unexpected error in the lexer."),
+        vec![
+            If,
+            WhiteSpace,
+            Ident("iff".to_string()),
+            WhiteSpace,
+            While,
+        ]
+    );
+}
@@ -0,0 +1,41 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+extern crate luther;
+
+#[macro_use]
+extern crate luther_derive;
+
+#[derive(Lexer, Debug, PartialEq)]
+#[luther(kind_codes)]
+enum Token {
+    #[luther(regex = "[a-z]+", keywords(If = "if"))] Ident(String),
+    #[luther(regex = "\\(", code = "40")] LParen,
+    #[luther(regex = "\\)")] RParen,
+    #[luther(code = "7")] If,
+}
+
+#[test]
+fn kind_codes_follow_the_previous_variant() {
+    assert_eq!(Token::Ident("ab".to_string()).kind_code(), 0);
+    assert_eq!(Token::RParen.kind_code(), 41);
+}
+
+#[test]
+fn kind_codes_use_code_option() {
+    assert_eq!(Token::LParen.kind_code(), 40);
+    assert_eq!(Token::If.kind_code(), 7);
+}
+
+#[test]
+fn from_code_finds_unit_variants() {
+    assert_eq!(Token::from_code(7), Some(Token::If));
+    assert_eq!(Token::from_code(41), Some(Token::RParen));
+    assert_eq!(Token::from_code(0), None);
+    assert_eq!(Token::from_code(1), None);
+}
@@ -0,0 +1,77 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+extern crate luther;
+
+#[macro_use]
+extern crate luther_derive;
+
+use luther::Lexer;
+use luther::spanned::StrExt;
+
+#[derive(Lexer, Debug, PartialEq)]
+#[luther(minimize = "fast")]
+enum FastToken {
+    #[luther(regex = "if|else|elif")] Keyword(String),
+    #[luther(regex = "[a-z]+", priority_group = "1")] Ident(String),
+    #[luther(regex = "[0-9]+|0x[0-9a-f]+")] Int(String),
+    #[luther(regex = " +")] WhiteSpace,
+}
+
+#[derive(Lexer, Debug, PartialEq)]
+#[luther(minimize = "full", codegen = "tables")]
+enum FullToken {
+    #[luther(regex = "if|else|elif")] Keyword(String),
+    #[luther(regex = "[a-z]+", priority_group = "1")] Ident(String),
+    #[luther(regex = "[0-9]+|0x[0-9a-f]+")] Int(String),
+    #[luther(regex = " +")] WhiteSpace,
+}
+
+#[test]
+fn fast_minimized_token_lexes_as_expected() {
+    use FastToken::*;
+    let input = "elif elf 0x1f 42".spanned_chars();
+
+    let sut = FastToken::lexer(input).map_span(|s| s.into_inner().1);
+    let results: Result<Vec<_>, _> = sut.collect();
+
+    assert_eq!(
+        results.expect("Unexpected error in the lexer."),
+        vec![
+            Keyword("elif".to_string()),
+            WhiteSpace,
+            Ident("elf".to_string()),
+            WhiteSpace,
+            Int("0x1f".to_string()),
+            WhiteSpace,
+            Int("42".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn full_minimized_token_lexes_as_expected() {
+    use FullToken::*;
+    let input = "elif elf 0x1f 42".spanned_chars();
+
+    let sut = FullToken::lexer(input).map_span(|s| s.into_inner().1);
+    let results: Result<Vec<_>, _> = sut.collect();
+
+    assert_eq!(
+        results.expect("Unexpected error in the lexer."),
+        vec![
+            Keyword("elif".to_string()),
+            WhiteSpace,
+            Ident("elf".to_string()),
+            WhiteSpace,
+            Int("0x1f".to_string()),
+            WhiteSpace,
+            Int("42".to_string()),
+        ]
+    );
+}
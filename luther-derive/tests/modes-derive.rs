@@ -0,0 +1,49 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+extern crate luther;
+
+#[macro_use]
+extern crate luther_derive;
+
+use luther::Lexer;
+use luther::spanned::StrExt;
+
+#[derive(Lexer, Debug, PartialEq)]
+enum Token {
+    #[luther(regex = "[a-z]+")] Ident(String),
+    #[luther(regex = " +")] WhiteSpace,
+    #[luther(regex = "\"", push_mode = "STRING")] StringStart,
+    #[luther(regex = "[^\"\\\\]+", mode = "STRING")] StringText(String),
+    #[luther(regex = "\\\\.", mode = "STRING")] StringEscape(String),
+    #[luther(regex = "\"", mode = "STRING", pop_mode)] StringEnd,
+}
+
+#[test]
+fn token_lexes_string_contents_in_string_mode() {
+    use Token::*;
+    let input = r#"say "hi \" there" now"#.spanned_chars();
+
+    let sut = Token::lexer(input).map_span(|s| s.into_inner().1);
+    let results: Result<Vec<_>, _> = sut.collect();
+
+    assert_eq!(
+        results.expect("Unexpected error in the lexer."),
+        vec![
+            Ident("say".to_string()),
+            WhiteSpace,
+            StringStart,
+            StringText("hi ".to_string()),
+            StringEscape("\\\"".to_string()),
+            StringText(" there".to_string()),
+            StringEnd,
+            WhiteSpace,
+            Ident("now".to_string()),
+        ]
+    );
+}
@@ -0,0 +1,61 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+extern crate luther;
+
+#[macro_use]
+extern crate luther_derive;
+
+use luther::Lexer;
+use luther::spanned::StrExt;
+
+#[derive(Lexer, Debug, PartialEq)]
+#[luther(syntax = "posix", fragment(octet = "[0-9]{1,3}"))]
+enum Token {
+    #[luther(regex = "[[:alpha:]_][[:alnum:]_]*")] Ident(String),
+    #[luther(regex = r"{octet}(\.{octet}){3}")] Address(String),
+    #[luther(regex = "[[:digit:]]+")] Number(String),
+    #[luther(regex = "&&|~")] Op(String),
+    #[luther(regex = "[[:space:]]+")] WhiteSpace,
+}
+
+#[test]
+fn token_lexes_posix_regexs() {
+    use Token::*;
+    let input = "x_1 10.0.0.1 1234 &&~".spanned_chars();
+
+    let sut = Token::lexer(input).map_span(|s| s.into_inner().1);
+    let results: Result<Vec<_>, _> = sut.collect();
+
+    assert_eq!(
+        results.expect("Unexpected error in the lexer."),
+        vec![
+            Ident("x_1".to_string()),
+            WhiteSpace,
+            Address("10.0.0.1".to_string()),
+            WhiteSpace,
+            Number("1234".to_string()),
+            WhiteSpace,
+            Op("&&".to_string()),
+            Op("~".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn token_does_not_lex_more_than_interval_allows() {
+    let input = "1234.5.6.7".spanned_chars();
+
+    let mut sut = Token::lexer(input).map_span(|s| s.into_inner().1);
+    let result = sut.next();
+
+    assert_eq!(
+        result.expect("Unexpected end of input.").expect("Unexpected error in the lexer."),
+        Token::Number("1234".to_string())
+    );
+}
@@ -0,0 +1,46 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+extern crate luther;
+
+#[macro_use]
+extern crate luther_derive;
+
+use luther::Lexer;
+use luther::spanned::StrExt;
+
+#[derive(Lexer, Debug, PartialEq)]
+#[luther(codegen = "tables")]
+enum Token {
+    #[luther(regex = "if")] If,
+    #[luther(regex = "[a-zα-ω][a-zα-ω0-9]*")] Ident(String),
+    #[luther(regex = "[0-9]+")] Int(u32),
+    #[luther(regex = " +")] WhiteSpace,
+}
+
+#[test]
+fn token_lexes_with_table_driven_dfa() {
+    use Token::*;
+    let input = "if iff 42 λx1".spanned_chars();
+
+    let sut = Token::lexer(input).map_span(|s| s.into_inner().1);
+    let results: Result<Vec<_>, _> = sut.collect();
+
+    assert_eq!(
+        results.expect("Unexpected error in the lexer."),
+        vec![
+            If,
+            WhiteSpace,
+            Ident("iff".to_string()),
+            WhiteSpace,
+            Int(42),
+            WhiteSpace,
+            Ident("λx1".to_string()),
+        ]
+    );
+}
@@ -0,0 +1,171 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Defines `lex_in_background`, which lexes on a background thread.
+//!
+//! This module requires the `channel` feature (which is not enabled by default). It suits
+//! pipelines that parse the tokens on one thread while the input is lexed on another. The
+//! tokens are sent through a bounded channel, so the lexer waits for the parser whenever it is
+//! `bound` tokens ahead rather than buffering the whole token stream.
+
+use std::result::Result as StdResult;
+use std::sync::mpsc::{self, Receiver};
+use std::thread::{self, JoinHandle};
+
+use failure::Fail;
+
+use super::{Lexer, Result, Span};
+use dfa::LexerIter;
+
+/// Lexes `input` on a background thread.
+///
+/// At most `bound` tokens (and errors) are buffered between the lexer and the returned
+/// iterator. A `bound` of 0 makes each token a rendezvous between the two threads.
+///
+/// The lexer stops early if the returned iterator is dropped. If the lexer panics, the
+/// returned iterator panics with the same payload after yielding the tokens that were sent
+/// before the panic.
+pub fn lex_in_background<T, F, I>(input: I, bound: usize) -> TokenReceiver<T, F>
+where
+    T: Lexer + Send + 'static,
+    F: Fail,
+    I: IntoIterator<Item = StdResult<Span<char>, F>>,
+    I::IntoIter: Send + 'static,
+{
+    let (sender, receiver) = mpsc::sync_channel(bound);
+    let input = input.into_iter();
+    let handle = thread::spawn(move || {
+        let lexer: LexerIter<T, F, _, T::Dfa> = LexerIter::new(input);
+        for token in lexer {
+            if sender.send(token).is_err() {
+                break;
+            }
+        }
+    });
+
+    TokenReceiver {
+        receiver,
+        handle: Some(handle),
+    }
+}
+
+/// An iterator over the tokens that are lexed on a background thread.
+///
+/// This is created by `lex_in_background`.
+pub struct TokenReceiver<T, F: Fail> {
+    receiver: Receiver<Result<Span<T>, F>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl<T, F: Fail> Iterator for TokenReceiver<T, F> {
+    type Item = Result<Span<T>, F>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.receiver.recv() {
+            Ok(token) => Some(token),
+            Err(_) => {
+                // The lexer thread has ended, so pass on its panic (if any).
+                if let Some(handle) = self.handle.take() {
+                    if let Err(payload) = handle.join() {
+                        ::std::panic::resume_unwind(payload);
+                    }
+                }
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dfa::Dfa;
+    use spanned::{Never, StrExt};
+    use LexError;
+
+    #[derive(Debug, PartialEq)]
+    struct Digit(char);
+
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    enum DigitDfa {
+        Start,
+        Digit(char),
+        Error,
+    }
+
+    impl Default for DigitDfa {
+        fn default() -> Self {
+            DigitDfa::Start
+        }
+    }
+
+    impl Dfa<Digit> for DigitDfa {
+        fn is_error(&self) -> bool {
+            *self == DigitDfa::Error
+        }
+
+        fn transition(&self, c: char) -> Self {
+            match (*self, c) {
+                (DigitDfa::Start, '0'...'9') => DigitDfa::Digit(c),
+                (_, _) => DigitDfa::Error,
+            }
+        }
+
+        fn accept(&self, _: &str) -> Option<Digit> {
+            match *self {
+                DigitDfa::Digit(c) => Some(Digit(c)),
+                _ => None,
+            }
+        }
+    }
+
+    impl Lexer for Digit {
+        type Dfa = DigitDfa;
+    }
+
+    #[test]
+    fn lex_in_background_yields_tokens_in_order() {
+        let input: Vec<_> = "0123456789".spanned_chars().collect();
+
+        let sut = lex_in_background::<Digit, Never, _>(input, 2);
+
+        let tokens: StdResult<Vec<_>, _> = sut.collect();
+        let digits: Vec<_> = tokens
+            .expect("unexpected lexer error")
+            .into_iter()
+            .map(|span| {
+                let (_, Digit(c), _) = span.into_inner();
+                c
+            })
+            .collect();
+        assert_eq!(digits, "0123456789".chars().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn lex_in_background_yields_errors() {
+        let input: Vec<_> = "1x".spanned_chars().collect();
+
+        let mut sut = lex_in_background::<Digit, Never, _>(input, 0);
+
+        assert_matches!(sut.next(), Some(Ok(_)));
+        assert_matches!(sut.next(), Some(Err(LexError::InvalidCharacter('x'))));
+        assert_matches!(sut.next(), None);
+    }
+
+    #[test]
+    fn lex_in_background_stops_when_receiver_is_dropped() {
+        let input: Vec<_> = "0123456789".spanned_chars().collect();
+
+        let mut sut = lex_in_background::<Digit, Never, _>(input, 0);
+        assert_matches!(sut.next(), Some(Ok(_)));
+        let handle = sut.handle.take().expect("no lexer thread");
+        drop(sut);
+
+        assert!(handle.join().is_ok());
+    }
+}
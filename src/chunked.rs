@@ -0,0 +1,440 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Defines `ChunkedLexer`, a lexer that is fed its input one `&str` chunk at a time.
+//!
+//! `ChunkedLexer` suits hosts that own the input and hand it over in pieces, such as a
+//! JavaScript editor driving a lexer compiled to `wasm32-unknown-unknown`. It depends only on
+//! the parts of the crate that are available without the `io` feature.
+//!
+//! `ChunkedLexer::snapshot` saves the state of a lexer in the middle of its input (its offset,
+//! its mode stack, and the input that it is holding back) so that a long running stream
+//! processor can checkpoint its lexer and resume it with `ChunkedLexer::from_snapshot` after a
+//! restart. A snapshot is a version 1 binary encoding of the magic bytes `LUTS`, the format
+//! version as a little endian `u32`, and then these little endian `u64`'s:
+//!
+//! 1. the offset of the held back input from the start of the first chunk;
+//! 2. the number of modes on the mode stack, followed by each mode (from the bottom of the stack
+//!    up) and its delimiter depth; and
+//! 3. the length in bytes of the held back input, followed by the UTF-8 input itself.
+//!
+//! The dfa is not a part of a snapshot (it is the code or tables of the `Lexer`), so a snapshot
+//! must be resumed with the same `Lexer` that it was taken from. Nor is the state of the dfa:
+//! a resumed lexer lexes the held back input again from the start state of its mode.
+
+use std::marker::PhantomData;
+use std::result::Result as StdResult;
+use std::str;
+
+use super::{Lexer, Location, Result, Span};
+use dfa::{Dfa, LexerIter};
+use spanned::Never;
+
+/// A lexer that is fed its input in `&str` chunks and returns the tokens that it has lexed
+/// after each chunk.
+///
+/// A token at the end of a chunk may continue into the next chunk, so `push` holds back the
+/// input from the start of the last token and lexes it again once a later chunk ends it. Until
+/// then the dfa resumes from the state it stopped in, so a token that spans many chunks is
+/// scanned in time linear in its length. `finish` lexes whatever input has been held back. The `Location`'s of the tokens are byte
+/// offsets from the start of the first chunk (which may be past 4 GiB, even on 32-bit targets),
+/// and the lexer mode carries over from one chunk to the next.
+///
+/// The `action` of a token that is lexed from input that is held back runs each time that the
+/// input is lexed.
+pub struct ChunkedLexer<T: Lexer> {
+    buffer: String,
+    offset: u64,
+    modes: Vec<(usize, usize)>,
+    state: Option<T::Dfa>,
+    _t: PhantomData<T>,
+}
+
+impl<T: Lexer> ChunkedLexer<T> {
+    /// Creates a new `ChunkedLexer` that has not been fed any input.
+    pub fn new() -> ChunkedLexer<T> {
+        ChunkedLexer {
+            buffer: String::new(),
+            offset: 0,
+            modes: vec![(0, 0)],
+            state: None,
+            _t: PhantomData,
+        }
+    }
+
+    /// Feeds the next chunk of input to the lexer.
+    ///
+    /// # Returns
+    /// The tokens (and errors) that are known to be complete. This does not include the last
+    /// token lexed so far, which may continue into the next chunk.
+    pub fn push(&mut self, chunk: &str) -> Vec<Result<Span<T>, Never>> {
+        self.buffer.push_str(chunk);
+
+        // A chunk that only continues the held back token needs no lexing.
+        if let Some(state) = self.state.take() {
+            self.state = Self::resume(state, chunk);
+            if self.state.is_some() {
+                return Vec::new();
+            }
+        }
+
+        self.lex(false)
+    }
+
+    /// Ends the input.
+    ///
+    /// # Returns
+    /// The tokens (and errors) for the input that has been held back.
+    pub fn finish(&mut self) -> Vec<Result<Span<T>, Never>> {
+        self.lex(true)
+    }
+
+    fn lex(&mut self, finish: bool) -> Vec<Result<Span<T>, Never>> {
+        let offset = self.offset;
+        let mut tokens = Vec::new();
+
+        // The tokens to return, the length of the input that they cover, and the lexer modes
+        // after them. Unless this is the end of the input they stop before the last scan of
+        // the input (and its token, unless it was skipped), which may continue.
+        let (count, len, modes) = {
+            let input = self
+                .buffer
+                .char_indices()
+                .map(|(pos, c)| {
+                    let start = Location::from_u64(offset) + pos;
+                    Ok(Span::new(start, start + (c.len_utf8() - 1), c))
+                });
+            let mut lexer: LexerIter<T, Never, _, T::Dfa> =
+                LexerIter::with_modes(input, self.modes.clone()).tracking_scans();
+
+            let mut last = None;
+            while let Some(token) = lexer.next() {
+                tokens.push(token);
+                last = lexer.last_scan().map(|&(start, _)| start);
+            }
+
+            match lexer.last_scan() {
+                Some(&(start, ref modes)) if !finish => {
+                    let count = if last == Some(start) {
+                        tokens.len() - 1
+                    } else {
+                        tokens.len()
+                    };
+                    (count, (start.as_u64() - offset) as usize, modes.clone())
+                }
+                _ => (tokens.len(), self.buffer.len(), lexer.modes().to_vec()),
+            }
+        };
+
+        tokens.truncate(count);
+        self.buffer.drain(..len);
+        self.offset += len as u64;
+        self.modes = modes;
+
+        let mode = self.modes.last().map_or(0, |&(mode, _)| mode);
+        self.state = if finish {
+            None
+        } else {
+            Self::resume(T::Dfa::start(mode), &self.buffer)
+        };
+
+        tokens
+    }
+
+    // The state of the dfa after it scans input from state (or None if the scan stops, in
+    // which case the input must be lexed).
+    fn resume(mut state: T::Dfa, input: &str) -> Option<T::Dfa> {
+        for c in input.chars() {
+            state = state.transition(c);
+            if state.is_error() {
+                return None;
+            }
+        }
+        Some(state)
+    }
+
+    /// Saves the state of the lexer in the binary encoding of a snapshot.
+    ///
+    /// The tokens that have already been returned are not a part of the snapshot, so a lexer
+    /// resumed from it produces the tokens from the held back input on.
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut bytes = SNAPSHOT_MAGIC.to_vec();
+        bytes.extend_from_slice(&u32_bytes(SNAPSHOT_VERSION));
+        push_u64(&mut bytes, self.offset);
+        push_u64(&mut bytes, self.modes.len() as u64);
+        for &(mode, depth) in &self.modes {
+            push_u64(&mut bytes, mode as u64);
+            push_u64(&mut bytes, depth as u64);
+        }
+        push_u64(&mut bytes, self.buffer.len() as u64);
+        bytes.extend_from_slice(self.buffer.as_bytes());
+        bytes
+    }
+
+    /// Resumes a lexer from the binary encoding of a snapshot that was taken with `snapshot`.
+    pub fn from_snapshot(bytes: &[u8]) -> StdResult<ChunkedLexer<T>, SnapshotError> {
+        if bytes.len() < 4 || &bytes[..4] != SNAPSHOT_MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+        if bytes.len() < 8 {
+            return Err(SnapshotError::BadLength);
+        }
+
+        let version = read_u32(&bytes[4..8]);
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let mut rest = &bytes[8..];
+        let offset = read_u64(&mut rest).ok_or(SnapshotError::BadLength)?;
+        let count = read_u64(&mut rest).ok_or(SnapshotError::BadLength)?;
+        if count == 0 {
+            return Err(SnapshotError::InvalidValue("mode stack"));
+        }
+
+        let mut modes = Vec::new();
+        for _ in 0..count {
+            let mode = read_usize(&mut rest, "mode stack")?;
+            let depth = read_usize(&mut rest, "mode stack")?;
+            modes.push((mode, depth));
+        }
+
+        let len = read_usize(&mut rest, "held back input")?;
+        if rest.len() != len {
+            return Err(SnapshotError::BadLength);
+        }
+        let buffer = str::from_utf8(rest)
+            .map_err(|_| SnapshotError::InvalidValue("held back input"))?
+            .to_string();
+
+        Ok(ChunkedLexer {
+            buffer,
+            offset,
+            modes,
+            state: None,
+            _t: PhantomData,
+        })
+    }
+}
+
+/// The magic bytes at the start of the binary encoding of a snapshot.
+pub const SNAPSHOT_MAGIC: &[u8; 4] = b"LUTS";
+
+/// The version of the binary encoding of a snapshot that this module writes and reads.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+// COV_EXCL_START
+/// The error type for resuming a `ChunkedLexer` from a snapshot.
+#[derive(Debug, Fail, PartialEq)]
+pub enum SnapshotError {
+    /// The input does not start with the magic bytes.
+    #[fail(display = "The input is not a luther lexer snapshot.")]
+    BadMagic,
+
+    /// The input is encoded in a version of the format that is not supported.
+    #[fail(display = "The luther lexer snapshot format version {} is not supported.", _0)]
+    UnsupportedVersion(u32),
+
+    /// The input is shorter (or longer) than its contents say it is.
+    #[fail(display = "The luther lexer snapshot has the wrong length.")]
+    BadLength,
+
+    /// A value in the input is out of range. The field names the section it is in.
+    #[fail(display = "The luther lexer snapshot has an invalid value in its {}.", _0)]
+    InvalidValue(&'static str),
+}
+// COV_EXCL_END
+
+// The little endian bytes of value (shared with the recording log of the record module).
+pub(crate) fn u32_bytes(value: u32) -> [u8; 4] {
+    [
+        value as u8,
+        (value >> 8) as u8,
+        (value >> 16) as u8,
+        (value >> 24) as u8,
+    ]
+}
+
+pub(crate) fn push_u64(bytes: &mut Vec<u8>, value: u64) {
+    bytes.extend_from_slice(&u32_bytes(value as u32));
+    bytes.extend_from_slice(&u32_bytes((value >> 32) as u32));
+}
+
+pub(crate) fn read_u32(b: &[u8]) -> u32 {
+    u32::from(b[0]) | u32::from(b[1]) << 8 | u32::from(b[2]) << 16 | u32::from(b[3]) << 24
+}
+
+// Reads a u64 from the front of rest and advances rest past it (or is None if rest is too
+// short).
+pub(crate) fn read_u64(rest: &mut &[u8]) -> Option<u64> {
+    if rest.len() < 8 {
+        return None;
+    }
+    let value = u64::from(read_u32(&rest[..4])) | u64::from(read_u32(&rest[4..8])) << 32;
+    *rest = &rest[8..];
+    Some(value)
+}
+
+// Reads a u64 that must fit in a usize, naming the section it is in if it does not.
+fn read_usize(rest: &mut &[u8], section: &'static str) -> StdResult<usize, SnapshotError> {
+    let value = read_u64(rest).ok_or(SnapshotError::BadLength)?;
+    if value > usize::MAX as u64 {
+        return Err(SnapshotError::InvalidValue(section));
+    }
+    Ok(value as usize)
+}
+
+impl<T: Lexer> Default for ChunkedLexer<T> {
+    fn default() -> Self {
+        ChunkedLexer::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dfa::Dfa;
+    use std::result::Result as StdResult;
+
+    #[derive(Debug, PartialEq)]
+    enum Word {
+        Word(String),
+    }
+
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    enum WordDfa {
+        Start,
+        Word,
+        Space,
+        Error,
+    }
+
+    impl Default for WordDfa {
+        fn default() -> Self {
+            WordDfa::Start
+        }
+    }
+
+    impl Dfa<Word> for WordDfa {
+        fn is_error(&self) -> bool {
+            *self == WordDfa::Error
+        }
+
+        fn transition(&self, c: char) -> Self {
+            match (*self, c) {
+                (WordDfa::Start, 'a'...'z') | (WordDfa::Word, 'a'...'z') => WordDfa::Word,
+                (WordDfa::Start, ' ') => WordDfa::Space,
+                (_, _) => WordDfa::Error,
+            }
+        }
+
+        fn accept(&self, input: &str) -> Option<Word> {
+            match *self {
+                WordDfa::Word => Some(Word::Word(input.to_string())),
+                WordDfa::Space => Some(Word::Word(String::new())),
+                _ => None,
+            }
+        }
+
+        fn skip(&self) -> bool {
+            *self == WordDfa::Space
+        }
+    }
+
+    impl Lexer for Word {
+        type Dfa = WordDfa;
+    }
+
+    fn words(tokens: Vec<Result<Span<Word>, Never>>) -> Vec<(usize, String, usize)> {
+        let tokens: StdResult<Vec<_>, _> = tokens.into_iter().collect();
+        tokens
+            .expect("unexpected lexer error")
+            .into_iter()
+            .map(|span| {
+                let (start, Word::Word(word), end) = span.into_inner();
+                (start.offset(), word, end.offset())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn chunked_lexer_holds_back_last_token() {
+        let mut sut = ChunkedLexer::<Word>::new();
+
+        let first = words(sut.push("ab c"));
+        let second = words(sut.push("d ef"));
+        let last = words(sut.finish());
+
+        assert_eq!(first, vec![(0, "ab".to_string(), 1)]);
+        assert_eq!(second, vec![(3, "cd".to_string(), 4)]);
+        assert_eq!(last, vec![(6, "ef".to_string(), 7)]);
+    }
+
+    #[test]
+    fn chunked_lexer_resumes_from_snapshot() {
+        let mut sut = ChunkedLexer::<Word>::new();
+        let first = words(sut.push("ab c"));
+
+        let mut resumed = ChunkedLexer::<Word>::from_snapshot(&sut.snapshot())
+            .expect("unexpected snapshot error");
+        let second = words(resumed.push("d ef"));
+        let last = words(resumed.finish());
+
+        assert_eq!(first, vec![(0, "ab".to_string(), 1)]);
+        assert_eq!(second, vec![(3, "cd".to_string(), 4)]);
+        assert_eq!(last, vec![(6, "ef".to_string(), 7)]);
+    }
+
+    #[test]
+    fn chunked_lexer_from_snapshot_is_err_for_truncated_snapshot() {
+        let mut sut = ChunkedLexer::<Word>::new();
+        sut.push("ab c");
+        let snapshot = sut.snapshot();
+
+        let result = ChunkedLexer::<Word>::from_snapshot(&snapshot[..snapshot.len() - 1]);
+
+        assert_eq!(result.err(), Some(SnapshotError::BadLength));
+    }
+
+    #[test]
+    fn chunked_lexer_streams_multi_megabyte_token() {
+        let mut sut = ChunkedLexer::<Word>::new();
+        let chunk = "a".repeat(4096);
+
+        let mut result = Vec::new();
+        for _ in 0..1024 {
+            result.extend(words(sut.push(&chunk)));
+        }
+        let pending = result.len();
+        result.extend(words(sut.push(" b")));
+        result.extend(words(sut.finish()));
+
+        let len = 4096 * 1024;
+        assert_eq!(pending, 0);
+        assert_eq!(
+            result,
+            vec![(0, "a".repeat(len), len - 1), (len + 1, "b".to_string(), len + 1)]
+        );
+    }
+
+    #[test]
+    fn chunked_lexer_lexes_token_split_across_many_chunks() {
+        let mut sut = ChunkedLexer::<Word>::new();
+
+        let mut result = Vec::new();
+        for chunk in &["a", "b", "c", " ", "d"] {
+            result.extend(words(sut.push(chunk)));
+        }
+        result.extend(words(sut.finish()));
+
+        assert_eq!(
+            result,
+            vec![(0, "abc".to_string(), 2), (4, "d".to_string(), 4)]
+        );
+    }
+}
@@ -9,6 +9,7 @@
 //! Defines the `LexerIter` iterator that lexes a `char` iterator using a supplied deterministic
 //! finite automaton.
 
+use std::cmp::Ordering;
 use std::iter;
 use std::result::Result as StdResult;
 use std::marker::PhantomData;
@@ -36,6 +37,12 @@ where
     D: Dfa<T>,
 {
     input: iter::Peekable<I>,
+    modes: Vec<(usize, usize)>,
+    max_input: u64,
+    too_large: bool,
+    control_chars: ControlChars,
+    track_scans: bool,
+    scan: Option<(Location, Vec<(usize, usize)>)>,
     _d: PhantomData<D>,
     _t: PhantomData<T>,
 }
@@ -48,13 +55,62 @@ where
 {
     /// Create a new `LexerIter` from the supplied iterator.
     pub fn new(input: I) -> LexerIter<T, F, I, D> {
+        LexerIter::with_modes(input, vec![(0, 0)])
+    }
+
+    // Creates a new LexerIter that starts with the given mode stack (of modes and their
+    // delimiter depths).
+    pub(crate) fn with_modes(input: I, modes: Vec<(usize, usize)>) -> LexerIter<T, F, I, D> {
         LexerIter {
             input: input.peekable(),
+            modes,
+            max_input: u64::MAX,
+            too_large: false,
+            control_chars: ControlChars::PassThrough,
+            track_scans: false,
+            scan: None,
             _d: PhantomData,
             _t: PhantomData,
         }
     }
 
+    // The current mode stack (of modes and their delimiter depths).
+    pub(crate) fn modes(&self) -> &[(usize, usize)] {
+        &self.modes
+    }
+
+    // Records the start of each scan of the input (for a token, a skipped token, or an
+    // error) and the mode stack before it, for last_scan().
+    pub(crate) fn tracking_scans(mut self) -> Self {
+        self.track_scans = true;
+        self
+    }
+
+    // The start of the last scan of the input and the mode stack before it (if the scans are
+    // tracked).
+    pub(crate) fn last_scan(&self) -> Option<&(Location, Vec<(usize, usize)>)> {
+        self.scan.as_ref()
+    }
+
+    /// Limits the size of the input that the lexer accepts.
+    ///
+    /// A `char` of the input that ends at or past `max` (in the units of the `Location`'s of the
+    /// input, such as bytes) ends the input: the lexer produces the tokens before it and then
+    /// a `LexError::InputTooLarge` error rather than lexing an unbounded input. There is no
+    /// limit by default.
+    pub fn with_max_input(mut self, max: u64) -> Self {
+        self.max_input = max;
+        self
+    }
+
+    /// Sets how the lexer treats the control characters in the input (see `ControlChars`).
+    ///
+    /// The lexer passes them through to the dfa by default, like any other `char`.
+    pub fn with_control_chars(mut self, control_chars: ControlChars) -> Self {
+        self.control_chars = control_chars;
+        self
+    }
+
     /// Maps the `Span`'s that are being iterated over to a different type.
     ///
     /// This is a convience method to allow mapping the consetive `Span`'s while
@@ -66,6 +122,61 @@ where
         MapSpan { inner: self, f }
     }
 
+    /// The current lexer mode.
+    ///
+    /// The lexer starts in mode 0 and changes modes as directed by the `mode_switch()` of the
+    /// accepting states of the dfa.
+    pub fn mode(&self) -> usize {
+        self.modes.last().map_or(0, |&(mode, _)| mode)
+    }
+
+    /// The current delimiter depth.
+    ///
+    /// The depth is the number of open delimiters (see `Dfa::delimiter()`) that have not been
+    /// closed since the lexer entered the current mode. Each mode on the mode stack has its own
+    /// depth, so the depth is 0 after a mode is pushed and is restored when it is popped.
+    pub fn depth(&self) -> usize {
+        self.modes.last().map_or(0, |&(_, depth)| depth)
+    }
+
+    fn switch_mode(&mut self, switch: ModeSwitch) {
+        match switch {
+            ModeSwitch::Push(mode) => self.modes.push((mode, 0)),
+            ModeSwitch::Pop => if self.modes.len() > 1 {
+                self.modes.pop();
+            },
+            ModeSwitch::Set(mode) => {
+                self.modes.pop();
+                self.modes.push((mode, 0));
+            }
+        }
+    }
+
+    fn nest(&mut self, delimiter: Delimiter) {
+        if let Some(&mut (_, ref mut depth)) = self.modes.last_mut() {
+            *depth = match delimiter {
+                Delimiter::Open => *depth + 1,
+                Delimiter::Close => depth.saturating_sub(1),
+            };
+        }
+    }
+
+    // Peeks at the next input, first consuming any control chars if they are skipped.
+    fn peek(&mut self) -> Option<&StdResult<Span<char>, F>> {
+        if self.control_chars == ControlChars::Skip {
+            loop {
+                match self.input.peek() {
+                    Some(Ok(span)) if is_control(*span.value_ref()) => {}
+                    _ => break,
+                }
+
+                self.input.next();
+            }
+        }
+
+        self.input.peek()
+    }
+
     // The Ok return is what is needed to drive the Iterator::next() loop. The Err
     // return is the return type from Iterator::next() when the pre-conditions for
     // loop aren't there.
@@ -79,11 +190,24 @@ where
     // (though it will also detect errors at the start of the first Iterator::next() call
     // as well).
     fn init_dfa(&mut self) -> StdResult<(Location, Location, D), Option<Result<Span<T>, F>>> {
-        let state = D::default();
+        let state = D::start(self.mode());
+
+        // The input past the maximum input size is reported once and then ignored
+        if self.too_large {
+            return Err(None);
+        }
 
         // The Ok() case peeks but does not read
-        if let Some(&Ok(ref span)) = self.input.peek() {
-            if !(state.transition(*span.value_ref()).is_error()) {
+        let max_input = self.max_input;
+        let control_chars = self.control_chars;
+        if let Some(&Ok(ref span)) = self.peek() {
+            if span.end().as_u64() >= max_input {
+                self.too_large = true;
+                return Err(Some(Err(LexError::InputTooLarge(self.max_input))));
+            }
+            if !control_chars.rejects(*span.value_ref())
+                && !(state.transition(*span.value_ref()).is_error())
+            {
                 return Ok((span.start(), span.end(), state));
             }
         }
@@ -92,7 +216,47 @@ where
         match self.input.next() {
             None => Err(None),
             Some(Err(err)) => Err(Some(Err(err.into()))),
-            Some(Ok(span)) => Err(Some(Err(LexError::InvalidCharacter(*span.value_ref())))),
+            Some(Ok(ref span)) if control_chars.rejects(*span.value_ref()) => Err(Some(Err(
+                LexError::ControlCharacter(*span.value_ref(), span.start(), span.end()),
+            ))),
+            Some(Ok(span)) => Err(Some(self.invalid_character(span))),
+        }
+    }
+
+    // Produces either the error token for the maximal run of characters (starting with
+    // span) that cannot start a token or, if the dfa has no error token, InvalidCharacter.
+    fn invalid_character(&mut self, span: Span<char>) -> Result<Span<T>, F> {
+        let c = *span.value_ref();
+        if D::error_token(&c.to_string()).is_none() {
+            return Err(LexError::InvalidCharacter(c));
+        }
+
+        let state = D::start(self.mode());
+        let start = span.start();
+        let mut end = span.end();
+        let mut unmatched = c.to_string();
+        let max_input = self.max_input;
+        let control_chars = self.control_chars;
+
+        loop {
+            match self.peek() {
+                Some(Ok(span))
+                    if span.end().as_u64() < max_input
+                        && !control_chars.rejects(*span.value_ref())
+                        && state.transition(*span.value_ref()).is_error() =>
+                {
+                    end = span.end();
+                    unmatched.push(*span.value_ref());
+                }
+                _ => break,
+            }
+
+            self.input.next();
+        }
+
+        match D::error_token(&unmatched) {
+            Some(t) => Ok(Span::new(start, end, t)),
+            None => Err(LexError::InvalidCharacter(c)),
         }
     }
 }
@@ -106,41 +270,79 @@ where
     type Item = Result<Span<T>, F>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        // Initialize the dfa and tracking state
-        let (start, mut end, mut state) = match self.init_dfa() {
-            Ok(ok) => ok,
-            Err(err) => return err,
-        };
-
-        let mut tok_str = String::new();
-
-        // Loop while there is more input that does not cause
-        // an error transition.
+        // Loop until a token that is not skipped has been lexed
         loop {
-            match self.input.peek() {
-                Some(&Ok(ref span)) => {
-                    let next_state = state.transition(*span.value_ref());
-                    if next_state.is_error() {
-                        break;
+            if self.track_scans {
+                let start = self.peek().and_then(|next| next.as_ref().ok()).map(Span::start);
+                if let Some(start) = start {
+                    self.scan = Some((start, self.modes.clone()));
+                }
+            }
+
+            // Initialize the dfa and tracking state
+            let (start, mut end, mut state) = match self.init_dfa() {
+                Ok(ok) => ok,
+                Err(err) => return err,
+            };
+
+            let mut tok_str = String::new();
+            let max_input = self.max_input;
+            let control_chars = self.control_chars;
+
+            // Loop while there is more input (within the maximum input size and not a rejected
+            // control char) that does not cause an error transition.
+            loop {
+                match self.peek() {
+                    Some(&Ok(ref span))
+                        if span.end().as_u64() < max_input
+                            && !control_chars.rejects(*span.value_ref()) =>
+                    {
+                        let next_state = state.transition(*span.value_ref());
+                        if next_state.is_error() {
+                            break;
+                        }
+
+                        state = next_state;
+                        end = span.end();
+                        tok_str.push(*span.value_ref());
                     }
+                    _ => break,
+                }
 
-                    state = next_state;
-                    end = span.end();
-                    tok_str.push(*span.value_ref());
+                self.input.next();
+            }
+
+            // Track the delimiter depth and switch modes if the token was accepted
+            let depth = self.depth();
+            let accepted = state.try_accept(&tok_str);
+            if accepted.is_some() {
+                if let Some(delimiter) = state.delimiter() {
+                    self.nest(delimiter);
+                }
+                if let Some(switch) = state.mode_switch_at_depth(depth) {
+                    self.switch_mode(switch);
                 }
-                _ => break,
             }
 
-            self.input.next();
+            // Run the action for and return the accepted token (unless it is skipped),
+            // or return InvalidValue, or the error token or InvalidToken
+            match accepted {
+                Some(Ok(t)) => {
+                    let span = Span::new(start, end, t);
+                    state.action_at_depth(&tok_str, &span, depth);
+                    if !state.skip() {
+                        return Some(Ok(span));
+                    }
+                }
+                Some(Err(reason)) => return Some(Err(LexError::InvalidValue(tok_str, reason))),
+                None => {
+                    return Some(match D::error_token(&tok_str) {
+                        Some(t) => Ok(Span::new(start, end, t)),
+                        None => Err(LexError::InvalidToken(tok_str)),
+                    })
+                }
+            }
         }
-
-        // Return the accepted token or InvalidToken
-        Some(
-            state
-                .accept(&tok_str)
-                .map(|t| Span::new(start, end, t))
-                .ok_or(LexError::InvalidToken(tok_str)),
-        )
     }
 }
 
@@ -174,6 +376,72 @@ where
     }
 }
 
+/// How a lexer treats the control characters in its input.
+///
+/// The control characters are the C0 controls (U+0000 to U+001F), DEL (U+007F), and the C1
+/// controls (U+0080 to U+009F), except for those that are whitespace (tab, line feed, vertical
+/// tab, form feed, carriage return, and next line). They are rarely part of a token but source
+/// files from the wild (such as those padded with NUL's) often have them.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ControlChars {
+    /// Pass them to the dfa like any other `char`.
+    PassThrough,
+
+    /// Drop them from the input (even in the middle of a token) before the dfa sees them. The
+    /// span of a token still runs from its first `char` to its last, so it includes any control
+    /// characters that were dropped from the middle of it.
+    Skip,
+
+    /// End the token before them and report each one as a `LexError::ControlCharacter` (with
+    /// its span).
+    Error,
+}
+
+impl ControlChars {
+    // Whether the lexer reports c as an error rather than lexing it.
+    fn rejects(self, c: char) -> bool {
+        self == ControlChars::Error && is_control(c)
+    }
+}
+
+// Whether c is a control char in the sense of ControlChars.
+fn is_control(c: char) -> bool {
+    c.is_control() && !c.is_whitespace()
+}
+
+/// A change to the mode of a lexer.
+///
+/// A lexer keeps a stack of modes with its current mode on the top of the stack. The
+/// `Dfa` for the lexer uses a `ModeSwitch` to change the current mode after accepting
+/// a token.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ModeSwitch {
+    /// Push the given mode onto the mode stack, making it the current mode.
+    Push(usize),
+
+    /// Pop the current mode from the mode stack, returning to the previous mode. Popping the
+    /// last mode on the stack returns to mode 0.
+    Pop,
+
+    /// Replace the current mode with the given mode.
+    Set(usize),
+}
+
+/// A delimiter that changes the nesting depth of a lexer.
+///
+/// A lexer keeps a delimiter depth for each mode on its mode stack. The `Dfa` for the lexer
+/// uses a `Delimiter` to mark the tokens that open or close a nested group (such as `{` and
+/// `}`), and the depth is passed to `Dfa::mode_switch_at_depth()` and `Dfa::action_at_depth()`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Delimiter {
+    /// Open a nested group, increasing the depth by one.
+    Open,
+
+    /// Close a nested group, decreasing the depth by one. Closing a group at depth 0 leaves the
+    /// depth at 0.
+    Close,
+}
+
 /// Interface to describe a deterministic finite atomaton.
 ///
 /// Mathematically a dfa is a 5-tuple: (Q, q₀, Σ, δ, A) where
@@ -195,6 +463,15 @@ where
 /// In addition to the mathematical elements of a dfa, `Dfa` has the `error_state()`
 /// method which identifies one of the states as a special error state.
 ///
+/// A `Dfa` may also support lexer modes. Each mode has its own start state (given by
+/// `start()`) and an accepting state can switch the mode of the lexer (through
+/// `mode_switch()`). Mode 0 is the default mode and its start state is
+/// `Default::default()`.
+///
+/// An accepting state can also open or close a nested group (through `delimiter()`) so that
+/// the lexer tracks the nesting depth in the current mode, which `mode_switch_at_depth()` and
+/// `action_at_depth()` are given.
+///
 /// # Type Parameters
 /// - `T`: the token type returned for accepting states
 pub trait Dfa<T>: Default {
@@ -225,6 +502,155 @@ pub trait Dfa<T>: Default {
     /// - `None`: the current state is not an accepting state
     /// - `Some(t)`: the current state is an aceepting state and `t` is the corresponding token
     fn accept(&self, matched: &str) -> Option<T>;
+
+    /// Tests for being in an accepting state, allowing the conversion of `matched` to fail.
+    ///
+    /// The default implementation defers to `accept()` and never fails.
+    ///
+    /// # Parameters
+    /// - matched: the `str` of matched characters for the transtions from the start state to the
+    ///   current state
+    ///
+    /// # Returns
+    /// - `None`: the current state is not an accepting state
+    /// - `Some(Ok(t))`: the current state is an aceepting state and `t` is the corresponding token
+    /// - `Some(Err(reason))`: the current state is an accepting state but `matched` could not be
+    ///   converted into the corresponding token for the given `reason`
+    fn try_accept(&self, matched: &str) -> Option<StdResult<T, String>> {
+        self.accept(matched).map(Ok)
+    }
+
+    /// The start state for a lexer mode.
+    ///
+    /// The default implementation supports only mode 0 and returns `Default::default()`.
+    ///
+    /// # Parameters
+    /// - mode: the lexer mode for which to get the start state
+    fn start(mode: usize) -> Self {
+        let _ = mode;
+        Self::default()
+    }
+
+    /// The change to the lexer mode after accepting a token in the current state.
+    ///
+    /// The default implementation never changes the lexer mode.
+    ///
+    /// # Returns
+    /// - `None`: the lexer mode is unchanged
+    /// - `Some(switch)`: the lexer mode changes as described by `switch`
+    fn mode_switch(&self) -> Option<ModeSwitch> {
+        None
+    }
+
+    /// The change to the lexer mode after accepting a token in the current state at a given
+    /// delimiter depth.
+    ///
+    /// This lets a token end a mode only when it is not nested, such as a `}` that ends a string
+    /// interpolation only at depth 0. The default implementation ignores the depth and defers to
+    /// `mode_switch()`.
+    ///
+    /// # Parameters
+    /// - depth: the delimiter depth of the current mode before the token was accepted
+    fn mode_switch_at_depth(&self, depth: usize) -> Option<ModeSwitch> {
+        let _ = depth;
+        self.mode_switch()
+    }
+
+    /// The delimiter for the tokens accepted in the current state.
+    ///
+    /// `LexerIter` increases its delimiter depth after accepting a token in an `Open` state and
+    /// decreases it after accepting a token in a `Close` state, before it switches modes. The
+    /// default implementation has no delimiters, so the depth is always 0.
+    fn delimiter(&self) -> Option<Delimiter> {
+        None
+    }
+
+    /// The token for input that the `Dfa` cannot match.
+    ///
+    /// `LexerIter` uses this token in place of a `LexError::InvalidToken` error and, for the
+    /// maximal run of characters that cannot start a token, in place of a
+    /// `LexError::InvalidCharacter` error. The default implementation has no such token.
+    ///
+    /// # Parameters
+    /// - unmatched: the `str` of characters that could not be matched
+    ///
+    /// # Returns
+    /// - `None`: the `Dfa` has no token for unmatched input
+    /// - `Some(t)`: `t` is the token for the `unmatched` characters
+    fn error_token(unmatched: &str) -> Option<T> {
+        let _ = unmatched;
+        None
+    }
+
+    /// Tests for the tokens accepted in the current state being skipped.
+    ///
+    /// `LexerIter` does not produce the tokens accepted in a skip state (such as whitespace
+    /// or comments) but they otherwise take part in maximal-munch lexing like any other token.
+    /// The default implementation never skips tokens.
+    fn skip(&self) -> bool {
+        false
+    }
+
+    /// Performs the action for the tokens accepted in the current state.
+    ///
+    /// `LexerIter` calls this for each accepted token (including skipped tokens) with the
+    /// matched characters and the token's `Span`, before the token is produced. The default
+    /// implementation does nothing.
+    fn action(&self, matched: &str, span: &Span<T>) {
+        let _ = (matched, span);
+    }
+
+    /// Performs the action for the tokens accepted in the current state at a given delimiter
+    /// depth.
+    ///
+    /// `LexerIter` calls this in place of `action()` with the delimiter depth of the mode in
+    /// which the token was accepted (before the token was accepted). The default implementation
+    /// ignores the depth and defers to `action()`.
+    fn action_at_depth(&self, matched: &str, span: &Span<T>, depth: usize) {
+        let _ = depth;
+        self.action(matched, span);
+    }
+}
+
+/// A row of the transition table of a table driven `Dfa`.
+///
+/// A derived `Dfa` uses one `TableRow` for each of its states when it is generated with
+/// `#[luther(codegen = "tables")]`. The row describes the transitions out of its state
+/// as ranges of `char`'s and the state that each range leads to.
+#[derive(Debug)]
+pub struct TableRow {
+    /// The transitions out of the state as (first, last, target) triples. The ranges from
+    /// `first` to `last` (inclusive) must be sorted and must not overlap.
+    pub ranges: &'static [(char, char, usize)],
+
+    /// The target of the transition for a `char` that is not in any of the `ranges`.
+    pub default: usize,
+}
+
+impl TableRow {
+    /// The transition function for the state described by this row.
+    ///
+    /// # Parameters
+    /// - c: the `char` that names the transition to follow
+    ///
+    /// # Returns
+    /// The number of the new state after following the transition.
+    pub fn transition(&self, c: char) -> usize {
+        let found = self.ranges.binary_search_by(|&(first, last, _)| {
+            if last < c {
+                Ordering::Less
+            } else if first > c {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        });
+
+        match found {
+            Ok(index) => self.ranges[index].2,
+            Err(_) => self.default,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -372,6 +798,51 @@ mod test {
         assert_eq!(strings, vec!["ab", "ac", "abccc"])
     }
 
+    #[test]
+    fn lexer_is_input_too_large_once_past_max_input() {
+        let input = "abacabccc".char_indices().map(|i| Ok(i.into()));
+
+        let mut sut = DfaLexer::new(input).with_max_input(4);
+
+        assert_matches!(sut.next(), Some(Ok(_)));
+        assert_matches!(sut.next(), Some(Ok(_)));
+        assert_matches!(sut.next(), Some(Err(LexError::InputTooLarge(4))));
+        assert_matches!(sut.next(), None);
+    }
+
+    #[test]
+    fn lexer_drops_skipped_control_chars_even_within_tokens() {
+        let input = "ab\u{0}ac\u{1}c".char_indices().map(|i| Ok(i.into()));
+
+        let sut = DfaLexer::new(input).with_control_chars(ControlChars::Skip);
+        let result: StdResult<Vec<_>, _> = sut.collect();
+
+        assert_eq!(
+            result.expect("DfaLexer had an unexpected error."),
+            vec![
+                Span::new(0.into(), 1.into(), Tokens::Token1("ab".to_string())),
+                Span::new(3.into(), 6.into(), Tokens::Token1("acc".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_is_control_character_with_span_for_rejected_control_chars() {
+        let input = "ab\u{0}ac".char_indices().map(|i| Ok(i.into()));
+
+        let mut sut = DfaLexer::new(input).with_control_chars(ControlChars::Error);
+
+        assert_matches!(sut.next(), Some(Ok(_)));
+        let err = sut.next().expect("DfaLexer ended early.").unwrap_err();
+        assert_matches!(sut.next(), Some(Ok(_)));
+        assert_matches!(sut.next(), None);
+        assert_matches!(err, LexError::ControlCharacter('\u{0}', _, _));
+        assert_eq!(
+            err.to_string(),
+            "The lexer encountered the control character '\\0' at 2-2."
+        );
+    }
+
     #[test]
     fn lexer_is_invalid_character_for_invalid_second_token_in_input() {
         let input = "abb".char_indices().map(|i| Ok(i.into()));
@@ -424,6 +895,430 @@ mod test {
         );
     }
 
+    // This dfa corresponds to the re "[0-9]+" with a value that must fit in a u8
+    #[derive(PartialEq, Eq, Debug, Clone, Copy)]
+    enum ByteDfa {
+        Start,
+        Digits,
+        Error,
+    }
+
+    impl Default for ByteDfa {
+        fn default() -> Self {
+            ByteDfa::Start
+        }
+    }
+
+    impl Dfa<u8> for ByteDfa {
+        fn is_error(&self) -> bool {
+            *self == ByteDfa::Error
+        }
+
+        fn transition(&self, c: char) -> Self {
+            match (*self, c) {
+                (ByteDfa::Error, _) => ByteDfa::Error,
+                (_, '0'...'9') => ByteDfa::Digits,
+                (_, _) => ByteDfa::Error,
+            }
+        }
+
+        fn accept(&self, input: &str) -> Option<u8> {
+            self.try_accept(input).and_then(|r| r.ok())
+        }
+
+        fn try_accept(&self, input: &str) -> Option<StdResult<u8, String>> {
+            if *self == ByteDfa::Digits {
+                Some(input.parse().map_err(|e: ::std::num::ParseIntError| e.to_string()))
+            } else {
+                None
+            }
+        }
+    }
+
+    type ByteLexer<I> = LexerIter<u8, NoFail, I, ByteDfa>;
+
+    #[derive(PartialEq, Eq, Debug)]
+    enum ModeTokens {
+        Quote,
+        Word,
+        Text,
+    }
+
+    // This dfa has two modes. Mode 0 corresponds to the res ["\"", "[a-z]+"] where "\"" pushes
+    // mode 1. Mode 1 corresponds to the res ["\"", "[a-z ]+"] where "\"" pops the mode.
+    #[derive(PartialEq, Eq, Debug, Clone, Copy)]
+    enum ModeDfa {
+        Start0,
+        Start1,
+        OpenQuote,
+        CloseQuote,
+        Word,
+        Text,
+        Error,
+    }
+
+    impl Default for ModeDfa {
+        fn default() -> Self {
+            ModeDfa::Start0
+        }
+    }
+
+    impl Dfa<ModeTokens> for ModeDfa {
+        fn is_error(&self) -> bool {
+            *self == ModeDfa::Error
+        }
+
+        fn transition(&self, c: char) -> Self {
+            use self::ModeDfa::*;
+
+            match (*self, c) {
+                (Start0, '"') => OpenQuote,
+                (Start0, 'a'...'z') | (Word, 'a'...'z') => Word,
+                (Start1, '"') => CloseQuote,
+                (Start1, 'a'...'z') | (Start1, ' ') => Text,
+                (Text, 'a'...'z') | (Text, ' ') => Text,
+                (_, _) => Error,
+            }
+        }
+
+        fn accept(&self, _: &str) -> Option<ModeTokens> {
+            match *self {
+                ModeDfa::OpenQuote | ModeDfa::CloseQuote => Some(ModeTokens::Quote),
+                ModeDfa::Word => Some(ModeTokens::Word),
+                ModeDfa::Text => Some(ModeTokens::Text),
+                _ => None,
+            }
+        }
+
+        fn start(mode: usize) -> Self {
+            match mode {
+                1 => ModeDfa::Start1,
+                _ => ModeDfa::Start0,
+            }
+        }
+
+        fn mode_switch(&self) -> Option<ModeSwitch> {
+            match *self {
+                ModeDfa::OpenQuote => Some(ModeSwitch::Push(1)),
+                ModeDfa::CloseQuote => Some(ModeSwitch::Pop),
+                _ => None,
+            }
+        }
+    }
+
+    type ModeLexer<I> = LexerIter<ModeTokens, NoFail, I, ModeDfa>;
+
+    #[derive(PartialEq, Eq, Debug, Clone, Copy)]
+    enum DelimTokens {
+        Dollar,
+        Open,
+        Close,
+        X,
+    }
+
+    // This dfa has two modes that both have the res ["\\$", "\\{", "\\}", "x"]. In mode 0 "$"
+    // pushes mode 1 and in mode 1 "}" pops the mode when it is not nested in "{" "}".
+    #[derive(PartialEq, Eq, Debug, Clone, Copy)]
+    enum DelimDfa {
+        Start0,
+        Start1,
+        Dollar,
+        Open,
+        Close0,
+        Close1,
+        X,
+        Error,
+    }
+
+    impl Default for DelimDfa {
+        fn default() -> Self {
+            DelimDfa::Start0
+        }
+    }
+
+    impl Dfa<DelimTokens> for DelimDfa {
+        fn is_error(&self) -> bool {
+            *self == DelimDfa::Error
+        }
+
+        fn transition(&self, c: char) -> Self {
+            use self::DelimDfa::*;
+
+            match (*self, c) {
+                (Start0, '$') | (Start1, '$') => Dollar,
+                (Start0, '{') | (Start1, '{') => Open,
+                (Start0, '}') => Close0,
+                (Start1, '}') => Close1,
+                (Start0, 'x') | (Start1, 'x') => X,
+                (_, _) => Error,
+            }
+        }
+
+        fn accept(&self, _: &str) -> Option<DelimTokens> {
+            match *self {
+                DelimDfa::Dollar => Some(DelimTokens::Dollar),
+                DelimDfa::Open => Some(DelimTokens::Open),
+                DelimDfa::Close0 | DelimDfa::Close1 => Some(DelimTokens::Close),
+                DelimDfa::X => Some(DelimTokens::X),
+                _ => None,
+            }
+        }
+
+        fn start(mode: usize) -> Self {
+            match mode {
+                1 => DelimDfa::Start1,
+                _ => DelimDfa::Start0,
+            }
+        }
+
+        fn mode_switch_at_depth(&self, depth: usize) -> Option<ModeSwitch> {
+            match (*self, depth) {
+                (DelimDfa::Dollar, _) => Some(ModeSwitch::Push(1)),
+                (DelimDfa::Close1, 0) => Some(ModeSwitch::Pop),
+                _ => None,
+            }
+        }
+
+        fn delimiter(&self) -> Option<Delimiter> {
+            match *self {
+                DelimDfa::Open => Some(Delimiter::Open),
+                DelimDfa::Close0 | DelimDfa::Close1 => Some(Delimiter::Close),
+                _ => None,
+            }
+        }
+    }
+
+    type DelimLexer<I> = LexerIter<DelimTokens, NoFail, I, DelimDfa>;
+
+    // This dfa is DfaStates with an error token.
+    #[derive(PartialEq, Eq, Debug, Default)]
+    struct ErrorTokenDfa(DfaStates);
+
+    impl Dfa<Tokens> for ErrorTokenDfa {
+        fn is_error(&self) -> bool {
+            self.0.is_error()
+        }
+
+        fn transition(&self, c: char) -> Self {
+            ErrorTokenDfa(self.0.transition(c))
+        }
+
+        fn accept(&self, input: &str) -> Option<Tokens> {
+            self.0.accept(input)
+        }
+
+        fn error_token(unmatched: &str) -> Option<Tokens> {
+            Some(Tokens::Token1(format!("error: {}", unmatched)))
+        }
+    }
+
+    type ErrorTokenLexer<I> = LexerIter<Tokens, NoFail, I, ErrorTokenDfa>;
+
+    // This dfa corresponds to the res ["[a-z]+", " +"] where " +" is skipped.
+    #[derive(PartialEq, Eq, Debug, Clone, Copy)]
+    enum SkipDfa {
+        Start,
+        Word,
+        Space,
+        Error,
+    }
+
+    impl Default for SkipDfa {
+        fn default() -> Self {
+            SkipDfa::Start
+        }
+    }
+
+    impl Dfa<Tokens> for SkipDfa {
+        fn is_error(&self) -> bool {
+            *self == SkipDfa::Error
+        }
+
+        fn transition(&self, c: char) -> Self {
+            match (*self, c) {
+                (SkipDfa::Start, 'a'...'z') | (SkipDfa::Word, 'a'...'z') => SkipDfa::Word,
+                (SkipDfa::Start, ' ') | (SkipDfa::Space, ' ') => SkipDfa::Space,
+                (_, _) => SkipDfa::Error,
+            }
+        }
+
+        fn accept(&self, input: &str) -> Option<Tokens> {
+            match *self {
+                SkipDfa::Word | SkipDfa::Space => Some(Tokens::Token1(input.to_string())),
+                _ => None,
+            }
+        }
+
+        fn skip(&self) -> bool {
+            *self == SkipDfa::Space
+        }
+
+        fn action(&self, matched: &str, _: &Span<Tokens>) {
+            ACTIONS.with(|actions| actions.borrow_mut().push(matched.to_string()));
+        }
+    }
+
+    thread_local! {
+        static ACTIONS: ::std::cell::RefCell<Vec<String>> = ::std::cell::RefCell::new(Vec::new());
+    }
+
+    type SkipLexer<I> = LexerIter<Tokens, NoFail, I, SkipDfa>;
+
+    static ROW: TableRow = TableRow {
+        ranges: &[('0', '9', 1), ('a', 'a', 2), ('x', 'z', 3)],
+        default: 4,
+    };
+
+    #[test]
+    fn lexer_is_some_ok_for_convertable_input() {
+        let input = "255".char_indices().map(|i| Ok(i.into()));
+
+        let mut sut = ByteLexer::new(input).map_span(|s| s.into_inner().1);
+        let result = sut.next();
+
+        assert_matches!(result, Some(Ok(255)));
+    }
+
+    #[test]
+    fn lexer_is_some_err_invalid_value_for_unconvertable_input() {
+        let input = "256".char_indices().map(|i| Ok(i.into()));
+
+        let mut sut = ByteLexer::new(input).map_span(|s| s.into_inner().1);
+        let result = sut.next();
+
+        assert_matches!(result, Some(Err(LexError::InvalidValue(ref s, _))) if s == "256");
+    }
+
+    #[test]
+    fn lexer_switches_modes_after_accepting_tokens() {
+        use self::ModeTokens::*;
+        let input = "ab\"c d\"ef".char_indices().map(|i| Ok(i.into()));
+
+        let sut = ModeLexer::new(input).map_span(|s| s.into_inner().1);
+        let result: StdResult<Vec<_>, _> = sut.collect();
+
+        assert_eq!(
+            result.expect("Unexpected error in the mode lexer."),
+            vec![Word, Quote, Text, Quote, Word]
+        );
+    }
+
+    #[test]
+    fn lexer_mode_tracks_mode_stack() {
+        let input = "ab\"c".char_indices().map(|i| Ok(i.into()));
+
+        let mut sut = ModeLexer::new(input);
+        assert_eq!(sut.mode(), 0);
+        sut.next();
+        assert_eq!(sut.mode(), 0);
+        sut.next();
+        assert_eq!(sut.mode(), 1);
+    }
+
+    #[test]
+    fn lexer_tracks_delimiter_depth_per_mode() {
+        use self::DelimTokens::*;
+        let input = "{$x{x}}x}".char_indices().map(|i| Ok(i.into()));
+
+        let mut sut = DelimLexer::new(input);
+        let mut result = Vec::new();
+        while let Some(token) = sut.next() {
+            let token = token.expect("Unexpected error in the delimiter lexer.");
+            result.push((token.into_inner().1, sut.mode(), sut.depth()));
+        }
+
+        assert_eq!(
+            result,
+            vec![
+                (Open, 0, 1),
+                (Dollar, 1, 0),
+                (X, 1, 0),
+                (Open, 1, 1),
+                (X, 1, 1),
+                (Close, 1, 0),
+                (Close, 0, 1),
+                (X, 0, 1),
+                (Close, 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_is_error_token_for_run_of_invalid_characters() {
+        let input = "bbdabe".char_indices().map(|i| Ok(i.into()));
+
+        let sut = ErrorTokenLexer::new(input).map(|r| r.map(|s| s.into_inner()));
+        let result: StdResult<Vec<_>, _> = sut.collect();
+
+        assert_eq!(
+            result.expect("Unexpected error in the error token lexer."),
+            vec![
+                (0.into(), Tokens::Token1("error: bbd".to_string()), 2.into()),
+                (3.into(), Tokens::Token1("ab".to_string()), 4.into()),
+                (5.into(), Tokens::Token1("error: e".to_string()), 5.into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_is_error_token_for_invalid_token() {
+        let input = "aab".char_indices().map(|i| Ok(i.into()));
+
+        let sut = ErrorTokenLexer::new(input).map_span(|s| s.into_inner().1);
+        let result: StdResult<Vec<_>, _> = sut.collect();
+
+        assert_eq!(
+            result.expect("Unexpected error in the error token lexer."),
+            vec![
+                Tokens::Token1("error: a".to_string()),
+                Tokens::Token1("ab".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_does_not_produce_skipped_tokens() {
+        let input = "  ab  cd ".char_indices().map(|i| Ok(i.into()));
+
+        let sut = SkipLexer::new(input).map(|r| r.map(|s| s.into_inner()));
+        let result: StdResult<Vec<_>, _> = sut.collect();
+
+        assert_eq!(
+            result.expect("Unexpected error in the skip lexer."),
+            vec![
+                (2.into(), Tokens::Token1("ab".to_string()), 3.into()),
+                (6.into(), Tokens::Token1("cd".to_string()), 7.into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_performs_action_for_each_accepted_token() {
+        let input = "  ab  cd ".char_indices().map(|i| Ok(i.into()));
+
+        let sut = SkipLexer::new(input);
+        let _: Vec<_> = sut.collect();
+
+        ACTIONS.with(|actions| {
+            assert_eq!(*actions.borrow(), vec!["  ", "ab", "  ", "cd", " "]);
+        });
+    }
+
+    #[test]
+    fn table_row_transitions_to_target_of_containing_range() {
+        assert_eq!(ROW.transition('0'), 1);
+        assert_eq!(ROW.transition('5'), 1);
+        assert_eq!(ROW.transition('a'), 2);
+        assert_eq!(ROW.transition('z'), 3);
+    }
+
+    #[test]
+    fn table_row_transitions_to_default_outside_ranges() {
+        assert_eq!(ROW.transition(' '), 4);
+        assert_eq!(ROW.transition('b'), 4);
+        assert_eq!(ROW.transition('€'), 4);
+    }
+
     // COV_EXCL_START
     quickcheck! {
         fn prop_lexer_matches_regex(input: Vec<StdResult<char,FakeError>>) -> bool {
@@ -441,6 +1336,9 @@ mod test {
                 Err(InputError(_)) => true,
                 Err(InvalidCharacter(c)) => c != 'a',
                 Err(InvalidToken(s)) => !RE.is_match(&s),
+                Err(InvalidValue(_, _)) => false,
+                Err(InputTooLarge(_)) => false,
+                Err(ControlCharacter(_, _, _)) => false,
                 Ok(vec) => vec.into_iter().all(|Tokens::Token1(s)| RE.is_match(&s))
             }
         }
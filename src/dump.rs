@@ -0,0 +1,172 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Formats a token stream for bug reports and tests.
+//!
+//! `dump` writes a token stream as aligned columns (the kind, the span, and the escaped text of
+//! each token) or as JSON lines (an object with `kind`, `start`, `end`, and `text` for each
+//! token), so that the output of a lexer can be shared and compared in a canonical form rather
+//! than as ad-hoc `Debug` output.
+
+use std::fmt::{self, Write};
+use std::result::Result as StdResult;
+
+use Span;
+
+/// The format of a token stream dump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// A line for each token with its kind, span, and text in aligned columns.
+    Columns,
+
+    /// A JSON object on a line for each token.
+    JsonLines,
+}
+
+/// Formats the lexed `tokens` of `input` in the given `format`.
+///
+/// The kind of a token is its `Debug` representation. The spans of the tokens must be byte
+/// offsets into `input` (as they are for the `char`'s from `spanned::StrExt::spanned_chars`);
+/// the span is shown as the half open range of bytes that it covers. An error in the token
+/// stream is shown in place of a token (with the kind `error` in the columns format, and as
+/// an object with an `error` field in the JSON lines format).
+pub fn dump<T, E, I>(input: &str, tokens: I, format: DumpFormat) -> String
+where
+    T: fmt::Debug,
+    E: fmt::Display,
+    I: IntoIterator<Item = StdResult<Span<T>, E>>,
+{
+    let rows: Vec<_> = tokens
+        .into_iter()
+        .map(|token| match token {
+            Ok(span) => {
+                let start = span.start().offset();
+                let end = span.end().offset() + 1;
+                let text = input.get(start..end).unwrap_or("");
+                Ok((format!("{:?}", span.value_ref()), start, end, text))
+            }
+            Err(err) => Err(err.to_string()),
+        })
+        .collect();
+
+    let mut output = String::new();
+    match format {
+        DumpFormat::Columns => {
+            let kind_width = rows.iter()
+                .map(|row| row.as_ref().map_or(0, |(kind, ..)| kind.len()))
+                .max()
+                .unwrap_or(0)
+                .max("error".len());
+            let span_width = rows.iter()
+                .map(|row| {
+                    row.as_ref()
+                        .map_or(0, |&(_, start, end, _)| format!("{}..{}", start, end).len())
+                })
+                .max()
+                .unwrap_or(0);
+
+            for row in rows {
+                match row {
+                    Ok((kind, start, end, text)) => writeln!(
+                        output,
+                        "{:kind_width$}  {:span_width$}  {:?}",
+                        kind,
+                        format!("{}..{}", start, end),
+                        text,
+                        kind_width = kind_width,
+                        span_width = span_width
+                    ),
+                    Err(err) => writeln!(
+                        output,
+                        "{:kind_width$}  {}",
+                        "error",
+                        err,
+                        kind_width = kind_width
+                    ),
+                }.unwrap();
+            }
+        }
+        DumpFormat::JsonLines => for row in rows {
+            match row {
+                Ok((kind, start, end, text)) => writeln!(
+                    output,
+                    "{{\"kind\":{},\"start\":{},\"end\":{},\"text\":{}}}",
+                    json_string(&kind),
+                    start,
+                    end,
+                    json_string(text)
+                ),
+                Err(err) => writeln!(output, "{{\"error\":{}}}", json_string(&err)),
+            }.unwrap();
+        },
+    }
+
+    output
+}
+
+// Quotes and escapes s as a JSON string.
+fn json_string(s: &str) -> String {
+    let mut json = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use Location;
+
+    #[derive(Debug)]
+    enum Token {
+        Ab,
+        Acc,
+    }
+
+    fn tokens() -> Vec<StdResult<Span<Token>, String>> {
+        vec![
+            Ok(Span::new(Location::new(0), Location::new(1), Token::Ab)),
+            Ok(Span::new(Location::new(2), Location::new(11), Token::Acc)),
+            Err("bad \"input\"".to_string()),
+        ]
+    }
+
+    #[test]
+    fn dump_formats_columns() {
+        let sut = dump("ab acccccc\"c", tokens(), DumpFormat::Columns);
+
+        assert_eq!(
+            sut,
+            "Ab     0..2   \"ab\"\n\
+             Acc    2..12  \" acccccc\\\"c\"\n\
+             error  bad \"input\"\n"
+        );
+    }
+
+    #[test]
+    fn dump_formats_json_lines() {
+        let sut = dump("ab acccccc\"c", tokens(), DumpFormat::JsonLines);
+
+        assert_eq!(
+            sut,
+            "{\"kind\":\"Ab\",\"start\":0,\"end\":2,\"text\":\"ab\"}\n\
+             {\"kind\":\"Acc\",\"start\":2,\"end\":12,\"text\":\" acccccc\\\"c\"}\n\
+             {\"error\":\"bad \\\"input\\\"\"}\n"
+        );
+    }
+}
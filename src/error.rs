@@ -8,6 +8,7 @@
 
 use std::result;
 use failure::Fail;
+use span::Location;
 
 // COV_EXCL_START
 /// The error type for the lexers produced by Lexer implementations.
@@ -15,15 +16,33 @@ use failure::Fail;
 pub enum LexError<F: Fail> {
     /// The lexer encountered an invalid chararter in the input. This error occurs
     /// when the invalid character would be the first character of a new token.
-    #[fail(display = "The lexer encountered an invalid character in the input: {}.", _0)]
+    #[fail(display = "The lexer encountered an invalid character in the input: {:?}.", _0)]
     InvalidCharacter(char),
 
+    /// The lexer encountered a control character in the input that it was configured to reject
+    /// (see `LexerIter::with_control_chars`). The fields are the character and the start and
+    /// end of its span.
+    #[fail(display = "The lexer encountered the control character {:?} at {}-{}.", _0, _1, _2)]
+    ControlCharacter(char, Location, Location),
+
     /// The lexer encountered an invalid token in the input. This error occurs
     /// when the lexer has consumed some valid characters but cannot make further
     /// progress and the consumed characters do not form a valid token.
     #[fail(display = "The lexer encountered an invalid token: {}.", _0)]
     InvalidToken(String),
 
+    /// The lexer matched a token but could not convert the matched characters into the
+    /// value of the token. The first field is the matched characters and the second is
+    /// the reason that the conversion failed.
+    #[fail(display = "The lexer could not convert the token {}: {}.", _0, _1)]
+    InvalidValue(String, String),
+
+    /// The input is longer than the maximum input size of the lexer (see
+    /// `LexerIter::with_max_input`). The field is the maximum input size, in the units of the
+    /// `Location`'s of the input.
+    #[fail(display = "The input is larger than the maximum input size of {}.", _0)]
+    InputTooLarge(u64),
+
     /// The lexer encountered an error in the input stream.
     #[fail(display = "The lexer encountered an input error.")]
     InputError(#[cause] F),
@@ -0,0 +1,372 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! A C ABI for scanning with a `table::TableDfa`.
+//!
+//! This module requires the `ffi` feature (which is not enabled by default). It lets programs
+//! in C (or any language with a C foreign function interface, such as Python through `ctypes`)
+//! lex with a dfa that was compiled ahead of time by the `luther` command line tool or by
+//! `luther-build`. The declarations for C are:
+//!
+//! ```c
+//! #include <stddef.h>
+//! #include <stdint.h>
+//!
+//! #define LUTHER_NO_TOKEN UINT32_MAX
+//!
+//! typedef struct luther_scanner luther_scanner;
+//!
+//! typedef struct {
+//!     uint32_t kind;
+//!     uint64_t start;
+//!     uint64_t end;
+//! } luther_token;
+//!
+//! luther_scanner *luther_scanner_new(const uint8_t *dfa, size_t len);
+//! void luther_scanner_free(luther_scanner *scanner);
+//! int luther_scanner_feed(luther_scanner *scanner, const uint8_t *input, size_t len);
+//! int luther_scanner_finish(luther_scanner *scanner);
+//! int luther_scanner_next(luther_scanner *scanner, luther_token *token);
+//! const uint8_t *luther_scanner_token_name(const luther_scanner *scanner, uint32_t kind,
+//!                                          size_t *len);
+//! ```
+//!
+//! A scanner is fed its UTF-8 input in any number of buffers (which may split a `char`) and
+//! then told that the input is finished. `luther_scanner_next` produces the next token as its
+//! `kind` (the token number) and the byte offsets of its `start` and (just past) its `end` in
+//! the whole input (as `uint64_t` so that an input that is fed in buffers can be larger than
+//! 4 GiB on 32-bit targets). A `char` that no token matches is produced with the kind
+//! `LUTHER_NO_TOKEN`. The tokens are the longest matches in the default lexer mode.
+
+use std::os::raw::c_int;
+use std::{ptr, slice, str};
+
+use table::{TableDfa, NO_TOKEN};
+
+/// A token found by a scanner.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LutherToken {
+    /// The token number (or `table::NO_TOKEN` for a `char` that no token matches).
+    pub kind: u32,
+
+    /// The byte offset of the start of the token.
+    pub start: u64,
+
+    /// The byte offset just past the end of the token.
+    pub end: u64,
+}
+
+/// A scanner that is fed its input in buffers.
+///
+/// This is opaque to C, which only handles pointers to it.
+pub struct LutherScanner {
+    // dfa borrows its tables from the allocation at tables, which the scanner owns (it comes
+    // from Box::into_raw) and frees only when it is dropped. TableDfa has no Drop of its own,
+    // so dfa is not used after that.
+    dfa: TableDfa<'static>,
+    tables: *mut [u8],
+    input: String,
+    pending: Vec<u8>,
+    pos: usize,
+    base: u64,
+    finished: bool,
+}
+
+impl LutherScanner {
+    fn new(bytes: &[u8]) -> Option<LutherScanner> {
+        let tables = Box::into_raw(bytes.to_vec().into_boxed_slice());
+        // The allocation is only freed by Drop (or below if it is not a valid dfa).
+        let dfa = match TableDfa::from_bytes(unsafe { &*tables }) {
+            Ok(dfa) => dfa,
+            Err(_) => {
+                drop(unsafe { Box::from_raw(tables) });
+                return None;
+            }
+        };
+
+        Some(LutherScanner {
+            dfa,
+            tables,
+            input: String::new(),
+            pending: Vec::new(),
+            pos: 0,
+            base: 0,
+            finished: false,
+        })
+    }
+
+    // Appends bytes to the input, holding back a char that is split at the end of them.
+    fn feed(&mut self, bytes: &[u8]) -> bool {
+        if self.finished {
+            return false;
+        }
+
+        self.pending.extend_from_slice(bytes);
+        // An error without an error_len is a char that continues in the next bytes.
+        let (valid, invalid) = match str::from_utf8(&self.pending) {
+            Ok(_) => (self.pending.len(), false),
+            Err(e) => (e.valid_up_to(), e.error_len().is_some()),
+        };
+        if invalid {
+            self.pending.truncate(self.pending.len() - bytes.len());
+            return false;
+        }
+
+        self.input
+            .push_str(str::from_utf8(&self.pending[..valid]).unwrap());
+        self.pending.drain(..valid);
+        true
+    }
+
+    fn finish(&mut self) -> bool {
+        self.finished = true;
+        self.pending.is_empty()
+    }
+
+    fn next_token(&mut self) -> Option<LutherToken> {
+        // Drop the input before the current token once it is most of the buffer.
+        if self.pos > self.input.len() / 2 {
+            self.input.drain(..self.pos);
+            self.base += self.pos as u64;
+            self.pos = 0;
+        }
+
+        let rest = &self.input[self.pos..];
+        let first = rest.chars().next()?;
+
+        let mut state = self.dfa.start_state();
+        let mut longest = None;
+        let mut stopped = false;
+        for (offset, c) in rest.char_indices() {
+            state = self.dfa.transition(state, c);
+            if state == self.dfa.error_state() {
+                stopped = true;
+                break;
+            }
+            if let Some(token) = self.dfa.accept(state) {
+                longest = Some((token, offset + c.len_utf8()));
+            }
+        }
+
+        // A longer match may need input that has not been fed yet.
+        if !stopped && !self.finished {
+            return None;
+        }
+
+        let (kind, len) = longest.unwrap_or((NO_TOKEN, first.len_utf8()));
+        let start = self.base + self.pos as u64;
+        self.pos += len;
+        Some(LutherToken {
+            kind,
+            start,
+            end: start + len as u64,
+        })
+    }
+}
+
+impl Drop for LutherScanner {
+    fn drop(&mut self) {
+        // tables came from Box::into_raw in new and is freed only here.
+        drop(unsafe { Box::from_raw(self.tables) });
+    }
+}
+
+// The return value for success (0) or failure (-1).
+fn status(ok: bool) -> c_int {
+    if ok {
+        0
+    } else {
+        -1
+    }
+}
+
+/// Creates a scanner for the dfa in the binary encoding of `table::TableDfa`.
+///
+/// The `len` bytes at `dfa` are copied. This returns a null pointer if they are not a valid dfa.
+/// The scanner must be freed with `luther_scanner_free`.
+///
+/// # Safety
+/// `dfa` must be null or point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn luther_scanner_new(dfa: *const u8, len: usize) -> *mut LutherScanner {
+    if dfa.is_null() {
+        return ptr::null_mut();
+    }
+
+    match LutherScanner::new(slice::from_raw_parts(dfa, len)) {
+        Some(scanner) => Box::into_raw(Box::new(scanner)),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Frees a scanner created by `luther_scanner_new` (or does nothing for a null pointer).
+///
+/// # Safety
+/// `scanner` must be null or a pointer returned by `luther_scanner_new` that has not been
+/// freed.
+#[no_mangle]
+pub unsafe extern "C" fn luther_scanner_free(scanner: *mut LutherScanner) {
+    if !scanner.is_null() {
+        drop(Box::from_raw(scanner));
+    }
+}
+
+/// Feeds the next `len` bytes of UTF-8 input to `scanner`.
+///
+/// This returns 0 on success and -1 if the bytes are not valid UTF-8 (in which case they are
+/// ignored), if the input is already finished, or if a pointer is null.
+///
+/// # Safety
+/// `scanner` must be null or a pointer returned by `luther_scanner_new` that has not been
+/// freed, and `input` must be null or point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn luther_scanner_feed(
+    scanner: *mut LutherScanner,
+    input: *const u8,
+    len: usize,
+) -> c_int {
+    match scanner.as_mut() {
+        Some(scanner) if !input.is_null() => {
+            status(scanner.feed(slice::from_raw_parts(input, len)))
+        }
+        _ => -1,
+    }
+}
+
+/// Marks the end of the input of `scanner`.
+///
+/// This returns 0 on success and -1 if the input ends in the middle of a `char` or if
+/// `scanner` is null.
+///
+/// # Safety
+/// `scanner` must be null or a pointer returned by `luther_scanner_new` that has not been
+/// freed.
+#[no_mangle]
+pub unsafe extern "C" fn luther_scanner_finish(scanner: *mut LutherScanner) -> c_int {
+    match scanner.as_mut() {
+        Some(scanner) => status(scanner.finish()),
+        None => -1,
+    }
+}
+
+/// Writes the next token of `scanner` to `token`.
+///
+/// This returns 1 if it wrote a token and 0 if there is no token until more input is fed (or,
+/// after `luther_scanner_finish`, if the input is exhausted). It returns -1 if a pointer is
+/// null.
+///
+/// # Safety
+/// `scanner` must be null or a pointer returned by `luther_scanner_new` that has not been
+/// freed, and `token` must be null or point to a writable
+/// `LutherToken`.
+#[no_mangle]
+pub unsafe extern "C" fn luther_scanner_next(
+    scanner: *mut LutherScanner,
+    token: *mut LutherToken,
+) -> c_int {
+    match (scanner.as_mut(), token.as_mut()) {
+        (Some(scanner), Some(token)) => match scanner.next_token() {
+            Some(next) => {
+                *token = next;
+                1
+            }
+            None => 0,
+        },
+        _ => -1,
+    }
+}
+
+/// The UTF-8 name of the token `kind` (not terminated by a NUL), with its length in `len`.
+///
+/// This returns a null pointer if the dfa has no name for the token or if a pointer is null.
+/// The name lives as long as `scanner`.
+///
+/// # Safety
+/// `scanner` must be null or a pointer returned by `luther_scanner_new` that has not been
+/// freed, and `len` must be null or point to a writable `usize`.
+#[no_mangle]
+pub unsafe extern "C" fn luther_scanner_token_name(
+    scanner: *const LutherScanner,
+    kind: u32,
+    len: *mut usize,
+) -> *const u8 {
+    match (scanner.as_ref(), len.as_mut()) {
+        (Some(scanner), Some(len)) => match scanner.dfa.token_name(kind) {
+            Some(name) => {
+                *len = name.len();
+                name.as_ptr()
+            }
+            None => ptr::null(),
+        },
+        _ => ptr::null(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use table::test::{ab_dfa, ab_names_dfa};
+
+    fn tokens(scanner: *mut LutherScanner) -> Vec<(u32, u64, u64)> {
+        let mut tokens = Vec::new();
+        let mut token = LutherToken::default();
+        while unsafe { luther_scanner_next(scanner, &mut token) } == 1 {
+            tokens.push((token.kind, token.start, token.end));
+        }
+        tokens
+    }
+
+    #[test]
+    fn scanner_holds_back_token_that_may_continue() {
+        let dfa = ab_dfa();
+        let scanner = unsafe { luther_scanner_new(dfa.as_ptr(), dfa.len()) };
+        assert!(!scanner.is_null());
+
+        let input = "aab\u{e9}a".as_bytes();
+        let first = unsafe { luther_scanner_feed(scanner, input.as_ptr(), 4) };
+        let before_finish = tokens(scanner);
+        let second = unsafe { luther_scanner_feed(scanner, input[4..].as_ptr(), 2) };
+        let finish = unsafe { luther_scanner_finish(scanner) };
+        let after_finish = tokens(scanner);
+        unsafe { luther_scanner_free(scanner) };
+
+        assert_eq!((first, second, finish), (0, 0, 0));
+        assert_eq!(before_finish, vec![(0, 0, 2)]);
+        assert_eq!(after_finish, vec![(1, 2, 3), (NO_TOKEN, 3, 5), (0, 5, 6)]);
+    }
+
+    #[test]
+    fn scanner_rejects_invalid_input() {
+        let dfa = ab_dfa();
+        let scanner = unsafe { luther_scanner_new(dfa.as_ptr(), dfa.len()) };
+
+        let invalid = unsafe { luther_scanner_feed(scanner, [0x61, 0xff].as_ptr(), 2) };
+        let split = unsafe { luther_scanner_feed(scanner, [0xc3].as_ptr(), 1) };
+        let finish = unsafe { luther_scanner_finish(scanner) };
+        unsafe { luther_scanner_free(scanner) };
+
+        assert_eq!((invalid, split, finish), (-1, 0, -1));
+        assert!(unsafe { luther_scanner_new(dfa.as_ptr(), 8) }.is_null());
+    }
+
+    #[test]
+    fn scanner_has_token_names() {
+        let dfa = ab_names_dfa();
+        let scanner = unsafe { luther_scanner_new(dfa.as_ptr(), dfa.len()) };
+
+        let mut len = 0;
+        let name = unsafe { luther_scanner_token_name(scanner, 0, &mut len) };
+        let name = unsafe { slice::from_raw_parts(name, len) };
+        let missing = unsafe { luther_scanner_token_name(scanner, 2, &mut len) };
+
+        assert_eq!(name, b"Many");
+        assert!(missing.is_null());
+        unsafe { luther_scanner_free(scanner) };
+    }
+}
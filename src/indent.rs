@@ -0,0 +1,322 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Defines `Indented`, an adapter that adds the layout tokens of an indentation sensitive
+//! language to a token iterator.
+//!
+//! Languages such as Python and YAML mark their blocks by indentation rather than by
+//! delimiters. Their parsers expect an INDENT token where a line is indented further than the
+//! line before it, a DEDENT token for each level that a line closes, and a NEWLINE token at the
+//! end of each logical line. A lexer for such a language only needs tokens for the line
+//! terminators and for whitespace (which must not be skipped), and `Indented` replaces them
+//! with the layout tokens.
+
+use std::collections::VecDeque;
+use std::result::Result as StdResult;
+
+use failure::Fail;
+
+use super::{Location, Span};
+
+/// The tokens that `Indented` treats specially.
+///
+/// This is implemented by the token type of the underlying lexer, which also provides the
+/// layout tokens that `Indented` adds.
+pub trait IndentToken: Sized {
+    /// Whether the token is a line terminator.
+    fn is_newline(&self) -> bool;
+
+    /// Whether the token is whitespace (other than a line terminator).
+    fn is_whitespace(&self) -> bool;
+
+    /// The token for the end of a logical line.
+    fn newline() -> Self;
+
+    /// The token for an increase in the indentation.
+    fn indent() -> Self;
+
+    /// The token for each level of indentation that a line closes.
+    fn dedent() -> Self;
+}
+
+// COV_EXCL_START
+/// The error type for `Indented`.
+#[derive(Debug, Fail)]
+pub enum IndentError<E: Fail> {
+    /// A line was dedented to an indentation that matches none of the enclosing levels. The
+    /// field is the `Location` of the first token of the line.
+    #[fail(display = "The line at {:?} does not match any outer indentation level.", _0)]
+    InconsistentDedent(Location),
+
+    /// The underlying lexer returned an error.
+    #[fail(display = "The lexer encountered an error.")]
+    LexError(#[cause] E),
+}
+// COV_EXCL_END
+
+/// A token iterator with the INDENT, DEDENT, and NEWLINE tokens of an indentation sensitive
+/// language.
+///
+/// The indentation of a line is the length of the whitespace tokens at its start (in the
+/// units of their `Location`'s, so each `char` counts as one column for a `char` lexer). A
+/// line that is indented further than the current level starts a new level with an INDENT
+/// token (spanning the whitespace). A line that is indented less closes levels with a DEDENT
+/// token for each (at the first token of the line), and it is an error if it then does not
+/// match the level that it returns to. The line is still lexed at that level.
+///
+/// The line terminator at the end of a line with any other tokens is replaced by a NEWLINE
+/// token, while lines that are blank (empty or only whitespace) produce no tokens at all.
+/// Whitespace tokens are dropped. At the end of the input there is a NEWLINE token (if the
+/// last line has no line terminator) and a DEDENT token for each open level.
+#[derive(Debug)]
+pub struct Indented<T, E, I>
+where
+    E: Fail,
+    I: Iterator<Item = StdResult<Span<T>, E>>,
+{
+    tokens: I,
+    levels: Vec<usize>,
+    pending: VecDeque<StdResult<Span<T>, IndentError<E>>>,
+    line_start: bool,
+    leading: Option<(Location, Location)>,
+    last: Option<Location>,
+    done: bool,
+}
+
+impl<T, E, I> Indented<T, E, I>
+where
+    T: IndentToken,
+    E: Fail,
+    I: Iterator<Item = StdResult<Span<T>, E>>,
+{
+    /// Creates an `Indented` over `tokens`.
+    pub fn new(tokens: I) -> Indented<T, E, I> {
+        Indented {
+            tokens,
+            levels: vec![0],
+            pending: VecDeque::new(),
+            line_start: true,
+            leading: None,
+            last: None,
+            done: false,
+        }
+    }
+
+    fn push(&mut self, span: Span<T>) {
+        self.last = Some(span.end());
+
+        if span.value_ref().is_newline() {
+            if !self.line_start {
+                let (start, _, end) = span.into_inner();
+                self.pending.push_back(Ok(Span::new(start, end, T::newline())));
+            }
+            self.line_start = true;
+            self.leading = None;
+        } else if span.value_ref().is_whitespace() {
+            if self.line_start {
+                let start = self.leading.map_or(span.start(), |(start, _)| start);
+                self.leading = Some((start, span.end()));
+            }
+        } else {
+            if self.line_start {
+                self.line_start = false;
+                self.layout(span.start());
+            }
+            self.pending.push_back(Ok(span));
+        }
+    }
+
+    // Adds the INDENT or DEDENT tokens for a line whose first token is at.
+    fn layout(&mut self, at: Location) {
+        let width = self.leading
+            .map_or(0, |(start, end)| end.offset() - start.offset() + 1);
+
+        if width > self.level() {
+            let (start, end) = self.leading.unwrap();
+            self.levels.push(width);
+            self.pending.push_back(Ok(Span::new(start, end, T::indent())));
+            return;
+        }
+
+        while width < self.level() {
+            self.levels.pop();
+            self.pending.push_back(Ok(Span::new(at, at, T::dedent())));
+        }
+        if width != self.level() {
+            self.pending
+                .push_back(Err(IndentError::InconsistentDedent(at)));
+        }
+    }
+
+    fn finish(&mut self) {
+        let at = match self.last {
+            Some(at) => at,
+            None => return,
+        };
+
+        if !self.line_start {
+            self.pending.push_back(Ok(Span::new(at, at, T::newline())));
+        }
+        while self.levels.len() > 1 {
+            self.levels.pop();
+            self.pending.push_back(Ok(Span::new(at, at, T::dedent())));
+        }
+    }
+
+    fn level(&self) -> usize {
+        *self.levels.last().unwrap()
+    }
+}
+
+impl<T, E, I> Iterator for Indented<T, E, I>
+where
+    T: IndentToken,
+    E: Fail,
+    I: Iterator<Item = StdResult<Span<T>, E>>,
+{
+    type Item = StdResult<Span<T>, IndentError<E>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.pending.pop_front() {
+                return Some(item);
+            }
+            if self.done {
+                return None;
+            }
+
+            match self.tokens.next() {
+                Some(Ok(span)) => self.push(span),
+                Some(Err(e)) => return Some(Err(IndentError::LexError(e))),
+                None => {
+                    self.finish();
+                    self.done = true;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use spanned::Never;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Token {
+        Word,
+        Space,
+        Eol,
+        Newline,
+        Indent,
+        Dedent,
+    }
+
+    impl IndentToken for Token {
+        fn is_newline(&self) -> bool {
+            *self == Token::Eol
+        }
+
+        fn is_whitespace(&self) -> bool {
+            *self == Token::Space
+        }
+
+        fn newline() -> Token {
+            Token::Newline
+        }
+
+        fn indent() -> Token {
+            Token::Indent
+        }
+
+        fn dedent() -> Token {
+            Token::Dedent
+        }
+    }
+
+    // Lexes input with 'w' as a word, ' ' as a space, and '\n' as a line terminator.
+    fn spans(input: &str) -> Vec<StdResult<Span<Token>, Never>> {
+        input
+            .char_indices()
+            .map(|(i, c)| {
+                let token = match c {
+                    'w' => Token::Word,
+                    ' ' => Token::Space,
+                    _ => Token::Eol,
+                };
+                Ok(Span::new(Location::new(i), Location::new(i), token))
+            })
+            .collect()
+    }
+
+    fn lex(input: &str) -> Vec<(usize, Token)> {
+        Indented::new(spans(input).into_iter())
+            .map(|span| {
+                let (start, token, _) = span.expect("unexpected indentation error").into_inner();
+                (start.offset(), token)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn indented_adds_layout_tokens() {
+        use self::Token::*;
+
+        let sut = lex("w\n  w\n\n  w\n    w\nw");
+
+        assert_eq!(
+            sut,
+            vec![
+                (0, Word),
+                (1, Newline),
+                (2, Indent),
+                (4, Word),
+                (5, Newline),
+                (9, Word),
+                (10, Newline),
+                (11, Indent),
+                (15, Word),
+                (16, Newline),
+                (17, Dedent),
+                (17, Dedent),
+                (17, Word),
+                (17, Newline),
+            ]
+        );
+    }
+
+    #[test]
+    fn indented_closes_levels_at_end_of_input() {
+        use self::Token::*;
+
+        let sut = lex("w\n w w\n");
+
+        assert_eq!(
+            sut,
+            vec![
+                (0, Word),
+                (1, Newline),
+                (2, Indent),
+                (3, Word),
+                (5, Word),
+                (6, Newline),
+                (6, Dedent),
+            ]
+        );
+    }
+
+    #[test]
+    fn indented_rejects_inconsistent_dedent() {
+        let sut: Vec<_> = Indented::new(spans("w\n    w\n  w").into_iter()).collect();
+
+        assert_matches!(
+            sut[6],
+            Err(IndentError::InconsistentDedent(location)) if location == Location::new(10)
+        );
+    }
+}
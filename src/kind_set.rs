@@ -0,0 +1,256 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Defines `TokenKindSet`, a set of token kinds for checking the next token in a parser.
+//!
+//! A recursive descent parser checks the next token against a set of token kinds at almost
+//! every step (such as the FIRST set of a rule or the tokens to skip to when recovering from an
+//! error). A `TokenKindSet` is a fixed size bitset over the numeric codes of the kinds, so
+//! these checks are a shift and a mask rather than a `match` or a search. The codes are the
+//! `kind_code()`'s of a token type that derives `Lexer` with the `kind_codes` option, or the
+//! token numbers of a `table::TableDfa`.
+//!
+//! The constructors are `const fn`'s so that the sets of a grammar can be `const` items:
+//!
+//! ```rust
+//! use luther::kind_set::TokenKindSet;
+//!
+//! const IDENT: u32 = 0;
+//! const NUMBER: u32 = 1;
+//! const LPAREN: u32 = 2;
+//!
+//! const FIRST_EXPR: TokenKindSet = TokenKindSet::new().with(IDENT).with(NUMBER).with(LPAREN);
+//!
+//! assert!(FIRST_EXPR.contains(NUMBER));
+//! assert!(!FIRST_EXPR.contains(3));
+//! ```
+
+use std::fmt;
+use std::ops;
+
+const WORDS: usize = 4;
+
+/// A set of token kinds, by their numeric codes.
+///
+/// A `TokenKindSet` holds the codes from 0 up to (but not including) `TokenKindSet::CAPACITY`.
+///
+/// # Panics
+///
+/// Adding a code of `TokenKindSet::CAPACITY` or more to a set panics (or, in a `const` item,
+/// fails to compile). Testing for such a code is always `false`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct TokenKindSet {
+    bits: [u64; WORDS],
+}
+
+impl TokenKindSet {
+    /// The number of codes that a `TokenKindSet` can hold.
+    pub const CAPACITY: u32 = 64 * WORDS as u32;
+
+    /// Creates an empty `TokenKindSet`.
+    pub const fn new() -> TokenKindSet {
+        TokenKindSet { bits: [0; WORDS] }
+    }
+
+    /// Creates a `TokenKindSet` with the codes in `codes`.
+    pub fn from_codes(codes: &[u32]) -> TokenKindSet {
+        let mut set = TokenKindSet::new();
+        for &code in codes {
+            set.insert(code);
+        }
+        set
+    }
+
+    /// The set with `code` added to it.
+    pub const fn with(self, code: u32) -> TokenKindSet {
+        let mut bits = self.bits;
+        bits[(code / 64) as usize] |= 1 << (code % 64);
+        TokenKindSet { bits }
+    }
+
+    /// The codes that are in either set.
+    pub const fn union(self, other: TokenKindSet) -> TokenKindSet {
+        TokenKindSet {
+            bits: [
+                self.bits[0] | other.bits[0],
+                self.bits[1] | other.bits[1],
+                self.bits[2] | other.bits[2],
+                self.bits[3] | other.bits[3],
+            ],
+        }
+    }
+
+    /// The codes that are in both sets.
+    pub const fn intersection(self, other: TokenKindSet) -> TokenKindSet {
+        TokenKindSet {
+            bits: [
+                self.bits[0] & other.bits[0],
+                self.bits[1] & other.bits[1],
+                self.bits[2] & other.bits[2],
+                self.bits[3] & other.bits[3],
+            ],
+        }
+    }
+
+    /// The codes that are in this set but not in `other`.
+    pub const fn difference(self, other: TokenKindSet) -> TokenKindSet {
+        TokenKindSet {
+            bits: [
+                self.bits[0] & !other.bits[0],
+                self.bits[1] & !other.bits[1],
+                self.bits[2] & !other.bits[2],
+                self.bits[3] & !other.bits[3],
+            ],
+        }
+    }
+
+    /// Tests for `code` being in the set.
+    pub const fn contains(&self, code: u32) -> bool {
+        code < TokenKindSet::CAPACITY && self.bits[(code / 64) as usize] & 1 << (code % 64) != 0
+    }
+
+    /// Adds `code` to the set.
+    pub fn insert(&mut self, code: u32) {
+        assert!(
+            code < TokenKindSet::CAPACITY,
+            "token kind {} is too large for a TokenKindSet",
+            code
+        );
+        self.bits[(code / 64) as usize] |= 1 << (code % 64);
+    }
+
+    /// Removes `code` from the set.
+    pub fn remove(&mut self, code: u32) {
+        if code < TokenKindSet::CAPACITY {
+            self.bits[(code / 64) as usize] &= !(1 << (code % 64));
+        }
+    }
+
+    /// Tests for the set being empty.
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|&word| word == 0)
+    }
+
+    /// The number of codes in the set.
+    pub fn len(&self) -> usize {
+        self.bits.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Iterates over the codes in the set in increasing order.
+    pub fn iter(&self) -> Iter {
+        Iter { set: *self, next: 0 }
+    }
+}
+
+impl fmt::Debug for TokenKindSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl ops::BitOr for TokenKindSet {
+    type Output = TokenKindSet;
+
+    fn bitor(self, rhs: TokenKindSet) -> TokenKindSet {
+        self.union(rhs)
+    }
+}
+
+impl ops::BitAnd for TokenKindSet {
+    type Output = TokenKindSet;
+
+    fn bitand(self, rhs: TokenKindSet) -> TokenKindSet {
+        self.intersection(rhs)
+    }
+}
+
+impl ops::Sub for TokenKindSet {
+    type Output = TokenKindSet;
+
+    fn sub(self, rhs: TokenKindSet) -> TokenKindSet {
+        self.difference(rhs)
+    }
+}
+
+impl IntoIterator for TokenKindSet {
+    type Item = u32;
+    type IntoIter = Iter;
+
+    fn into_iter(self) -> Iter {
+        Iter { set: self, next: 0 }
+    }
+}
+
+/// An iterator over the codes in a `TokenKindSet`.
+///
+/// This struct is created by the `iter` method on `TokenKindSet`.
+#[derive(Debug, Clone)]
+pub struct Iter {
+    set: TokenKindSet,
+    next: u32,
+}
+
+impl Iterator for Iter {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        while self.next < TokenKindSet::CAPACITY {
+            let code = self.next;
+            self.next += 1;
+            if self.set.contains(code) {
+                return Some(code);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FIRST: TokenKindSet = TokenKindSet::new().with(1).with(70).with(255);
+
+    #[test]
+    fn token_kind_set_const_contains_its_codes() {
+        assert!(FIRST.contains(1));
+        assert!(FIRST.contains(70));
+        assert!(FIRST.contains(255));
+        assert!(!FIRST.contains(0));
+        assert!(!FIRST.contains(256));
+        assert_eq!(FIRST.len(), 3);
+    }
+
+    #[test]
+    fn token_kind_set_combines_sets() {
+        let other = TokenKindSet::from_codes(&[1, 2]);
+
+        assert_eq!((FIRST | other).iter().collect::<Vec<_>>(), vec![1, 2, 70, 255]);
+        assert_eq!((FIRST & other).iter().collect::<Vec<_>>(), vec![1]);
+        assert_eq!((FIRST - other).iter().collect::<Vec<_>>(), vec![70, 255]);
+        assert!((FIRST - FIRST).is_empty());
+    }
+
+    #[test]
+    fn token_kind_set_inserts_and_removes_codes() {
+        let mut sut = TokenKindSet::new();
+
+        sut.insert(64);
+        sut.insert(3);
+        sut.remove(3);
+        sut.remove(1000);
+
+        assert_eq!(format!("{:?}", sut), "{64}");
+    }
+
+    #[test]
+    #[should_panic]
+    fn token_kind_set_insert_panics_for_too_large_code() {
+        TokenKindSet::new().insert(TokenKindSet::CAPACITY);
+    } // COV_EXCL_LINE
+}
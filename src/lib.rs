@@ -16,14 +16,92 @@
 //! The input to the `lexer` method is a fallible iterator (i.e. an iterator with a Result item
 //! type) over a `Span` of `char`. The output is a fallible iterator over a `Span` of the token
 //! type.
+//!
+//! The `prelude` module re-exports the types and traits that most users need (the `Lexer`
+//! trait, `LexerIter`, `Span`, `LexError`, and so on) for `use luther::prelude::*;`. Prefer it
+//! to the paths of the modules that define them, which may be reorganized.
+//!
+//! The `io` feature (enabled by default) adds `Lexer::byte_lexer` and the `spanned` utilities
+//! for lexing bytes and readers. Without it the crate does not use `std::io`, which suits
+//! targets such as `wasm32-unknown-unknown`. For those targets `chunked::ChunkedLexer` takes the
+//! input as `&str` chunks and returns the lexed tokens in a `Vec` after each chunk.
+//!
+//! `Lexer::wtf8_lexer` lexes WTF-8 bytes (which may have unpaired surrogates), treating each
+//! unpaired surrogate as a `char` of the caller's choosing.
+//!
+//! `dfa::LexerIter::with_control_chars` sets whether the NUL and other control characters in
+//! the input are lexed like any other `char`, skipped, or reported as errors (with their spans).
+//!
+//! The `spanned::Utf16Ext` trait lexes a `[u16]` buffer of UTF-16 code units (as editors and
+//! Windows APIs provide) without transcoding it first.
+//!
+//! The `mmap` feature (not enabled by default) adds `mmap::lex_mmap`, which lexes a file through
+//! a memory mapping of it rather than reading it into memory.
+//!
+//! The `channel` feature (not enabled by default) adds `channel::lex_in_background`, which lexes
+//! on a background thread and sends the tokens through a bounded channel.
+//!
+//! The `ffi` feature (not enabled by default) adds the `ffi` module, a C ABI for scanning with a
+//! dfa compiled ahead of time, so that tools in other languages can use the same tables.
+//!
+//! The `dump` module formats a lexed token stream as aligned columns or JSON lines for bug
+//! reports and tests.
+//!
+//! The `indent` module adds the INDENT, DEDENT, and NEWLINE tokens of an indentation sensitive
+//! language (such as Python or YAML) to the tokens of a lexer.
+//!
+//! The `kind_set` module has `TokenKindSet`, a bitset of token kinds (by their numeric codes)
+//! for the FIRST and FOLLOW set checks of a recursive descent parser.
+//!
+//! The `session` module lexes a set of named inputs (such as the files of a program) with
+//! `Location`'s that are unique across all of them, and finds the input, line, and column of a
+//! `Location`.
+//!
+//! The `source_map` module maps the `Location`'s of tokens in a preprocessed buffer (for
+//! example with its shebang line stripped or its includes expanded) back to the original files.
+//!
+//! The `position` module converts the `Location`'s of tokens into line and column numbers. The
+//! `unicode-segmentation` feature (not enabled by default) lets it count the columns in
+//! extended grapheme clusters, as editors do.
+//!
+//! The `record` module (unstable) records the chunks fed to a `chunked::ChunkedLexer` and the
+//! tokens it produced, and replays the recording step by step to reproduce a bug that depends
+//! on where the input was split.
+//!
+//! # Stability
+//! The public API of this crate is in one of two tiers:
+//!
+//! * Stable: everything that is not marked unstable. It follows semantic versioning, so it
+//!   only changes in a breaking way in a new major (or, before 1.0, minor) version. This
+//!   includes the `prelude` and the modules behind the `io`, `mmap`, `channel`, `ffi`, and
+//!   `unicode-segmentation` features.
+//! * Unstable: the APIs behind the `unstable` feature (not enabled by default), which are
+//!   experimental subsystems that are still being iterated on. Their documentation starts with
+//!   "Unstable" and gives the version in which they were added. They may change or be removed
+//!   in any release, including a patch release, so a crate that enables the feature should
+//!   depend on an exact version of luther (`luther = "=0.2.1"`).
+//!
+//! An unstable API graduates to the stable tier (and loses its `unstable` gate) in a minor
+//! release once it has been through at least one minor release without a breaking change.
+//! The gate is kept (as a no-op) for a release after that so that crates that enable it
+//! continue to build.
 
 #![deny(missing_docs)]
+// The Fail derive of failure implements Fail inside a named const, which newer compilers lint.
+#![allow(unknown_lints, non_local_definitions)]
 
 #[macro_use]
 extern crate failure;
 
+#[cfg(feature = "io")]
 extern crate encode_unicode;
 
+#[cfg(feature = "unicode-segmentation")]
+extern crate unicode_segmentation;
+
+#[cfg(feature = "mmap")]
+extern crate memmap2;
+
 #[cfg(test)]
 #[macro_use]
 extern crate assert_matches;
@@ -41,12 +119,31 @@ extern crate regex;
 
 mod error;
 mod span;
+#[cfg(feature = "channel")]
+pub mod channel;
+pub mod chunked;
 pub mod dfa;
+pub mod dump;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod indent;
+pub mod kind_set;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod position;
+pub mod prelude;
+#[cfg(feature = "unstable")]
+pub mod record;
+pub mod session;
+pub mod source_map;
 pub mod spanned;
+pub mod table;
 
 pub use error::{LexError, Result};
 pub use span::{Location, Span};
 
+#[cfg(feature = "io")]
+use std::io;
 use std::result::Result as StdResult;
 
 /// An interface for creating a lexer for a `char` iterator for the type on which it is
@@ -72,4 +169,68 @@ pub trait Lexer: Sized {
     {
         dfa::LexerIter::new(input.into_iter())
     }
+
+    /// Creates a lexer from the supplied iterator over the bytes of utf8 encoded input.
+    ///
+    /// This method requires the `io` feature (which is enabled by default).
+    ///
+    /// This is a utf8 decoding adapter rather than a dfa over bytes: it decodes the input into
+    /// `char`'s and lexes them with the same `Dfa` as `lexer`. Bytes that are not valid utf8
+    /// are an `io::ErrorKind::InvalidData` error. The `Location`'s of the tokens it produces
+    /// are byte offsets from the start of the input.
+    ///
+    /// # Type Parameters
+    /// - I: a type convertable to a fallible iterator over `u8`
+    ///
+    /// # Returns
+    /// An fallible iterator over `Span<Self>`.
+    #[cfg(feature = "io")]
+    fn byte_lexer<I>(
+        input: I,
+    ) -> dfa::LexerIter<
+        Self,
+        io::Error,
+        spanned::SpannedUtf8Iter<<I as IntoIterator>::IntoIter>,
+        Self::Dfa,
+    >
+    where
+        I: IntoIterator<Item = io::Result<u8>>,
+    {
+        dfa::LexerIter::new(spanned::SpannedUtf8Iter::new(
+            Location::default(),
+            input.into_iter(),
+        ))
+    }
+
+    /// Creates a lexer from the supplied iterator over the bytes of WTF-8 encoded input.
+    ///
+    /// This method requires the `io` feature (which is enabled by default).
+    ///
+    /// This is `byte_lexer` for input that may have unpaired surrogates (such as Windows file
+    /// names or JavaScript strings). The lexer sees each unpaired surrogate as the `surrogate`
+    /// `char` rather than failing on it (see `spanned::SpannedUtf8Iter::with_surrogates`).
+    ///
+    /// # Type Parameters
+    /// - I: a type convertable to a fallible iterator over `u8`
+    ///
+    /// # Returns
+    /// An fallible iterator over `Span<Self>`.
+    #[cfg(feature = "io")]
+    fn wtf8_lexer<I>(
+        input: I,
+        surrogate: char,
+    ) -> dfa::LexerIter<
+        Self,
+        io::Error,
+        spanned::SpannedUtf8Iter<<I as IntoIterator>::IntoIter>,
+        Self::Dfa,
+    >
+    where
+        I: IntoIterator<Item = io::Result<u8>>,
+    {
+        dfa::LexerIter::new(
+            spanned::SpannedUtf8Iter::new(Location::default(), input.into_iter())
+                .with_surrogates(surrogate),
+        )
+    }
 }
@@ -0,0 +1,223 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Defines `lex_mmap`, which lexes a memory mapped file.
+//!
+//! This module requires the `mmap` feature (which is not enabled by default). It suits tools
+//! that lex files too large to read into memory, such as logs. The file is mapped rather than
+//! read, and the mapping is checked to be valid UTF-8 one chunk at a time as the lexer reaches
+//! it, so neither the mapping nor the check costs memory in proportion to the file.
+//!
+//! There is no dfa over bytes: the mapping is decoded into `char`'s for the `char` dfa of the
+//! `Lexer`, so the file must be UTF-8. A file (such as a binary log) with bytes that are not
+//! valid UTF-8 is rejected at the first of them rather than lexed.
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::{cmp, str};
+
+use memmap2::Mmap;
+
+use super::{Lexer, Location, Span};
+use dfa::LexerIter;
+
+const CHUNK_LEN: usize = 64 * 1024;
+
+/// Lexes the file at `path` through a memory mapping of it.
+///
+/// The `Location`'s of the tokens are byte offsets from the start of the file.
+///
+/// The file must not be changed (by this or any other process) while the lexer is in use.
+/// Changing a mapped file is undefined behaviour.
+///
+/// # Errors
+/// Opening or mapping the file returns an error immediately. The lexer produces an
+/// `ErrorKind::InvalidData` error (whose message gives the byte offset in the file) for a byte
+/// sequence that is not valid UTF-8 and an `ErrorKind::UnexpectedEof` error if the file ends in
+/// the middle of a multibyte `char`.
+pub fn lex_mmap<T, P>(path: P) -> io::Result<LexerIter<T, io::Error, MmapChars, T::Dfa>>
+where
+    T: Lexer,
+    P: AsRef<Path>,
+{
+    Ok(LexerIter::new(MmapChars::open(path)?))
+}
+
+/// An iterator over the spanned `char`'s of a memory mapped file.
+///
+/// See `lex_mmap` for the errors that it produces.
+pub struct MmapChars {
+    map: Mmap,
+    pos: usize,
+    valid_end: usize,
+    chunk_len: usize,
+}
+
+impl MmapChars {
+    /// Maps the file at `path` to iterate over its spanned `char`'s.
+    ///
+    /// The file must not be changed while the iterator is in use (see `lex_mmap`).
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<MmapChars> {
+        let file = File::open(path)?;
+        // The mapping is only read, and the caller is responsible for the file not changing.
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(MmapChars::with_chunk_len(map, CHUNK_LEN))
+    }
+
+    fn with_chunk_len(map: Mmap, chunk_len: usize) -> MmapChars {
+        MmapChars {
+            map,
+            pos: 0,
+            valid_end: 0,
+            chunk_len,
+        }
+    }
+
+    // Checks the next chunk of the mapping, leaving valid_end after its last complete char.
+    fn validate_chunk(&mut self) -> io::Result<()> {
+        let end = cmp::min(self.pos + cmp::max(self.chunk_len, 4), self.map.len());
+        match str::from_utf8(&self.map[self.pos..end]) {
+            Ok(_) => {
+                self.valid_end = end;
+                Ok(())
+            }
+            Err(ref e) if e.valid_up_to() > 0 => {
+                self.valid_end = self.pos + e.valid_up_to();
+                Ok(())
+            }
+            Err(e) => match e.error_len() {
+                Some(len) => {
+                    let offset = self.pos;
+                    self.pos += len;
+                    Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "The file is not valid UTF-8 at byte offset {} (the mmap lexer \
+                             decodes the file for a char dfa).",
+                            offset
+                        ),
+                    ))
+                }
+                None => {
+                    self.pos = end;
+                    Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "Too few bytes for utf8 character.",
+                    ))
+                }
+            },
+        }
+    }
+}
+
+impl Iterator for MmapChars {
+    type Item = io::Result<Span<char>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.map.len() {
+            return None;
+        }
+        if self.pos >= self.valid_end {
+            if let Err(e) = self.validate_chunk() {
+                return Some(Err(e));
+            }
+        }
+
+        // The bytes up to valid_end were checked by validate_chunk.
+        let rest = unsafe { str::from_utf8_unchecked(&self.map[self.pos..self.valid_end]) };
+        rest.chars().next().map(|c| {
+            let start = Location::new(self.pos);
+            self.pos += c.len_utf8();
+            Ok(Span::new(start, start + (c.len_utf8() - 1), c))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::io::ErrorKind;
+    use std::path::PathBuf;
+
+    fn mapped(name: &str, bytes: &[u8]) -> (PathBuf, Mmap) {
+        let path = env::temp_dir().join(format!("luther-mmap-{}-{}", name, ::std::process::id()));
+        fs::write(&path, bytes).expect("could not write the test file");
+        let file = File::open(&path).expect("could not open the test file");
+        let map = unsafe { Mmap::map(&file) }.expect("could not map the test file");
+        (path, map)
+    }
+
+    fn chars(sut: MmapChars) -> Vec<Result<(usize, char, usize), ErrorKind>> {
+        sut.map(|r| {
+            r.map(|span| {
+                let (start, c, end) = span.into_inner();
+                (start.offset(), c, end.offset())
+            }).map_err(|e| e.kind())
+        }).collect()
+    }
+
+    #[test]
+    fn mmap_chars_decodes_chars_split_across_chunks() {
+        let (path, map) = mapped("split", "a¢ᐁb".as_bytes());
+
+        let result = chars(MmapChars::with_chunk_len(map, 4));
+        fs::remove_file(path).ok();
+
+        assert_eq!(
+            result,
+            vec![
+                Ok((0, 'a', 0)),
+                Ok((1, '¢', 2)),
+                Ok((3, 'ᐁ', 5)),
+                Ok((6, 'b', 6)),
+            ]
+        );
+    }
+
+    #[test]
+    fn mmap_chars_reports_invalid_utf8_and_continues() {
+        let (path, map) = mapped("invalid", &[0x61, 0x80, 0x62, 0xe1, 0x90]);
+
+        let result = chars(MmapChars::with_chunk_len(map, 4));
+        fs::remove_file(path).ok();
+
+        assert_eq!(
+            result,
+            vec![
+                Ok((0, 'a', 0)),
+                Err(ErrorKind::InvalidData),
+                Ok((2, 'b', 2)),
+                Err(ErrorKind::UnexpectedEof),
+            ]
+        );
+    }
+
+    #[test]
+    fn mmap_chars_invalid_utf8_error_gives_offset() {
+        let (path, map) = mapped("offset", &[0x61, 0x62, 0xff]);
+
+        let result = MmapChars::with_chunk_len(map, 4).find(|r| r.is_err());
+        fs::remove_file(path).ok();
+
+        assert_matches!(result, Some(Err(ref e))
+            if e.kind() == ErrorKind::InvalidData && e.to_string().contains("byte offset 2"));
+    }
+
+    #[test]
+    fn mmap_chars_of_empty_file_is_empty() {
+        let (path, _) = mapped("empty", &[]);
+
+        let result = MmapChars::open(&path).map(chars);
+        fs::remove_file(path).ok();
+
+        assert_matches!(result, Ok(ref v) if v.is_empty());
+    }
+}
@@ -0,0 +1,153 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Line and column positions for the `Location`'s in a source text.
+//!
+//! `PositionTracker` converts the byte offsets of the `Location`'s of a token stream into the
+//! line and column numbers that a user would look for in an editor. The columns can be counted
+//! in bytes, in `char`'s, or (with the `unicode-segmentation` feature) in extended grapheme
+//! clusters, which is how editors count the columns of text with emoji or combining marks.
+
+use std::fmt;
+
+#[cfg(feature = "unicode-segmentation")]
+use unicode_segmentation::UnicodeSegmentation;
+
+use Location;
+
+/// The unit in which a `PositionTracker` counts columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnUnit {
+    /// Each byte is a column.
+    Bytes,
+
+    /// Each `char` is a column.
+    Chars,
+
+    /// Each extended grapheme cluster is a column.
+    ///
+    /// This requires the `unicode-segmentation` feature.
+    #[cfg(feature = "unicode-segmentation")]
+    Graphemes,
+}
+
+/// A line and column in a source text (both starting at 1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    /// The line number.
+    pub line: usize,
+
+    /// The column number.
+    pub column: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Converts the `Location`'s in a source text into `Position`'s.
+///
+/// The `Location`'s must be byte offsets into the source (as they are for the tokens from
+/// `Lexer::byte_lexer` or for the `char`'s from `spanned::StrExt::spanned_chars`). The start of
+/// each line is found once when the tracker is made, so each conversion only counts the
+/// columns in one line.
+#[derive(Debug, Clone)]
+pub struct PositionTracker<'a> {
+    source: &'a str,
+    unit: ColumnUnit,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> PositionTracker<'a> {
+    /// Creates a `PositionTracker` for `source` that counts columns in `unit`'s.
+    pub fn new(source: &'a str, unit: ColumnUnit) -> PositionTracker<'a> {
+        PositionTracker {
+            source,
+            unit,
+            line_starts: line_starts(source),
+        }
+    }
+
+    /// The `Position` of `location`.
+    ///
+    /// # Panics
+    /// If `location` is past the end of the source or is not at a `char` boundary.
+    pub fn position(&self, location: Location) -> Position {
+        position(self.source, &self.line_starts, self.unit, location.offset())
+    }
+}
+
+// The byte offsets of the starts of the lines of source.
+pub(crate) fn line_starts(source: &str) -> Vec<usize> {
+    Some(0)
+        .into_iter()
+        .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+        .collect()
+}
+
+// The Position of the byte offset in source, given the line_starts of source.
+pub(crate) fn position(
+    source: &str,
+    line_starts: &[usize],
+    unit: ColumnUnit,
+    offset: usize,
+) -> Position {
+    let line = match line_starts.binary_search(&offset) {
+        Ok(line) => line,
+        Err(next) => next - 1,
+    };
+    let text = &source[line_starts[line]..offset];
+
+    let columns = match unit {
+        ColumnUnit::Bytes => text.len(),
+        ColumnUnit::Chars => text.chars().count(),
+        #[cfg(feature = "unicode-segmentation")]
+        ColumnUnit::Graphemes => text.graphemes(true).count(),
+    };
+
+    Position {
+        line: line + 1,
+        column: columns + 1,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SOURCE: &str = "let a = 1;\nlet b = \"\u{e9}\u{1f600}\";\n";
+
+    #[test]
+    fn position_counts_lines_and_bytes() {
+        let sut = PositionTracker::new(SOURCE, ColumnUnit::Bytes);
+
+        assert_eq!(sut.position(Location::new(0)), Position { line: 1, column: 1 });
+        assert_eq!(sut.position(Location::new(11)), Position { line: 2, column: 1 });
+        assert_eq!(sut.position(Location::new(26)), Position { line: 2, column: 16 });
+        assert_eq!(sut.position(Location::new(26)).to_string(), "2:16");
+    }
+
+    #[test]
+    fn position_counts_chars() {
+        let sut = PositionTracker::new(SOURCE, ColumnUnit::Chars);
+
+        assert_eq!(sut.position(Location::new(26)), Position { line: 2, column: 12 });
+    }
+
+    #[cfg(feature = "unicode-segmentation")]
+    #[test]
+    fn position_counts_graphemes() {
+        let source = "x = \"e\u{301}\u{1f469}\u{200d}\u{1f52c}\";";
+        let sut = PositionTracker::new(source, ColumnUnit::Graphemes);
+
+        let quote = Location::new(source.len() - 2);
+        assert_eq!(sut.position(quote), Position { line: 1, column: 8 });
+    }
+}
@@ -0,0 +1,41 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! The types and traits that most users of the crate need, for a glob import.
+//!
+//! ```
+//! use luther::prelude::*;
+//!
+//! let mut chars = "ab".spanned_chars();
+//! assert_eq!(chars.next().map(|c| c.ok()), Some(Some(Span::new(0.into(), 0.into(), 'a'))));
+//! ```
+//!
+//! The prelude is part of the stable tier of the public API (see the crate level
+//! documentation): what it re-exports will stay at these paths within a major (or, before 1.0,
+//! minor) version even if the modules that define them are reorganized. It re-exports
+//!
+//! - the `Lexer` trait and the `Dfa` trait that its implementations provide,
+//! - the `LexerIter` that lexes with a `Dfa` and its settings (`ModeSwitch`, `Delimiter`, and
+//!   `ControlChars`),
+//! - the `Span` and `Location` of a token and the `LexError` of a lexer,
+//! - the extension traits that make the spanned `char` iterators that a lexer takes as input,
+//! - the `ChunkedLexer` for input that arrives in pieces, and the `TableDfa` that loads a dfa
+//!   compiled ahead of time.
+//!
+//! The crate's `Result` alias is not in the prelude, so that a glob import does not shadow the
+//! standard `Result`.
+
+pub use Lexer;
+pub use error::LexError;
+pub use span::{Location, Span};
+pub use dfa::{ControlChars, Delimiter, Dfa, LexerIter, ModeSwitch};
+pub use spanned::{StrExt, Utf16Ext};
+#[cfg(feature = "io")]
+pub use spanned::Utf8SpannedChars;
+pub use chunked::ChunkedLexer;
+pub use table::TableDfa;
@@ -0,0 +1,429 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Unstable (since 0.2.1): records the input and output of a `ChunkedLexer` and replays them.
+//!
+//! This module requires the `unstable` feature. A bug that depends on where a stream happens
+//! to be split into chunks is hard to reproduce from the tokens alone. `Recorder` wraps a
+//! `ChunkedLexer` and writes a compact log of each step: the chunk that was pushed (or the end
+//! of the input), the tokens and errors that the step produced, and a snapshot (see
+//! `ChunkedLexer::snapshot`) of the lexer after it. `Replayer` re-executes a log one step at a
+//! time with a fresh lexer and compares each step with the recording, so the bug can be
+//! reproduced (and stepped through) offline.
+//!
+//! The log is a version 1 binary encoding of the magic bytes `LUTR` and the format version as
+//! a little endian `u32`, followed by the steps. All of the other values are little endian
+//! `u64`'s, and a string is its length in bytes followed by its UTF-8 bytes. Each step is:
+//!
+//! 1. 0 and the chunk that was pushed, or 1 for the end of the input;
+//! 2. the number of outputs, followed by each output as either 0, its start and end
+//!    `Location`'s, and the `Debug` formatting of the token, or 1 and the error message; and
+//! 3. the snapshot of the lexer after the step, as a string of bytes.
+
+use std::fmt::Debug;
+use std::result::Result as StdResult;
+use std::str;
+
+use chunked::{push_u64, read_u32, read_u64, u32_bytes, ChunkedLexer};
+use spanned::Never;
+use super::{Lexer, Location, Result, Span};
+
+/// The magic bytes at the start of the log of a `Recorder`.
+pub const RECORDING_MAGIC: &[u8; 4] = b"LUTR";
+
+/// The version of the log format that this module writes and reads.
+pub const RECORDING_VERSION: u32 = 1;
+
+// COV_EXCL_START
+/// The error type for reading the log of a `Recorder`.
+#[derive(Debug, Fail, PartialEq)]
+pub enum RecordingError {
+    /// The input does not start with the magic bytes.
+    #[fail(display = "The input is not a luther recording.")]
+    BadMagic,
+
+    /// The input is encoded in a version of the format that is not supported.
+    #[fail(display = "The luther recording format version {} is not supported.", _0)]
+    UnsupportedVersion(u32),
+
+    /// The input ends in the middle of a step.
+    #[fail(display = "The luther recording ends in the middle of a step.")]
+    BadLength,
+
+    /// A value in the input is out of range. The field names the part of the step it is in.
+    #[fail(display = "The luther recording has an invalid value in its {}.", _0)]
+    InvalidValue(&'static str),
+}
+// COV_EXCL_END
+
+/// The input to a step of a `ChunkedLexer`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input {
+    /// A chunk that was pushed.
+    Push(String),
+
+    /// The end of the input.
+    Finish,
+}
+
+/// A token or error that a step of a `ChunkedLexer` produced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Output {
+    /// A token with its start and end `Location`'s and its `Debug` formatting.
+    Token(Location, Location, String),
+
+    /// An error with its message.
+    Error(String),
+}
+
+impl Output {
+    fn new<T: Debug>(result: &Result<Span<T>, Never>) -> Output {
+        match *result {
+            Ok(ref span) => {
+                Output::Token(span.start(), span.end(), format!("{:?}", span.value_ref()))
+            }
+            Err(ref error) => Output::Error(error.to_string()),
+        }
+    }
+}
+
+/// A recorded step of a `ChunkedLexer`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    /// The input to the step.
+    pub input: Input,
+
+    /// The tokens and errors that the step produced.
+    pub outputs: Vec<Output>,
+
+    /// The snapshot of the lexer after the step.
+    pub snapshot: Vec<u8>,
+}
+
+/// A `ChunkedLexer` that records a log of its steps.
+pub struct Recorder<T: Lexer> {
+    lexer: ChunkedLexer<T>,
+    log: Vec<u8>,
+}
+
+impl<T: Lexer + Debug> Recorder<T> {
+    /// Creates a new `Recorder` for a `ChunkedLexer` that has not been fed any input.
+    pub fn new() -> Recorder<T> {
+        let mut log = RECORDING_MAGIC.to_vec();
+        log.extend_from_slice(&u32_bytes(RECORDING_VERSION));
+        Recorder {
+            lexer: ChunkedLexer::new(),
+            log,
+        }
+    }
+
+    /// Feeds the next chunk of input to the lexer and records the step.
+    ///
+    /// # Returns
+    /// The tokens (and errors) that `ChunkedLexer::push` returns.
+    pub fn push(&mut self, chunk: &str) -> Vec<Result<Span<T>, Never>> {
+        let tokens = self.lexer.push(chunk);
+        self.record(&Input::Push(chunk.to_string()), &tokens);
+        tokens
+    }
+
+    /// Ends the input and records the step.
+    ///
+    /// # Returns
+    /// The tokens (and errors) that `ChunkedLexer::finish` returns.
+    pub fn finish(&mut self) -> Vec<Result<Span<T>, Never>> {
+        let tokens = self.lexer.finish();
+        self.record(&Input::Finish, &tokens);
+        tokens
+    }
+
+    /// The log of the steps so far.
+    pub fn log(&self) -> &[u8] {
+        &self.log
+    }
+
+    fn record(&mut self, input: &Input, tokens: &[Result<Span<T>, Never>]) {
+        let log = &mut self.log;
+        match *input {
+            Input::Push(ref chunk) => {
+                push_u64(log, 0);
+                push_bytes(log, chunk.as_bytes());
+            }
+            Input::Finish => push_u64(log, 1),
+        }
+
+        push_u64(log, tokens.len() as u64);
+        for token in tokens {
+            match Output::new(token) {
+                Output::Token(start, end, value) => {
+                    push_u64(log, 0);
+                    push_u64(log, start.as_u64());
+                    push_u64(log, end.as_u64());
+                    push_bytes(log, value.as_bytes());
+                }
+                Output::Error(message) => {
+                    push_u64(log, 1);
+                    push_bytes(log, message.as_bytes());
+                }
+            }
+        }
+
+        push_bytes(log, &self.lexer.snapshot());
+    }
+}
+
+impl<T: Lexer + Debug> Default for Recorder<T> {
+    fn default() -> Self {
+        Recorder::new()
+    }
+}
+
+/// A step of a recording that has been replayed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replayed {
+    /// The index of the step in the recording.
+    pub index: usize,
+
+    /// The step as it was recorded.
+    pub recorded: Step,
+
+    /// The tokens and errors that the step produced when it was replayed.
+    pub outputs: Vec<Output>,
+
+    /// The snapshot of the lexer after the step when it was replayed.
+    pub snapshot: Vec<u8>,
+}
+
+impl Replayed {
+    /// Tests for the replayed step differing from the recorded step (in its outputs or in
+    /// the state of the lexer after it).
+    pub fn diverged(&self) -> bool {
+        self.outputs != self.recorded.outputs || self.snapshot != self.recorded.snapshot
+    }
+}
+
+/// Re-executes the log of a `Recorder` one step at a time.
+pub struct Replayer<T: Lexer> {
+    steps: Vec<Step>,
+    next: usize,
+    lexer: ChunkedLexer<T>,
+}
+
+impl<T: Lexer + Debug> Replayer<T> {
+    /// Creates a `Replayer` for a log that was written by a `Recorder`.
+    pub fn new(log: &[u8]) -> StdResult<Replayer<T>, RecordingError> {
+        Ok(Replayer {
+            steps: read_steps(log)?,
+            next: 0,
+            lexer: ChunkedLexer::new(),
+        })
+    }
+
+    /// The recorded steps.
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Replays the next step (or is `None` if every step has been replayed).
+    pub fn step(&mut self) -> Option<Replayed> {
+        let recorded = self.steps.get(self.next)?.clone();
+        let tokens = match recorded.input {
+            Input::Push(ref chunk) => self.lexer.push(chunk),
+            Input::Finish => self.lexer.finish(),
+        };
+
+        let replayed = Replayed {
+            index: self.next,
+            outputs: tokens.iter().map(Output::new).collect(),
+            snapshot: self.lexer.snapshot(),
+            recorded,
+        };
+        self.next += 1;
+        Some(replayed)
+    }
+
+    /// Replays the rest of the steps until one of them diverges from the recording.
+    pub fn first_divergence(&mut self) -> Option<Replayed> {
+        while let Some(replayed) = self.step() {
+            if replayed.diverged() {
+                return Some(replayed);
+            }
+        }
+        None
+    }
+}
+
+// Reads the steps from a log.
+fn read_steps(log: &[u8]) -> StdResult<Vec<Step>, RecordingError> {
+    if log.len() < 4 || &log[..4] != RECORDING_MAGIC {
+        return Err(RecordingError::BadMagic);
+    }
+    if log.len() < 8 {
+        return Err(RecordingError::BadLength);
+    }
+
+    let version = read_u32(&log[4..8]);
+    if version != RECORDING_VERSION {
+        return Err(RecordingError::UnsupportedVersion(version));
+    }
+
+    let mut rest = &log[8..];
+    let mut steps = Vec::new();
+    while !rest.is_empty() {
+        let input = match read_value(&mut rest)? {
+            0 => Input::Push(read_string(&mut rest, "input")?),
+            1 => Input::Finish,
+            _ => return Err(RecordingError::InvalidValue("input")),
+        };
+
+        let mut outputs = Vec::new();
+        for _ in 0..read_value(&mut rest)? {
+            outputs.push(match read_value(&mut rest)? {
+                0 => {
+                    let start = Location::from_u64(read_value(&mut rest)?);
+                    let end = Location::from_u64(read_value(&mut rest)?);
+                    Output::Token(start, end, read_string(&mut rest, "outputs")?)
+                }
+                1 => Output::Error(read_string(&mut rest, "outputs")?),
+                _ => return Err(RecordingError::InvalidValue("outputs")),
+            });
+        }
+
+        let snapshot = read_bytes(&mut rest)?.to_vec();
+        steps.push(Step {
+            input,
+            outputs,
+            snapshot,
+        });
+    }
+
+    Ok(steps)
+}
+
+fn push_bytes(log: &mut Vec<u8>, bytes: &[u8]) {
+    push_u64(log, bytes.len() as u64);
+    log.extend_from_slice(bytes);
+}
+
+fn read_value(rest: &mut &[u8]) -> StdResult<u64, RecordingError> {
+    read_u64(rest).ok_or(RecordingError::BadLength)
+}
+
+// Reads the bytes of a string (or snapshot) from the front of rest.
+fn read_bytes<'a>(rest: &mut &'a [u8]) -> StdResult<&'a [u8], RecordingError> {
+    let len = read_value(rest)?;
+    if (rest.len() as u64) < len {
+        return Err(RecordingError::BadLength);
+    }
+    let (bytes, tail) = rest.split_at(len as usize);
+    *rest = tail;
+    Ok(bytes)
+}
+
+fn read_string(rest: &mut &[u8], part: &'static str) -> StdResult<String, RecordingError> {
+    let bytes = read_bytes(rest)?;
+    str::from_utf8(bytes)
+        .map(|s| s.to_string())
+        .map_err(|_| RecordingError::InvalidValue(part))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dfa::Dfa;
+
+    #[derive(Debug, PartialEq)]
+    struct Word;
+
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    enum WordDfa {
+        Start,
+        Word,
+        Error,
+    }
+
+    impl Default for WordDfa {
+        fn default() -> Self {
+            WordDfa::Start
+        }
+    }
+
+    impl Dfa<Word> for WordDfa {
+        fn is_error(&self) -> bool {
+            *self == WordDfa::Error
+        }
+
+        fn transition(&self, c: char) -> Self {
+            match (*self, c) {
+                (WordDfa::Start, 'a'...'z') | (WordDfa::Word, 'a'...'z') => WordDfa::Word,
+                (_, _) => WordDfa::Error,
+            }
+        }
+
+        fn accept(&self, _: &str) -> Option<Word> {
+            match *self {
+                WordDfa::Word => Some(Word),
+                _ => None,
+            }
+        }
+    }
+
+    impl Lexer for Word {
+        type Dfa = WordDfa;
+    }
+
+    fn record() -> Vec<u8> {
+        let mut recorder = Recorder::<Word>::new();
+        recorder.push("ab");
+        recorder.push("c d");
+        recorder.finish();
+        recorder.log().to_vec()
+    }
+
+    #[test]
+    fn replayer_reproduces_recorded_steps() {
+        let log = record();
+
+        let mut sut = Replayer::<Word>::new(&log).expect("unexpected error reading the log");
+
+        assert_eq!(sut.steps().len(), 3);
+        assert_eq!(
+            sut.steps()[1].outputs,
+            vec![
+                Output::Token(0.into(), 2.into(), "Word".to_string()),
+                Output::Error(
+                    "The lexer encountered an invalid character in the input: ' '.".to_string()
+                ),
+            ]
+        );
+        assert_eq!(sut.first_divergence(), None);
+    }
+
+    #[test]
+    fn replayer_finds_first_divergent_step() {
+        let mut log = record();
+        // Change the recorded token of the second step from "Word" to "Ward".
+        let position = log.windows(4).position(|w| w == b"Word").unwrap();
+        log[position + 1] = b'a';
+
+        let mut sut = Replayer::<Word>::new(&log).expect("unexpected error reading the log");
+        let result = sut.first_divergence().expect("expected a divergent step");
+
+        assert_eq!(result.index, 1);
+        assert_eq!(result.recorded.input, Input::Push("c d".to_string()));
+    }
+
+    #[test]
+    fn replayer_rejects_truncated_log() {
+        let log = record();
+
+        let result = Replayer::<Word>::new(&log[..log.len() - 1]);
+
+        assert_eq!(result.err(), Some(RecordingError::BadLength));
+    }
+}
@@ -0,0 +1,333 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Defines `Session`, a set of named inputs that are lexed with one `Location` space.
+//!
+//! A compiler front end usually lexes several files (a crate root and its modules, or a file
+//! and its includes) and reports errors against any of them. The `Location`'s of the tokens
+//! that a `Session` lexes are unique across all of its inputs, so a `Span` alone is enough to
+//! find the file, line, and column that it refers to.
+
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::result::Result as StdResult;
+use std::str;
+
+use super::{Lexer, Location, Result, Span};
+use dfa::LexerIter;
+use position::{self, ColumnUnit, Position};
+use source_map::FileId;
+use spanned::Never;
+
+// A named input and where its Locations start.
+#[derive(Debug, Clone)]
+struct Input {
+    name: String,
+    source: String,
+    base: usize,
+    line_starts: Vec<usize>,
+}
+
+/// A set of named inputs with `Location`'s that are unique across all of them.
+///
+/// Each input is given the range of `Location`'s after those of the input before it, with a
+/// gap of one so that the `Location` just past the end of an input (where an unexpected end of
+/// input is reported) still belongs to that input. Line and column numbers are counted in the
+/// `ColumnUnit` that the `Session` was created with.
+#[derive(Debug, Clone)]
+pub struct Session {
+    inputs: Vec<Input>,
+    unit: ColumnUnit,
+}
+
+impl Session {
+    /// Creates an empty `Session` that counts columns in `unit`'s.
+    pub fn new(unit: ColumnUnit) -> Session {
+        Session {
+            inputs: Vec::new(),
+            unit,
+        }
+    }
+
+    /// Adds the input named `name` with the text `source`.
+    pub fn add_input<N, S>(&mut self, name: N, source: S) -> FileId
+    where
+        N: Into<String>,
+        S: Into<String>,
+    {
+        let base = self
+            .inputs
+            .last()
+            .map(|input| input.base + input.source.len() + 1)
+            .unwrap_or(0);
+        let source = source.into();
+
+        self.inputs.push(Input {
+            name: name.into(),
+            line_starts: position::line_starts(&source),
+            source,
+            base,
+        });
+        FileId(self.inputs.len() - 1)
+    }
+
+    /// The name of the input `file`.
+    ///
+    /// # Panics
+    /// If `file` was not returned by this `Session`.
+    pub fn name(&self, file: FileId) -> &str {
+        &self.inputs[file.0].name
+    }
+
+    /// The text of the input `file`.
+    ///
+    /// # Panics
+    /// If `file` was not returned by this `Session`.
+    pub fn source(&self, file: FileId) -> &str {
+        &self.inputs[file.0].source
+    }
+
+    /// The input that `location` belongs to and the byte offset in it of `location`.
+    ///
+    /// This is `None` if `location` does not belong to any input of the `Session`.
+    pub fn local(&self, location: Location) -> Option<(FileId, usize)> {
+        let offset = location.offset();
+        let index = match self
+            .inputs
+            .binary_search_by_key(&offset, |input| input.base)
+        {
+            Ok(index) => index,
+            Err(0) => return None,
+            Err(next) => next - 1,
+        };
+
+        let input = &self.inputs[index];
+        if offset - input.base <= input.source.len() {
+            Some((FileId(index), offset - input.base))
+        } else {
+            None
+        }
+    }
+
+    /// The input that `location` belongs to and the line and column of `location` in it.
+    ///
+    /// This is `None` if `location` does not belong to any input of the `Session`.
+    ///
+    /// # Panics
+    /// If `location` is not at a `char` boundary of its input.
+    pub fn position(&self, location: Location) -> Option<(FileId, Position)> {
+        self.local(location).map(|(file, offset)| {
+            let input = &self.inputs[file.0];
+            let position = position::position(&input.source, &input.line_starts, self.unit, offset);
+            (file, position)
+        })
+    }
+
+    /// The input that `span` belongs to and the line and column of its first and last `char`.
+    ///
+    /// This is `None` if the start of `span` does not belong to any input of the `Session`.
+    ///
+    /// # Panics
+    /// If the start or end of `span` is not at a `char` boundary of its input.
+    pub fn span_position<T>(&self, span: &Span<T>) -> Option<(FileId, Position, Position)> {
+        self.position(span.start()).map(|(file, start)| {
+            let end = self.position(span.end()).map_or(start, |(_, end)| end);
+            (file, start, end)
+        })
+    }
+
+    /// Lexes the input `file`.
+    ///
+    /// # Panics
+    /// If `file` was not returned by this `Session`.
+    pub fn lex<'a, T: Lexer>(&'a self, file: FileId) -> SessionLexer<'a, T> {
+        SessionLexer::new(self, file.0..file.0 + 1)
+    }
+
+    /// Lexes each input of the `Session` in the order in which they were added.
+    pub fn lex_all<'a, T: Lexer>(&'a self) -> SessionLexer<'a, T> {
+        SessionLexer::new(self, 0..self.inputs.len())
+    }
+}
+
+/// An iterator over the spanned `char`'s of an input of a `Session`.
+pub struct SessionChars<'a> {
+    inner: str::CharIndices<'a>,
+    base: usize,
+}
+
+impl<'a> Iterator for SessionChars<'a> {
+    type Item = StdResult<Span<char>, Never>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let base = self.base;
+        self.inner
+            .next()
+            .map(|(pos, c)| Ok(Span::from((base + pos, c))))
+    }
+}
+
+// The lexer for one input of a Session.
+type InputLexer<'a, T> = LexerIter<T, Never, SessionChars<'a>, <T as Lexer>::Dfa>;
+
+/// A lexer over one or more inputs of a `Session` that tags each token with its input.
+///
+/// This is created by the `lex` and `lex_all` methods of `Session`. The `Location`'s of the
+/// tokens are those of the `Session`. Each input is lexed separately (a token never spans two
+/// inputs) starting in mode 0.
+pub struct SessionLexer<'a, T: Lexer> {
+    session: &'a Session,
+    files: Range<usize>,
+    current: Option<(FileId, InputLexer<'a, T>)>,
+    _t: PhantomData<T>,
+}
+
+impl<'a, T: Lexer> SessionLexer<'a, T> {
+    fn new(session: &'a Session, files: Range<usize>) -> SessionLexer<'a, T> {
+        SessionLexer {
+            session,
+            files,
+            current: None,
+            _t: PhantomData,
+        }
+    }
+
+    fn next_input(&mut self) -> Option<(FileId, InputLexer<'a, T>)> {
+        let session = self.session;
+        self.files.next().map(|index| {
+            let input = &session.inputs[index];
+            let chars = SessionChars {
+                inner: input.source.char_indices(),
+                base: input.base,
+            };
+            (FileId(index), LexerIter::new(chars))
+        })
+    }
+}
+
+impl<'a, T: Lexer> Iterator for SessionLexer<'a, T> {
+    type Item = (FileId, Result<Span<T>, Never>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((file, ref mut lexer)) = self.current {
+                if let Some(token) = lexer.next() {
+                    return Some((file, token));
+                }
+            }
+            self.current = self.next_input();
+            self.current.as_ref()?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dfa::Dfa;
+
+    #[derive(Debug, PartialEq)]
+    struct Word(String);
+
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    enum WordDfa {
+        Start,
+        Word,
+        Space,
+        Error,
+    }
+
+    impl Default for WordDfa {
+        fn default() -> Self {
+            WordDfa::Start
+        }
+    }
+
+    impl Dfa<Word> for WordDfa {
+        fn is_error(&self) -> bool {
+            *self == WordDfa::Error
+        }
+
+        fn transition(&self, c: char) -> Self {
+            match (*self, c) {
+                (WordDfa::Start, 'a'...'z') | (WordDfa::Word, 'a'...'z') => WordDfa::Word,
+                (WordDfa::Start, ' ') | (WordDfa::Start, '\n') => WordDfa::Space,
+                (_, _) => WordDfa::Error,
+            }
+        }
+
+        fn accept(&self, input: &str) -> Option<Word> {
+            match *self {
+                WordDfa::Word => Some(Word(input.to_string())),
+                WordDfa::Space => Some(Word(String::new())),
+                _ => None,
+            }
+        }
+
+        fn skip(&self) -> bool {
+            *self == WordDfa::Space
+        }
+    }
+
+    impl Lexer for Word {
+        type Dfa = WordDfa;
+    }
+
+    fn session() -> (Session, FileId, FileId) {
+        let mut session = Session::new(ColumnUnit::Chars);
+        let main = session.add_input("main.txt", "ab cd");
+        let other = session.add_input("other.txt", "ef\ngh");
+        (session, main, other)
+    }
+
+    #[test]
+    fn session_lex_all_tags_tokens_with_input() {
+        let (session, main, other) = session();
+
+        let tokens: Vec<_> = session
+            .lex_all::<Word>()
+            .map(|(file, token)| {
+                let (start, Word(word), _) = token.expect("unexpected lexer error").into_inner();
+                (file, word, start.offset())
+            })
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                (main, "ab".to_string(), 0),
+                (main, "cd".to_string(), 3),
+                (other, "ef".to_string(), 6),
+                (other, "gh".to_string(), 9),
+            ]
+        );
+    }
+
+    #[test]
+    fn session_position_finds_input_line_and_column() {
+        let (session, main, other) = session();
+
+        let (_, last) = session.lex::<Word>(other).last().expect("no tokens");
+        let last = last.expect("unexpected lexer error");
+
+        assert_eq!(
+            session.span_position(&last),
+            Some((
+                other,
+                Position { line: 2, column: 1 },
+                Position { line: 2, column: 2 }
+            ))
+        );
+        assert_eq!(
+            session.position(Location::new(5)),
+            Some((main, Position { line: 1, column: 6 }))
+        );
+        assert_eq!(session.position(Location::new(12)), None);
+        assert_eq!(session.name(other), "other.txt");
+    }
+}
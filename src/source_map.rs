@@ -0,0 +1,178 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Maps the `Location`'s in a preprocessed buffer back to the files that it was made from.
+//!
+//! A lexer often runs over a buffer that is not exactly what the user wrote: a shebang line
+//! may have been stripped, included files spliced in, or escapes decoded. `SourceMap` records
+//! where each part of the buffer came from, so that the spans of the tokens (and of any errors)
+//! can be reported against the original files.
+
+use super::{Location, Span};
+
+/// Identifies one of the original files of a `SourceMap` (or one of the inputs of a
+/// `session::Session`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileId(pub(crate) usize);
+
+// The buffer from `start` on comes from `file`, starting at `original`.
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    start: usize,
+    file: FileId,
+    original: usize,
+}
+
+/// The mapping from the `Location`'s in a preprocessed buffer to those in its original files.
+///
+/// The buffer is divided into segments by calls to `remap`. Each segment is a copy of part of
+/// one original file, so a `Location` in the segment maps to the `Location` at the same
+/// distance from the start of that part of the file. Until `remap` is first called the whole
+/// buffer maps to the same `Location`'s in the file that the `SourceMap` was created for.
+///
+/// ```
+/// use luther::Location;
+/// use luther::source_map::SourceMap;
+///
+/// // The buffer is "main.txt" without its 12 byte shebang line.
+/// let mut map = SourceMap::new("main.txt");
+/// let main = map.main_file();
+/// map.remap(Location::new(0), main, Location::new(12));
+///
+/// assert_eq!(map.original(Location::new(3)), (main, Location::new(15)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SourceMap {
+    files: Vec<String>,
+    segments: Vec<Segment>,
+}
+
+impl SourceMap {
+    /// Creates a `SourceMap` for a buffer that is a copy of the file named `name`.
+    pub fn new<S: Into<String>>(name: S) -> SourceMap {
+        SourceMap {
+            files: vec![name.into()],
+            segments: vec![Segment {
+                start: 0,
+                file: FileId(0),
+                original: 0,
+            }],
+        }
+    }
+
+    /// The file that the `SourceMap` was created for.
+    pub fn main_file(&self) -> FileId {
+        FileId(0)
+    }
+
+    /// Adds another original file (such as an included file).
+    pub fn add_file<S: Into<String>>(&mut self, name: S) -> FileId {
+        self.files.push(name.into());
+        FileId(self.files.len() - 1)
+    }
+
+    /// The name of `file`.
+    ///
+    /// # Panics
+    /// If `file` was not returned by this `SourceMap`.
+    pub fn file_name(&self, file: FileId) -> &str {
+        &self.files[file.0]
+    }
+
+    /// Records that the buffer from `start` on (up to the start of the next segment) comes from
+    /// `file`, starting at `original`.
+    ///
+    /// This replaces any segment that already starts at `start`.
+    pub fn remap(&mut self, start: Location, file: FileId, original: Location) {
+        let segment = Segment {
+            start: start.offset(),
+            file,
+            original: original.offset(),
+        };
+
+        match self.find(segment.start) {
+            Ok(index) => self.segments[index] = segment,
+            Err(index) => self.segments.insert(index, segment),
+        }
+    }
+
+    /// The file and the `Location` in it of `location` in the buffer.
+    pub fn original(&self, location: Location) -> (FileId, Location) {
+        let offset = location.offset();
+        // There is always a segment at offset 0, so next is never 0.
+        let segment = match self.find(offset) {
+            Ok(index) => self.segments[index],
+            Err(next) => self.segments[next - 1],
+        };
+
+        (
+            segment.file,
+            Location::new(segment.original + (offset - segment.start)),
+        )
+    }
+
+    /// The file of `span` in the buffer and the same span in that file.
+    ///
+    /// This suits `LexerIter::map_span`. The file is that of the start of the span. A span
+    /// that crosses into another segment (such as a token that runs past the end of an
+    /// included file) ends at the end `Location` mapped by its own segment.
+    pub fn original_span<T>(&self, span: Span<T>) -> (FileId, Span<T>) {
+        let (start, value, end) = span.into_inner();
+        let (file, start) = self.original(start);
+        let (_, end) = self.original(end);
+
+        (file, Span::new(start, end, value))
+    }
+
+    fn find(&self, offset: usize) -> Result<usize, usize> {
+        self.segments.binary_search_by_key(&offset, |segment| segment.start)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn source_map_without_remapping_is_identity() {
+        let sut = SourceMap::new("main.txt");
+
+        let result = sut.original(Location::new(7));
+
+        assert_eq!(result, (sut.main_file(), Location::new(7)));
+    }
+
+    #[test]
+    fn source_map_maps_included_file_and_rest_of_main_file() {
+        // main.txt is "ab#include\ncd", and inc.txt ("xyz") replaces its second line.
+        let mut sut = SourceMap::new("main.txt");
+        let main = sut.main_file();
+        let inc = sut.add_file("inc.txt");
+        sut.remap(Location::new(2), inc, Location::new(0));
+        sut.remap(Location::new(5), main, Location::new(11));
+
+        assert_eq!(sut.original(Location::new(1)), (main, Location::new(1)));
+        assert_eq!(sut.original(Location::new(3)), (inc, Location::new(1)));
+        assert_eq!(sut.original(Location::new(6)), (main, Location::new(12)));
+        assert_eq!(sut.file_name(inc), "inc.txt");
+    }
+
+    #[test]
+    fn source_map_maps_span_by_its_start() {
+        let mut sut = SourceMap::new("main.txt");
+        let inc = sut.add_file("inc.txt");
+        sut.remap(Location::new(4), inc, Location::new(0));
+
+        let result = sut.original_span(Span::new(Location::new(5), Location::new(6), 'x'));
+
+        assert_eq!(
+            result,
+            (inc, Span::new(Location::new(1), Location::new(2), 'x'))
+        );
+    }
+}
@@ -6,7 +6,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms
 
-use std::ops;
+use std::{cmp, fmt, ops};
 
 /// Wraps a value with start and end `Location`'s.
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -48,6 +48,81 @@ impl<T> Span<T> {
     pub fn into_inner(self) -> (Location, T, Location) {
         (self.start, self.value, self.end)
     }
+
+    /// Gets the text of `source` that the `Span` covers.
+    ///
+    /// The `Location`'s of the `Span` must be byte offsets into `source` (as they are for the
+    /// `char`'s from `spanned::StrExt::spanned_chars` or `str::char_indices`).
+    ///
+    /// # Panics
+    /// If the `Span` is past the end of `source` or does not start and end at `char` boundaries.
+    pub fn slice<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start.offset()..self.end.offset() + 1]
+    }
+
+    /// Gets the line of `source` that contains the start of the `Span` (without its line
+    /// terminator).
+    ///
+    /// # Panics
+    /// If the start of the `Span` is past the end of `source` or is not at a `char` boundary.
+    pub fn line_containing<'a>(&self, source: &'a str) -> &'a str {
+        let (start, end) = line_bounds(source, self.start.offset());
+        &source[start..end]
+    }
+
+    /// Formats the line of `source` that contains the start of the `Span` for an error message.
+    ///
+    /// The line is preceded by (up to) `n_lines` lines before it and followed by a line with a
+    /// `^` under each `char` of the `Span` on that line (or a single `^` for a `Span` that
+    /// starts at the end of the line). Each line ends with a `\n`.
+    ///
+    /// # Panics
+    /// If the start of the `Span` is past the end of `source` or is not at a `char` boundary.
+    pub fn context(&self, source: &str, n_lines: usize) -> String {
+        let offset = self.start.offset();
+        let (line_start, line_end) = line_bounds(source, offset);
+
+        let mut first = line_start;
+        for _ in 0..n_lines {
+            if first == 0 {
+                break;
+            }
+            first = line_bounds(source, first - 1).0;
+        }
+
+        let mut context = String::new();
+        for line in source[first..line_start].lines() {
+            context.push_str(line);
+            context.push('\n');
+        }
+        context.push_str(&source[line_start..line_end]);
+        context.push('\n');
+
+        // Keep the tabs before the span so that the carets line up with it
+        for c in source[line_start..offset].chars() {
+            context.push(if c == '\t' { '\t' } else { ' ' });
+        }
+        let end = cmp::max(cmp::min(self.end.offset() + 1, line_end), offset);
+        let width = cmp::max(source[offset..end].chars().count(), 1);
+        for _ in 0..width {
+            context.push('^');
+        }
+        context.push('\n');
+
+        context
+    }
+}
+
+// The byte offsets of the start and end (before its line terminator) of the line of source
+// that contains offset.
+fn line_bounds(source: &str, offset: usize) -> (usize, usize) {
+    let start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let end = source[offset..].find('\n').map_or(source.len(), |i| offset + i);
+    if end > start && source.as_bytes()[end - 1] == b'\r' {
+        (start, end - 1)
+    } else {
+        (start, end)
+    }
 }
 
 impl From<(usize, char)> for Span<char> {
@@ -69,23 +144,49 @@ impl From<(usize, char)> for Span<char> {
 /// `Location` in the same stream, just whether its equal or not equal to some other `Location`.
 /// It is possible to create a new `Location` from a `usize` or by adding a `usize` to an exising `Location`.
 ///
+/// The value of a `Location` is a `u64` on every target, so that the offsets into inputs that
+/// are larger than 4 GiB (such as concatenated log archives) can be represented on 32-bit
+/// targets as well.
+///
 /// # Panics
 ///
-/// Adding a usize to a `Location` will panic if the resulting `Location` value is greater than
-/// `usize::max_value()`.
+/// Adding a usize to a `Location` will panic (in release builds as well as debug builds) if the
+/// resulting `Location` value is greater than `u64::max_value()`. Use `checked_add` to detect
+/// this instead.
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Default)]
-pub struct Location(usize);
+pub struct Location(u64);
 
 impl Location {
     /// Create a new `Location` for a given starting point.
     pub fn new(location: usize) -> Location {
+        Location(location as u64)
+    }
+
+    /// Create a new `Location` for a given `u64` starting point.
+    pub fn from_u64(location: u64) -> Location {
         Location(location)
     }
+
+    /// Gets the value of the `Location` as a `u64`.
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+
+    /// Adds a `usize` to the `Location`, returning `None` if the result would overflow.
+    pub fn checked_add(self, rhs: usize) -> Option<Location> {
+        self.0.checked_add(rhs as u64).map(Location)
+    }
+
+    // The value of the location, for use where it is known to be a byte offset into an input
+    // that is in memory (and so is no larger than usize).
+    pub(crate) fn offset(self) -> usize {
+        self.0 as usize
+    }
 }
 
 impl ops::AddAssign<usize> for Location {
     fn add_assign(&mut self, rhs: usize) {
-        self.0 += rhs;
+        *self = self.checked_add(rhs).expect("Location overflowed u64");
     }
 }
 
@@ -98,6 +199,12 @@ impl ops::Add<usize> for Location {
     }
 }
 
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 impl From<usize> for Location {
     fn from(value: usize) -> Self {
         Self::new(value)
@@ -106,7 +213,7 @@ impl From<usize> for Location {
 
 #[cfg(test)]
 mod test {
-    use std::usize;
+    use std::u64;
     use super::*;
 
     #[test]
@@ -129,9 +236,47 @@ mod test {
     #[test]
     #[should_panic]
     fn location_add_overflow_panics() {
-        Location::new(usize::max_value()) + 3;
+        Location::from_u64(u64::max_value()) + 3;
     } // COV_EXCL_LINE
 
+    #[test]
+    fn location_checked_add_is_none_on_overflow() {
+        let sut = Location::from_u64(u64::max_value() - 1);
+
+        assert_eq!(sut.checked_add(1), Some(Location::from_u64(u64::max_value())));
+        assert_eq!(sut.checked_add(2), None);
+    }
+
+    #[test]
+    fn location_from_u64_past_4_gib_round_trips() {
+        let value = 5 * (1 << 30);
+
+        let sut = Location::from_u64(value) + 3;
+
+        assert_eq!(sut.as_u64(), value + 3);
+    }
+
+    const SOURCE: &str = "fn main() {\r\n\tlet x = 1 @ 2;\n}\n";
+
+    #[test]
+    fn span_slice_is_covered_text() {
+        let sut = Span::new(Location::new(18), Location::new(18), ());
+
+        assert_eq!(sut.slice(SOURCE), "x");
+        assert_eq!(sut.line_containing(SOURCE), "\tlet x = 1 @ 2;");
+    }
+
+    #[test]
+    fn span_context_has_carets_under_span() {
+        let sut = Span::new(Location::new(22), Location::new(26), ());
+
+        assert_eq!(
+            sut.context(SOURCE, 1),
+            "fn main() {\n\tlet x = 1 @ 2;\n\t        ^^^^^\n"
+        );
+        assert_eq!(sut.context(SOURCE, 0), "\tlet x = 1 @ 2;\n\t        ^^^^^\n");
+    }
+
     #[test]
     fn span_from_usize_char_gives_expected_result_for_euro_sign() {
         let value = '€';
@@ -8,10 +8,15 @@
 
 //! Defines iterators and other utilities for working with `Span<T>`.
 
-use std::{fmt, io, str};
+use std::{char, fmt, iter, slice, str};
 use super::{Location, Span};
-use encode_unicode::{U8UtfExt, Utf8Char};
+
+#[cfg(feature = "io")]
+use std::io;
+#[cfg(feature = "io")]
 use std::io::prelude::*;
+#[cfg(feature = "io")]
+use encode_unicode::{U8UtfExt, Utf8Char};
 
 /// A failure that cannot occur.
 ///
@@ -72,6 +77,113 @@ impl StrExt for str {
     }
 }
 
+/// An error for a UTF-16 code unit that is a surrogate without its pair.
+///
+/// The `Location` is that of the surrogate, counted in UTF-16 code units.
+#[derive(Fail, Debug, Clone, PartialEq, Eq)] // COV_EXCL_LINE
+pub struct Utf16Error {
+    surrogate: u16,
+    location: Location,
+}
+
+impl Utf16Error {
+    /// The unpaired surrogate.
+    pub fn surrogate(&self) -> u16 {
+        self.surrogate
+    }
+
+    /// The `Location` of the unpaired surrogate.
+    pub fn location(&self) -> Location {
+        self.location
+    }
+}
+
+impl fmt::Display for Utf16Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Unpaired surrogate {:#06x} at code unit {} of the utf16 input.",
+            self.surrogate,
+            self.location.offset()
+        )
+    }
+}
+
+/// An iterator that decodes UTF-16 code units to spanned `char`'s.
+///
+/// The `Location`'s of the `Span<char>`'s that are produced count UTF-16 code units rather than
+/// bytes, so they can be used as indices into the original `[u16]` buffer. The code units are
+/// decoded as they are read, so the input does not have to be transcoded to UTF-8 first.
+///
+/// # Errors
+/// The iterator produces an `Err(Utf16Error)` item for each surrogate that is not part of a
+/// surrogate pair, and then continues with the code unit after it.
+pub struct SpannedUtf16Iter<I>
+where
+    I: Iterator<Item = u16>,
+{
+    inner: char::DecodeUtf16<I>,
+    current: Location,
+}
+
+impl<I> SpannedUtf16Iter<I>
+where
+    I: Iterator<Item = u16>,
+{
+    /// Create a new `SpannedUtf16Iter` over the given code units iterator.
+    ///
+    /// The `Location`'s for the `Span<char>`'s that are produced
+    /// will start at `start`.
+    pub fn new<T>(start: Location, iter: T) -> SpannedUtf16Iter<I>
+    where
+        T: IntoIterator<Item = u16, IntoIter = I>,
+    {
+        SpannedUtf16Iter {
+            inner: char::decode_utf16(iter),
+            current: start,
+        }
+    }
+}
+
+impl<I> Iterator for SpannedUtf16Iter<I>
+where
+    I: Iterator<Item = u16>,
+{
+    type Item = Result<Span<char>, Utf16Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.current;
+        match self.inner.next() {
+            None => None,
+            Some(Ok(c)) => {
+                let len = c.len_utf16();
+                self.current += len;
+                Some(Ok(Span::new(start, start + (len - 1), c)))
+            }
+            Some(Err(e)) => {
+                self.current += 1;
+                Some(Err(Utf16Error {
+                    surrogate: e.unpaired_surrogate(),
+                    location: start,
+                }))
+            }
+        }
+    }
+}
+
+/// Extention trait for `[u16]` to provide the `spanned_utf16_chars` method.
+pub trait Utf16Ext {
+    /// Iterate over the spanned `char`'s of the UTF-16 encoded code units.
+    fn spanned_utf16_chars<'a>(&'a self) -> SpannedUtf16Iter<iter::Cloned<slice::Iter<'a, u16>>>;
+}
+
+impl Utf16Ext for [u16] {
+    fn spanned_utf16_chars<'a>(&'a self) -> SpannedUtf16Iter<iter::Cloned<slice::Iter<'a, u16>>> {
+        SpannedUtf16Iter::new(Location::default(), self.iter().cloned())
+    }
+}
+
+#[cfg(feature = "io")]
 /// An iterator that converts bytes to spanned utf8 `chars`.
 ///
 /// # Errors
@@ -82,15 +194,26 @@ impl StrExt for str {
 /// - `ErrorKind::UnexpectedEof`: the underlying iterator ended in the middle of a
 /// multibyte utf8 `char`
 /// - `ErrorKind::InvalidData`: a byte or byte sequence from the underlying iterator
-/// was not a valid utf8 encoded `char`
+///   was not a valid utf8 encoded `char`, or the `Location` after a `char` would be greater than
+///   `u64::max_value()`
+///
+/// # WTF-8
+/// With `with_surrogates` the iterator accepts WTF-8 (the superset of UTF-8 that Windows file
+/// names and JavaScript strings are converted to) rather than only UTF-8. Each unpaired
+/// surrogate (encoded as a three byte sequence starting with `0xED`) is produced as the given
+/// replacement `char` rather than as an `ErrorKind::InvalidData` error, so a dfa can treat it
+/// as a class of its own (for example a private use `char` that only an error token matches).
+/// The `Span` of the replacement covers the three bytes of the surrogate.
 pub struct SpannedUtf8Iter<I>
 where
     I: Iterator<Item = io::Result<u8>>,
 {
     inner: I,
     current: Location,
+    surrogate: Option<char>,
 }
 
+#[cfg(feature = "io")]
 impl<I> SpannedUtf8Iter<I>
 where
     I: Iterator<Item = io::Result<u8>>,
@@ -103,18 +226,30 @@ where
         SpannedUtf8Iter {
             inner: iter,
             current: start,
+            surrogate: None,
         }
     }
 
-    fn make_span(&mut self, c: char) -> Span<char> {
+    /// Accepts WTF-8 input, producing `replacement` for each unpaired surrogate.
+    pub fn with_surrogates(mut self, replacement: char) -> SpannedUtf8Iter<I> {
+        self.surrogate = Some(replacement);
+        self
+    }
+
+    fn make_span(&mut self, (c, len): (char, usize)) -> io::Result<Span<char>> {
         let start = self.current;
-        let len = c.len_utf8();
 
-        self.current += len;
-        Span::new(start, start + (len - 1), c)
+        self.current = start.checked_add(len).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The input is too large for its Locations.",
+            )
+        })?;
+        Ok(Span::new(start, start + (len - 1), c))
     }
 }
 
+#[cfg(feature = "io")]
 impl<I> Iterator for SpannedUtf8Iter<I>
 where
     I: Iterator<Item = io::Result<u8>>,
@@ -126,16 +261,32 @@ where
             None => None,
             Some(Err(e)) => Some(Err(e)),
             Some(Ok(first)) => {
-                Some(extract_utf8_char(first, &mut self.inner).map(|c| self.make_span(c)))
+                let surrogate = self.surrogate;
+                Some(
+                    extract_utf8_char(first, &mut self.inner, surrogate)
+                        .and_then(|c| self.make_span(c)),
+                )
             }
         }
     }
 }
 
-fn extract_utf8_char<I>(first: u8, iter: &mut I) -> io::Result<char>
+#[cfg(feature = "io")]
+// Extracts the next char and its length in bytes, producing surrogate (if there is one) for
+// a WTF-8 encoded surrogate.
+fn extract_utf8_char<I>(
+    first: u8,
+    iter: &mut I,
+    surrogate: Option<char>,
+) -> io::Result<(char, usize)>
 where
     I: Iterator<Item = io::Result<u8>>,
 {
+    // Most input is ASCII, which needs no decoding
+    if first < 0x80 {
+        return Ok((first as char, 1));
+    }
+
     let count = first.extra_utf8_bytes().map_err(map_invalid_data)?;
 
     let mut buffer = [first, 0, 0, 0];
@@ -143,11 +294,19 @@ where
         buffer[index] = extract_utf8_continutaion_byte(iter)?;
     }
 
+    // The surrogates U+D800 to U+DFFF are 0xED followed by 0xA0 to 0xBF and a continuation byte
+    if let Some(replacement) = surrogate {
+        if first == 0xED && buffer[1] & 0xE0 == 0xA0 && buffer[2] & 0xC0 == 0x80 {
+            return Ok((replacement, 3));
+        }
+    }
+
     Utf8Char::from_array(buffer)
         .map_err(map_invalid_data)
-        .map(|c| c.to_char())
+        .map(|c| (c.to_char(), count + 1))
 }
 
+#[cfg(feature = "io")]
 fn map_invalid_data<E>(error: E) -> io::Error
 where
     E: Into<Box<::std::error::Error + Send + Sync>>,
@@ -155,6 +314,7 @@ where
     io::Error::new(io::ErrorKind::InvalidData, error)
 }
 
+#[cfg(feature = "io")]
 fn extract_utf8_continutaion_byte<I>(iter: &mut I) -> io::Result<u8>
 where
     I: Iterator<Item = io::Result<u8>>,
@@ -171,6 +331,7 @@ where
     }
 } // COV_EXCL_LINE
 
+#[cfg(feature = "io")]
 /// Extention trait for a reader to allow it to prodcue a spanned `char`s iterator.
 ///
 /// Although there is a default implementation for every reader, there will likely
@@ -181,6 +342,7 @@ pub trait Utf8SpannedChars: Read + Sized {
     fn spanned_chars(self, start: Location) -> SpannedUtf8Iter<io::Bytes<Self>>;
 }
 
+#[cfg(feature = "io")]
 impl<R: Read> Utf8SpannedChars for R {
     fn spanned_chars(self, start: Location) -> SpannedUtf8Iter<io::Bytes<Self>> {
         SpannedUtf8Iter::new(start, self.bytes())
@@ -190,6 +352,7 @@ impl<R: Read> Utf8SpannedChars for R {
 #[cfg(test)]
 mod test {
     use super::*;
+    #[cfg(feature = "io")]
     use std::io::ErrorKind;
 
     #[test]
@@ -209,46 +372,117 @@ mod test {
     }
 
     #[test]
+    fn utf16_iterates_expected_spans() {
+        let units: Vec<u16> = "a℞😀b".encode_utf16().collect();
+
+        let results: Result<Vec<_>, _> = units.spanned_utf16_chars().collect();
+
+        assert_eq!(
+            results.expect("Unexpected error in the spanned_utf16_chars() iterator."),
+            vec![
+                Span::new(0.into(), 0.into(), 'a'),
+                Span::new(1.into(), 1.into(), '℞'),
+                Span::new(2.into(), 3.into(), '😀'),
+                Span::new(4.into(), 4.into(), 'b'),
+            ]
+        )
+    }
+
+    #[test]
+    fn utf16_unpaired_surrogates_are_errors() {
+        let units = [0x61, 0xd83d, 0x62, 0xde00];
+
+        let results: Vec<_> = units.spanned_utf16_chars().collect();
+
+        assert_eq!(
+            results,
+            vec![
+                Ok(Span::new(0.into(), 0.into(), 'a')),
+                Err(Utf16Error {
+                    surrogate: 0xd83d,
+                    location: 1.into(),
+                }),
+                Ok(Span::new(2.into(), 2.into(), 'b')),
+                Err(Utf16Error {
+                    surrogate: 0xde00,
+                    location: 3.into(),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "io")]
     fn extract_invalid_first_byte_is_error() {
         use std::iter;
         let continuation_byte: u8 = 0x80;
 
-        let result = extract_utf8_char(continuation_byte, &mut iter::empty::<io::Result<u8>>());
+        let mut input = iter::empty::<io::Result<u8>>();
+
+        let result = extract_utf8_char(continuation_byte, &mut input, None);
 
         assert_matches!(result, Err(ref e) if e.kind() == ErrorKind::InvalidData);
     }
 
     #[test]
+    #[cfg(feature = "io")]
     fn extract_with_too_few_bytes_is_error() {
         let first: u8 = 0xe1; // first byte of 3 byte sequence
         let rest: Vec<io::Result<u8>> = vec![Ok(90)]; // 1 following byte
 
-        let result = extract_utf8_char(first, &mut rest.into_iter());
+        let result = extract_utf8_char(first, &mut rest.into_iter(), None);
 
         assert_matches!(result, Err(ref e) if e.kind() == ErrorKind::UnexpectedEof);
     }
 
     #[test]
+    #[cfg(feature = "io")]
     fn extract_with_invalid_continuation_is_error() {
         let first: u8 = 0xc2; // first byte of 2 byte sequence
         let rest: Vec<io::Result<u8>> = vec![Ok(0xc2)]; // not continuation byte
 
-        let result = extract_utf8_char(first, &mut rest.into_iter());
+        let result = extract_utf8_char(first, &mut rest.into_iter(), None);
 
         assert_matches!(result, Err(ref e) if e.kind() == ErrorKind::InvalidData);
     }
 
     #[test]
+    #[cfg(feature = "io")]
     fn extract_with_valid_bytes_is_expected_char() {
         let first: u8 = 0xe1; // first byte of 3 byte sequence
         let rest: Vec<io::Result<u8>> = vec![Ok(0x90), Ok(0x81)];
 
-        let result = extract_utf8_char(first, &mut rest.into_iter());
+        let result = extract_utf8_char(first, &mut rest.into_iter(), None);
+
+        assert_matches!(result, Ok((c, 3)) if c == 'ᐁ');
+    }
+
+    #[test]
+    #[cfg(feature = "io")]
+    fn spanned_utf8_with_surrogates_replaces_unpaired_surrogates() {
+        // "a", the surrogate U+D800 in WTF-8, and "b"
+        let bytes = vec![0x61, 0xed, 0xa0, 0x80, 0x62];
+        let iter = bytes.clone().into_iter().map(|b| Ok(b));
+
+        let sut = SpannedUtf8Iter::new(0.into(), iter).with_surrogates('\u{fffd}');
+        let results: Result<Vec<_>, _> = sut.collect();
+
+        assert_eq!(
+            results.expect("Unexpected error in the SpannedUtf8Iter."),
+            vec![
+                Span::new(0.into(), 0.into(), 'a'),
+                Span::new(1.into(), 3.into(), '\u{fffd}'),
+                Span::new(4.into(), 4.into(), 'b'),
+            ]
+        );
 
-        assert_matches!(result, Ok(c) if c == 'ᐁ');
+        let results: Result<Vec<_>, _> =
+            SpannedUtf8Iter::new(0.into(), bytes.into_iter().map(|b| Ok(b))).collect();
+        assert_matches!(results, Err(ref e) if e.kind() == ErrorKind::InvalidData);
     }
 
     #[test]
+    #[cfg(feature = "io")]
     fn spanned_utf8_decodes_expected_chars() {
         let bytes = vec![0x41, 0x42, 0xc2, 0xa2, 0xe1, 0x90, 0x81];
         let iter = bytes.into_iter().map(|b| Ok(b));
@@ -267,6 +501,7 @@ mod test {
     }
 
     #[test]
+    #[cfg(feature = "io")]
     fn utf8_spanned_chars_decodes_expected_chars() {
         let bytes = vec![0x41, 0x42, 0xc2, 0xa2, 0xe1, 0x90, 0x81];
 
@@ -0,0 +1,1312 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Defines `TableDfa`, a precompiled dfa loaded from its binary encoding.
+//!
+//! The binary encoding lets a dfa be compiled ahead of time (for example by the `luther` command
+//! line tool or in a build script) and loaded at runtime without parsing or copying its
+//! tables. All of the values in the encoding are little endian `u32`'s, in these sections:
+//!
+//! 1. a header of the magic bytes `LUTH`, the format version, the number of states, the number
+//!    of alphabet classes, the number of classified `char`'s, the start state, and the error
+//!    state;
+//! 2. the classified `char`'s as (`char`, class) pairs sorted by `char` (every other `char` is
+//!    in class 0);
+//! 3. the transition table with a row for each state and a column for each class;
+//! 4. the accept map with the token accepted by each state (or `NO_TOKEN`);
+//! 5. the number of lexer modes other than the default mode followed by their start states;
+//!    and
+//! 6. the number of tokens, the length in bytes of the name of each token, and then the UTF-8
+//!    names themselves, padded with zero bytes to a multiple of 4 bytes.
+//!
+//! Version 1 of the encoding (which is also read) has neither the number of modes in section
+//! 5 nor section 6: the rest of the input after section 4 is the start states of the modes.
+//! A dfa in version 1 has no token names.
+//!
+//! `TableDfa::scanning` makes a `ScanningDfa`, which finds the longest match faster on input
+//! that stays in one state for long runs (such as comments and string literals). Its
+//! `find_match` searches for the leftmost match anywhere in the input, skipping ahead to the
+//! literal prefix that every token starts with (if there is one).
+//!
+//! `ModeSet` has the start state of each lexer mode. The modes share the alphabet classes and
+//! the transition table, so switching modes only changes the start state of the next match.
+//! Each mode also has its own skip tokens and `ErrorPolicy`, since (for example) whitespace is
+//! skipped in code but is part of the token inside a string literal.
+//!
+//! `TableDfa::run_traced` finds the longest match like `TableDfa::longest_match` but also
+//! records each step that it took, for debugging a surprising match.
+
+use std::fmt;
+use std::result::Result as StdResult;
+
+/// The magic bytes at the start of the binary encoding.
+pub const MAGIC: &[u8; 4] = b"LUTH";
+
+/// The version of the binary encoding that this module writes (and the latest that it reads).
+pub const VERSION: u32 = 2;
+
+/// The oldest version of the binary encoding that this module reads.
+pub const MIN_VERSION: u32 = 1;
+
+/// The value in the accept map for a state that does not accept a token.
+pub const NO_TOKEN: u32 = u32::MAX;
+
+const HEADER_LEN: usize = 7;
+
+/// The most `char`'s that a scanning state of a `ScanningDfa` can leave on.
+pub const MAX_SCAN_CHARS: usize = 3;
+
+// COV_EXCL_START
+/// The error type for loading a `TableDfa`.
+#[derive(Debug, Fail, PartialEq)]
+pub enum TableError {
+    /// The input does not start with the magic bytes.
+    #[fail(display = "The input is not a luther dfa.")]
+    BadMagic,
+
+    /// The input is encoded in a version of the format that is not supported.
+    #[fail(display = "The luther dfa format version {} is not supported.", _0)]
+    UnsupportedVersion(u32),
+
+    /// The input is shorter (or longer) than its header says it is.
+    #[fail(display = "The luther dfa has the wrong length for its header.")]
+    BadLength,
+
+    /// A value in the input is out of range. The field names the section it is in.
+    #[fail(display = "The luther dfa has an invalid value in its {}.", _0)]
+    InvalidValue(&'static str),
+}
+// COV_EXCL_END
+
+/// A dfa loaded from its binary encoding.
+///
+/// A `TableDfa` borrows its tables from the input rather than copying them, so the input can
+/// be a `static` array (from `include_bytes!`) or a memory mapped file. It has no state of its
+/// own beyond the tables (the current state of a match is a local of `longest_match`), so a
+/// single `TableDfa` can be shared by any number of threads.
+#[derive(Debug, Clone, Copy)]
+pub struct TableDfa<'a> {
+    states: u32,
+    classes: u32,
+    start: u32,
+    error: u32,
+    chars: &'a [u8],
+    table: &'a [u8],
+    accepts: &'a [u8],
+    modes: &'a [u8],
+    name_lens: &'a [u8],
+    names: &'a [u8],
+}
+
+impl<'a> TableDfa<'a> {
+    /// Loads a `TableDfa` from its binary encoding.
+    ///
+    /// The tables are validated, so the other methods of a loaded `TableDfa` do not fail.
+    pub fn from_bytes(bytes: &'a [u8]) -> StdResult<TableDfa<'a>, TableError> {
+        if bytes.len() < 4 || &bytes[..4] != MAGIC {
+            return Err(TableError::BadMagic);
+        }
+        if bytes.len() < HEADER_LEN * 4 {
+            return Err(TableError::BadLength);
+        }
+
+        let version = read_u32(bytes, 1);
+        if !(MIN_VERSION..=VERSION).contains(&version) {
+            return Err(TableError::UnsupportedVersion(version));
+        }
+
+        let states = read_u32(bytes, 2);
+        let classes = read_u32(bytes, 3);
+        let chars = read_u32(bytes, 4);
+        let start = read_u32(bytes, 5);
+        let error = read_u32(bytes, 6);
+
+        let chars_len = chars as u64 * 8;
+        let table_len = states as u64 * classes as u64 * 4;
+        let accepts_len = states as u64 * 4;
+        let len = (HEADER_LEN * 4) as u64 + chars_len + table_len + accepts_len;
+        if len > bytes.len() as u64 || !(bytes.len() as u64 - len).is_multiple_of(4) {
+            return Err(TableError::BadLength);
+        }
+
+        let (chars, rest) = bytes[HEADER_LEN * 4..].split_at(chars_len as usize);
+        let (table, rest) = rest.split_at(table_len as usize);
+        let (accepts, rest) = rest.split_at(accepts_len as usize);
+        let (modes, name_lens, names) = if version == 1 {
+            (rest, &rest[..0], &rest[..0])
+        } else {
+            split_names(rest)?
+        };
+        let dfa = TableDfa {
+            states,
+            classes,
+            start,
+            error,
+            chars,
+            table,
+            accepts,
+            modes,
+            name_lens,
+            names,
+        };
+        dfa.validate()?;
+
+        Ok(dfa)
+    }
+
+    /// The number of states.
+    pub fn state_count(&self) -> u32 {
+        self.states
+    }
+
+    /// The start state (of the default mode).
+    pub fn start_state(&self) -> u32 {
+        self.start
+    }
+
+    /// The number of lexer modes (including the default mode).
+    pub fn mode_count(&self) -> usize {
+        1 + self.modes.len() / 4
+    }
+
+    /// The start state of `mode` (where mode 0 is the default mode), or `None` if there is no
+    /// such mode.
+    pub fn mode_start_state(&self, mode: usize) -> Option<u32> {
+        match mode {
+            0 => Some(self.start),
+            mode if mode < self.mode_count() => Some(read_u32(self.modes, mode - 1)),
+            _ => None,
+        }
+    }
+
+    /// The number of tokens that have names (which is 0 for a dfa in version 1 of the
+    /// encoding).
+    pub fn token_count(&self) -> usize {
+        self.name_lens.len() / 4
+    }
+
+    /// The name of `token`, or `None` if the dfa has no name for it.
+    ///
+    /// The names are found by adding up the lengths of the names before `token`, so a caller
+    /// that looks up names often should collect them once.
+    pub fn token_name(&self, token: u32) -> Option<&'a str> {
+        let token = token as usize;
+        if token >= self.token_count() {
+            return None;
+        }
+
+        let start: usize = (0..token)
+            .map(|i| read_u32(self.name_lens, i) as usize)
+            .sum();
+        let end = start + read_u32(self.name_lens, token) as usize;
+        ::std::str::from_utf8(&self.names[start..end]).ok()
+    }
+
+    /// The error state (which has no transitions to any other state).
+    pub fn error_state(&self) -> u32 {
+        self.error
+    }
+
+    /// The state reached from `state` on `c`.
+    ///
+    /// # Panics
+    /// If `state` is not less than `state_count`.
+    pub fn transition(&self, state: u32, c: char) -> u32 {
+        assert!(state < self.states, "invalid state {}", state);
+        read_u32(self.table, (state * self.classes + self.class(c)) as usize)
+    }
+
+    /// The token (as its index in the token definitions) accepted by `state`, if any.
+    ///
+    /// # Panics
+    /// If `state` is not less than `state_count`.
+    pub fn accept(&self, state: u32) -> Option<u32> {
+        assert!(state < self.states, "invalid state {}", state);
+        match read_u32(self.accepts, state as usize) {
+            NO_TOKEN => None,
+            token => Some(token),
+        }
+    }
+
+    /// The token and length (in bytes) of the longest prefix of `input` that is accepted.
+    pub fn longest_match(&self, input: &str) -> Option<(u32, usize)> {
+        self.longest_match_from(self.start, input)
+    }
+
+    fn longest_match_from(&self, start: u32, input: &str) -> Option<(u32, usize)> {
+        let mut state = start;
+        let mut longest = None;
+
+        for (offset, c) in input.char_indices() {
+            state = self.transition(state, c);
+            if state == self.error {
+                break;
+            }
+            if let Some(token) = self.accept(state) {
+                longest = Some((token, offset + c.len_utf8()));
+            }
+        }
+
+        longest
+    }
+
+    /// The token and length (in bytes) of the shortest prefix of `input` that is accepted.
+    ///
+    /// This stops at the first accepting state rather than continuing to the longest match
+    /// (for example so that a delimiter ends a token as soon as it is read).
+    pub fn shortest_match(&self, input: &str) -> Option<(u32, usize)> {
+        let mut state = self.start;
+
+        for (offset, c) in input.char_indices() {
+            state = self.transition(state, c);
+            if state == self.error {
+                break;
+            }
+            if let Some(token) = self.accept(state) {
+                return Some((token, offset + c.len_utf8()));
+            }
+        }
+
+        None
+    }
+
+    /// The token and length (in bytes) of the prefix of `input` that is accepted under `kind`.
+    pub fn find(&self, input: &str, kind: MatchKind) -> Option<(u32, usize)> {
+        match kind {
+            MatchKind::Longest => self.longest_match(input),
+            MatchKind::Shortest => self.shortest_match(input),
+        }
+    }
+
+    /// Finds the longest match at the start of `input` and records how it was found.
+    ///
+    /// The trace has a step for each `char` that the dfa took a transition on (including the
+    /// one that led to the error state, if any). The match in the trace is the same as the
+    /// result of `longest_match`.
+    pub fn run_traced(&self, input: &str) -> ExecutionTrace {
+        let mut trace = ExecutionTrace {
+            start: self.start,
+            steps: Vec::new(),
+            longest: None,
+        };
+        let mut state = self.start;
+
+        for (offset, c) in input.char_indices() {
+            let class = self.class(c);
+            state = read_u32(self.table, (state * self.classes + class) as usize);
+            let accept = if state == self.error {
+                None
+            } else {
+                self.accept(state)
+            };
+            trace.steps.push(TraceStep {
+                offset,
+                c,
+                class,
+                state,
+                accept,
+            });
+
+            if state == self.error {
+                break;
+            }
+            if let Some(token) = accept {
+                trace.longest = Some((token, offset + c.len_utf8()));
+            }
+        }
+
+        trace
+    }
+
+    /// Finds the scanning states of the dfa for use in a faster `longest_match`.
+    pub fn scanning(&self) -> ScanningDfa<'a> {
+        let scans: Vec<_> = (0..self.states)
+            .map(|state| self.scan_chars(state))
+            .collect();
+        let ascii_classes: Vec<_> = (0..128u8).map(|b| self.class(b as char)).collect();
+        let runs = (0..self.states)
+            .map(|state| {
+                if state == self.error || scans[state as usize].is_some() {
+                    return 0;
+                }
+                let row = state * self.classes;
+                (0..128)
+                    .filter(|&b| read_u32(self.table, (row + ascii_classes[b]) as usize) == state)
+                    .fold(0, |loops, b| loops | 1 << b)
+            })
+            .collect();
+
+        ScanningDfa {
+            dfa: *self,
+            scans,
+            runs,
+            ascii_classes,
+            prefix: self.literal_prefix(),
+        }
+    }
+
+    // The chars that every match must start with: the chars on the path from the start state
+    // while each state does not accept and has exactly one char that does not lead to the
+    // error state.
+    fn literal_prefix(&self) -> String {
+        let mut prefix = String::new();
+        let mut state = self.start;
+
+        for _ in 0..self.states {
+            let row = |class: u32| read_u32(self.table, (state * self.classes + class) as usize);
+            if self.accept(state).is_some() || row(0) != self.error {
+                break;
+            }
+
+            let mut next = None;
+            for i in 0..self.chars.len() / 8 {
+                let to = row(read_u32(self.chars, i * 2 + 1));
+                if to != self.error {
+                    if next.is_some() {
+                        return prefix;
+                    }
+                    next = ::std::char::from_u32(read_u32(self.chars, i * 2)).map(|c| (c, to));
+                }
+            }
+
+            match next {
+                Some((c, to)) => {
+                    prefix.push(c);
+                    state = to;
+                }
+                None => break,
+            }
+        }
+
+        prefix
+    }
+
+    // The chars that leave state, if state stays in itself on every other char and there are
+    // at most MAX_SCAN_CHARS of them.
+    fn scan_chars(&self, state: u32) -> Option<Vec<char>> {
+        let row = |class: u32| read_u32(self.table, (state * self.classes + class) as usize);
+        if state == self.error || row(0) != state {
+            return None;
+        }
+
+        let mut chars = Vec::new();
+        for i in 0..self.chars.len() / 8 {
+            if row(read_u32(self.chars, i * 2 + 1)) != state {
+                if chars.len() == MAX_SCAN_CHARS {
+                    return None;
+                }
+                chars.extend(::std::char::from_u32(read_u32(self.chars, i * 2)));
+            }
+        }
+
+        Some(chars)
+    }
+
+    fn class(&self, c: char) -> u32 {
+        let c = c as u32;
+        let (mut low, mut high) = (0, self.chars.len() / 8);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let mid_char = read_u32(self.chars, mid * 2);
+            if mid_char == c {
+                return read_u32(self.chars, mid * 2 + 1);
+            } else if mid_char < c {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        0
+    }
+
+    fn validate(&self) -> StdResult<(), TableError> {
+        if self.classes == 0 || self.start >= self.states || self.error >= self.states {
+            return Err(TableError::InvalidValue("header"));
+        }
+
+        let mut prev = None;
+        for i in 0..self.chars.len() / 8 {
+            let c = read_u32(self.chars, i * 2);
+            let class = read_u32(self.chars, i * 2 + 1);
+            if ::std::char::from_u32(c).is_none() || prev.is_some_and(|p| p >= c)
+                || class >= self.classes
+            {
+                return Err(TableError::InvalidValue("alphabet classes"));
+            }
+            prev = Some(c);
+        }
+
+        if (0..self.table.len() / 4).any(|i| read_u32(self.table, i) >= self.states) {
+            return Err(TableError::InvalidValue("transition table"));
+        }
+
+        if (0..self.modes.len() / 4).any(|i| read_u32(self.modes, i) >= self.states) {
+            return Err(TableError::InvalidValue("mode start states"));
+        }
+
+        let mut start = 0;
+        for i in 0..self.token_count() {
+            let end = start + read_u32(self.name_lens, i) as usize;
+            if ::std::str::from_utf8(&self.names[start..end]).is_err() {
+                return Err(TableError::InvalidValue("token names"));
+            }
+            start = end;
+        }
+
+        Ok(())
+    }
+}
+
+/// The lexer modes of a `TableDfa`.
+///
+/// A `ModeSet` has the start state of each mode at hand so that a lexer can switch modes
+/// between tokens by indexing rather than by looking up the start state in the encoding.
+///
+/// Each mode has its own set of skip tokens and its own `ErrorPolicy`, which `next_token`
+/// applies. By default a mode skips no tokens and stops at an error.
+#[derive(Debug, Clone)]
+pub struct ModeSet<'a> {
+    dfa: TableDfa<'a>,
+    starts: Vec<u32>,
+    skips: Vec<Vec<u32>>,
+    errors: Vec<ErrorPolicy>,
+}
+
+/// What `ModeSet::next_token` does with input that no token of a mode matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorPolicy {
+    /// The error is the next `char` and lexing should not continue after it.
+    #[default]
+    Stop,
+
+    /// The error is the next `char` and lexing continues after it.
+    SkipChar,
+
+    /// The error is the input up to the next offset at which a token of the mode matches (or
+    /// the end of the input) and lexing continues from there.
+    SkipToToken,
+}
+
+/// A token or an error found by `ModeSet::next_token`.
+///
+/// The offsets are byte offsets into the whole input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModeMatch {
+    /// A token that is not skipped in the mode.
+    Token {
+        /// The token.
+        token: u32,
+        /// The offset of the start of the token.
+        start: usize,
+        /// The offset just past the end of the token.
+        end: usize,
+    },
+
+    /// Input that no token of the mode matches.
+    Error {
+        /// The offset of the start of the error.
+        start: usize,
+        /// The offset just past the end of the error (where lexing continues).
+        end: usize,
+        /// Whether lexing should stop (for `ErrorPolicy::Stop`).
+        fatal: bool,
+    },
+}
+
+impl<'a> ModeSet<'a> {
+    /// Makes the `ModeSet` for the modes of `dfa`.
+    pub fn new(dfa: TableDfa<'a>) -> ModeSet<'a> {
+        let starts: Vec<_> = (0..dfa.mode_count())
+            .map(|mode| dfa.mode_start_state(mode).unwrap())
+            .collect();
+        let skips = vec![Vec::new(); starts.len()];
+        let errors = vec![ErrorPolicy::default(); starts.len()];
+
+        ModeSet {
+            dfa,
+            starts,
+            skips,
+            errors,
+        }
+    }
+
+    /// The underlying `TableDfa`.
+    pub fn dfa(&self) -> &TableDfa<'a> {
+        &self.dfa
+    }
+
+    /// The number of modes.
+    pub fn mode_count(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// The start state of `mode`.
+    ///
+    /// # Panics
+    /// Panics if `mode` is not less than `mode_count()`.
+    pub fn start_state(&self, mode: usize) -> u32 {
+        self.starts[mode]
+    }
+
+    /// The token and length (in bytes) of the longest prefix of `input` that is accepted in
+    /// `mode`.
+    ///
+    /// # Panics
+    /// Panics if `mode` is not less than `mode_count()`.
+    pub fn longest_match(&self, mode: usize, input: &str) -> Option<(u32, usize)> {
+        self.dfa.longest_match_from(self.starts[mode], input)
+    }
+
+    /// Sets the tokens that `next_token` skips in `mode`.
+    ///
+    /// # Panics
+    /// Panics if `mode` is not less than `mode_count()`.
+    pub fn set_skip(&mut self, mode: usize, tokens: &[u32]) {
+        let mut tokens = tokens.to_vec();
+        tokens.sort();
+        tokens.dedup();
+        self.skips[mode] = tokens;
+    }
+
+    /// Whether `next_token` skips `token` in `mode`.
+    ///
+    /// # Panics
+    /// Panics if `mode` is not less than `mode_count()`.
+    pub fn is_skip(&self, mode: usize, token: u32) -> bool {
+        self.skips[mode].binary_search(&token).is_ok()
+    }
+
+    /// Sets what `next_token` does with input that no token of `mode` matches.
+    ///
+    /// # Panics
+    /// Panics if `mode` is not less than `mode_count()`.
+    pub fn set_error_policy(&mut self, mode: usize, policy: ErrorPolicy) {
+        self.errors[mode] = policy;
+    }
+
+    /// What `next_token` does with input that no token of `mode` matches.
+    ///
+    /// # Panics
+    /// Panics if `mode` is not less than `mode_count()`.
+    pub fn error_policy(&self, mode: usize) -> ErrorPolicy {
+        self.errors[mode]
+    }
+
+    /// The next token or error at or after `offset` in `input` when lexing in `mode`.
+    ///
+    /// The skip tokens of `mode` are passed over, and input that no token matches is an error
+    /// as given by the `ErrorPolicy` of `mode`. The lexer continues from the `end` of the
+    /// result (usually after switching modes for the token). This is `None` at the end of the
+    /// input.
+    ///
+    /// # Panics
+    /// Panics if `mode` is not less than `mode_count()` or if `offset` is not on a `char`
+    /// boundary of `input`.
+    pub fn next_token(&self, mode: usize, input: &str, offset: usize) -> Option<ModeMatch> {
+        let mut start = offset;
+        while start < input.len() {
+            match self.longest_match(mode, &input[start..]) {
+                Some((token, len)) if self.is_skip(mode, token) => start += len,
+                Some((token, len)) => {
+                    return Some(ModeMatch::Token {
+                        token,
+                        start,
+                        end: start + len,
+                    })
+                }
+                None => return Some(self.error_at(mode, input, start)),
+            }
+        }
+
+        None
+    }
+
+    fn error_at(&self, mode: usize, input: &str, start: usize) -> ModeMatch {
+        let next_char = |offset: usize| {
+            offset + input[offset..].chars().next().map_or(0, |c| c.len_utf8())
+        };
+
+        let mut end = next_char(start);
+        if self.errors[mode] == ErrorPolicy::SkipToToken {
+            while end < input.len() && self.longest_match(mode, &input[end..]).is_none() {
+                end = next_char(end);
+            }
+        }
+
+        ModeMatch::Error {
+            start,
+            end,
+            fatal: self.errors[mode] == ErrorPolicy::Stop,
+        }
+    }
+}
+
+/// A `TableDfa` that skips ahead through the input in its scanning states.
+///
+/// A scanning state stays in itself on every `char` except for a few (at most
+/// `MAX_SCAN_CHARS`), such as the state for the inside of a comment or string literal that is
+/// waiting for its terminator. `longest_match` searches for the next of those `char`'s with
+/// `str::find` (which uses `memchr` for a single `char`) instead of following the transitions
+/// for each `char` in between.
+///
+/// In the other states `longest_match` skips over a run of the ASCII `char`'s on which the
+/// state stays in itself (such as the spaces in a run of whitespace) by testing each byte
+/// against a bit mask instead of following the transitions.
+///
+/// `find_match` finds the leftmost longest match anywhere in the input (rather than only at
+/// its start). If every token starts with the same literal prefix (for example a log format
+/// whose lines all start with `"20"`) it skips to the next occurrence of the prefix with
+/// `str::find` before running the dfa, instead of trying every offset.
+///
+/// A `ScanningDfa` also looks up the alphabet class of an ASCII `char` in a table rather than
+/// searching for it. Like `TableDfa` it is immutable once it is made, so it can be made once
+/// and shared by any number of threads (for example in an `Arc`).
+#[derive(Debug, Clone)]
+pub struct ScanningDfa<'a> {
+    dfa: TableDfa<'a>,
+    scans: Vec<Option<Vec<char>>>,
+    runs: Vec<u128>,
+    ascii_classes: Vec<u32>,
+    prefix: String,
+}
+
+impl<'a> ScanningDfa<'a> {
+    /// The underlying `TableDfa`.
+    pub fn dfa(&self) -> &TableDfa<'a> {
+        &self.dfa
+    }
+
+    /// The state reached from `state` on `c`.
+    ///
+    /// # Panics
+    /// If `state` is not less than the number of states.
+    pub fn transition(&self, state: u32, c: char) -> u32 {
+        let dfa = &self.dfa;
+        assert!(state < dfa.states, "invalid state {}", state);
+
+        let class = match self.ascii_classes.get(c as usize) {
+            Some(&class) => class,
+            None => dfa.class(c),
+        };
+        read_u32(dfa.table, (state * dfa.classes + class) as usize)
+    }
+
+    /// The literal prefix that every match starts with (which may be empty).
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Finds the leftmost longest match in `input`.
+    ///
+    /// The result is the offset of the start of the match, the token, and the length (in
+    /// bytes) of the match.
+    pub fn find_match(&self, input: &str) -> Option<(usize, u32, usize)> {
+        let mut start = 0;
+
+        while start < input.len() {
+            start += input[start..].find(self.prefix.as_str())?;
+            if let Some((token, len)) = self.longest_match(&input[start..]) {
+                return Some((start, token, len));
+            }
+            start += input[start..].chars().next().map_or(1, char::len_utf8);
+        }
+
+        None
+    }
+
+    /// Tests for `state` being a scanning state.
+    pub fn is_scanning(&self, state: u32) -> bool {
+        self.scans[state as usize].is_some()
+    }
+
+    /// The token and length (in bytes) of the longest prefix of `input` that is accepted.
+    ///
+    /// The result is the same as for `TableDfa::longest_match`.
+    pub fn longest_match(&self, input: &str) -> Option<(u32, usize)> {
+        let mut state = self.dfa.start;
+        let mut longest = None;
+        let mut offset = 0;
+
+        while let Some(c) = input[offset..].chars().next() {
+            if let Some(ref chars) = self.scans[state as usize] {
+                let rest = &input[offset..];
+                let skipped = match chars.len() {
+                    0 => rest.len(),
+                    1 => rest.find(chars[0]).unwrap_or(rest.len()),
+                    _ => rest.find(&chars[..]).unwrap_or(rest.len()),
+                };
+                if skipped > 0 {
+                    offset += skipped;
+                    if let Some(token) = self.dfa.accept(state) {
+                        longest = Some((token, offset));
+                    }
+                    continue;
+                }
+            }
+
+            let loops = self.runs[state as usize];
+            if loops != 0 {
+                let run = input[offset..]
+                    .bytes()
+                    .take_while(|&b| b < 128 && loops & (1 << b) != 0)
+                    .count();
+                if run > 0 {
+                    offset += run;
+                    if let Some(token) = self.dfa.accept(state) {
+                        longest = Some((token, offset));
+                    }
+                    continue;
+                }
+            }
+
+            state = self.transition(state, c);
+            if state == self.dfa.error {
+                break;
+            }
+            offset += c.len_utf8();
+            if let Some(token) = self.dfa.accept(state) {
+                longest = Some((token, offset));
+            }
+        }
+
+        longest
+    }
+}
+
+/// The prefix of the input that `TableDfa::find` matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchKind {
+    /// The longest prefix that is accepted (maximal munch).
+    #[default]
+    Longest,
+
+    /// The shortest prefix that is accepted.
+    Shortest,
+}
+
+/// A record of the steps that a `TableDfa` took to find the longest match.
+///
+/// The `Display` implementation shows a line for each step (marking the steps that reached an
+/// accepting state) followed by the match and the number of `char`'s that were backtracked
+/// over to get back to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionTrace {
+    /// The start state.
+    pub start: u32,
+
+    /// The steps in the order they were taken.
+    pub steps: Vec<TraceStep>,
+
+    /// The longest match as a token and its length in bytes.
+    pub longest: Option<(u32, usize)>,
+}
+
+/// A transition taken by a `TableDfa` in an `ExecutionTrace`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceStep {
+    /// The offset (in bytes) of `c` in the input.
+    pub offset: usize,
+
+    /// The `char` read.
+    pub c: char,
+
+    /// The alphabet class of `c`.
+    pub class: u32,
+
+    /// The state after the transition.
+    pub state: u32,
+
+    /// The token accepted by `state`, if any.
+    pub accept: Option<u32>,
+}
+
+impl ExecutionTrace {
+    /// The number of `char`'s that were read past the end of the longest match.
+    pub fn backtracked(&self) -> usize {
+        let matched = self.longest.map_or(0, |(_, len)| len);
+        self.steps
+            .iter()
+            .filter(|step| step.offset >= matched)
+            .count()
+    }
+}
+
+impl fmt::Display for ExecutionTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "start in state {}", self.start)?;
+        for step in self.steps.iter() {
+            write!(
+                f,
+                "{:>6} {:?} (class {}) -> state {}",
+                step.offset, step.c, step.class, step.state
+            )?;
+            match step.accept {
+                Some(token) => writeln!(f, ", accept token {}", token)?,
+                None => writeln!(f)?,
+            }
+        }
+
+        match self.longest {
+            Some((token, len)) => write!(
+                f,
+                "matched token {} at 0..{} (backtracked {} chars)",
+                token,
+                len,
+                self.backtracked()
+            ),
+            None => write!(f, "no match"),
+        }
+    }
+}
+
+// The mode start states, the lengths of the token names, and the token names.
+type NameSections<'a> = (&'a [u8], &'a [u8], &'a [u8]);
+
+// Splits the sections after the accept map (in version 2 of the encoding) into the mode start
+// states, the lengths of the token names, and the token names (without their padding).
+fn split_names(rest: &[u8]) -> StdResult<NameSections<'_>, TableError> {
+    let count = |bytes: &[u8]| -> StdResult<u64, TableError> {
+        if bytes.len() < 4 {
+            return Err(TableError::BadLength);
+        }
+        Ok(read_u32(bytes, 0) as u64 * 4)
+    };
+
+    let modes_len = count(rest)?;
+    if modes_len + 4 > rest.len() as u64 {
+        return Err(TableError::BadLength);
+    }
+    let (modes, rest) = rest[4..].split_at(modes_len as usize);
+
+    let lens_len = count(rest)?;
+    if lens_len + 4 > rest.len() as u64 {
+        return Err(TableError::BadLength);
+    }
+    let (name_lens, names) = rest[4..].split_at(lens_len as usize);
+
+    let names_len: u64 = (0..name_lens.len() / 4)
+        .map(|i| read_u32(name_lens, i) as u64)
+        .sum();
+    if names_len.div_ceil(4) * 4 != names.len() as u64 {
+        return Err(TableError::BadLength);
+    }
+
+    Ok((modes, name_lens, &names[..names_len as usize]))
+}
+
+// Reads the index'th little endian u32 from bytes.
+fn read_u32(bytes: &[u8], index: usize) -> u32 {
+    let b = &bytes[index * 4..index * 4 + 4];
+    u32::from(b[0]) | u32::from(b[1]) << 8 | u32::from(b[2]) << 16 | u32::from(b[3]) << 24
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    pub(crate) fn encode(values: &[u32]) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        for v in values {
+            let le = [*v as u8, (*v >> 8) as u8, (*v >> 16) as u8, (*v >> 24) as u8];
+            bytes.extend_from_slice(&le);
+        }
+        bytes
+    }
+
+    // A dfa for the tokens "a+" (token 0) and "b" (token 1). State 0 is the error state, state 1
+    // the start state. Class 0 is every other char, class 1 is 'a', and class 2 is 'b'.
+    pub(crate) fn ab_dfa() -> Vec<u8> {
+        encode(&[
+            1, 4, 3, 2, 1, 0,
+            'a' as u32, 1, 'b' as u32, 2,
+            0, 0, 0,
+            0, 2, 3,
+            0, 2, 0,
+            0, 0, 0,
+            NO_TOKEN, NO_TOKEN, 0, 1,
+        ])
+    }
+
+    #[test]
+    fn table_dfa_finds_longest_match() {
+        let bytes = ab_dfa();
+        let dfa = TableDfa::from_bytes(&bytes).expect("unexpected error loading dfa");
+
+        assert_eq!(dfa.longest_match("aab"), Some((0, 2)));
+        assert_eq!(dfa.longest_match("ba"), Some((1, 1)));
+        assert_eq!(dfa.longest_match("c"), None);
+    }
+
+    // The ab_dfa in version 2 of the encoding, with no other modes and the token names "Many"
+    // and "B".
+    pub(crate) fn ab_names_dfa() -> Vec<u8> {
+        let mut bytes = ab_dfa();
+        bytes[4] = 2;
+        bytes.extend(encode(&[0, 2, 4, 1])[4..].iter());
+        bytes.extend_from_slice(b"ManyB\0\0\0");
+        bytes
+    }
+
+    #[test]
+    fn table_dfa_has_token_names() {
+        let bytes = ab_names_dfa();
+        let dfa = TableDfa::from_bytes(&bytes).expect("unexpected error loading dfa");
+
+        assert_eq!(dfa.token_count(), 2);
+        assert_eq!(dfa.token_name(0), Some("Many"));
+        assert_eq!(dfa.token_name(1), Some("B"));
+        assert_eq!(dfa.token_name(2), None);
+        assert_eq!(dfa.mode_count(), 1);
+        assert_eq!(dfa.longest_match("aab"), Some((0, 2)));
+    }
+
+    #[test]
+    fn table_dfa_in_version_1_has_no_token_names() {
+        let bytes = ab_dfa();
+        let dfa = TableDfa::from_bytes(&bytes).expect("unexpected error loading dfa");
+
+        assert_eq!(dfa.token_count(), 0);
+        assert_eq!(dfa.token_name(0), None);
+    }
+
+    #[test]
+    fn table_dfa_rejects_truncated_token_names() {
+        let bytes = ab_names_dfa();
+
+        assert_eq!(
+            TableDfa::from_bytes(&bytes[..bytes.len() - 4]).unwrap_err(),
+            TableError::BadLength
+        );
+    }
+
+    #[test]
+    fn table_dfa_rejects_invalid_token_names() {
+        let mut bytes = ab_names_dfa();
+        let len = bytes.len();
+        bytes[len - 8] = 0xff;
+
+        assert_eq!(
+            TableDfa::from_bytes(&bytes).unwrap_err(),
+            TableError::InvalidValue("token names")
+        );
+    }
+
+    // The ab_dfa with a second mode that starts in state 2 (so it only matches "a*").
+    fn ab_modes_dfa() -> Vec<u8> {
+        let mut bytes = ab_dfa();
+        bytes.extend_from_slice(&[2, 0, 0, 0]);
+        bytes
+    }
+
+    #[test]
+    fn mode_set_matches_in_each_mode() {
+        let bytes = ab_modes_dfa();
+        let dfa = TableDfa::from_bytes(&bytes).expect("unexpected error loading dfa");
+
+        let sut = ModeSet::new(dfa);
+
+        assert_eq!(sut.mode_count(), 2);
+        assert_eq!(sut.longest_match(0, "aab"), Some((0, 2)));
+        assert_eq!(sut.longest_match(1, "aab"), Some((0, 2)));
+        assert_eq!(sut.longest_match(0, "b"), Some((1, 1)));
+        assert_eq!(sut.longest_match(1, "b"), None);
+    }
+
+    #[test]
+    fn mode_set_skips_tokens_per_mode() {
+        let bytes = ab_modes_dfa();
+        let dfa = TableDfa::from_bytes(&bytes).expect("unexpected error loading dfa");
+        let mut sut = ModeSet::new(dfa);
+
+        sut.set_skip(0, &[1]);
+
+        assert!(sut.is_skip(0, 1));
+        assert!(!sut.is_skip(1, 1));
+        assert_eq!(
+            sut.next_token(0, "baab", 0),
+            Some(ModeMatch::Token {
+                token: 0,
+                start: 1,
+                end: 3,
+            })
+        );
+        assert_eq!(sut.next_token(0, "baab", 3), None);
+        assert_eq!(
+            sut.next_token(1, "baab", 0),
+            Some(ModeMatch::Error {
+                start: 0,
+                end: 1,
+                fatal: true,
+            })
+        );
+    }
+
+    #[test]
+    fn mode_set_applies_error_policy_per_mode() {
+        let bytes = ab_modes_dfa();
+        let dfa = TableDfa::from_bytes(&bytes).expect("unexpected error loading dfa");
+        let mut sut = ModeSet::new(dfa);
+
+        sut.set_error_policy(1, ErrorPolicy::SkipToToken);
+
+        assert_eq!(sut.error_policy(0), ErrorPolicy::Stop);
+        assert_eq!(
+            sut.next_token(1, "bba", 0),
+            Some(ModeMatch::Error {
+                start: 0,
+                end: 2,
+                fatal: false,
+            })
+        );
+        sut.set_error_policy(1, ErrorPolicy::SkipChar);
+        assert_eq!(
+            sut.next_token(1, "bba", 0),
+            Some(ModeMatch::Error {
+                start: 0,
+                end: 1,
+                fatal: false,
+            })
+        );
+    }
+
+    #[test]
+    fn table_dfa_without_modes_has_one_mode() {
+        let bytes = ab_dfa();
+        let dfa = TableDfa::from_bytes(&bytes).expect("unexpected error loading dfa");
+
+        assert_eq!(dfa.mode_count(), 1);
+        assert_eq!(dfa.mode_start_state(0), Some(1));
+        assert_eq!(dfa.mode_start_state(1), None);
+    }
+
+    // A dfa for the tokens "ab" (token 0) and "ac" (token 1). State 0 is the error state, state
+    // 1 the start state. Class 0 is every other char, classes 1 to 3 are 'a' to 'c'.
+    fn prefix_dfa() -> Vec<u8> {
+        encode(&[
+            1, 5, 4, 3, 1, 0,
+            'a' as u32, 1, 'b' as u32, 2, 'c' as u32, 3,
+            0, 0, 0, 0,
+            0, 2, 0, 0,
+            0, 0, 3, 4,
+            0, 0, 0, 0,
+            0, 0, 0, 0,
+            NO_TOKEN, NO_TOKEN, NO_TOKEN, 0, 1,
+        ])
+    }
+
+    #[test]
+    fn scanning_dfa_finds_match_after_literal_prefix() {
+        let bytes = prefix_dfa();
+        let dfa = TableDfa::from_bytes(&bytes).expect("unexpected error loading dfa");
+
+        let sut = dfa.scanning();
+
+        assert_eq!(sut.prefix(), "a");
+        assert_eq!(sut.find_match("xxab ac"), Some((2, 0, 2)));
+        assert_eq!(sut.find_match("xxa ac"), Some((4, 1, 2)));
+        assert_eq!(sut.find_match("xyz a"), None);
+    }
+
+    #[test]
+    fn scanning_dfa_without_prefix_finds_match_at_any_offset() {
+        let bytes = ab_dfa();
+        let dfa = TableDfa::from_bytes(&bytes).expect("unexpected error loading dfa");
+
+        let sut = dfa.scanning();
+
+        assert_eq!(sut.prefix(), "");
+        assert_eq!(sut.find_match("ccaa"), Some((2, 0, 2)));
+        assert_eq!(sut.find_match("\u{e9}b"), Some((2, 1, 1)));
+        assert_eq!(sut.find_match("cc"), None);
+    }
+
+    #[test]
+    fn table_dfa_finds_shortest_match() {
+        let bytes = ab_dfa();
+        let dfa = TableDfa::from_bytes(&bytes).expect("unexpected error loading dfa");
+
+        assert_eq!(dfa.shortest_match("aab"), Some((0, 1)));
+        assert_eq!(dfa.find("aab", MatchKind::Shortest), Some((0, 1)));
+        assert_eq!(dfa.find("aab", MatchKind::Longest), Some((0, 2)));
+        assert_eq!(dfa.shortest_match("c"), None);
+    }
+
+    #[test]
+    fn table_dfa_traces_longest_match() {
+        let bytes = ab_dfa();
+        let dfa = TableDfa::from_bytes(&bytes).expect("unexpected error loading dfa");
+
+        let sut = dfa.run_traced("aab");
+
+        assert_eq!(sut.longest, dfa.longest_match("aab"));
+        assert_eq!(
+            sut.steps.iter().map(|s| (s.class, s.state)).collect::<Vec<_>>(),
+            vec![(1, 2), (1, 2), (2, 0)]
+        );
+        assert_eq!(sut.backtracked(), 1);
+        assert_eq!(
+            sut.to_string(),
+            "start in state 1\n     \
+             0 'a' (class 1) -> state 2, accept token 0\n     \
+             1 'a' (class 1) -> state 2, accept token 0\n     \
+             2 'b' (class 2) -> state 0\n\
+             matched token 0 at 0..2 (backtracked 1 chars)"
+        );
+    }
+
+    // A dfa for the token "\"[^\"]*\"" (token 0). State 0 is the error state, state 1 the start
+    // state, and state 2 the inside of the string. Class 0 is every other char and class 1 is
+    // '"'.
+    fn string_dfa() -> Vec<u8> {
+        encode(&[
+            1, 4, 2, 1, 1, 0,
+            '"' as u32, 1,
+            0, 0,
+            0, 2,
+            2, 3,
+            0, 0,
+            NO_TOKEN, NO_TOKEN, NO_TOKEN, 0,
+        ])
+    }
+
+    #[test]
+    fn scanning_dfa_finds_scanning_states() {
+        let bytes = string_dfa();
+        let dfa = TableDfa::from_bytes(&bytes).expect("unexpected error loading dfa");
+
+        let sut = dfa.scanning();
+
+        assert!(!sut.is_scanning(1));
+        assert!(sut.is_scanning(2));
+    }
+
+    #[test]
+    fn scanning_dfa_finds_same_longest_match() {
+        let bytes = string_dfa();
+        let dfa = TableDfa::from_bytes(&bytes).expect("unexpected error loading dfa");
+
+        let sut = dfa.scanning();
+
+        for input in &["\"ab ℞ c\" x", "\"\"", "\"abc", "x"] {
+            assert_eq!(sut.longest_match(input), dfa.longest_match(input));
+        }
+    }
+
+    #[test]
+    fn scanning_dfa_skips_runs_of_looping_chars() {
+        let bytes = ab_dfa();
+        let dfa = TableDfa::from_bytes(&bytes).expect("unexpected error loading dfa");
+        let sut = dfa.scanning();
+
+        assert_eq!(sut.runs[2], 1 << b'a');
+        assert_eq!(sut.runs[1], 0);
+        for input in &["aaaab", "a", "aaaa\u{e9}", "b", ""] {
+            assert_eq!(sut.longest_match(input), dfa.longest_match(input));
+        }
+    }
+
+    #[test]
+    fn scanning_dfa_transitions_like_table_dfa() {
+        let bytes = ab_dfa();
+        let dfa = TableDfa::from_bytes(&bytes).expect("unexpected error loading dfa");
+
+        let sut = dfa.scanning();
+
+        for state in 0..dfa.state_count() {
+            for c in (0..130u8).map(char::from).chain(Some('℞')) {
+                assert_eq!(sut.transition(state, c), dfa.transition(state, c));
+            }
+        }
+    }
+
+    #[test]
+    fn table_dfas_can_be_shared_between_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<TableDfa<'static>>();
+        assert_send_sync::<ScanningDfa<'static>>();
+    }
+
+    #[test]
+    fn scanning_dfa_matches_on_many_threads() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let bytes: &'static [u8] = Box::leak(string_dfa().into_boxed_slice());
+        let sut = Arc::new(
+            TableDfa::from_bytes(bytes)
+                .expect("unexpected error loading dfa")
+                .scanning(),
+        );
+
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let sut = sut.clone();
+                thread::spawn(move || {
+                    let input = format!("\"{}\"", "x".repeat(i));
+                    sut.longest_match(&input)
+                })
+            })
+            .collect();
+
+        for (i, handle) in handles.into_iter().enumerate() {
+            assert_eq!(handle.join().unwrap(), Some((0, i + 2)));
+        }
+    }
+
+    #[test]
+    fn table_dfa_rejects_bad_magic() {
+        let mut bytes = ab_dfa();
+        bytes[0] = b'X';
+
+        assert_eq!(TableDfa::from_bytes(&bytes).unwrap_err(), TableError::BadMagic);
+    }
+
+    #[test]
+    fn table_dfa_rejects_other_versions() {
+        let mut bytes = ab_dfa();
+        bytes[4] = 3;
+
+        assert_eq!(
+            TableDfa::from_bytes(&bytes).unwrap_err(),
+            TableError::UnsupportedVersion(3)
+        );
+    }
+
+    #[test]
+    fn table_dfa_rejects_truncated_input() {
+        let bytes = ab_dfa();
+
+        assert_eq!(
+            TableDfa::from_bytes(&bytes[..bytes.len() - 4]).unwrap_err(),
+            TableError::BadLength
+        );
+    }
+
+    #[test]
+    fn table_dfa_rejects_invalid_transitions() {
+        let mut bytes = ab_dfa();
+        bytes[(HEADER_LEN + 4 + 4) * 4] = 9;
+
+        assert_eq!(
+            TableDfa::from_bytes(&bytes).unwrap_err(),
+            TableError::InvalidValue("transition table")
+        );
+    }
+
+    #[test]
+    fn table_dfa_rejects_invalid_mode_start_states() {
+        let mut bytes = ab_dfa();
+        bytes.extend_from_slice(&[4, 0, 0, 0]);
+
+        assert_eq!(
+            TableDfa::from_bytes(&bytes).unwrap_err(),
+            TableError::InvalidValue("mode start states")
+        );
+    }
+}
@@ -70,6 +70,60 @@ impl luther::Lexer for Tokens {
 
 // End luther-dervie exemplar
 
+#[derive(Debug, PartialEq)]
+enum NumTokens {
+    Num(u8),
+}
+
+// dfa matches ["[0-9]+"] and converts the match with FromStr
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum NumTokensDfa {
+    State0, // ["[0-9]+"]
+    State1, // [null]
+    State2, // ["[0-9]*"]
+}
+
+impl default::Default for NumTokensDfa {
+    fn default() -> Self {
+        NumTokensDfa::State0
+    }
+}
+
+impl luther::dfa::Dfa<NumTokens> for NumTokensDfa {
+    fn is_error(&self) -> bool {
+        *self == NumTokensDfa::State1
+    }
+
+    fn transition(&self, c: char) -> Self {
+        use NumTokensDfa::*;
+
+        match (*self, c) {
+            (State0, '0'...'9') | (State2, '0'...'9') => State2,
+            (_, _) => State1,
+        }
+    }
+
+    fn accept(&self, matched: &str) -> Option<NumTokens> {
+        self.try_accept(matched).and_then(|r| r.ok())
+    }
+
+    fn try_accept(&self, matched: &str) -> Option<Result<NumTokens, String>> {
+        match *self {
+            NumTokensDfa::State2 => Some(
+                matched
+                    .parse()
+                    .map(NumTokens::Num)
+                    .map_err(|e| ToString::to_string(&e)),
+            ),
+            _ => None, // COV_EXCL_LINE
+        }
+    }
+}
+
+impl luther::Lexer for NumTokens {
+    type Dfa = NumTokensDfa;
+}
+
 #[test]
 fn luther_matches_for_tokens_ab_and_accc() {
     let input = "abaccc".spanned_chars();
@@ -80,3 +134,27 @@ fn luther_matches_for_tokens_ab_and_accc() {
 
     assert_eq!(result, vec![Tokens::Ab, Tokens::Acc("accc".to_string())]);
 }
+
+#[test]
+#[cfg(feature = "io")]
+fn luther_matches_for_utf8_bytes() {
+    let input = "abaccc".bytes().map(Ok);
+
+    let sut = Tokens::byte_lexer(input).map(|r| r.map(|s| s.into_inner().1));
+    let result: Result<Vec<_>, _> = sut.collect();
+    let result = result.expect("unexpected lexer error");
+
+    assert_eq!(result, vec![Tokens::Ab, Tokens::Acc("accc".to_string())]);
+}
+
+#[test]
+fn luther_is_invalid_value_for_failed_conversion() {
+    let input = "300".spanned_chars();
+
+    let mut sut = NumTokens::lexer(input);
+
+    match sut.next() {
+        Some(Err(luther::LexError::InvalidValue(matched, _))) => assert_eq!(matched, "300"),
+        other => panic!("unexpected lexer result: {:?}", other.map(|r| r.map(|s| s.into_inner()))),
+    }
+}
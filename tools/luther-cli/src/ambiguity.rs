@@ -0,0 +1,149 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! A machine readable report of the token definitions that match some of the same strings.
+//!
+//! Overlapping tokens are normal (a keyword overlaps an identifier) and the dfa resolves them
+//! by priority, but a change to a regex or a priority group can quietly change which token
+//! wins. The report lists every pair of tokens in the same mode that overlap, with the
+//! shortest string that both match (and, of those, the first in lexicographic order) and the
+//! token that wins it, so that it can be checked in next to the token definitions and its
+//! changes reviewed like a snapshot.
+//!
+//! The anchors and word boundaries of the tokens are ignored, so a pair is reported if their
+//! regexes overlap at all.
+
+use std::result::Result as StdResult;
+
+use redfa::Regex;
+use redfa::dfa::Normalize;
+use serde_json;
+
+use automaton::{best_token, parse, Automaton};
+use equiv::distinguishing_string;
+use error::CompileError;
+use tokens::TokenDef;
+
+/// A pair of tokens that match some of the same strings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Overlap {
+    /// The lexer mode of the tokens.
+    pub mode: String,
+
+    /// The name of the token that is defined first.
+    pub first: String,
+
+    /// The name of the token that is defined second.
+    pub second: String,
+
+    /// The shortest string that both tokens match.
+    pub witness: String,
+
+    /// The name of the token that wins under the current priorities (or `None` if neither
+    /// does).
+    pub winner: Option<String>,
+}
+
+/// The overlapping pairs of a list of token definitions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Report {
+    /// The overlapping pairs, in the order of their first and then their second token.
+    pub overlaps: Vec<Overlap>,
+}
+
+impl Report {
+    /// Formats the report as pretty printed JSON (ending with a newline).
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap() + "\n"
+    }
+}
+
+/// Finds the overlapping pairs of `tokens`.
+///
+/// It is an error if the tokens do not compile (see `Automaton::new`).
+pub fn report(tokens: &[TokenDef]) -> StdResult<Report, CompileError> {
+    let automaton = Automaton::new(tokens.to_vec())?;
+    let mut regexs = Vec::new();
+    for token in tokens {
+        regexs.push(parse(token)?);
+    }
+
+    let mut overlaps = Vec::new();
+    for (i, first) in tokens.iter().enumerate() {
+        for (j, second) in tokens.iter().enumerate().skip(i + 1) {
+            if first.mode_name() != second.mode_name() {
+                continue;
+            }
+
+            let both = Regex::And(vec![regexs[i].clone(), regexs[j].clone()]);
+            let both = vec![both].normalize().remove(0);
+            if let Some(witness) = distinguishing_string(&both, &Regex::Null) {
+                let winner = best_token(&[i, j], tokens, &automaton.simple_strings);
+                overlaps.push(Overlap {
+                    mode: first.mode_name().to_string(),
+                    first: first.name.clone(),
+                    second: second.name.clone(),
+                    witness,
+                    winner: winner.ok().and_then(|w| w).map(|w| tokens[w].name.clone()),
+                });
+            }
+        }
+    }
+
+    Ok(Report { overlaps })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn report_lists_overlapping_pairs_with_witness_and_winner() {
+        let mut hex = TokenDef::new("Hex", "0x[0-9]+|[a-f]+");
+        hex.priority_group = 2;
+        let tokens = vec![
+            TokenDef::new("Ident", "[a-z]+"),
+            TokenDef::new("If", "if"),
+            TokenDef::new("Num", "[0-9]+"),
+            hex,
+        ];
+
+        let result = report(&tokens).expect("unexpected error in the report");
+
+        assert_eq!(
+            result.overlaps,
+            vec![
+                Overlap {
+                    mode: "INITIAL".to_string(),
+                    first: "Ident".to_string(),
+                    second: "If".to_string(),
+                    witness: "if".to_string(),
+                    winner: Some("If".to_string()),
+                },
+                Overlap {
+                    mode: "INITIAL".to_string(),
+                    first: "Ident".to_string(),
+                    second: "Hex".to_string(),
+                    witness: "a".to_string(),
+                    winner: Some("Ident".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn report_ignores_tokens_in_other_modes() {
+        let mut text = TokenDef::new("Text", "[a-z]+");
+        text.mode = Some("STRING".to_string());
+        let tokens = vec![TokenDef::new("Ident", "[a-z]+"), text];
+
+        let result = report(&tokens).expect("unexpected error in the report");
+
+        assert_eq!(result.to_json(), "{\n  \"overlaps\": []\n}\n");
+    }
+}
@@ -0,0 +1,201 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Random regexes for property tests (with the `quickcheck` feature).
+//!
+//! `RegexGen` generates the text of random regexes over a small alphabet. It favours the
+//! shapes that are most likely to find bugs in taking derivatives: nested stars, complements,
+//! and intersections. `ArbitraryRegex` wraps it for use as a `quickcheck` argument.
+
+use quickcheck::{Arbitrary, Gen};
+use redfa::Regex;
+
+/// A generator of random regexes over an alphabet up to a maximum depth.
+#[derive(Debug, Clone)]
+pub struct RegexGen {
+    alphabet: Vec<char>,
+    max_depth: usize,
+}
+
+impl RegexGen {
+    /// Creates a generator of regexes over the `char`s of `alphabet` with at most
+    /// `max_depth` nested operators.
+    ///
+    /// The `char`s of `alphabet` should be letters or digits (so that they need no escaping).
+    ///
+    /// # Panics
+    /// Panics if `alphabet` is empty.
+    pub fn new(alphabet: &str, max_depth: usize) -> RegexGen {
+        let alphabet: Vec<_> = alphabet.chars().collect();
+        assert!(!alphabet.is_empty(), "the alphabet for RegexGen is empty");
+
+        RegexGen {
+            alphabet,
+            max_depth,
+        }
+    }
+
+    /// The `char`s that the generated regexes are over.
+    pub fn alphabet(&self) -> &[char] {
+        &self.alphabet
+    }
+
+    /// Generates the text of a random regex.
+    pub fn generate<G: Gen>(&self, g: &mut G) -> String {
+        let mut regex = String::new();
+        self.generate_at(g, self.max_depth, &mut regex);
+        regex
+    }
+
+    fn generate_at<G: Gen>(&self, g: &mut G, depth: usize, out: &mut String) {
+        // the weights of: a char, a class, concatenation, alternation, star, complement,
+        // and intersection (a leaf is more likely the deeper we are)
+        let leaf_weight = 2 + self.max_depth - depth;
+        let weights = [leaf_weight, leaf_weight, 3, 3, 3, 2, 2];
+        let choice = if depth == 0 {
+            g.gen_range(0, 2)
+        } else {
+            choose(g, &weights)
+        };
+
+        match choice {
+            0 => out.push(*g.choose(&self.alphabet).unwrap()),
+            1 => {
+                out.push('[');
+                for &c in self.alphabet.iter() {
+                    if g.gen() {
+                        out.push(c);
+                    }
+                }
+                out.push(*g.choose(&self.alphabet).unwrap());
+                out.push(']');
+            }
+            2 => {
+                self.group(g, depth, out);
+                self.group(g, depth, out);
+            }
+            3 => {
+                self.group(g, depth, out);
+                out.push('|');
+                self.group(g, depth, out);
+            }
+            4 => {
+                self.group(g, depth, out);
+                out.push('*');
+            }
+            5 => {
+                out.push('~');
+                self.group(g, depth, out);
+            }
+            _ => {
+                self.group(g, depth, out);
+                out.push('&');
+                self.group(g, depth, out);
+            }
+        }
+    }
+
+    fn group<G: Gen>(&self, g: &mut G, depth: usize, out: &mut String) {
+        out.push('(');
+        self.generate_at(g, depth - 1, out);
+        out.push(')');
+    }
+}
+
+impl Default for RegexGen {
+    /// A generator over `"abc"` with at most 4 nested operators.
+    fn default() -> RegexGen {
+        RegexGen::new("abc", 4)
+    }
+}
+
+/// The text of a random regex from `RegexGen::default()` as a `quickcheck` argument.
+///
+/// The text always parses as a regex. It shrinks to the smaller texts (with one `char`
+/// removed) that still parse.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArbitraryRegex(pub String);
+
+impl Arbitrary for ArbitraryRegex {
+    fn arbitrary<G: Gen>(g: &mut G) -> ArbitraryRegex {
+        ArbitraryRegex(RegexGen::default().generate(g))
+    }
+
+    fn shrink(&self) -> Box<Iterator<Item = ArbitraryRegex>> {
+        let chars: Vec<_> = self.0.chars().collect();
+        let smaller = (0..chars.len())
+            .map(move |i| {
+                chars[..i]
+                    .iter()
+                    .chain(chars[i + 1..].iter())
+                    .collect::<String>()
+            })
+            .filter(|text| !text.is_empty() && text.parse::<Regex<char>>().is_ok())
+            .map(ArbitraryRegex);
+
+        Box::new(smaller)
+    }
+}
+
+fn choose<G: Gen>(g: &mut G, weights: &[usize]) -> usize {
+    let mut pick = g.gen_range(0, weights.iter().sum::<usize>());
+    for (i, &weight) in weights.iter().enumerate() {
+        if pick < weight {
+            return i;
+        }
+        pick -= weight;
+    }
+    unreachable!()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use debug::{derivative, parse_regex};
+    use equiv::distinguishing_string;
+
+    fn matches(regex: &str, input: &str) -> bool {
+        let regex = parse_regex(regex).expect("generated regex did not parse");
+        input.chars().fold(regex, |re, c| derivative(&re, c)).nullable()
+    }
+
+    fn equivalent(lhs: &str, rhs: &str) -> bool {
+        let lhs = parse_regex(lhs).expect("generated regex did not parse");
+        let rhs = parse_regex(rhs).expect("generated regex did not parse");
+        distinguishing_string(&lhs, &rhs).is_none()
+    }
+
+    quickcheck! {
+        fn generated_regexes_parse(r: ArbitraryRegex) -> bool {
+            parse_regex(&r.0).is_ok()
+        }
+
+        fn alternation_matches_either(r: ArbitraryRegex, s: ArbitraryRegex, input: String)
+            -> bool
+        {
+            let input: String = input.chars().filter(|c| "abc".contains(*c)).collect();
+            let alt = format!("({})|({})", r.0, s.0);
+
+            matches(&alt, &input) == (matches(&r.0, &input) || matches(&s.0, &input))
+        }
+
+        fn complement_matches_the_rest(r: ArbitraryRegex, input: String) -> bool {
+            let input: String = input.chars().filter(|c| "abc".contains(*c)).collect();
+
+            matches(&format!("~({})", r.0), &input) == !matches(&r.0, &input)
+        }
+
+        fn alternation_commutes(r: ArbitraryRegex, s: ArbitraryRegex) -> bool {
+            equivalent(&format!("({})|({})", r.0, s.0), &format!("({})|({})", s.0, r.0))
+        }
+
+        fn star_is_idempotent(r: ArbitraryRegex) -> bool {
+            equivalent(&format!("(({})*)*", r.0), &format!("({})*", r.0))
+        }
+    }
+}
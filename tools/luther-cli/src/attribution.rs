@@ -0,0 +1,79 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Attribution of the states of a dfa to the token definitions that caused them.
+//!
+//! Each state of the dfa for a list of tokens is a vector of the derivatives of the tokens'
+//! regexes, so a token contributes to a state if its derivative there is not `Regex::Null`. A
+//! token that contributes to many states (such as one with a large bounded repetition) is
+//! usually the one to look at when the dfa is larger than expected.
+
+use std::fmt::Write;
+
+use automaton::Automaton;
+
+/// The number of states that each token contributes to.
+pub fn state_counts(automaton: &Automaton) -> Vec<usize> {
+    let mut counts = vec![0; automaton.tokens.len()];
+    for (state, contributors) in automaton.contributors.iter().enumerate() {
+        if state == automaton.error_state {
+            continue;
+        }
+        for &token in contributors.iter() {
+            counts[token] += 1;
+        }
+    }
+    counts
+}
+
+/// Reports the tokens that contribute to the most states (in descending order of the number of
+/// states) followed by the tokens that contribute to each state.
+pub fn report(automaton: &Automaton) -> String {
+    let mut report = String::new();
+
+    let mut counts: Vec<_> = state_counts(automaton).into_iter().enumerate().collect();
+    counts.sort_by(|&(lhs, lhs_count), &(rhs, rhs_count)| {
+        rhs_count.cmp(&lhs_count).then(lhs.cmp(&rhs))
+    });
+    for (token, count) in counts {
+        writeln!(report, "{}: {} states", automaton.tokens[token].name, count).unwrap();
+    }
+
+    writeln!(report).unwrap();
+    for (state, contributors) in automaton.contributors.iter().enumerate() {
+        if state == automaton.error_state {
+            continue;
+        }
+        let names: Vec<_> = contributors
+            .iter()
+            .map(|&token| automaton.tokens[token].name.as_str())
+            .collect();
+        writeln!(report, "State{}: {}", state, names.join(", ")).unwrap();
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokens::TokenDef;
+
+    #[test]
+    fn state_counts_attributes_states_to_tokens() {
+        let automaton = Automaton::new(vec![
+            TokenDef::new("Short", "ab"),
+            TokenDef::new("Long", "a[a-z]?[a-z]?[a-z]?x"),
+        ]).unwrap();
+
+        let counts = state_counts(&automaton);
+
+        assert!(counts[1] > counts[0]);
+        assert!(report(&automaton).starts_with(&format!("Long: {} states\n", counts[1])));
+    }
+}
@@ -0,0 +1,197 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Portable C for a compiled automaton.
+//!
+//! The C is a single header with the transition and accept tables of the dfa and a minimal
+//! driver that finds the longest match at the start of its input. The driver works on
+//! decoded code points (`uint32_t`), so decoding the input (from UTF-8 or otherwise) is left
+//! to the caller.
+
+use std::fmt::Write;
+use std::result::Result as StdResult;
+
+use automaton::Automaton;
+use binary::check;
+use error::ExportError;
+
+/// Generates a C header for `automaton`.
+///
+/// It is an error if a token cannot be exported (see `binary::check`).
+pub fn to_c(automaton: &Automaton) -> StdResult<String, ExportError> {
+    check(automaton)?;
+    let mut c = String::new();
+    let states = &automaton.dfa.states;
+
+    writeln!(c, "/* Generated by luther. Do not edit. */").unwrap();
+    writeln!(c, "#ifndef LUTHER_TOKENS_H").unwrap();
+    writeln!(c, "#define LUTHER_TOKENS_H").unwrap();
+    writeln!(c).unwrap();
+    writeln!(c, "#include <stddef.h>").unwrap();
+    writeln!(c, "#include <stdint.h>").unwrap();
+    writeln!(c).unwrap();
+
+    for (num, token) in automaton.tokens.iter().enumerate() {
+        writeln!(c, "#define LUTHER_TOKEN_{} {}", screaming_snake(&token.name), num).unwrap();
+    }
+    writeln!(c, "#define LUTHER_NO_TOKEN (-1)").unwrap();
+    writeln!(c, "#define LUTHER_START_STATE {}u", automaton.start_state).unwrap();
+    writeln!(c, "#define LUTHER_ERROR_STATE {}u", automaton.error_state).unwrap();
+    writeln!(c).unwrap();
+
+    writeln!(
+        c,
+        "static const char *const luther_token_names[{}] = {{",
+        automaton.tokens.len()
+    ).unwrap();
+    for token in automaton.tokens.iter() {
+        writeln!(c, "    {:?},", token.name).unwrap();
+    }
+    writeln!(c, "}};").unwrap();
+    writeln!(c).unwrap();
+
+    writeln!(c, "struct luther_transition {{").unwrap();
+    writeln!(c, "    uint32_t c;").unwrap();
+    writeln!(c, "    uint32_t to;").unwrap();
+    writeln!(c, "}};").unwrap();
+    writeln!(c).unwrap();
+
+    // The transitions of each state are sorted by char (as by_char is) so that the driver can
+    // use a binary search. An extra entry keeps the array from being empty.
+    let mut offsets = vec![0];
+    writeln!(c, "static const struct luther_transition luther_transitions[] = {{").unwrap();
+    for state in states.iter() {
+        for (ch, to) in state.by_char.iter() {
+            writeln!(c, "    {{0x{:x}u, {}u}},", *ch as u32, to).unwrap();
+        }
+        let last = offsets[offsets.len() - 1];
+        offsets.push(last + state.by_char.len());
+    }
+    writeln!(c, "    {{0u, 0u}}").unwrap();
+    writeln!(c, "}};").unwrap();
+    writeln!(c).unwrap();
+
+    write_array(&mut c, "uint32_t", "luther_transition_offsets", &offsets);
+    let defaults: Vec<_> = states.iter().map(|state| state.default).collect();
+    write_array(&mut c, "uint32_t", "luther_defaults", &defaults);
+    let accepts: Vec<_> = states
+        .iter()
+        .map(|state| state.value.map_or(-1, |token| token as i64))
+        .collect();
+    write_array(&mut c, "int32_t", "luther_accepts", &accepts);
+
+    c.push_str(DRIVER);
+    writeln!(c).unwrap();
+    writeln!(c, "#endif /* LUTHER_TOKENS_H */").unwrap();
+
+    Ok(c)
+}
+
+const DRIVER: &str = r#"/* The state reached from state on the code point c. */
+static uint32_t luther_next_state(uint32_t state, uint32_t c)
+{
+    size_t low = luther_transition_offsets[state];
+    size_t high = luther_transition_offsets[state + 1];
+
+    while (low < high) {
+        size_t mid = low + (high - low) / 2;
+        if (luther_transitions[mid].c == c) {
+            return luther_transitions[mid].to;
+        } else if (luther_transitions[mid].c < c) {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+
+    return luther_defaults[state];
+}
+
+/*
+ * The token for the longest match at the start of the len code points of input, or
+ * LUTHER_NO_TOKEN if there is none. The length of the match (in code points) is stored in
+ * *match_len.
+ */
+static int32_t luther_longest_match(const uint32_t *input, size_t len, size_t *match_len)
+{
+    uint32_t state = LUTHER_START_STATE;
+    int32_t token = LUTHER_NO_TOKEN;
+    size_t i;
+
+    *match_len = 0;
+    for (i = 0; i < len; i++) {
+        state = luther_next_state(state, input[i]);
+        if (state == LUTHER_ERROR_STATE) {
+            break;
+        }
+        if (luther_accepts[state] != LUTHER_NO_TOKEN) {
+            token = luther_accepts[state];
+            *match_len = i + 1;
+        }
+    }
+
+    return token;
+}
+"#;
+
+fn write_array<T: ::std::fmt::Display>(c: &mut String, ty: &str, name: &str, values: &[T]) {
+    writeln!(c, "static const {} {}[{}] = {{", ty, name, values.len()).unwrap();
+    for chunk in values.chunks(12) {
+        let line: Vec<_> = chunk.iter().map(|v| v.to_string()).collect();
+        writeln!(c, "    {},", line.join(", ")).unwrap();
+    }
+    writeln!(c, "}};").unwrap();
+    writeln!(c).unwrap();
+}
+
+// Converts a token name like "LeftParen" to "LEFT_PAREN".
+pub(crate) fn screaming_snake(name: &str) -> String {
+    let mut result = String::new();
+    let mut prev_lower = false;
+
+    for c in name.chars() {
+        if c.is_uppercase() && prev_lower {
+            result.push('_');
+        }
+        prev_lower = c.is_lowercase() || c.is_numeric();
+        result.extend(c.to_uppercase());
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokens::TokenDef;
+
+    #[test]
+    fn screaming_snake_splits_words() {
+        assert_eq!(screaming_snake("LeftParen"), "LEFT_PAREN");
+        assert_eq!(screaming_snake("Int32"), "INT32");
+        assert_eq!(screaming_snake("EOF"), "EOF");
+    }
+
+    #[test]
+    fn to_c_defines_tokens_and_tables() {
+        let automaton = Automaton::new(vec![
+            TokenDef::new("LeftParen", "[(]"),
+            TokenDef::new("Ident", "[a-z]+"),
+        ]).unwrap();
+
+        let c = to_c(&automaton).unwrap();
+
+        assert!(c.contains("#define LUTHER_TOKEN_LEFT_PAREN 0"));
+        assert!(c.contains("#define LUTHER_TOKEN_IDENT 1"));
+        assert!(c.contains("{0x28u, "));
+        assert!(c.contains(&format!(
+            "luther_accepts[{}]",
+            automaton.dfa.states.len()
+        )));
+    }
+}
@@ -0,0 +1,305 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Conformance tests generated from the token definitions.
+//!
+//! For each token in the default mode the corpus has an example (the smallest string that is
+//! lexed as the whole token, found as in the `equiv` module) and its near misses: the strings
+//! one `char` away from the example (by deleting, replacing, or inserting a `char`) that are
+//! not lexed as the whole token. The replaced and inserted `char`'s are one from each class of
+//! the partition of the alphabet at that point in the dfa (see `Automaton::state_classes`), so
+//! the near misses try every way the dfa can go there without trying every `char`.
+//!
+//! `Corpus::to_rust` writes the corpus as a Rust test file that checks the `longest_match` of a
+//! `Token` generated by `luther-build`. Checked in, it is a regression suite for the token
+//! definitions: a change to a regex or a priority that changes how an example or a near miss is
+//! lexed fails a test.
+
+use std::fmt::Write;
+
+use automaton::{Automaton, StateClass};
+use c_source::screaming_snake;
+use equiv::{search, unused_char};
+
+/// A string that is lexed as the whole of a token, and the near misses to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Example {
+    /// The token (an index into the tokens of the automaton).
+    pub token: usize,
+
+    /// The smallest string that is lexed as the whole token.
+    pub text: String,
+
+    /// The strings that are one `char` away from `text` and are not lexed as the whole token.
+    pub near_misses: Vec<NearMiss>,
+}
+
+/// A string that is one `char` away from an example and how it is lexed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NearMiss {
+    /// The string.
+    pub text: String,
+
+    /// The token and length (in bytes) of the longest match at the start of `text`.
+    pub longest_match: Option<(usize, usize)>,
+}
+
+/// The examples for the tokens of an automaton.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Corpus {
+    /// The examples, in the order of their tokens.
+    pub examples: Vec<Example>,
+
+    /// The tokens that have no example: those in a mode other than the default one and those
+    /// that never win a match.
+    pub missing: Vec<usize>,
+}
+
+impl Corpus {
+    /// Formats the corpus as a Rust test file with a `#[test]` function for each example.
+    ///
+    /// `token_type` is the path of the `Token` enum generated by `luther-build` for the same
+    /// token definitions (such as `Token` or `::tokens::Token`), which the tests use for
+    /// `longest_match` and the token variants. `source` names the token definitions in the
+    /// header comment.
+    pub fn to_rust(&self, automaton: &Automaton, token_type: &str, source: &str) -> String {
+        let mut rust = String::new();
+        let variant = |token: usize| format!("{}::{}", token_type, automaton.tokens[token].name);
+
+        writeln!(rust, "// Generated by luther conformance from {}. Do not edit.", source).unwrap();
+        for &token in self.missing.iter() {
+            writeln!(
+                rust,
+                "// {} has no example (it is in another mode or never wins a match).",
+                automaton.tokens[token].name
+            ).unwrap();
+        }
+
+        for example in self.examples.iter() {
+            let name = screaming_snake(&automaton.tokens[example.token].name).to_lowercase();
+            writeln!(rust).unwrap();
+            writeln!(rust, "#[test]").unwrap();
+            writeln!(rust, "fn conformance_{}() {{", name).unwrap();
+            writeln!(
+                rust,
+                "    assert_eq!({}::longest_match({:?}), Some(({}, {})));",
+                token_type,
+                example.text,
+                variant(example.token),
+                example.text.len()
+            ).unwrap();
+            for near_miss in example.near_misses.iter() {
+                let expected = match near_miss.longest_match {
+                    Some((token, len)) => format!("Some(({}, {}))", variant(token), len),
+                    None => "None".to_string(),
+                };
+                writeln!(
+                    rust,
+                    "    assert_eq!({}::longest_match({:?}), {});",
+                    token_type, near_miss.text, expected
+                ).unwrap();
+            }
+            writeln!(rust, "}}").unwrap();
+        }
+
+        rust
+    }
+}
+
+/// Generates the examples and near misses for the tokens in the default mode of `automaton`.
+///
+/// The `min_len` and `max_len` of the tokens are not checked, as for the `longest_match` of a
+/// `Token` generated by `luther-build`. The anchors and word boundaries of the tokens are
+/// checked (with the examples at the start of a line), but the dfa that `luther-build` writes
+/// ignores them, so the tests from `Corpus::to_rust` may fail for tokens that have them.
+pub fn generate(automaton: &Automaton) -> Corpus {
+    let mut corpus = Corpus {
+        examples: Vec::new(),
+        missing: Vec::new(),
+    };
+
+    for token in 0..automaton.tokens.len() {
+        match example(automaton, token) {
+            Some(text) => corpus.examples.push(Example {
+                token,
+                near_misses: near_misses(automaton, token, &text),
+                text,
+            }),
+            None => corpus.missing.push(token),
+        }
+    }
+
+    corpus
+}
+
+// The smallest string that is lexed as the whole token, if there is one.
+fn example(automaton: &Automaton, token: usize) -> Option<String> {
+    if automaton.tokens[token].mode_name() != automaton.modes[0] {
+        return None;
+    }
+
+    let text = search(automaton.line_start_states[0], |state| {
+        let mut next: Vec<_> = automaton
+            .state_classes(state)
+            .into_iter()
+            .filter(|class| class.to != automaton.error_state)
+            .filter_map(|class| representative(automaton, state, &class).map(|c| (c, class.to)))
+            .collect();
+        next.sort();
+        (automaton.dfa.states[state].value == Some(token), next)
+    })?;
+
+    if automaton.longest_match(&text) == Some((token, text.len())) {
+        Some(text)
+    } else {
+        None
+    }
+}
+
+// The strings that are one char away from text and are not lexed as the whole token, in order.
+fn near_misses(automaton: &Automaton, token: usize, text: &str) -> Vec<NearMiss> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut candidates = Vec::new();
+    let mut state = automaton.line_start_states[0];
+
+    for i in 0..chars.len() + 1 {
+        let classes = automaton.state_classes(state);
+        for c in classes.iter().filter_map(|class| representative(automaton, state, class)) {
+            let mut inserted = chars.clone();
+            inserted.insert(i, c);
+            candidates.push(inserted);
+            if i < chars.len() && c != chars[i] {
+                let mut replaced = chars.clone();
+                replaced[i] = c;
+                candidates.push(replaced);
+            }
+        }
+        if i < chars.len() {
+            let mut deleted = chars.clone();
+            deleted.remove(i);
+            candidates.push(deleted);
+            state = automaton.transition(state, chars[i]);
+        }
+    }
+
+    let mut candidates: Vec<String> = candidates
+        .into_iter()
+        .map(|chars| chars.into_iter().collect())
+        .collect();
+    candidates.sort();
+    candidates.dedup();
+
+    candidates
+        .into_iter()
+        .filter_map(|text| {
+            let longest_match = automaton.longest_match(&text);
+            if longest_match == Some((token, text.len())) {
+                None
+            } else {
+                Some(NearMiss {
+                    text,
+                    longest_match,
+                })
+            }
+        })
+        .collect()
+}
+
+// The least char of a class of the partition at state.
+fn representative(automaton: &Automaton, state: usize, class: &StateClass) -> Option<char> {
+    let listed = class.ranges.first().map(|&(first, _)| first);
+    let unlisted = if class.other {
+        unused_char(&automaton.dfa.states[state].by_char, None)
+    } else {
+        None
+    };
+
+    match (listed, unlisted) {
+        (Some(listed), Some(unlisted)) => Some(listed.min(unlisted)),
+        (listed, unlisted) => listed.or(unlisted),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokens::TokenDef;
+
+    fn automaton() -> Automaton {
+        Automaton::new(vec![
+            TokenDef::new("Ident", "[a-z]+"),
+            TokenDef::new("If", "if"),
+            TokenDef::new("Num", "[0-9]+"),
+        ]).expect("unexpected error compiling the tokens")
+    }
+
+    #[test]
+    fn examples_are_the_smallest_whole_tokens() {
+        let corpus = generate(&automaton());
+
+        let examples: Vec<_> = corpus
+            .examples
+            .iter()
+            .map(|example| (example.token, example.text.as_str()))
+            .collect();
+        assert_eq!(examples, vec![(0, "a"), (1, "if"), (2, "0")]);
+        assert!(corpus.missing.is_empty());
+    }
+
+    #[test]
+    fn near_misses_are_one_char_away_and_not_the_whole_token() {
+        let corpus = generate(&automaton());
+
+        let near_misses = &corpus.examples[1].near_misses;
+        assert!(near_misses.contains(&NearMiss {
+            text: "i".to_string(),
+            longest_match: Some((0, 1)),
+        }));
+        assert!(near_misses.contains(&NearMiss {
+            text: "i\u{0}".to_string(),
+            longest_match: Some((0, 1)),
+        }));
+        assert!(near_misses.contains(&NearMiss {
+            text: "\u{0}f".to_string(),
+            longest_match: None,
+        }));
+        assert!(near_misses.iter().all(|near_miss| near_miss.text.chars().count() <= 3));
+    }
+
+    #[test]
+    fn shadowed_and_other_mode_tokens_are_missing() {
+        let mut name = TokenDef::new("Name", "[a-z]+");
+        name.priority_group = 2;
+        let mut text = TokenDef::new("Text", "[a-z]+");
+        text.mode = Some("STRING".to_string());
+        let automaton = Automaton::new(vec![
+            TokenDef::new("Ident", "[a-z]+"),
+            name,
+            text,
+        ]).expect("unexpected error compiling the tokens");
+
+        let corpus = generate(&automaton);
+
+        assert_eq!(corpus.missing, vec![1, 2]);
+    }
+
+    #[test]
+    fn to_rust_writes_a_test_for_each_example() {
+        let automaton = Automaton::new(vec![TokenDef::new("LeftParen", "\\(")])
+            .expect("unexpected error compiling the tokens");
+
+        let rust = generate(&automaton).to_rust(&automaton, "Token", "tokens.toml");
+
+        assert!(rust.starts_with("// Generated by luther conformance from tokens.toml."));
+        assert!(rust.contains("#[test]\nfn conformance_left_paren() {\n"));
+        assert!(rust.contains(
+            "    assert_eq!(Token::longest_match(\"(\"), Some((Token::LeftParen, 1)));\n"
+        ));
+        assert!(rust.contains("    assert_eq!(Token::longest_match(\"\"), None);\n"));
+    }
+}
@@ -0,0 +1,106 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Counting the strings of a given length that a regex or a list of token definitions accepts.
+//!
+//! The counts are found by the transfer matrix method: the number of strings of each length
+//! that reach each state is found from the counts for one `char` shorter, with a default
+//! transition standing for every `char` that is not otherwise listed. The strings counted are
+//! strings of `char`'s (so a count is typically huge for a pattern that has a negated class).
+//! A count that overflows a `u128` is `None`.
+
+use redfa::{self, Regex};
+
+use automaton::Automaton;
+
+/// The number of `char`'s (the Unicode scalar values).
+pub(crate) const CHAR_COUNT: u128 = 0x11_0000 - 0x800;
+
+/// The number of strings of `len` `char`'s that `regex` matches.
+pub fn count_regex_strings(regex: &Regex<char>, len: usize) -> Option<u128> {
+    let start = vec![regex.clone()];
+    let (dfa, map) = redfa::Dfa::from_derivatives(vec![start.clone()]);
+    let dfa = dfa.map(|res| res[0].nullable());
+
+    count(&dfa, map[&start] as usize, |&accepts| accepts, len)
+}
+
+/// The number of strings of `len` `char`'s that are a token in the default mode of
+/// `automaton`.
+pub fn count_strings(automaton: &Automaton, len: usize) -> Option<u128> {
+    count(
+        &automaton.dfa,
+        automaton.start_state,
+        |token| token.is_some(),
+        len,
+    )
+}
+
+fn count<V, F>(dfa: &redfa::Dfa<char, V>, start: usize, accepts: F, len: usize) -> Option<u128>
+where
+    F: Fn(&V) -> bool,
+{
+    let mut counts = vec![0u128; dfa.states.len()];
+    counts[start] = 1;
+
+    for _ in 0..len {
+        let mut next = vec![0u128; dfa.states.len()];
+        for (state, &count) in dfa.states.iter().zip(counts.iter()) {
+            if count == 0 {
+                continue;
+            }
+            for &to in state.by_char.values() {
+                next[to as usize] = next[to as usize].checked_add(count)?;
+            }
+            let others = CHAR_COUNT - state.by_char.len() as u128;
+            let to = state.default as usize;
+            next[to] = next[to].checked_add(count.checked_mul(others)?)?;
+        }
+        counts = next;
+    }
+
+    dfa.states
+        .iter()
+        .zip(counts.iter())
+        .filter(|&(state, _)| accepts(&state.value))
+        .fold(Some(0u128), |total, (_, &count)| total?.checked_add(count))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use debug::parse_regex;
+    use tokens::TokenDef;
+
+    #[test]
+    fn count_regex_strings_counts_each_length() {
+        let regex = parse_regex("[ab]*c").unwrap();
+
+        let counts: Vec<_> = (0..4).map(|len| count_regex_strings(&regex, len)).collect();
+
+        assert_eq!(counts, vec![Some(0), Some(1), Some(2), Some(4)]);
+    }
+
+    #[test]
+    fn count_strings_counts_tokens() {
+        let mut any = TokenDef::new("Any", ".");
+        any.priority_group = 2;
+        let automaton = Automaton::new(vec![TokenDef::new("Ident", "[a-z]+"), any]).unwrap();
+
+        assert_eq!(count_strings(&automaton, 1), Some(CHAR_COUNT));
+        assert_eq!(count_strings(&automaton, 2), Some(26 * 26));
+    }
+
+    #[test]
+    fn count_strings_is_none_on_overflow() {
+        let automaton = Automaton::new(vec![TokenDef::new("Any", ".+")]).unwrap();
+
+        assert!(count_strings(&automaton, 6).is_some());
+        assert_eq!(count_strings(&automaton, 7), None);
+    }
+}
@@ -0,0 +1,156 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Coverage of the alphabet by a list of token definitions.
+//!
+//! A `char` that leads from a start state only to dead states (states from which no accepting
+//! state can be reached) can never start a token in that mode, so it is always a lexical error
+//! at the start of a token.
+
+use std::char;
+use std::fmt::Write;
+
+use automaton::Automaton;
+use render::range;
+
+/// The largest `char` (as a `u32`).
+pub(crate) const MAX_CHAR: u32 = 0x10_FFFF;
+
+/// The ranges of `char`'s that no token in `mode` (an index into `automaton.modes`) can start
+/// with, in order.
+pub fn uncovered(automaton: &Automaton, mode: usize) -> Vec<(char, char)> {
+    let dead = dead_states(automaton);
+    let state = &automaton.dfa.states[automaton.start_states[mode]];
+    let default_dead = dead[state.default as usize];
+    let mut ranges = Vec::new();
+    let mut next = 0;
+
+    for (&c, &to) in state.by_char.iter() {
+        if default_dead && next < c as u32 {
+            push_range(&mut ranges, next, c as u32 - 1);
+        }
+        if dead[to as usize] {
+            push_range(&mut ranges, c as u32, c as u32);
+        }
+        next = c as u32 + 1;
+    }
+    if default_dead && next <= MAX_CHAR {
+        push_range(&mut ranges, next, MAX_CHAR);
+    }
+
+    ranges
+}
+
+/// Reports the uncovered ranges of `char`'s for each mode of `automaton`.
+///
+/// The report has a line for each mode with the mode name followed by its ranges (or "none").
+pub fn report(automaton: &Automaton) -> String {
+    let mut report = String::new();
+
+    for (mode, name) in automaton.modes.iter().enumerate() {
+        let ranges = uncovered(automaton, mode);
+        let ranges = if ranges.is_empty() {
+            "none".to_string()
+        } else {
+            format_ranges(&ranges)
+        };
+        writeln!(report, "{}: {}", name, ranges).unwrap();
+    }
+
+    report
+}
+
+// Formats ranges as a space separated list of 'a'-'z' (or 'a' for a range of one char).
+pub(crate) fn format_ranges(ranges: &[(char, char)]) -> String {
+    ranges
+        .iter()
+        .map(|&(first, last)| range(first, last))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// The states from which no accepting state can be reached.
+fn dead_states(automaton: &Automaton) -> Vec<bool> {
+    let states = &automaton.dfa.states;
+    let mut dead: Vec<_> = states.iter().map(|state| state.value.is_none()).collect();
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (num, state) in states.iter().enumerate() {
+            if dead[num]
+                && !state
+                    .by_char
+                    .values()
+                    .chain(Some(&state.default))
+                    .all(|&to| dead[to as usize])
+            {
+                dead[num] = false;
+                changed = true;
+            }
+        }
+    }
+
+    dead
+}
+
+// Adds the code points first..=last (less the surrogates) to ranges, merging with the last
+// range if they are adjacent.
+pub(crate) fn push_range(ranges: &mut Vec<(char, char)>, first: u32, last: u32) {
+    let parts = [(first, last.min(0xD7FF)), (first.max(0xE000), last)];
+    for &(first, last) in parts.iter().filter(|&&(first, last)| first <= last) {
+        let first = char::from_u32(first).unwrap();
+        let last = char::from_u32(last).unwrap();
+        match ranges.last_mut() {
+            Some(&mut (_, ref mut end)) if adjacent(*end, first) => *end = last,
+            _ => ranges.push((first, last)),
+        }
+    }
+}
+
+fn adjacent(end: char, next: char) -> bool {
+    end as u32 + 1 == next as u32 || (end == '\u{D7FF}' && next == '\u{E000}')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokens::TokenDef;
+
+    #[test]
+    fn uncovered_finds_chars_that_start_no_token() {
+        let automaton = Automaton::new(vec![
+            TokenDef::new("Ident", "[a-z]+"),
+            TokenDef::new("Digit", "[0-9]"),
+            TokenDef::new("Arrow", "->"),
+        ]).unwrap();
+
+        let sut = uncovered(&automaton, 0);
+
+        assert_eq!(
+            sut,
+            vec![
+                ('\0', ','),
+                ('.', '/'),
+                (':', '`'),
+                ('{', '\u{10FFFF}'),
+            ]
+        );
+    }
+
+    #[test]
+    fn uncovered_is_empty_when_any_char_starts_a_token() {
+        let automaton = Automaton::new(vec![
+            TokenDef::new("Ident", "[a-z]+"),
+            TokenDef::new("Other", "[^a-z]"),
+        ]).unwrap();
+
+        assert_eq!(uncovered(&automaton, 0), vec![]);
+        assert_eq!(report(&automaton), "INITIAL: none\n");
+    }
+}
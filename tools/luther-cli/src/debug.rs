@@ -0,0 +1,71 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+use std::fmt::Write;
+
+use redfa::Regex;
+use redfa::derivatives::Differentiable;
+use redfa::dfa::Normalize;
+
+pub use automaton::parse_regex;
+use render::{literal, render};
+
+/// Traces the derivatives taken of `regex` for each `char` of `input`.
+///
+/// The trace has a line for `regex` itself followed by a line for the derivative with
+/// respect to each `char` of `input`, and shows whether each of them is nullable (i.e.
+/// matches the empty string). The input matches `regex` if the last of them is nullable.
+pub fn trace(regex: &Regex<char>, input: &str) -> String {
+    let mut trace = String::new();
+    let mut current = regex.clone();
+
+    writeln!(trace, "{:>8}  {}", nullable_mark(&current), render(&current)).unwrap();
+    for c in input.chars() {
+        current = derivative(&current, c);
+        writeln!(
+            trace,
+            "{:>8}  {}",
+            format!("{} {}", literal(c), nullable_mark(&current)),
+            render(&current)
+        ).unwrap();
+    }
+
+    let outcome = if current.nullable() {
+        "matches"
+    } else if current == Regex::Null {
+        "does not match (and no extension can match)"
+    } else {
+        "does not match (but an extension might)"
+    };
+    writeln!(trace, "{:?} {}", input, outcome).unwrap();
+
+    trace
+}
+
+/// The derivative of `regex` with respect to `c`.
+///
+/// The derivative is normalized (`redfa` does not normalize the derivatives that it takes), so
+/// a derivative that matches nothing is `Regex::Null`.
+pub fn derivative(regex: &Regex<char>, c: char) -> Regex<char> {
+    let derivatives = regex.derivative();
+    let derivative = derivatives
+        .d
+        .into_iter()
+        .find(|&(ref cs, _)| cs.contains(&c))
+        .map_or(derivatives.rest, |(_, re)| re);
+
+    vec![derivative].normalize().remove(0)
+}
+
+fn nullable_mark(regex: &Regex<char>) -> &'static str {
+    if regex.nullable() {
+        "[ε]"
+    } else {
+        "[ ]"
+    }
+}
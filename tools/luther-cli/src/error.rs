@@ -0,0 +1,33 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! The errors for the phases of compiling token definitions.
+//!
+//! Compiling token definitions has two phases: parsing the regex of each token (which fails
+//! with a `ParseError`) and building the dfa for the tokens (which fails with a
+//! `CompileError`). The errors from the third phase, scanning an input with the compiled dfa,
+//! are `luther::LexError` (and a `LengthError` for a token that breaks the length limits of its
+//! definition).
+//!
+//! The errors for compiling and scanning are defined in `luther_build::error` (with the rest of
+//! the compilation of token definitions) and re-exported here; `ColorError` is for the
+//! `highlight` subcommand.
+
+pub use luther_build::error::{CompileError, ExportError, LengthError, LimitError, ParseError};
+
+/// A color of a token for the `highlight` subcommand cannot be shown in the output format.
+#[derive(Debug, Fail, Clone, PartialEq)]
+#[fail(display = "the color \"{}\" of token {} is not an ANSI color name or SGR code", color,
+       token)]
+pub struct ColorError {
+    /// The name of the token.
+    pub token: String,
+
+    /// The color of the token.
+    pub color: String,
+}
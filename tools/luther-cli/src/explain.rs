@@ -0,0 +1,176 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Explanations of how an input is split into tokens.
+//!
+//! For each token an explanation gives its span, the token that won, the other tokens that
+//! matched the same text (and lost on priority), and the tokens that could still have matched
+//! a longer text at the point where the match was cut off.
+
+use std::fmt::Write;
+
+use redfa::Regex;
+use redfa::derivatives::Differentiable;
+use redfa::dfa::Normalize;
+
+use automaton::Automaton;
+use debug::derivative;
+use render::Escape;
+
+/// Explains how `input` is split into tokens by `automaton`.
+///
+/// The explanation has a paragraph for each token (or for each `char` at which no token
+/// matches). Spans are byte offsets into `input`.
+pub fn explain(automaton: &Automaton, input: &str) -> String {
+    let mut explanation = String::new();
+    let mut start = 0;
+
+    while start < input.len() {
+        let rest = &input[start..];
+        match automaton.longest_match_at(input, start) {
+            Some((token, len)) => {
+                explain_token(&mut explanation, automaton, rest, start, token, len);
+                start += len;
+            }
+            None => {
+                let c = rest.chars().next().unwrap();
+                writeln!(
+                    explanation,
+                    "{}..{} error: no token matches at {}",
+                    start,
+                    start + c.len_utf8(),
+                    c.escaped()
+                ).unwrap();
+                start += c.len_utf8();
+            }
+        }
+    }
+
+    explanation
+}
+
+fn explain_token(
+    explanation: &mut String,
+    automaton: &Automaton,
+    rest: &str,
+    start: usize,
+    token: usize,
+    len: usize,
+) {
+    let names = |tokens: &[usize]| -> String {
+        tokens
+            .iter()
+            .map(|&t| automaton.tokens[t].name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    writeln!(
+        explanation,
+        "{}..{} {} {:?}",
+        start,
+        start + len,
+        automaton.tokens[token].name,
+        &rest[..len]
+    ).unwrap();
+
+    let matched = derivatives(&automaton.regexs, &rest[..len]);
+    let losers: Vec<_> = matched
+        .iter()
+        .enumerate()
+        .filter(|&(t, re)| t != token && re.nullable())
+        .map(|(t, _)| t)
+        .collect();
+    if !losers.is_empty() {
+        writeln!(explanation, "    also matched: {}", names(&losers)).unwrap();
+    }
+
+    let (cut_off, alive) = cut_off(automaton, rest);
+    let reason = match cut_off {
+        Some(c) => format!("cut off at {}", c.escaped()),
+        None => "cut off at the end of the input".to_string(),
+    };
+    if alive.is_empty() {
+        writeln!(explanation, "    {}; no token could match a longer text", reason).unwrap();
+    } else {
+        writeln!(explanation, "    {}; still alive: {}", reason, names(&alive)).unwrap();
+    }
+}
+
+// The char at which the dfa reached the error state when scanning rest (or None if it
+// reached the end of rest) and the tokens that could still match a longer text before that
+// char.
+fn cut_off(automaton: &Automaton, rest: &str) -> (Option<char>, Vec<usize>) {
+    let mut state = automaton.start_state;
+    let mut end = rest.len();
+    let mut cut_off = None;
+
+    for (offset, c) in rest.char_indices() {
+        let next = automaton.transition(state, c);
+        if next == automaton.error_state {
+            end = offset;
+            cut_off = Some(c);
+            break;
+        }
+        state = next;
+    }
+
+    let scanned = derivatives(&automaton.regexs, &rest[..end]);
+    let alive = scanned
+        .iter()
+        .enumerate()
+        .filter(|&(_, re)| *re != Regex::Null && !is_exhausted(re))
+        .map(|(t, _)| t)
+        .collect();
+
+    (cut_off, alive)
+}
+
+fn derivatives(regexs: &[Regex<char>], text: &str) -> Vec<Regex<char>> {
+    regexs
+        .iter()
+        .map(|re| text.chars().fold(re.clone(), |re, c| derivative(&re, c)))
+        .collect()
+}
+
+// Whether no non-empty text can be matched by re.
+fn is_exhausted(re: &Regex<char>) -> bool {
+    let derivatives = re.derivative().map(|re| vec![re].normalize().remove(0));
+    derivatives.rest == Regex::Null && derivatives.d.iter().all(|&(_, ref re)| *re == Regex::Null)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokens::TokenDef;
+
+    fn automaton() -> Automaton {
+        Automaton::new(vec![
+            TokenDef::new("Arrow", "->"),
+            TokenDef::new("Minus", "-"),
+            TokenDef::new("Ident", "[a-z]+"),
+            TokenDef::new("If", "if"),
+        ]).unwrap()
+    }
+
+    #[test]
+    fn explain_shows_losing_tokens() {
+        let explanation = explain(&automaton(), "if");
+
+        assert!(explanation.contains("0..2 If \"if\""));
+        assert!(explanation.contains("also matched: Ident"));
+    }
+
+    #[test]
+    fn explain_shows_tokens_alive_at_cut_off() {
+        let explanation = explain(&automaton(), "-=");
+
+        assert!(explanation.contains("0..1 Minus \"-\""));
+        assert!(explanation.contains("cut off at '='; still alive: Arrow"));
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! The `char`'s that can begin a match of a regex.
+//!
+//! The first set of a regex is found from its derivatives: a `char` can begin a match if the
+//! derivative of the regex with respect to it is not `Regex::Null`. A driver can use the first
+//! set of the tokens to skip ahead to a plausible start of a token, and a diagnostic can use it
+//! to describe the `char`'s that were expected.
+
+use std::collections::BTreeMap;
+
+use redfa::Regex;
+use redfa::derivatives::Differentiable;
+use redfa::dfa::Normalize;
+
+use coverage::{format_ranges, push_range, MAX_CHAR};
+
+/// The ranges of `char`'s (in order) that can begin a match of `regex`.
+pub fn first_set(regex: &Regex<char>) -> Vec<(char, char)> {
+    let derivatives = regex.derivative().map(|re| vec![re].normalize().remove(0));
+    let mut listed = BTreeMap::new();
+    for &(ref cs, ref re) in derivatives.d.iter() {
+        for &c in cs.iter() {
+            listed.insert(c, *re != Regex::Null);
+        }
+    }
+    let others = derivatives.rest != Regex::Null;
+
+    let mut ranges = Vec::new();
+    let mut next = 0;
+    for (&c, &live) in listed.iter() {
+        if others && next < c as u32 {
+            push_range(&mut ranges, next, c as u32 - 1);
+        }
+        if live {
+            push_range(&mut ranges, c as u32, c as u32);
+        }
+        next = c as u32 + 1;
+    }
+    if others && next <= MAX_CHAR {
+        push_range(&mut ranges, next, MAX_CHAR);
+    }
+
+    ranges
+}
+
+/// Describes the first set of `regex` as the `char`'s that were expected.
+pub fn expected(regex: &Regex<char>) -> String {
+    match first_set(regex) {
+        ref ranges if ranges.is_empty() => "expected nothing".to_string(),
+        ref ranges => format!("expected one of {}", format_ranges(ranges)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use debug::parse_regex;
+
+    #[test]
+    fn first_set_has_chars_that_begin_a_match() {
+        let regex = parse_regex("[a-c]x|(0|9)*;").unwrap();
+
+        assert_eq!(
+            first_set(&regex),
+            vec![('0', '0'), ('9', '9'), (';', ';'), ('a', 'c')]
+        );
+        assert_eq!(expected(&regex), "expected one of '0' '9' ';' 'a'-'c'");
+    }
+
+    #[test]
+    fn first_set_of_negated_class_has_other_chars() {
+        let regex = parse_regex("[^b-y]").unwrap();
+
+        assert_eq!(
+            first_set(&regex),
+            vec![('\0', 'a'), ('z', '\u{10FFFF}')]
+        );
+    }
+}
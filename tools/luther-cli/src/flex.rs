@@ -0,0 +1,533 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+use std::fmt::Write;
+use std::iter::Peekable;
+
+use quicli::prelude::*;
+
+use render::literal;
+use syntax::Syntax;
+use tokens::TokenDef;
+
+/// A flex file translated into luther token definitions.
+#[derive(Debug, Default)]
+pub struct FlexFile {
+    /// The named definitions (which become luther fragments).
+    pub definitions: Vec<(String, String)>,
+    pub rules: Vec<FlexRule>,
+
+    /// The parts of the flex file that could not be translated.
+    pub warnings: Vec<String>,
+}
+
+/// A rule from the rules section of a flex file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlexRule {
+    /// The name of the token (from the `return` in the action or made up).
+    pub name: String,
+
+    /// The pattern translated into the luther regex syntax.
+    pub regex: String,
+
+    /// The position of the rule in the flex file (earlier rules have priority).
+    pub priority: u32,
+
+    /// The start condition of the rule (which becomes a luther mode).
+    pub mode: Option<String>,
+
+    /// The start condition that the action switches to with `BEGIN`.
+    pub begin: Option<String>,
+
+    /// Whether the action does not return a token (so the match is skipped).
+    pub skip: bool,
+}
+
+/// Parses the definitions and rules sections of a flex file.
+///
+/// The C code in the flex file (the user code section, `%{ %}` blocks, and the actions of
+/// the rules) is ignored except for a `return` of a token name (which names the token) and
+/// a `BEGIN` (which switches the lexer mode). Rules with the same token name and start
+/// condition are combined. Features of flex that have no equivalent in luther are reported
+/// in `warnings`.
+pub fn parse(source: &str) -> Result<FlexFile> {
+    let mut flex = FlexFile::default();
+    let mut lines = source.lines().enumerate();
+    let mut in_code = false;
+
+    // definitions section
+    while let Some((num, line)) = lines.next() {
+        if line.starts_with("%%") {
+            break;
+        } else if line.starts_with("%{") {
+            in_code = true;
+        } else if line.starts_with("%}") {
+            in_code = false;
+        } else if in_code || line.trim().is_empty() || starts_with_whitespace(line) {
+            continue;
+        } else if line.starts_with("%s") {
+            flex.warnings.push(format!(
+                "line {}: inclusive start conditions are treated as exclusive modes",
+                num + 1
+            ));
+        } else if line.starts_with('%') || line.starts_with("/*") {
+            continue;
+        } else {
+            let name_len = line.find(char::is_whitespace).unwrap_or(line.len());
+            let (regex, warnings) = translate(line[name_len..].trim());
+            flex.definitions.push((line[..name_len].to_string(), regex));
+            flex.warnings
+                .extend(warnings.into_iter().map(|w| format!("line {}: {}", num + 1, w)));
+        }
+    }
+
+    // rules section
+    let mut pending: Vec<(usize, Option<String>, String)> = Vec::new();
+    while let Some((num, line)) = lines.next() {
+        if line.starts_with("%%") {
+            break;
+        } else if line.starts_with("%{") {
+            in_code = true;
+            continue;
+        } else if line.starts_with("%}") {
+            in_code = false;
+            continue;
+        } else if in_code || line.trim().is_empty() || starts_with_whitespace(line) {
+            continue;
+        }
+
+        let (condition, rest) = split_condition(line);
+        if rest.starts_with("<<EOF>>") {
+            flex.warnings
+                .push(format!("line {}: <<EOF>> rules are ignored", num + 1));
+            continue;
+        }
+
+        let pattern_len = pattern_len(rest);
+        let mut action = rest[pattern_len..].trim().to_string();
+        let mut depth = brace_depth(&action);
+        while depth > 0 {
+            match lines.next() {
+                Some((_, line)) => {
+                    action.push('\n');
+                    action.push_str(line);
+                    depth += brace_depth(line);
+                }
+                None => break,
+            }
+        }
+
+        let mode = match condition {
+            Some(ref conditions) if conditions.contains(',') || conditions == "*" => {
+                flex.warnings.push(format!(
+                    "line {}: only the first start condition of <{}> is used",
+                    num + 1,
+                    conditions
+                ));
+                conditions.split(',').next().map(|s| s.trim().to_string())
+            }
+            condition => condition,
+        };
+
+        pending.push((num, mode, rest[..pattern_len].to_string()));
+        if action == "|" {
+            continue;
+        }
+
+        for (num, mode, pattern) in pending.drain(..) {
+            let (regex, warnings) = translate(&pattern);
+            flex.warnings
+                .extend(warnings.into_iter().map(|w| format!("line {}: {}", num + 1, w)));
+            add_rule(&mut flex.rules, regex, mode, &action);
+        }
+    }
+
+    if flex.rules.is_empty() {
+        bail!("the flex file does not have any rules");
+    }
+
+    Ok(flex)
+}
+
+impl FlexFile {
+    /// Renders the rules as a Rust `enum` that derives `luther::Lexer`.
+    pub fn to_rust(&self) -> String {
+        let mut rust = String::new();
+
+        for warning in self.warnings.iter() {
+            writeln!(rust, "// warning: {}", warning).unwrap();
+        }
+        writeln!(rust, "#[derive(Lexer, Debug)]").unwrap();
+        if !self.definitions.is_empty() {
+            let fragments: Vec<_> = self.definitions
+                .iter()
+                .map(|&(ref name, ref regex)| format!("    {} = {:?}", name, regex))
+                .collect();
+            writeln!(rust, "#[luther(fragment(\n{}\n))]", fragments.join(",\n")).unwrap();
+        }
+        writeln!(rust, "pub enum Token {{").unwrap();
+
+        for rule in self.rules.iter() {
+            let mut options = vec![
+                format!("regex = {:?}", rule.regex),
+                format!("priority_group = \"{}\"", rule.priority),
+            ];
+            if let Some(ref mode) = rule.mode {
+                options.push(format!("mode = {:?}", mode));
+            }
+            if let Some(ref begin) = rule.begin {
+                options.push(format!("set_mode = {:?}", begin));
+            }
+            if rule.skip {
+                options.push("skip".to_string());
+            }
+
+            writeln!(rust, "    #[luther({})]", options.join(", ")).unwrap();
+            writeln!(rust, "    {},", rule.name).unwrap();
+        }
+
+        writeln!(rust, "}}").unwrap();
+        rust
+    }
+
+    /// The rules as token definitions (with the definitions expanded in place).
+    ///
+    /// The start conditions become modes and the rules without a `return` are skipped, but the
+    /// `BEGIN`'s and the rest of the actions are lost.
+    pub fn to_tokens(&self) -> Vec<TokenDef> {
+        self.rules
+            .iter()
+            .map(|rule| TokenDef {
+                name: rule.name.clone(),
+                regex: self.expand(&rule.regex),
+                priority_group: rule.priority,
+                mode: rule.mode.clone(),
+                skip: rule.skip,
+                word_boundary: false,
+                min_len: None,
+                max_len: None,
+                groups: Vec::new(),
+                syntax: Syntax::default(),
+            })
+            .collect()
+    }
+
+    // Expands the references to definitions in regex (which have already been translated
+    // so that any other braces are escaped).
+    fn expand(&self, regex: &str) -> String {
+        let mut expanded = regex.to_string();
+        for _ in 0..self.definitions.len() {
+            for &(ref name, ref definition) in self.definitions.iter() {
+                expanded = expanded.replace(&format!("{{{}}}", name), &format!("({})", definition));
+            }
+        }
+        expanded
+    }
+}
+
+fn add_rule(rules: &mut Vec<FlexRule>, regex: String, mode: Option<String>, action: &str) {
+    let priority = rules.len() as u32;
+    let begin = begin_condition(action);
+    let (name, skip) = match returned_token(action) {
+        Some(token) => (camel_case(&token), false),
+        None => (format!("Skip{}", priority), true),
+    };
+
+    let existing = rules
+        .iter_mut()
+        .find(|rule| rule.name == name && rule.mode == mode && rule.begin == begin);
+    match existing {
+        Some(rule) => rule.regex = format!("({})|({})", rule.regex, regex),
+        None => rules.push(FlexRule {
+            name,
+            regex,
+            priority,
+            mode,
+            begin,
+            skip,
+        }),
+    }
+}
+
+/// Translates a flex pattern into the luther regex syntax.
+///
+/// Quoted strings, `.`, POSIX character classes, and `{n,m}` repetitions are rewritten.
+/// References to definitions are kept as luther fragments. Anchors and trailing context
+/// have no equivalent in luther and are dropped with a warning.
+pub fn translate(pattern: &str) -> (String, Vec<String>) {
+    let mut regex = String::new();
+    let mut warnings = Vec::new();
+    let mut groups = Vec::new();
+    let mut last_atom = 0;
+    let mut chars = pattern.chars().peekable();
+    let mut first = true;
+
+    while let Some(c) = chars.next() {
+        let atom_start = regex.len();
+        match c {
+            '\\' => {
+                regex.push('\\');
+                regex.extend(chars.next());
+            }
+            '"' => {
+                let mut quoted = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => quoted.push(unescape(chars.next().unwrap_or('\\'))),
+                        c => quoted.push(c),
+                    }
+                }
+                regex.push('(');
+                regex.extend(quoted.chars().map(literal));
+                regex.push(')');
+            }
+            '[' => translate_class(&mut chars, &mut regex),
+            '.' => regex.push_str("[^\\n]"),
+            '{' => {
+                let mut inner = String::new();
+                while let Some(c) = chars.next() {
+                    if c == '}' {
+                        break;
+                    }
+                    inner.push(c);
+                }
+
+                if inner.starts_with(|c: char| c.is_digit(10)) {
+                    let atom = regex[last_atom..].to_string();
+                    regex.truncate(last_atom);
+                    regex.push_str(&repeat(&atom, &inner));
+                    first = false;
+                    continue;
+                } else {
+                    write!(regex, "{{{}}}", inner).unwrap();
+                }
+            }
+            '(' => {
+                groups.push(regex.len());
+                regex.push('(');
+            }
+            ')' => {
+                regex.push(')');
+                last_atom = groups.pop().unwrap_or(0);
+                first = false;
+                continue;
+            }
+            '*' | '+' | '?' | '|' => {
+                regex.push(c);
+                first = false;
+                continue;
+            }
+            '^' if first => warnings.push("the ^ anchor is not supported".to_string()),
+            '$' if chars.peek().is_none() => {
+                warnings.push("the $ anchor is not supported".to_string())
+            }
+            '/' => {
+                warnings.push("trailing context is not supported".to_string());
+                break;
+            }
+            c => regex.push_str(&literal(c)),
+        }
+        last_atom = atom_start;
+        first = false;
+    }
+
+    (regex, warnings)
+}
+
+fn translate_class<I>(chars: &mut Peekable<I>, regex: &mut String)
+where
+    I: Iterator<Item = char>,
+{
+    regex.push('[');
+    if chars.peek() == Some(&'^') {
+        chars.next();
+        regex.push('^');
+    }
+    if chars.peek() == Some(&']') {
+        chars.next();
+        regex.push_str("\\]");
+    }
+
+    while let Some(c) = chars.next() {
+        match c {
+            ']' => break,
+            '\\' => {
+                regex.push('\\');
+                regex.extend(chars.next());
+            }
+            '[' if chars.peek() == Some(&':') => {
+                let name: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                regex.push_str(posix_class(name.trim_matches(':')));
+            }
+            c => regex.push(c),
+        }
+    }
+
+    regex.push(']');
+}
+
+fn posix_class(name: &str) -> &'static str {
+    match name {
+        "alpha" => "a-zA-Z",
+        "digit" => "0-9",
+        "alnum" => "a-zA-Z0-9",
+        "upper" => "A-Z",
+        "lower" => "a-z",
+        "xdigit" => "0-9a-fA-F",
+        "space" => " \\t\\n\\r\\x0b\\x0c",
+        "blank" => " \\t",
+        "punct" => "!-/:-@\\[-`{-~",
+        "cntrl" => "\\x00-\\x1f\\x7f",
+        "print" => " -~",
+        "graph" => "!-~",
+        _ => "",
+    }
+}
+
+// Repeats atom according to the flex repetition bounds ("n", "n,", or "n,m").
+fn repeat(atom: &str, bounds: &str) -> String {
+    let mut parts = bounds.splitn(2, ',');
+    let min: usize = parts.next().and_then(|n| n.trim().parse().ok()).unwrap_or(0);
+    let max = parts.next().map(|m| m.trim().parse::<usize>().ok());
+
+    let atom = format!("({})", atom);
+    let mut repeated = atom.repeat(min);
+    match max {
+        None => {}
+        Some(None) => write!(repeated, "{}*", atom).unwrap(),
+        Some(Some(max)) => {
+            for _ in min..max {
+                write!(repeated, "{}?", atom).unwrap();
+            }
+        }
+    }
+    repeated
+}
+
+fn unescape(c: char) -> char {
+    match c {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        c => c,
+    }
+}
+
+fn starts_with_whitespace(line: &str) -> bool {
+    line.starts_with(char::is_whitespace)
+}
+
+// Splits a leading <start condition> from line.
+fn split_condition(line: &str) -> (Option<String>, &str) {
+    if line.starts_with('<') && !line.starts_with("<<EOF>>") {
+        if let Some(end) = line.find('>') {
+            return (Some(line[1..end].to_string()), &line[end + 1..]);
+        }
+    }
+    (None, line)
+}
+
+// The length of the pattern at the start of line (up to the first unquoted whitespace).
+fn pattern_len(line: &str) -> usize {
+    let mut in_quotes = false;
+    let mut in_class = false;
+    let mut escaped = false;
+
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' if !in_class => in_quotes = !in_quotes,
+            '[' if !in_quotes => in_class = true,
+            ']' if !in_quotes => in_class = false,
+            c if c.is_whitespace() && !in_quotes && !in_class => return i,
+            _ => {}
+        }
+    }
+    line.len()
+}
+
+fn brace_depth(code: &str) -> i32 {
+    code.chars().fold(0, |depth, c| match c {
+        '{' => depth + 1,
+        '}' => depth - 1,
+        _ => depth,
+    })
+}
+
+fn returned_token(action: &str) -> Option<String> {
+    action.find("return").and_then(|start| {
+        let token: String = action[start + "return".len()..]
+            .trim_left_matches(|c: char| c.is_whitespace() || c == '(')
+            .chars()
+            .take_while(|&c| c.is_alphanumeric() || c == '_')
+            .collect();
+        if token.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+            Some(token)
+        } else {
+            None
+        }
+    })
+}
+
+fn begin_condition(action: &str) -> Option<String> {
+    action.find("BEGIN").map(|start| {
+        let condition: String = action[start + "BEGIN".len()..]
+            .trim_left_matches(|c: char| c.is_whitespace() || c == '(')
+            .chars()
+            .take_while(|&c| c.is_alphanumeric() || c == '_')
+            .collect();
+        if condition == "0" {
+            "INITIAL".to_string()
+        } else {
+            condition
+        }
+    })
+}
+
+fn camel_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .into_iter()
+                .flat_map(|c| c.to_uppercase())
+                .chain(chars.flat_map(|c| c.to_lowercase()))
+                .collect::<String>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn translate_rewrites_flex_syntax() {
+        assert_eq!(translate("\"+=\"").0, "(\\+=)");
+        assert_eq!(translate("a.").0, "a[^\\n]");
+        assert_eq!(translate("[[:digit:]]+").0, "[0-9]+");
+        assert_eq!(translate("a{2,3}").0, "(a)(a)(a)?");
+        assert_eq!(translate("{digit}+").0, "{digit}+");
+    }
+
+    #[test]
+    fn parse_names_rules_from_return() {
+        let source = "digit [0-9]\n%%\n{digit}+ { return INT_LIT; }\n[ \\t]+ ;\n%%\n";
+
+        let flex = parse(source).expect("unexpected error parsing the flex file");
+
+        assert_eq!(flex.definitions, vec![("digit".to_string(), "[0-9]".to_string())]);
+        assert_eq!(flex.rules[0].name, "IntLit");
+        assert!(!flex.rules[0].skip);
+        assert!(flex.rules[1].skip);
+    }
+}
@@ -0,0 +1,220 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Case folding of a compiled automaton.
+//!
+//! `case_fold` makes a case insensitive variant of an `Automaton` without recompiling the
+//! token regexes. The folded automaton treats each `char` like any of its case variants (its
+//! single `char` lower and upper case mappings). Its states are the sets of states of the
+//! original automaton that the case variants of an input can reach, so a folded state accepts
+//! the best of the tokens that its states accept.
+
+use std::char;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::result::Result as StdResult;
+
+use redfa;
+
+use automaton::{best_token, Automaton};
+use error::CompileError;
+
+/// Makes a case insensitive variant of `automaton`.
+///
+/// It is an error if a state of the folded automaton accepts more than one token of the same
+/// priority (for example for the tokens `if` and `IF`). The regexes of the folded automaton
+/// (in `Automaton::regexs`) are the original case sensitive ones.
+pub fn case_fold(automaton: &Automaton) -> StdResult<Automaton, CompileError> {
+    let inverse = inverse_variants();
+    let mut sets = StateSets::default();
+
+    let start_states: Vec<_> = automaton
+        .start_states
+        .iter()
+        .map(|&state| sets.number(automaton, vec![state]) as usize)
+        .collect();
+    let line_start_states: Vec<_> = automaton
+        .line_start_states
+        .iter()
+        .map(|&state| sets.number(automaton, vec![state]) as usize)
+        .collect();
+    let error_state = sets.number(automaton, Vec::new()) as usize;
+
+    let mut states = Vec::new();
+    let mut unconditional_accepts = Vec::new();
+    let mut contributors = Vec::new();
+    while let Some(set) = sets.queue.pop_front() {
+        let mut chars = BTreeSet::new();
+        for &state in set.iter() {
+            for &c in automaton.dfa.states[state].by_char.keys() {
+                chars.insert(c);
+                chars.extend(variants(c));
+                chars.extend(inverse.get(&c).into_iter().flat_map(|cs| cs.iter().cloned()));
+            }
+        }
+
+        let mut by_char = BTreeMap::new();
+        for c in chars {
+            let to = set.iter()
+                .flat_map(|&state| {
+                    variants(c)
+                        .into_iter()
+                        .map(move |v| automaton.transition(state, v))
+                })
+                .collect();
+            by_char.insert(c, sets.number(automaton, to));
+        }
+        let default = set.iter()
+            .map(|&state| automaton.dfa.states[state].default as usize)
+            .collect();
+        let default = sets.number(automaton, default);
+
+        let accepts: Vec<_> = set.iter()
+            .filter_map(|&state| automaton.dfa.states[state].value)
+            .collect();
+        let unconditional: Vec<_> = set.iter()
+            .filter_map(|&state| automaton.unconditional_accepts[state])
+            .collect();
+        let value = best(automaton, &accepts)?;
+        unconditional_accepts.push(best(automaton, &unconditional)?);
+
+        let mut contributing: Vec<_> = set.iter()
+            .flat_map(|&state| automaton.contributors[state].iter().cloned())
+            .collect();
+        contributing.sort();
+        contributing.dedup();
+        contributors.push(contributing);
+
+        states.push(redfa::dfa::State {
+            by_char,
+            default,
+            value,
+        });
+    }
+
+    Ok(Automaton {
+        tokens: automaton.tokens.clone(),
+        regexs: automaton.regexs.clone(),
+        dfa: redfa::Dfa { states },
+        modes: automaton.modes.clone(),
+        start_state: start_states[0],
+        start_states,
+        line_start_states,
+        end_anchored: automaton.end_anchored.clone(),
+        unconditional_accepts,
+        contributors,
+        simple_strings: automaton.simple_strings.clone(),
+        anchors: automaton.anchors,
+        error_state,
+    })
+}
+
+// The sets of states of the original automaton that are the states of the folded automaton,
+// numbered in the order they are found, with a queue of the sets whose transitions are not
+// yet known.
+#[derive(Default)]
+struct StateSets {
+    numbers: HashMap<Vec<usize>, u32>,
+    queue: VecDeque<Vec<usize>>,
+}
+
+impl StateSets {
+    fn number(&mut self, automaton: &Automaton, mut set: Vec<usize>) -> u32 {
+        set.retain(|&state| state != automaton.error_state);
+        set.sort();
+        set.dedup();
+
+        if let Some(&number) = self.numbers.get(&set) {
+            return number;
+        }
+        let number = self.numbers.len() as u32;
+        self.numbers.insert(set.clone(), number);
+        self.queue.push_back(set);
+        number
+    }
+}
+
+fn best(automaton: &Automaton, candidates: &[usize]) -> StdResult<Option<usize>, CompileError> {
+    let mut candidates = candidates.to_vec();
+    candidates.sort();
+    candidates.dedup();
+
+    best_token(&candidates, &automaton.tokens, &automaton.simple_strings).map_err(
+        |(first, second)| {
+            CompileError::AmbiguousTokens(
+                automaton.tokens[first].name.clone(),
+                automaton.tokens[second].name.clone(),
+            )
+        },
+    )
+}
+
+// The case variants of c: c and its single char lower and upper case mappings.
+fn variants(c: char) -> Vec<char> {
+    let mut variants = vec![c];
+    for mapped in [single(c.to_lowercase()), single(c.to_uppercase())].iter() {
+        if let &Some(mapped) = mapped {
+            if !variants.contains(&mapped) {
+                variants.push(mapped);
+            }
+        }
+    }
+    variants
+}
+
+fn single<I: Iterator<Item = char>>(mut chars: I) -> Option<char> {
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+// The chars that have each char as a case variant (other than itself).
+fn inverse_variants() -> HashMap<char, Vec<char>> {
+    let mut inverse = HashMap::new();
+    for c in (0..char::MAX as u32 + 1).filter_map(char::from_u32) {
+        for v in variants(c).into_iter().skip(1) {
+            inverse.entry(v).or_insert_with(Vec::new).push(c);
+        }
+    }
+    inverse
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokens::TokenDef;
+
+    #[test]
+    fn case_fold_matches_any_case() {
+        let tokens = vec![TokenDef::new("Ident", "[a-z]+"), TokenDef::new("If", "if")];
+        let automaton = Automaton::new(tokens).expect("unexpected error building the automaton");
+
+        let sut = case_fold(&automaton).expect("unexpected error folding the automaton");
+
+        assert_eq!(sut.longest_match("IF x"), Some((1, 2)));
+        assert_eq!(sut.longest_match("iF"), Some((1, 2)));
+        assert_eq!(sut.longest_match("AbC1"), Some((0, 3)));
+        assert_eq!(sut.longest_match("1"), None);
+        assert_eq!(automaton.longest_match("IF"), None);
+    }
+
+    #[test]
+    fn case_fold_rejects_tokens_that_differ_only_in_case() {
+        let tokens = vec![TokenDef::new("Lower", "if"), TokenDef::new("Upper", "IF")];
+        let automaton = Automaton::new(tokens).expect("unexpected error building the automaton");
+
+        let result = case_fold(&automaton);
+
+        match result {
+            Err(CompileError::AmbiguousTokens(ref first, ref second)) => {
+                assert_eq!((first.as_str(), second.as_str()), ("Lower", "Upper"))
+            }
+            _ => panic!("unexpected result {:?}", result.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,167 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Export of a compiled automaton in the AT&T text format used by OpenFST.
+//!
+//! The transitions of an automaton are labeled with sets of `char`'s, so each distinct set is
+//! given a symbol in a separate symbol table (with `<eps>` as symbol 0 and `<other>` for the
+//! default transitions). The text and the symbol table can be compiled into an acceptor with:
+//!
+//! ```text
+//! fstcompile --acceptor --isymbols=tokens.syms tokens.txt tokens.fst
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::result::Result as StdResult;
+
+use automaton::Automaton;
+use binary::check;
+use error::ExportError;
+
+/// The name of the symbol for the default transitions (the `char`'s not otherwise listed).
+pub const OTHER: &str = "<other>";
+
+/// Formats `automaton` as AT&T text: a `src dst label` line for each transition (starting with
+/// the transitions of the start state) followed by a line with each accepting state.
+///
+/// It is an error if a token cannot be exported (see `binary::check`).
+pub fn to_fst_text(automaton: &Automaton) -> StdResult<String, ExportError> {
+    check(automaton)?;
+    let mut text = String::new();
+    for state in states(automaton) {
+        for (to, label) in labels(automaton, state) {
+            writeln!(text, "{} {} {}", state, to, label).unwrap();
+        }
+    }
+    for state in states(automaton) {
+        if automaton.dfa.states[state].value.is_some() {
+            writeln!(text, "{}", state).unwrap();
+        }
+    }
+
+    Ok(text)
+}
+
+/// Formats the symbol table for the labels used by `to_fst_text`.
+pub fn to_fst_symbols(automaton: &Automaton) -> String {
+    let symbols = Symbols::new(automaton);
+    let mut text = String::new();
+
+    for (num, symbol) in symbols.names.iter().enumerate() {
+        writeln!(text, "{} {}", symbol, num).unwrap();
+    }
+
+    text
+}
+
+struct Symbols {
+    names: Vec<String>,
+    numbers: HashMap<String, usize>,
+}
+
+impl Symbols {
+    fn new(automaton: &Automaton) -> Symbols {
+        let mut symbols = Symbols {
+            names: Vec::new(),
+            numbers: HashMap::new(),
+        };
+
+        symbols.add("<eps>".to_string());
+        for state in states(automaton) {
+            for (_, label) in labels(automaton, state) {
+                symbols.add(label);
+            }
+        }
+
+        symbols
+    }
+
+    fn add(&mut self, name: String) {
+        if !self.numbers.contains_key(&name) {
+            self.numbers.insert(name.clone(), self.names.len());
+            self.names.push(name);
+        }
+    }
+}
+
+// The states other than the error state, starting with the start state.
+fn states(automaton: &Automaton) -> Vec<usize> {
+    let mut states = vec![automaton.start_state];
+    states.extend(
+        (0..automaton.dfa.states.len())
+            .filter(|&state| state != automaton.start_state && state != automaton.error_state),
+    );
+    states
+}
+
+// The labels of the transitions out of state (that do not go to the error state), grouped by
+// their target.
+pub(crate) fn labels(automaton: &Automaton, state: usize) -> Vec<(u32, String)> {
+    let classes: Vec<_> = automaton
+        .state_classes(state)
+        .into_iter()
+        .filter(|class| class.to != automaton.error_state)
+        .collect();
+
+    let mut labels: Vec<_> = classes
+        .iter()
+        .filter(|class| !class.ranges.is_empty())
+        .map(|class| (class.to as u32, label(&class.ranges)))
+        .collect();
+    labels.extend(
+        classes
+            .iter()
+            .filter(|class| class.other)
+            .map(|class| (class.to as u32, OTHER.to_string())),
+    );
+
+    labels
+}
+
+// A label for a set of ranges that has no whitespace (which would split an AT&T text field).
+fn label(ranges: &[(char, char)]) -> String {
+    let mut label = String::from("[");
+    for &(first, last) in ranges {
+        label.push_str(&symbol_char(first));
+        if last != first {
+            label.push('-');
+            label.push_str(&symbol_char(last));
+        }
+    }
+    label.push(']');
+    label
+}
+
+fn symbol_char(c: char) -> String {
+    match c {
+        '\\' | '[' | ']' | '-' => format!("\\{}", c),
+        c if c.is_whitespace() || c.is_control() => format!("\\u{{{:x}}}", c as u32),
+        c => c.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokens::TokenDef;
+
+    #[test]
+    fn fst_text_labels_transitions_with_ranges() {
+        let automaton = Automaton::new(vec![TokenDef::new("Ident", "[a-z][a-z0-9 ]*")]).unwrap();
+
+        let text = to_fst_text(&automaton).unwrap();
+        let symbols = to_fst_symbols(&automaton);
+
+        let start = automaton.start_state;
+        assert!(text.starts_with(&format!("{} ", start)));
+        assert!(text.lines().next().unwrap().ends_with(" [a-z]"));
+        assert!(symbols.starts_with("<eps> 0\n[a-z] 1\n"));
+        assert!(symbols.contains("[\\u{20}0-9a-z]"));
+    }
+}
@@ -0,0 +1,310 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Entry points for fuzzing the compilation of token definitions (for example with
+//! `cargo fuzz`) and helpers for minimizing the failures that are found.
+//!
+//! The entry points never panic for errors in their input (such as an invalid regex) so
+//! any panic that occurs is a bug in the compilation or the matching.
+//!
+//! `reproduce` checks the dfa against a reference matcher that takes the derivatives of the
+//! regexes directly, and shrinks a disagreement into a self-contained `Reproduction` (the
+//! fewest and shortest token definitions and the shortest input that still disagree) for a
+//! bug report.
+
+use std::fmt;
+use std::str;
+
+use redfa::Regex;
+
+use automaton::{best_token, Automaton};
+use debug::derivative;
+use limits::Limits;
+use tokens::{TokenDef, TokenFile};
+
+/// The largest number of token definitions that `compile_and_match` will compile.
+const MAX_TOKENS: usize = 8;
+
+/// Parses, compiles, and then matches with the token definitions in `data`.
+///
+/// The first line of `data` is the input to match and each following line is the regex for
+/// a token. Input that is not utf8 or that does not make a valid set of token definitions
+/// (within the default `Limits`) is ignored. The input is lexed (by repeatedly taking the
+/// longest match) until no token matches.
+pub fn compile_and_match(data: &[u8]) {
+    let text = match str::from_utf8(data) {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+
+    let mut lines = text.lines();
+    let mut input = lines.next().unwrap_or("");
+    let tokens: Vec<_> = lines
+        .take(MAX_TOKENS)
+        .enumerate()
+        .map(|(i, regex)| TokenDef::new(format!("Token{}", i), regex))
+        .collect();
+    if tokens.is_empty() {
+        return;
+    }
+
+    let automaton = match Automaton::with_limits(tokens, &Limits::default()) {
+        Ok(automaton) => automaton,
+        Err(_) => return,
+    };
+
+    while let Some((_, len)) = automaton.longest_match(input) {
+        input = &input[len..];
+    }
+}
+
+/// Minimizes `regex` while `fails` continues to return true for it.
+///
+/// This repeatedly removes chunks of `chars` (starting with large chunks) from `regex` and
+/// keeps the result if it still fails. The result is a regex for which removing any single
+/// `char` no longer fails. `fails` should return true for `regex` itself.
+pub fn minimize<F>(regex: &str, mut fails: F) -> String
+where
+    F: FnMut(&str) -> bool,
+{
+    let mut current: Vec<char> = regex.chars().collect();
+    let mut chunk = current.len() / 2;
+
+    while chunk > 0 {
+        let mut start = 0;
+        let mut reduced = false;
+
+        while start < current.len() {
+            let end = (start + chunk).min(current.len());
+            let candidate: String = current[..start]
+                .iter()
+                .chain(current[end..].iter())
+                .collect();
+
+            if fails(&candidate) {
+                current = candidate.chars().collect();
+                reduced = true;
+            } else {
+                start += chunk;
+            }
+        }
+
+        if !reduced {
+            chunk /= 2;
+        }
+    }
+
+    current.into_iter().collect()
+}
+
+/// The first place where the dfa and the reference matcher lex an input differently.
+///
+/// Each match is the token and the length (in bytes) of the longest match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Disagreement {
+    /// The byte offset of the match.
+    pub offset: usize,
+
+    /// The match by the dfa.
+    pub dfa: Option<(usize, usize)>,
+
+    /// The match by the reference matcher.
+    pub reference: Option<(usize, usize)>,
+}
+
+/// A minimized reproduction of a disagreement between the dfa and the reference matcher.
+#[derive(Debug)]
+pub struct Reproduction {
+    /// The token definitions.
+    pub tokens: Vec<TokenDef>,
+
+    /// The input.
+    pub input: String,
+
+    /// Where the dfa and the reference matcher disagree on `input`.
+    pub disagreement: Disagreement,
+}
+
+impl fmt::Display for Reproduction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = |found: Option<(usize, usize)>| {
+            found.map(|(token, len)| format!("{} ({} bytes)", self.tokens[token].name, len))
+        };
+        let file = TokenFile {
+            syntax: Default::default(),
+            token: self.tokens.clone(),
+        };
+
+        writeln!(f, "input: {:?}", self.input)?;
+        writeln!(
+            f,
+            "at byte {} the dfa matches {:?} but the reference matches {:?}",
+            self.disagreement.offset,
+            name(self.disagreement.dfa),
+            name(self.disagreement.reference)
+        )?;
+        writeln!(f)?;
+        write!(f, "{}", ::toml::to_string(&file).map_err(|_| fmt::Error)?)
+    }
+}
+
+/// Lexes `input` with both the dfa for `tokens` and the reference matcher and returns the
+/// first place where they differ.
+///
+/// This is `None` if they agree or if `tokens` do not compile. The reference matcher does
+/// not know about anchors, `word_boundary`, or lexer modes, so `tokens` should not use them.
+pub fn first_disagreement(tokens: &[TokenDef], input: &str) -> Option<Disagreement> {
+    let automaton = match Automaton::with_limits(tokens.to_vec(), &Limits::default()) {
+        Ok(automaton) => automaton,
+        Err(_) => return None,
+    };
+
+    let mut offset = 0;
+    while offset < input.len() {
+        let dfa = automaton.longest_match_at(input, offset);
+        let reference = reference_match(&automaton, &input[offset..]);
+        if dfa != reference {
+            return Some(Disagreement {
+                offset,
+                dfa,
+                reference,
+            });
+        }
+
+        offset += match dfa {
+            Some((_, len)) => len,
+            None => input[offset..].chars().next().unwrap().len_utf8(),
+        };
+    }
+
+    None
+}
+
+/// Finds a disagreement between the dfa and the reference matcher on `input` and minimizes
+/// it.
+///
+/// This is `None` if they agree (see `first_disagreement`). The minimizing is deterministic,
+/// so the same bug always gives the same reproduction.
+pub fn reproduce(tokens: &[TokenDef], input: &str) -> Option<Reproduction> {
+    first_disagreement(tokens, input)?;
+
+    let (tokens, input) = shrink(tokens, input, |tokens, input| {
+        first_disagreement(tokens, input).is_some()
+    });
+    let disagreement = first_disagreement(&tokens, &input)?;
+    Some(Reproduction {
+        tokens,
+        input,
+        disagreement,
+    })
+}
+
+/// Minimizes `tokens` and `input` while `fails` continues to return true for them.
+///
+/// The tokens that are not needed are removed first, then `input` and then the regex of each
+/// token are minimized (see `minimize`). `fails` should return true for `tokens` and `input`.
+pub fn shrink<F>(tokens: &[TokenDef], input: &str, mut fails: F) -> (Vec<TokenDef>, String)
+where
+    F: FnMut(&[TokenDef], &str) -> bool,
+{
+    let mut tokens = tokens.to_vec();
+    let mut i = 0;
+    while i < tokens.len() {
+        let mut fewer = tokens.clone();
+        fewer.remove(i);
+        if fails(&fewer, input) {
+            tokens = fewer;
+        } else {
+            i += 1;
+        }
+    }
+
+    let input = minimize(input, |input| fails(&tokens, input));
+
+    for i in 0..tokens.len() {
+        let regex = {
+            let mut candidate = tokens.clone();
+            minimize(&tokens[i].regex, |regex| {
+                candidate[i].regex = regex.to_string();
+                fails(&candidate, &input)
+            })
+        };
+        tokens[i].regex = regex;
+    }
+
+    (tokens, input)
+}
+
+// The longest match at the start of input by taking the derivatives of the regexes.
+fn reference_match(automaton: &Automaton, input: &str) -> Option<(usize, usize)> {
+    let mut regexs = automaton.regexs.clone();
+    let mut longest = None;
+
+    for (offset, c) in input.char_indices() {
+        regexs = regexs.iter().map(|re| derivative(re, c)).collect();
+        let nullable: Vec<_> = (0..regexs.len())
+            .filter(|&i| regexs[i].nullable())
+            .collect();
+        let best = best_token(&nullable, &automaton.tokens, &automaton.simple_strings);
+        if let Ok(Some(token)) = best {
+            longest = Some((token, offset + c.len_utf8()));
+        }
+        if regexs.iter().all(|re| *re == Regex::Null) {
+            break;
+        }
+    }
+
+    longest
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn compile_and_match_ignores_invalid_regex() {
+        compile_and_match(b"abc\n(a\n[a-z]+");
+    }
+
+    #[test]
+    fn reproduce_is_none_when_dfa_agrees() {
+        let tokens = vec![
+            TokenDef::new("If", "if"),
+            TokenDef::new("Ident", "[a-z]+"),
+            TokenDef::new("Int", "[0-9]+"),
+        ];
+
+        assert_eq!(first_disagreement(&tokens, "if x1 iffy 42"), None);
+        assert!(reproduce(&tokens, "if x1 iffy 42").is_none());
+    }
+
+    #[test]
+    fn shrink_removes_unneeded_tokens_and_input() {
+        let tokens = vec![
+            TokenDef::new("A", "a+"),
+            TokenDef::new("B", "b|bc"),
+            TokenDef::new("C", "c"),
+        ];
+        let fails = |tokens: &[TokenDef], input: &str| {
+            tokens.iter().any(|token| token.regex.contains('b')) && input.contains('x')
+        };
+
+        let (tokens, input) = shrink(&tokens, "aaxbc", fails);
+
+        assert_eq!(tokens.len(), 1);
+        assert_eq!((tokens[0].name.as_str(), tokens[0].regex.as_str()), ("B", "b"));
+        assert_eq!(input, "x");
+    }
+
+    #[test]
+    fn minimize_removes_unneeded_chars() {
+        let result = minimize("ab(cd)*ef", |re| re.contains("(c"));
+
+        assert_eq!(result, "(c");
+    }
+}
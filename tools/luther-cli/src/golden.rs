@@ -0,0 +1,191 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Golden file snapshots of compiled automata.
+//!
+//! A snapshot is a text rendering of an `Automaton` with its states numbered in the order in
+//! which they are reached from the start state (so that the numbering does not depend on the
+//! details of how the dfa was built). The first line of a snapshot has a hash of the rest of it
+//! so that a changed snapshot can be recognized quickly.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+
+use automaton::Automaton;
+use render::literal;
+
+/// Renders a snapshot of `automaton`.
+pub fn snapshot(automaton: &Automaton) -> String {
+    let order = automaton.bfs_order();
+    let numbers: HashMap<usize, usize> = order
+        .iter()
+        .enumerate()
+        .map(|(number, &state)| (state, number))
+        .collect();
+
+    let mut body = String::new();
+    for (number, &state) in order.iter().enumerate() {
+        let dfa_state = &automaton.dfa.states[state];
+        let result = match dfa_state.value {
+            Some(token) => writeln!(
+                body,
+                "S{} accepts {}",
+                number, automaton.tokens[token].name
+            ),
+            None => writeln!(body, "S{}", number),
+        };
+        result.unwrap();
+
+        for (c, &to) in dfa_state.by_char.iter() {
+            if to as usize != automaton.error_state {
+                writeln!(body, "  {} -> S{}", literal(*c), numbers[&(to as usize)]).unwrap();
+            }
+        }
+        if dfa_state.default as usize != automaton.error_state {
+            writeln!(body, "  other -> S{}", numbers[&(dfa_state.default as usize)]).unwrap();
+        }
+    }
+
+    format!(
+        "# luther dfa snapshot: {} states, hash {:016x}\n{}",
+        order.len(),
+        fnv_hash(&body),
+        body
+    )
+}
+
+/// Compares an `expected` snapshot with an `actual` one.
+///
+/// Returns `None` if they are the same and otherwise a readable description of the states
+/// that were added or removed and of the accepted tokens and transitions that changed.
+pub fn compare(expected: &str, actual: &str) -> Option<String> {
+    if expected == actual {
+        return None;
+    }
+
+    let expected = parse(expected);
+    let actual = parse(actual);
+    let mut diff = String::new();
+
+    for (state, expected_state) in expected.iter() {
+        match actual.get(state) {
+            None => writeln!(diff, "removed state {}", state).unwrap(),
+            Some(actual_state) => {
+                if expected_state.header != actual_state.header {
+                    writeln!(
+                        diff,
+                        "changed state: {} (was {})",
+                        actual_state.header, expected_state.header
+                    ).unwrap();
+                }
+                for removed in expected_state.transitions.difference(&actual_state.transitions) {
+                    writeln!(diff, "{}: removed transition {}", state, removed).unwrap();
+                }
+                for added in actual_state.transitions.difference(&expected_state.transitions) {
+                    writeln!(diff, "{}: added transition {}", state, added).unwrap();
+                }
+            }
+        }
+    }
+    for state in actual.keys().filter(|state| !expected.contains_key(*state)) {
+        writeln!(diff, "added state {}", actual[state].header).unwrap();
+    }
+
+    if diff.is_empty() {
+        diff.push_str("the snapshots differ only in their headers\n");
+    }
+    Some(diff)
+}
+
+struct SnapshotState {
+    header: String,
+    transitions: BTreeSet<String>,
+}
+
+fn parse(snapshot: &str) -> BTreeMap<String, SnapshotState> {
+    let mut states = BTreeMap::new();
+    let mut current: Option<String> = None;
+
+    for line in snapshot.lines().filter(|line| !line.starts_with('#')) {
+        if line.starts_with(' ') {
+            if let Some(ref state) = current {
+                states
+                    .get_mut(state)
+                    .map(|s: &mut SnapshotState| s.transitions.insert(line.trim().to_string()));
+            }
+        } else {
+            let name = line.split_whitespace().next().unwrap_or("").to_string();
+            states.insert(
+                name.clone(),
+                SnapshotState {
+                    header: line.to_string(),
+                    transitions: BTreeSet::new(),
+                },
+            );
+            current = Some(name);
+        }
+    }
+
+    states
+}
+
+// The 64 bit FNV-1a hash of s (which, unlike the std hashers, is stable between releases).
+fn fnv_hash(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokens::TokenDef;
+
+    fn automaton(tokens: Vec<TokenDef>) -> Automaton {
+        Automaton::new(tokens).expect("unexpected error building the automaton")
+    }
+
+    #[test]
+    fn snapshot_is_unchanged_for_same_tokens() {
+        let first = snapshot(&automaton(vec![TokenDef::new("Ab", "ab")]));
+        let second = snapshot(&automaton(vec![TokenDef::new("Ab", "ab")]));
+
+        assert_eq!(compare(&first, &second), None);
+    }
+
+    #[test]
+    fn snapshot_numbers_states_in_breadth_first_order() {
+        let tokens = vec![TokenDef::new("Ab", "ab"), TokenDef::new("Ac", "ac")];
+
+        let snapshot = snapshot(&automaton(tokens));
+
+        let body: Vec<_> = snapshot.lines().skip(1).collect();
+        assert_eq!(
+            body,
+            vec![
+                "S0",
+                "  a -> S1",
+                "S1",
+                "  b -> S2",
+                "  c -> S3",
+                "S2 accepts Ab",
+                "S3 accepts Ac",
+            ]
+        );
+    }
+
+    #[test]
+    fn compare_reports_added_states() {
+        let expected = snapshot(&automaton(vec![TokenDef::new("Ab", "ab")]));
+        let actual = snapshot(&automaton(vec![TokenDef::new("Ab", "abc")]));
+
+        let diff = compare(&expected, &actual).expect("snapshots unexpectedly the same");
+
+        assert!(diff.contains("added state S3 accepts Ab"));
+    }
+}
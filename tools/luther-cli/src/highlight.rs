@@ -0,0 +1,219 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Highlighting an input by the tokens that it lexes into.
+//!
+//! Each token is colored by its name through a `ColorMap`, a TOML table from token names to
+//! colors (such as `Ident = "blue"`). The output is either the input with ANSI escape codes
+//! for a terminal or an HTML `pre` element. A color for the ANSI output is one or more of the
+//! names `black`, `red`, `green`, `yellow`, `blue`, `magenta`, `cyan`, `white` (or their
+//! `bright-` variants) and `bold`, `dim`, `italic`, and `underline`, or the numbers of SGR
+//! codes, separated by spaces (as in `"bold bright-blue"` or `"38;5;208"`). A color for the
+//! HTML output is any CSS color.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use automaton::Automaton;
+use error::ColorError;
+use lex;
+
+/// The output format of `highlight`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// The input with ANSI escape codes around the tokens.
+    Ansi,
+
+    /// An HTML `pre` element with a `span` for each token.
+    Html,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "ansi" => Ok(Format::Ansi),
+            "html" => Ok(Format::Html),
+            s => Err(format!("{} is not a valid format (expected ansi or html)", s)),
+        }
+    }
+}
+
+/// The colors of the tokens, by token name.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColorMap {
+    colors: HashMap<String, String>,
+}
+
+impl ColorMap {
+    /// Parses a TOML table from token names to colors.
+    pub fn parse(text: &str) -> Result<ColorMap, ::toml::de::Error> {
+        ::toml::from_str(text).map(|colors| ColorMap { colors })
+    }
+
+    /// The color of the token named `token`, if it has one.
+    pub fn color(&self, token: &str) -> Option<&str> {
+        self.colors.get(token).map(|color| color.as_str())
+    }
+}
+
+/// Highlights `input` by the tokens that it lexes into.
+///
+/// The tokens that are marked `skip` are highlighted too. A token without a color, and a
+/// `char` that no token matches, are output as is.
+pub fn highlight(
+    automaton: &Automaton,
+    input: &str,
+    colors: &ColorMap,
+    format: Format,
+) -> Result<String, ColorError> {
+    let mut out = String::new();
+    if format == Format::Html {
+        out.push_str("<pre class=\"luther\">");
+    }
+
+    for token in lex::lex(automaton, input, true) {
+        let color = token
+            .kind
+            .as_ref()
+            .and_then(|kind| colors.color(kind).map(|color| (kind, color)));
+        match (format, color) {
+            (Format::Ansi, Some((kind, color))) => {
+                let code = ansi_code(color).ok_or_else(|| ColorError {
+                    token: kind.clone(),
+                    color: color.to_string(),
+                })?;
+                out.push_str(&format!("\x1b[{}m{}\x1b[0m", code, token.text));
+            }
+            (Format::Ansi, None) => out.push_str(&token.text),
+            (Format::Html, Some((kind, color))) => out.push_str(&format!(
+                "<span class=\"{}\" style=\"color: {}\">{}</span>",
+                escape_html(kind),
+                escape_html(color),
+                escape_html(&token.text)
+            )),
+            (Format::Html, None) => out.push_str(&escape_html(&token.text)),
+        }
+    }
+
+    if format == Format::Html {
+        out.push_str("</pre>\n");
+    }
+    Ok(out)
+}
+
+// The SGR parameters for color, or None if it is not a list of names and codes.
+fn ansi_code(color: &str) -> Option<String> {
+    let codes: Option<Vec<String>> = color.split_whitespace().map(sgr).collect();
+    codes.and_then(|codes| {
+        if codes.is_empty() {
+            None
+        } else {
+            Some(codes.join(";"))
+        }
+    })
+}
+
+fn sgr(word: &str) -> Option<String> {
+    const COLORS: [&str; 8] = [
+        "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"
+    ];
+
+    if !word.is_empty() && word.chars().all(|c| c.is_ascii_digit() || c == ';') {
+        return Some(word.to_string());
+    }
+
+    let (base, name) = if word.starts_with("bright-") {
+        (90, &word["bright-".len()..])
+    } else {
+        (30, word)
+    };
+    if let Some(index) = COLORS.iter().position(|&c| c == name) {
+        return Some((base + index).to_string());
+    }
+
+    match word {
+        "bold" => Some("1".to_string()),
+        "dim" => Some("2".to_string()),
+        "italic" => Some("3".to_string()),
+        "underline" => Some("4".to_string()),
+        _ => None,
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokens::TokenDef;
+
+    fn automaton() -> Automaton {
+        let mut space = TokenDef::new("Space", " +");
+        space.skip = true;
+        Automaton::new(vec![
+            TokenDef::new("Ident", "[a-z]+"),
+            TokenDef::new("Lt", "<"),
+            space,
+        ]).unwrap()
+    }
+
+    #[test]
+    fn highlight_wraps_colored_tokens_in_ansi_codes() {
+        let colors = ColorMap::parse("Ident = \"bold bright-blue\"\nLt = \"31\"").unwrap();
+
+        let result = highlight(&automaton(), "a <b!", &colors, Format::Ansi);
+
+        assert_eq!(
+            result,
+            Ok("\x1b[1;94ma\x1b[0m \x1b[31m<\x1b[0m\x1b[1;94mb\x1b[0m!".to_string())
+        );
+    }
+
+    #[test]
+    fn highlight_escapes_html() {
+        let colors = ColorMap::parse("Lt = \"#c00\"").unwrap();
+
+        let result = highlight(&automaton(), "a<&", &colors, Format::Html);
+
+        assert_eq!(
+            result,
+            Ok("<pre class=\"luther\">a<span class=\"Lt\" style=\"color: #c00\">&lt;</span>\
+                &amp;</pre>\n"
+                .to_string())
+        );
+    }
+
+    #[test]
+    fn highlight_rejects_unknown_ansi_color() {
+        let colors = ColorMap::parse("Ident = \"chartreuse\"").unwrap();
+
+        let result = highlight(&automaton(), "a", &colors, Format::Ansi);
+
+        assert_eq!(
+            result,
+            Err(ColorError {
+                token: "Ident".to_string(),
+                color: "chartreuse".to_string(),
+            })
+        );
+    }
+}
@@ -0,0 +1,103 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Lexing an input with a list of token definitions.
+//!
+//! The tokens are found by repeatedly taking the longest match in the default mode. Each token
+//! is a `LexedToken`, which serializes to a JSON object for the JSON lines output of the `lex`
+//! subcommand. A token that breaks the length limits of its definition is produced with an
+//! `error` that says why.
+
+use automaton::Automaton;
+
+/// A token (or a `char` that no token matches) in the input.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LexedToken {
+    /// The name of the token (or `None` for a `char` that no token matches).
+    pub kind: Option<String>,
+
+    /// The byte offset of the start of the token.
+    pub start: usize,
+
+    /// The byte offset just past the end of the token.
+    pub end: usize,
+
+    /// The text of the token.
+    pub text: String,
+
+    /// Why the token is an error (such as being longer than its `max_len`), if it is one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Lexes `input` into tokens.
+///
+/// The tokens that are marked `skip` are left out unless `include_skipped` is true.
+pub fn lex(automaton: &Automaton, input: &str, include_skipped: bool) -> Vec<LexedToken> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+
+    while start < input.len() {
+        let (kind, end, error) = match automaton.scan_at(input, start) {
+            Ok(Some((token, len))) => (Some(token), start + len, None),
+            Ok(None) => {
+                let len = input[start..].chars().next().unwrap().len_utf8();
+                (None, start + len, None)
+            }
+            Err(e) => {
+                let token = automaton.tokens.iter().position(|def| def.name == e.token());
+                (token, e.span().1, Some(e.to_string()))
+            }
+        };
+
+        let skipped = error.is_none() && kind.map_or(false, |token| automaton.tokens[token].skip);
+        if include_skipped || !skipped {
+            tokens.push(LexedToken {
+                kind: kind.map(|token| automaton.tokens[token].name.clone()),
+                start,
+                end,
+                text: input[start..end].to_string(),
+                error,
+            });
+        }
+        start = end;
+    }
+
+    tokens
+}
+
+/// Formats `tokens` as JSON lines (a JSON object on a line for each token).
+pub fn to_json_lines(tokens: &[LexedToken]) -> String {
+    tokens
+        .iter()
+        .map(|token| ::serde_json::to_string(token).unwrap() + "\n")
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokens::TokenDef;
+
+    #[test]
+    fn lex_emits_json_lines_for_tokens() {
+        let mut space = TokenDef::new("Space", " +");
+        space.skip = true;
+        let automaton = Automaton::new(vec![TokenDef::new("Ident", "[a-z]+"), space]).unwrap();
+
+        let tokens = lex(&automaton, "ab  c!", false);
+
+        assert_eq!(
+            to_json_lines(&tokens),
+            "{\"kind\":\"Ident\",\"start\":0,\"end\":2,\"text\":\"ab\"}\n\
+             {\"kind\":\"Ident\",\"start\":4,\"end\":5,\"text\":\"c\"}\n\
+             {\"kind\":null,\"start\":5,\"end\":6,\"text\":\"!\"}\n"
+        );
+        assert_eq!(lex(&automaton, "ab  c!", true).len(), 4);
+    }
+}
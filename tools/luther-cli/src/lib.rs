@@ -0,0 +1,95 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! The library behind the `luther` command line tool for inspecting Luther token definitions.
+//!
+//! The token definitions are read from a TOML file with a `[[token]]` table for each token or
+//! a JSON file with a `token` array. Each token has a `name`, a `regex`, and an optional
+//! `priority_group`, and the tokens are compiled into a dfa by the same rules that
+//! `luther-derive` uses for the variants of an `enum`.
+//!
+//! The compiler itself (the `tokens`, `syntax`, `automaton`, `binary`, `limits`, `simplify`,
+//! and `equiv` modules) is in the publishable `luther-build` crate and is re-exported here, so
+//! that a build script does not depend on this tool.
+//!
+//! The `error` module has the errors for parsing and compiling token definitions.
+//!
+//! The `simplify` module shrinks regexes with rewrites that `redfa` does not do, before the dfa
+//! for a large generated set of token definitions is built.
+//!
+//! The `ambiguity` module reports the pairs of tokens that overlap (with a string that both
+//! match and the token that wins it) as JSON, for reviewing changes to the token definitions.
+//!
+//! The `conformance` module generates a Rust test file with an example of each token and the
+//! near misses to it, as a regression suite for the token definitions.
+//!
+//! The `golden` module snapshots a compiled dfa for golden file regression tests.
+//!
+//! The `arbitrary` module (with the `quickcheck` feature) generates random regexes for property
+//! tests.
+//!
+//! The `sample` module chooses random tokens (that are lexically valid) for testing the code
+//! downstream of a lexer.
+//!
+//! The `python` module (with the `python` feature) is a Python extension module for compiling
+//! token definitions and lexing with them.
+//!
+//! The `fuzz` module has entry points for fuzzing the compilation of token definitions, and
+//! minimizes a disagreement between the dfa and a reference matcher into a reproduction.
+
+#[macro_use]
+extern crate quicli;
+
+#[macro_use]
+extern crate failure;
+
+extern crate luther;
+extern crate luther_build;
+#[cfg(feature = "python")]
+extern crate pyo3;
+#[cfg(feature = "quickcheck")]
+#[macro_use]
+extern crate quickcheck;
+extern crate rand;
+extern crate redfa;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate toml;
+
+pub use luther_build::{automaton, binary, equiv, limits, simplify, syntax, tokens};
+
+pub mod ambiguity;
+#[cfg(feature = "quickcheck")]
+pub mod arbitrary;
+pub mod attribution;
+pub mod c_source;
+pub mod conformance;
+pub mod count;
+pub mod coverage;
+pub mod debug;
+pub mod error;
+pub mod explain;
+pub mod first;
+pub mod flex;
+pub mod fold;
+pub mod lex;
+pub mod lint;
+pub mod fst;
+pub mod fuzz;
+pub mod golden;
+pub mod highlight;
+pub mod mermaid;
+pub mod output;
+pub mod payload;
+pub mod profile;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod render;
+pub mod sample;
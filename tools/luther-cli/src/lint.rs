@@ -0,0 +1,216 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Warnings about token definitions that compile but are probably not what was meant.
+//!
+//! The derivative construction silently tolerates a token that can never win, an alternative
+//! that adds nothing, and a repeated regex that matches the empty string. `lint` reports these
+//! (and tokens that differ only in case) so that they can be fixed at the source.
+
+use std::fmt;
+use std::result::Result as StdResult;
+
+use redfa::Regex;
+use redfa::dfa::Normalize;
+
+use automaton::{best_token, parse, split_anchors, Automaton};
+use equiv::distinguishing_string;
+use error::CompileError;
+use render::render;
+use tokens::TokenDef;
+
+/// A warning about a list of token definitions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lint {
+    /// The first token can never be produced since every string that it matches is also
+    /// matched by the second token, which wins.
+    ShadowedToken(String, String),
+
+    /// An alternative (rendered as a regex) in the regex for the token matches nothing that
+    /// the other alternatives do not.
+    RedundantBranch(String, String),
+
+    /// The regexes of the two tokens differ only in case.
+    CaseVariants(String, String),
+
+    /// The regex (rendered) under a `*` in the regex for the token matches the empty string.
+    NullableStar(String, String),
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &Lint::ShadowedToken(ref token, ref by) => write!(
+                f,
+                "{} can never match: every string that it matches is also matched by {}",
+                token, by
+            ),
+            &Lint::RedundantBranch(ref token, ref branch) => write!(
+                f,
+                "{}: the alternative {} matches nothing that the other alternatives do not",
+                token, branch
+            ),
+            &Lint::CaseVariants(ref first, ref second) => {
+                write!(f, "{} and {} differ only in case", first, second)
+            }
+            &Lint::NullableStar(ref token, ref regex) => write!(
+                f,
+                "{}: the repeated regex {} matches the empty string",
+                token, regex
+            ),
+        }
+    }
+}
+
+/// Finds the warnings for `tokens`.
+///
+/// It is an error if the tokens do not compile (see `Automaton::new`).
+pub fn lint(tokens: &[TokenDef]) -> StdResult<Vec<Lint>, CompileError> {
+    let automaton = Automaton::new(tokens.to_vec())?;
+    let mut regexs = Vec::new();
+    for token in tokens {
+        regexs.push(parse(token)?);
+    }
+
+    let mut lints = Vec::new();
+    for (token, regex) in tokens.iter().zip(regexs.iter()) {
+        lint_regex(&token.name, regex, &mut lints);
+    }
+
+    for (i, first) in tokens.iter().enumerate() {
+        for (j, second) in tokens.iter().enumerate().skip(i + 1) {
+            if first.mode_name() != second.mode_name() {
+                continue;
+            }
+
+            if first.regex != second.regex
+                && first.regex.to_lowercase() == second.regex.to_lowercase()
+            {
+                lints.push(Lint::CaseVariants(first.name.clone(), second.name.clone()));
+            }
+
+            if !is_unconditional(first) || !is_unconditional(second) {
+                continue;
+            }
+            let winner = best_token(&[i, j], tokens, &automaton.simple_strings);
+            let (winner, loser) = match winner {
+                Ok(Some(winner)) if winner == i => (i, j),
+                Ok(Some(_)) => (j, i),
+                _ => continue,
+            };
+            if is_subset(&regexs[loser], &regexs[winner]) {
+                lints.push(Lint::ShadowedToken(
+                    tokens[loser].name.clone(),
+                    tokens[winner].name.clone(),
+                ));
+            }
+        }
+    }
+
+    Ok(lints)
+}
+
+// Adds the lints for the structure of regex (redundant alternatives and nullable stars).
+fn lint_regex(token: &str, regex: &Regex<char>, lints: &mut Vec<Lint>) {
+    match regex {
+        &Regex::Alt(ref ts, ref res) => {
+            for (i, branch) in res.iter().enumerate() {
+                let mut others = res.clone();
+                others.remove(i);
+                if is_subset(branch, &Regex::Alt(ts.clone(), others)) {
+                    lints.push(Lint::RedundantBranch(token.to_string(), display(branch)));
+                }
+            }
+            for re in res {
+                lint_regex(token, re, lints);
+            }
+        }
+        &Regex::Cat(ref res) | &Regex::And(ref res) => for re in res {
+            lint_regex(token, re, lints);
+        },
+        &Regex::Not(ref re) => lint_regex(token, re, lints),
+        &Regex::Kleene(ref re) => {
+            if re.nullable() {
+                lints.push(Lint::NullableStar(token.to_string(), display(re)));
+            }
+            lint_regex(token, re, lints);
+        }
+        &Regex::Null | &Regex::Empty | &Regex::Except(_) => {}
+    }
+}
+
+// Renders part of a parsed regex (which is not normalized) as it would be written.
+fn display(regex: &Regex<char>) -> String {
+    render(&vec![regex.clone()].normalize().remove(0))
+}
+
+// Tests for the token always matching (it has no anchors and no word boundary).
+fn is_unconditional(token: &TokenDef) -> bool {
+    let (start, _, end) = split_anchors(&token.regex);
+    !start && !end && !token.word_boundary
+}
+
+// Tests for every string that sub matches also being matched by sup.
+fn is_subset(sub: &Regex<char>, sup: &Regex<char>) -> bool {
+    let difference = Regex::And(vec![sub.clone(), Regex::Not(Box::new(sup.clone()))]);
+    let difference = vec![difference].normalize().remove(0);
+    distinguishing_string(&difference, &Regex::Null).is_none()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lints(tokens: Vec<TokenDef>) -> Vec<Lint> {
+        lint(&tokens).expect("unexpected error linting the tokens")
+    }
+
+    #[test]
+    fn lint_finds_shadowed_tokens() {
+        let mut ident = TokenDef::new("Ident", "[a-z]+");
+        ident.priority_group = 0;
+        let tokens = vec![ident, TokenDef::new("If", "if"), TokenDef::new("Num", "[0-9]+")];
+
+        assert_eq!(
+            lints(tokens),
+            vec![Lint::ShadowedToken("If".to_string(), "Ident".to_string())]
+        );
+    }
+
+    #[test]
+    fn lint_allows_keywords_that_win_over_identifiers() {
+        let tokens = vec![TokenDef::new("Ident", "[a-z]+"), TokenDef::new("If", "if")];
+
+        assert_eq!(lints(tokens), vec![]);
+    }
+
+    #[test]
+    fn lint_finds_redundant_branches_and_nullable_stars() {
+        let tokens = vec![TokenDef::new("Word", "(ab|a[a-z]*)(c*)*")];
+
+        assert_eq!(
+            lints(tokens),
+            vec![
+                Lint::RedundantBranch("Word".to_string(), "ab".to_string()),
+                Lint::NullableStar("Word".to_string(), "c*".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lint_finds_case_variants() {
+        let mut upper = TokenDef::new("Upper", "SELECT");
+        upper.priority_group = 0;
+        let tokens = vec![TokenDef::new("Lower", "select"), upper];
+
+        assert_eq!(
+            lints(tokens),
+            vec![Lint::CaseVariants("Lower".to_string(), "Upper".to_string())]
+        );
+    }
+}
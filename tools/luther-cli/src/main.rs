@@ -0,0 +1,459 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! A command line tool for inspecting Luther token definitions.
+
+#[macro_use]
+extern crate quicli;
+
+extern crate luther_cli;
+extern crate toml;
+
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use quicli::prelude::*;
+use luther_cli::{ambiguity, automaton, binary, conformance, count, coverage, debug, equiv, explain,
+                 first, flex, fold, golden, highlight, lex, lint, output, profile, tokens};
+
+/// Compile and inspect Luther token definitions.
+#[derive(Debug, StructOpt)]
+enum Cli {
+    /// Compile the token definitions and output the resulting dfa.
+    #[structopt(name = "compile")]
+    Compile {
+        /// The token definitions file (.toml or .json).
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+
+        /// The output format: dot, table, stats, c, fst, fst-symbols, mermaid, or patterns.
+        #[structopt(short = "f", long = "format", default_value = "dot")]
+        format: output::Format,
+
+        /// Enable a feature group of the tokens (the tokens in no group are always compiled).
+        #[structopt(short = "g", long = "group")]
+        groups: Vec<String>,
+    },
+
+    /// Compile the token definitions into the binary format for `luther::table::TableDfa`.
+    #[structopt(name = "tables")]
+    Tables {
+        /// The token definitions file (.toml or .json).
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+
+        /// The file to write.
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+
+        /// Renumber the states so that those near the start state come first.
+        #[structopt(long = "hot-first")]
+        hot_first: bool,
+
+        /// Renumber the states so that those that a sample corpus visits most often come first.
+        #[structopt(long = "profile", parse(from_os_str))]
+        profile: Option<PathBuf>,
+
+        /// Fold the dfa so that it matches the tokens in any case.
+        #[structopt(short = "i", long = "case-insensitive")]
+        case_insensitive: bool,
+
+        /// Enable a feature group of the tokens (the tokens in no group are always compiled).
+        #[structopt(short = "g", long = "group")]
+        groups: Vec<String>,
+    },
+
+    /// Report the pairs of tokens that overlap as JSON.
+    ///
+    /// Each pair has a shortest string that both tokens match and the token that wins it. The
+    /// report is written to the output file (or standard output if it is not given).
+    #[structopt(name = "ambiguity")]
+    Ambiguity {
+        /// The token definitions file (.toml or .json).
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+
+        /// The file to write the report to.
+        #[structopt(parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+
+    /// Generate a Rust test file of examples and near misses for each token.
+    ///
+    /// The tests check the `longest_match` of the `Token` enum that luther-build generates for
+    /// the same token definitions. The test file is written to the output file (or standard
+    /// output if it is not given).
+    #[structopt(name = "conformance")]
+    Conformance {
+        /// The token definitions file (.toml or .json).
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+
+        /// The file to write the tests to.
+        #[structopt(parse(from_os_str))]
+        output: Option<PathBuf>,
+
+        /// The path of the generated `Token` enum in the tests.
+        #[structopt(short = "t", long = "token-type", default_value = "Token")]
+        token_type: String,
+
+        /// Enable a feature group of the tokens (the tokens in no group are always compiled).
+        #[structopt(short = "g", long = "group")]
+        groups: Vec<String>,
+    },
+
+    /// Count the strings of a given length that a regex matches.
+    #[structopt(name = "count")]
+    Count {
+        /// The regex.
+        regex: String,
+
+        /// The length (in chars) of the strings to count.
+        len: usize,
+    },
+
+    /// List the chars that no token can start with (in each mode).
+    #[structopt(name = "coverage")]
+    Coverage {
+        /// The token definitions file (.toml or .json).
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+    },
+
+    /// Trace the derivatives of a regex for candidate strings.
+    ///
+    /// The candidate strings are read from standard input (one per line) if none are given.
+    #[structopt(name = "debug")]
+    Debug {
+        /// The regex to trace.
+        regex: String,
+
+        /// The candidate strings.
+        input: Vec<String>,
+    },
+
+    /// Check whether two regexes (or two token definitions files) are equivalent.
+    ///
+    /// If they are not, a shortest string that distinguishes them is printed.
+    #[structopt(name = "equiv")]
+    Equiv {
+        /// The first regex (or token definitions file).
+        lhs: String,
+
+        /// The second regex (or token definitions file).
+        rhs: String,
+
+        /// Compare token definitions files instead of regexes.
+        #[structopt(short = "t", long = "tokens")]
+        token_files: bool,
+    },
+
+    /// Explain how strings are split into tokens.
+    ///
+    /// Each token is shown with its span, the token that won, and the tokens that were still
+    /// alive when the match was cut off. The strings are read from standard input (one per
+    /// line) if none are given.
+    #[structopt(name = "explain")]
+    Explain {
+        /// The token definitions file (.toml or .json).
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+
+        /// The strings to explain.
+        text: Vec<String>,
+    },
+
+    /// List the chars that can begin a match of a regex.
+    #[structopt(name = "first")]
+    First {
+        /// The regex.
+        regex: String,
+    },
+
+    /// Compare the dfa for the token definitions with a golden snapshot file.
+    ///
+    /// The snapshot file is written instead if it does not exist or if `--update` is given.
+    #[structopt(name = "golden")]
+    Golden {
+        /// The token definitions file (.toml or .json).
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+
+        /// The golden snapshot file.
+        #[structopt(parse(from_os_str))]
+        golden: PathBuf,
+
+        /// Overwrite the golden snapshot file with the current snapshot.
+        #[structopt(short = "u", long = "update")]
+        update: bool,
+    },
+
+    /// Print a file with its tokens colored for a terminal (ANSI) or a web page (HTML).
+    #[structopt(name = "highlight")]
+    Highlight {
+        /// The token definitions file (.toml or .json).
+        #[structopt(parse(from_os_str))]
+        tokens: PathBuf,
+
+        /// The colors of the tokens (a TOML table from token names to colors).
+        #[structopt(parse(from_os_str))]
+        colors: PathBuf,
+
+        /// The file to highlight (standard input if it is not given).
+        #[structopt(parse(from_os_str))]
+        input: Option<PathBuf>,
+
+        /// The output format: ansi or html.
+        #[structopt(short = "f", long = "format", default_value = "ansi")]
+        format: highlight::Format,
+    },
+
+    /// Lex a file and print each token as a JSON object on its own line.
+    ///
+    /// Each object has the kind (the token name, or null for a char that no token matches),
+    /// the start and end byte offsets, and the text of the token.
+    #[structopt(name = "lex")]
+    Lex {
+        /// The token definitions file (.toml or .json).
+        #[structopt(parse(from_os_str))]
+        tokens: PathBuf,
+
+        /// The file to lex (standard input if it is not given).
+        #[structopt(parse(from_os_str))]
+        input: Option<PathBuf>,
+
+        /// Include the tokens that are marked skip.
+        #[structopt(short = "a", long = "all")]
+        all: bool,
+
+        /// Enable a feature group of the tokens (the tokens in no group are always compiled).
+        #[structopt(short = "g", long = "group")]
+        groups: Vec<String>,
+    },
+
+    /// Warn about token definitions that compile but are probably not what was meant.
+    ///
+    /// The warnings are for tokens that can never match, alternatives that match nothing
+    /// new, repeated regexes that match the empty string, and tokens that differ only in case.
+    #[structopt(name = "lint")]
+    Lint {
+        /// The token definitions file (.toml or .json).
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+    },
+
+    /// Import the rules of a flex (.l) file as luther token definitions.
+    #[structopt(name = "import-flex")]
+    ImportFlex {
+        /// The flex file.
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+
+        /// The output format: rust (an enum that derives Lexer) or toml.
+        #[structopt(short = "f", long = "format", default_value = "rust")]
+        format: String,
+    },
+}
+
+main!(|args: Cli| {
+    match args {
+        Cli::Compile {
+            input,
+            format,
+            groups,
+        } => {
+            let automaton = automaton::Automaton::new(load_groups(&input, &groups)?)?;
+            print!("{}", output::format(&automaton, format)?);
+        }
+        Cli::Tables {
+            input,
+            output,
+            hot_first,
+            profile: corpus,
+            case_insensitive,
+            groups,
+        } => {
+            let mut automaton = automaton::Automaton::new(load_groups(&input, &groups)?)?;
+            if case_insensitive {
+                automaton = fold::case_fold(&automaton)?;
+            }
+            if let Some(corpus) = corpus {
+                let corpus = fs::read_to_string(&corpus)?;
+                profile::renumber_by_profile(&mut automaton, Some(corpus.as_str()));
+            } else if hot_first {
+                automaton.renumber_hot_first();
+            }
+            fs::write(&output, binary::to_bytes(&automaton)?)?;
+        }
+        Cli::Ambiguity { input, output } => {
+            let report = ambiguity::report(&tokens::load(&input)?)?.to_json();
+            match output {
+                Some(output) => fs::write(&output, report)?,
+                None => print!("{}", report),
+            }
+        }
+        Cli::Conformance {
+            input,
+            output,
+            token_type,
+            groups,
+        } => {
+            let automaton = automaton::Automaton::new(load_groups(&input, &groups)?)?;
+            let source = input.display().to_string();
+            let tests = conformance::generate(&automaton).to_rust(&automaton, &token_type, &source);
+            match output {
+                Some(output) => fs::write(&output, tests)?,
+                None => print!("{}", tests),
+            }
+        }
+        Cli::Count { regex, len } => {
+            let regex = debug::parse_regex(&regex)?;
+            match count::count_regex_strings(&regex, len) {
+                Some(count) => println!("{}", count),
+                None => println!("more than {}", u128::max_value()),
+            }
+        }
+        Cli::Coverage { input } => {
+            let automaton = automaton::Automaton::new(tokens::load(&input)?)?;
+            print!("{}", coverage::report(&automaton));
+        }
+        Cli::Debug { regex, input } => {
+            let regex = debug::parse_regex(&regex)?;
+            if input.is_empty() {
+                let stdin = io::stdin();
+                prompt()?;
+                for line in stdin.lock().lines() {
+                    print!("{}", debug::trace(&regex, &line?));
+                    prompt()?;
+                }
+            } else {
+                for input in input {
+                    print!("{}", debug::trace(&regex, &input));
+                }
+            }
+        }
+        Cli::Equiv {
+            lhs,
+            rhs,
+            token_files,
+        } => {
+            let distinguishing = if token_files {
+                let lhs = automaton::Automaton::new(tokens::load(&lhs)?)?;
+                let rhs = automaton::Automaton::new(tokens::load(&rhs)?)?;
+                equiv::distinguishing_input(&lhs, &rhs)
+            } else {
+                let lhs = debug::parse_regex(&lhs)?;
+                let rhs = debug::parse_regex(&rhs)?;
+                equiv::distinguishing_string(&lhs, &rhs)
+            };
+            match distinguishing {
+                None => println!("equivalent"),
+                Some(s) => bail!("not equivalent: {:?} distinguishes them", s),
+            }
+        }
+        Cli::Explain { input, text } => {
+            let automaton = automaton::Automaton::new(tokens::load(&input)?)?;
+            if text.is_empty() {
+                let stdin = io::stdin();
+                prompt()?;
+                for line in stdin.lock().lines() {
+                    print!("{}", explain::explain(&automaton, &line?));
+                    prompt()?;
+                }
+            } else {
+                for text in text {
+                    print!("{}", explain::explain(&automaton, &text));
+                }
+            }
+        }
+        Cli::First { regex } => {
+            let regex = debug::parse_regex(&regex)?;
+            println!("{}", first::expected(&regex));
+        }
+        Cli::Golden {
+            input,
+            golden,
+            update,
+        } => {
+            let automaton = automaton::Automaton::new(tokens::load(&input)?)?;
+            let snapshot = golden::snapshot(&automaton);
+            if update || !golden.exists() {
+                fs::write(&golden, snapshot)?;
+            } else if let Some(diff) = golden::compare(&fs::read_to_string(&golden)?, &snapshot) {
+                print!("{}", diff);
+                bail!("the dfa does not match {}", golden.display());
+            }
+        }
+        Cli::Highlight {
+            tokens: token_file,
+            colors,
+            input,
+            format,
+        } => {
+            let automaton = automaton::Automaton::new(tokens::load(&token_file)?)?;
+            let colors = highlight::ColorMap::parse(&fs::read_to_string(&colors)?)?;
+            let text = read_input(input)?;
+            print!("{}", highlight::highlight(&automaton, &text, &colors, format)?);
+        }
+        Cli::Lex {
+            tokens: token_file,
+            input,
+            all,
+            groups,
+        } => {
+            let automaton = automaton::Automaton::new(load_groups(&token_file, &groups)?)?;
+            let text = read_input(input)?;
+            print!("{}", lex::to_json_lines(&lex::lex(&automaton, &text, all)));
+        }
+        Cli::Lint { input } => {
+            for warning in lint::lint(&tokens::load(&input)?)? {
+                println!("warning: {}", warning);
+            }
+        }
+        Cli::ImportFlex { input, format } => {
+            let flex = flex::parse(&fs::read_to_string(&input)?)?;
+            match format.as_str() {
+                "rust" => print!("{}", flex.to_rust()),
+                "toml" => {
+                    for warning in flex.warnings.iter() {
+                        eprintln!("warning: {}", warning);
+                    }
+                    let file = tokens::TokenFile {
+                        syntax: Default::default(),
+                        token: flex.to_tokens(),
+                    };
+                    print!("{}", toml::to_string(&file)?);
+                }
+                format => bail!("{} is not a valid format (expected rust or toml)", format),
+            }
+        }
+    }
+});
+
+// Loads the tokens in the enabled feature groups from the token definitions file.
+fn load_groups(input: &Path, groups: &[String]) -> Result<Vec<tokens::TokenDef>> {
+    tokens::select(tokens::load(input)?, groups)
+}
+
+// Reads the file at input, or standard input if there is no input.
+fn read_input(input: Option<PathBuf>) -> Result<String> {
+    match input {
+        Some(input) => Ok(fs::read_to_string(&input)?),
+        None => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)?;
+            Ok(text)
+        }
+    }
+}
+
+fn prompt() -> Result<()> {
+    print!("> ");
+    io::stdout().flush()?;
+    Ok(())
+}
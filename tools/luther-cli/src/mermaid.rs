@@ -0,0 +1,95 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Export of a compiled automaton as a Mermaid state diagram.
+//!
+//! The diagram is a `stateDiagram-v2` block that can be pasted into a ```` ```mermaid ````
+//! fenced code block in Markdown (which GitHub renders without a Graphviz toolchain). The
+//! transitions are labeled with the same sets of `char`'s as in the `fst` format, and the
+//! accepting states are named for their token and drawn with a heavier border.
+
+use std::fmt::Write;
+
+use automaton::Automaton;
+use fst::labels;
+
+/// Formats `automaton` as a Mermaid `stateDiagram-v2`.
+pub fn to_mermaid(automaton: &Automaton) -> String {
+    let mut diagram = String::new();
+    writeln!(diagram, "stateDiagram-v2").unwrap();
+
+    let mut accepting = Vec::new();
+    for (num, state) in automaton.dfa.states.iter().enumerate() {
+        if let Some(token) = state.value {
+            writeln!(
+                diagram,
+                "    state \"S{} {}\" as S{}",
+                num, automaton.tokens[token].name, num
+            ).unwrap();
+            accepting.push(format!("S{}", num));
+        }
+    }
+
+    for (mode, &start) in automaton.start_states.iter().enumerate() {
+        writeln!(
+            diagram,
+            "    [*] --> S{} : {}",
+            start,
+            escape(&automaton.modes[mode])
+        ).unwrap();
+    }
+
+    for num in automaton.bfs_order() {
+        for (to, label) in labels(automaton, num) {
+            writeln!(diagram, "    S{} --> S{} : {}", num, to, escape(&label)).unwrap();
+        }
+    }
+
+    if !accepting.is_empty() {
+        writeln!(diagram, "    classDef accepting stroke-width:3px").unwrap();
+        writeln!(diagram, "    class {} accepting", accepting.join(", ")).unwrap();
+    }
+
+    diagram
+}
+
+// Escapes the characters that end a Mermaid label or start a comment as entity codes.
+fn escape(label: &str) -> String {
+    let mut escaped = String::new();
+    for c in label.chars() {
+        match c {
+            '#' | ';' | ':' | '%' => write!(escaped, "#{};", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokens::TokenDef;
+
+    #[test]
+    fn mermaid_diagram_has_start_and_accepting_states() {
+        let automaton = Automaton::new(vec![
+            TokenDef::new("Ident", "[a-z]+"),
+            TokenDef::new("Colon", ":"),
+        ]).unwrap();
+
+        let diagram = to_mermaid(&automaton);
+
+        let start = automaton.start_state;
+        assert!(diagram.starts_with("stateDiagram-v2\n"));
+        assert!(diagram.contains(&format!("    [*] --> S{} : INITIAL\n", start)));
+        assert!(diagram.contains(" Ident\" as S"));
+        assert!(diagram.contains(&format!("    S{} --> ", start)));
+        assert!(diagram.contains(" : [#58;]\n"));
+        assert!(diagram.contains("    class S"));
+    }
+}
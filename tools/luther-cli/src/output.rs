@@ -0,0 +1,202 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::result::Result as StdResult;
+use std::str::FromStr;
+
+use attribution;
+use automaton::Automaton;
+use c_source::to_c;
+use error::ExportError;
+use fst::{to_fst_symbols, to_fst_text};
+use mermaid::to_mermaid;
+use render::range;
+
+/// The output formats for a compiled automaton.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// The DOT format used by Graphviz.
+    Dot,
+
+    /// A table of the states and their transitions.
+    Table,
+
+    /// The number of states, transitions, and accepting states.
+    Stats,
+
+    /// A C header with the tables of the dfa and a driver for them.
+    C,
+
+    /// The AT&T text format used by OpenFST.
+    Fst,
+
+    /// The symbol table for the `Fst` format.
+    FstSymbols,
+
+    /// A Mermaid state diagram.
+    Mermaid,
+
+    /// The number of states that each token contributes to and the tokens that contribute to
+    /// each state.
+    Patterns,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "dot" => Ok(Format::Dot),
+            "table" => Ok(Format::Table),
+            "stats" => Ok(Format::Stats),
+            "c" => Ok(Format::C),
+            "fst" => Ok(Format::Fst),
+            "fst-symbols" => Ok(Format::FstSymbols),
+            "mermaid" => Ok(Format::Mermaid),
+            "patterns" => Ok(Format::Patterns),
+            s => Err(format!(
+                "{} is not a valid format \
+                 (expected dot, table, stats, c, fst, fst-symbols, mermaid, or patterns)",
+                s
+            )),
+        }
+    }
+}
+
+/// Formats `automaton` in the given `format`.
+///
+/// It is an error if the `C` or `Fst` format is asked for and a token cannot be exported (see
+/// `binary::check`).
+pub fn format(automaton: &Automaton, format: Format) -> StdResult<String, ExportError> {
+    let formatted = match format {
+        Format::Dot => to_dot(automaton),
+        Format::Table => to_table(automaton),
+        Format::Stats => to_stats(automaton),
+        Format::C => to_c(automaton)?,
+        Format::Fst => to_fst_text(automaton)?,
+        Format::FstSymbols => to_fst_symbols(automaton),
+        Format::Mermaid => to_mermaid(automaton),
+        Format::Patterns => attribution::report(automaton),
+    };
+
+    Ok(formatted)
+}
+
+fn to_stats(automaton: &Automaton) -> String {
+    format!(
+        "tokens: {}\nstates: {}\ntransitions: {}\naccepting states: {}\n",
+        automaton.tokens.len(),
+        automaton.dfa.states.len(),
+        automaton.transition_count(),
+        automaton.accepting_count()
+    )
+}
+
+fn to_dot(automaton: &Automaton) -> String {
+    let mut dot = String::new();
+
+    writeln!(dot, "digraph luther {{").unwrap();
+    writeln!(dot, "    rankdir=LR;").unwrap();
+    for (mode, &start) in automaton.start_states.iter().enumerate() {
+        writeln!(dot, "    start{} [shape=point];", mode).unwrap();
+        writeln!(
+            dot,
+            "    start{} -> State{} [label=\"{}\"];",
+            mode, start, automaton.modes[mode]
+        ).unwrap();
+    }
+
+    for (num, state) in automaton.dfa.states.iter().enumerate() {
+        if num == automaton.error_state {
+            continue;
+        }
+
+        let result = match state.value {
+            Some(token) => writeln!(
+                dot,
+                "    State{} [shape=doublecircle, label=\"State{}\\n{}\"];",
+                num, num, automaton.tokens[token].name
+            ),
+            None => writeln!(dot, "    State{} [shape=circle];", num),
+        };
+        result.unwrap();
+    }
+
+    for (num, labels) in transition_labels(automaton).into_iter().enumerate() {
+        for (to, label) in labels {
+            writeln!(
+                dot,
+                "    State{} -> State{} [label=\"{}\"];",
+                num,
+                to,
+                label.join(" ").replace('\\', "\\\\").replace('"', "\\\"")
+            ).unwrap();
+        }
+    }
+
+    writeln!(dot, "}}").unwrap();
+    dot
+}
+
+fn to_table(automaton: &Automaton) -> String {
+    let mut table = String::new();
+
+    for (num, labels) in transition_labels(automaton).into_iter().enumerate() {
+        if num == automaton.error_state {
+            continue;
+        }
+
+        let state = &automaton.dfa.states[num];
+        let start = match automaton.start_states.iter().position(|&start| start == num) {
+            Some(mode) => format!(" (start {})", automaton.modes[mode]),
+            None => String::new(),
+        };
+        let result = match state.value {
+            Some(token) => writeln!(
+                table,
+                "State{}{}: accepts {}",
+                num, start, automaton.tokens[token].name
+            ),
+            None => writeln!(table, "State{}{}:", num, start),
+        };
+        result.unwrap();
+
+        for (to, label) in labels {
+            writeln!(table, "    {} -> State{}", label.join(" "), to).unwrap();
+        }
+    }
+
+    table
+}
+
+// The labels for the transitions from each state that do not go to the error state,
+// grouped by their target.
+fn transition_labels(automaton: &Automaton) -> Vec<BTreeMap<u32, Vec<String>>> {
+    (0..automaton.dfa.states.len())
+        .map(|state| {
+            automaton
+                .state_classes(state)
+                .into_iter()
+                .filter(|class| class.to != automaton.error_state)
+                .map(|class| {
+                    let mut labels: Vec<_> = class
+                        .ranges
+                        .iter()
+                        .map(|&(first, last)| range(first, last))
+                        .collect();
+                    if class.other {
+                        labels.push("other".to_string());
+                    }
+                    (class.to as u32, labels)
+                })
+                .collect()
+        })
+        .collect()
+}
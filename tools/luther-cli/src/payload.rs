@@ -0,0 +1,95 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Payloads attached to the tokens of an automaton.
+//!
+//! A payload is any value that a user wants back with a match of a token (such as the radix
+//! of a number token or the mode to switch to after a token). `Payloads` stores a copy of the
+//! payload of each token at each state that accepts it, so that a match can return its
+//! payload without a separate lookup keyed by the token.
+
+use automaton::Automaton;
+
+/// The payloads of the accepting states of an automaton.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Payloads<V> {
+    by_state: Vec<Option<V>>,
+}
+
+impl<V: Clone> Payloads<V> {
+    /// Attaches `payloads[i]` to the i'th token of `automaton`.
+    ///
+    /// # Panics
+    /// Panics if there is not a payload for each token of `automaton`.
+    pub fn new(automaton: &Automaton, payloads: &[V]) -> Payloads<V> {
+        assert_eq!(
+            payloads.len(),
+            automaton.tokens.len(),
+            "there is not a payload for each token"
+        );
+
+        let by_state = automaton
+            .dfa
+            .states
+            .iter()
+            .map(|state| state.value.map(|token| payloads[token].clone()))
+            .collect();
+
+        Payloads { by_state }
+    }
+}
+
+impl<V> Payloads<V> {
+    /// The payload of the token accepted by `state` (or `None` if it is not accepting).
+    pub fn get(&self, state: usize) -> Option<&V> {
+        self.by_state.get(state).and_then(|payload| payload.as_ref())
+    }
+
+    /// The token, length (in bytes), and payload of the longest prefix of `input` that is
+    /// accepted by `automaton`.
+    ///
+    /// `automaton` should be the one that these payloads were made for.
+    pub fn longest_match(&self, automaton: &Automaton, input: &str) -> Option<(usize, usize, &V)> {
+        let mut state = automaton.start_state;
+        let mut longest = None;
+
+        for (offset, c) in input.char_indices() {
+            state = automaton.transition(state, c);
+            if state == automaton.error_state {
+                break;
+            }
+            let token = automaton.dfa.states[state].value;
+            if let (Some(token), Some(payload)) = (token, self.get(state)) {
+                longest = Some((token, offset + c.len_utf8(), payload));
+            }
+        }
+
+        longest
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokens::TokenDef;
+
+    #[test]
+    fn payloads_are_returned_with_matches() {
+        let automaton = Automaton::new(vec![
+            TokenDef::new("Hex", "0x[0-9a-f]+"),
+            TokenDef::new("Decimal", "[0-9]+"),
+        ]).unwrap();
+
+        let sut = Payloads::new(&automaton, &[16u32, 10]);
+
+        assert_eq!(sut.longest_match(&automaton, "0x1f "), Some((0, 4, &16)));
+        assert_eq!(sut.longest_match(&automaton, "0x"), Some((1, 1, &10)));
+        assert_eq!(sut.longest_match(&automaton, "x"), None);
+        assert_eq!(sut.get(automaton.start_state), None);
+    }
+}
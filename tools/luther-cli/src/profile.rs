@@ -0,0 +1,138 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Profiles of the states that a sample corpus visits.
+//!
+//! A `Profile` counts the visits to each state of an `Automaton` while lexing sample input.
+//! The counts can then be used to number the states that are visited most often first
+//! (instead of estimating the likelihood of a visit from the distance to a start state, as
+//! `Automaton::renumber_hot_first` does) before the tables are emitted.
+
+use automaton::Automaton;
+
+/// The number of visits to each state while lexing a sample corpus.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    hits: Vec<u64>,
+}
+
+impl Profile {
+    /// Creates an empty profile for `automaton`.
+    pub fn new(automaton: &Automaton) -> Profile {
+        Profile {
+            hits: vec![0; automaton.dfa.states.len()],
+        }
+    }
+
+    /// Lexes `input` with `automaton` and counts the visits to each state.
+    ///
+    /// The input is lexed by repeatedly taking the longest match (skipping a `char` where no
+    /// token matches). The visits that look past the end of a match (to find that it is the
+    /// longest) are counted as well since they cost the same as the others.
+    pub fn record(&mut self, automaton: &Automaton, input: &str) {
+        let mut start = 0;
+        while start < input.len() {
+            let mut state = automaton.start_state;
+            self.hits[state] += 1;
+            for c in input[start..].chars() {
+                state = automaton.transition(state, c);
+                if state == automaton.error_state {
+                    break;
+                }
+                self.hits[state] += 1;
+            }
+
+            start += match automaton.longest_match_at(input, start) {
+                Some((_, len)) => len,
+                None => input[start..].chars().next().unwrap().len_utf8(),
+            };
+        }
+    }
+
+    /// The number of visits to `state`.
+    pub fn hits(&self, state: usize) -> u64 {
+        self.hits[state]
+    }
+
+    /// The states that were visited, with the most visited first.
+    ///
+    /// States with the same number of visits are in breadth first order (see
+    /// `Automaton::bfs_order`).
+    pub fn hot_order(&self, automaton: &Automaton) -> Vec<usize> {
+        let mut order: Vec<_> = automaton
+            .bfs_order()
+            .into_iter()
+            .filter(|&state| self.hits[state] > 0)
+            .collect();
+        order.sort_by(|&lhs, &rhs| self.hits[rhs].cmp(&self.hits[lhs]));
+        order
+    }
+}
+
+/// Renumbers the states of `automaton` so that the states that `corpus` visits most often have
+/// the lowest numbers.
+///
+/// The states that are not visited follow in breadth first order (and then the error state).
+pub fn renumber_by_profile<'a, I>(automaton: &mut Automaton, corpus: I)
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut profile = Profile::new(automaton);
+    for input in corpus {
+        profile.record(automaton, input);
+    }
+
+    let mut order = profile.hot_order(automaton);
+    let unvisited: Vec<_> = automaton
+        .bfs_order()
+        .into_iter()
+        .filter(|&state| profile.hits(state) == 0)
+        .collect();
+    order.extend(unvisited);
+    automaton.renumber(&order);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokens::TokenDef;
+
+    fn automaton() -> Automaton {
+        let tokens = vec![
+            TokenDef::new("Ident", "[a-z]+"),
+            TokenDef::new("Number", "[0-9]+"),
+            TokenDef::new("Space", " "),
+        ];
+        Automaton::new(tokens).expect("unexpected error building the automaton")
+    }
+
+    #[test]
+    fn profile_counts_visits_to_states() {
+        let automaton = automaton();
+        let mut sut = Profile::new(&automaton);
+
+        sut.record(&automaton, "12 345");
+
+        let number = automaton.transition(automaton.start_state, '1');
+        let space = automaton.transition(automaton.start_state, ' ');
+        assert_eq!(sut.hits(automaton.start_state), 3);
+        assert_eq!(sut.hits(number), 5);
+        assert_eq!(sut.hits(space), 1);
+    }
+
+    #[test]
+    fn renumber_by_profile_puts_hot_states_first() {
+        let mut sut = automaton();
+
+        renumber_by_profile(&mut sut, vec!["1 22 333 4444"]);
+
+        assert_eq!(sut.transition(sut.start_state, '1'), 0);
+        assert_eq!(sut.start_state, 1);
+        assert_eq!(sut.longest_match("123 abc"), Some((1, 3)));
+    }
+}
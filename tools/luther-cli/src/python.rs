@@ -0,0 +1,169 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Python bindings for compiling token definitions and lexing with them.
+//!
+//! This module requires the `python` feature (which is not enabled by default) and adds a
+//! Python module named `luther` when the crate's `cdylib` is built as a Python extension (for
+//! example with maturin, with `module-name = "luther"` and the `pyo3/extension-module`
+//! feature). It suits prototyping a set of tokens in a notebook before writing the `enum` that
+//! derives `Lexer` for it:
+//!
+//! ```python
+//! import luther
+//!
+//! builder = luther.LexerBuilder()
+//! builder.token("Ident", "[a-z]+")
+//! builder.token("Space", " +", skip=True)
+//! lexer = builder.build()
+//! lexer.lex("ab cd")  # [("Ident", 0, 2, "ab"), ("Ident", 3, 5, "cd")]
+//! ```
+//!
+//! The spans of the tokens are Python string indices (counted in `char`'s), with the end just
+//! past the end of the token, so `text[start:end]` is the text of the token.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use automaton::Automaton;
+use lex;
+use tokens::{self, TokenDef};
+
+/// A list of token definitions that is compiled into a `Lexer`.
+#[pyclass]
+#[derive(Default)]
+pub struct LexerBuilder {
+    tokens: Vec<TokenDef>,
+}
+
+#[pymethods]
+impl LexerBuilder {
+    #[new]
+    fn new() -> LexerBuilder {
+        LexerBuilder::default()
+    }
+
+    /// Adds a token definition with the same options as in a token definitions file.
+    #[pyo3(signature = (name, regex, priority_group = 1, skip = false, mode = None))]
+    fn token(
+        &mut self,
+        name: String,
+        regex: String,
+        priority_group: u32,
+        skip: bool,
+        mode: Option<String>,
+    ) {
+        let mut token = TokenDef::new(name, regex);
+        token.priority_group = priority_group;
+        token.skip = skip;
+        token.mode = mode;
+        self.tokens.push(token);
+    }
+
+    /// Compiles the token definitions, raising `ValueError` if they are not valid.
+    fn build(&self) -> PyResult<Lexer> {
+        Automaton::new(self.tokens.clone())
+            .map(|automaton| Lexer { automaton })
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+}
+
+/// Compiled token definitions.
+#[pyclass(unsendable)]
+pub struct Lexer {
+    automaton: Automaton,
+}
+
+#[pymethods]
+impl Lexer {
+    /// Compiles the token definitions file (.toml or .json) at `path`.
+    #[staticmethod]
+    fn from_file(path: &str) -> PyResult<Lexer> {
+        let tokens = tokens::load(path).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Automaton::new(tokens)
+            .map(|automaton| Lexer { automaton })
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    /// Lexes `text` into a list of `(kind, start, end, text)` tuples.
+    ///
+    /// The kind is `None` for a `char` that no token matches. The tokens that are marked skip
+    /// are left out unless `include_skipped` is true.
+    #[pyo3(signature = (text, include_skipped = false))]
+    fn lex(
+        &self,
+        text: &str,
+        include_skipped: bool,
+    ) -> Vec<(Option<String>, usize, usize, String)> {
+        let tokens = lex::lex(&self.automaton, text, include_skipped);
+        let mut chars = CharOffsets::new(text);
+        tokens
+            .into_iter()
+            .map(|token| {
+                let start = chars.to_chars(token.start);
+                let end = chars.to_chars(token.end);
+                (token.kind, start, end, token.text)
+            })
+            .collect()
+    }
+
+    /// The names of the tokens, in the order in which they were defined.
+    fn token_names(&self) -> Vec<String> {
+        self.automaton
+            .tokens
+            .iter()
+            .map(|token| token.name.clone())
+            .collect()
+    }
+}
+
+/// The Python module.
+#[pymodule]
+fn luther(_py: Python, module: &PyModule) -> PyResult<()> {
+    module.add_class::<LexerBuilder>()?;
+    module.add_class::<Lexer>()?;
+    Ok(())
+}
+
+// Converts increasing byte offsets in text to char offsets.
+struct CharOffsets<'a> {
+    text: &'a str,
+    bytes: usize,
+    chars: usize,
+}
+
+impl<'a> CharOffsets<'a> {
+    fn new(text: &'a str) -> CharOffsets<'a> {
+        CharOffsets {
+            text,
+            bytes: 0,
+            chars: 0,
+        }
+    }
+
+    // The char offset of the byte offset, which must be at least the last one converted.
+    fn to_chars(&mut self, bytes: usize) -> usize {
+        self.chars += self.text[self.bytes..bytes].chars().count();
+        self.bytes = bytes;
+        self.chars
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn char_offsets_count_chars() {
+        let mut sut = CharOffsets::new("a\u{e9}\u{1f600}b");
+
+        assert_eq!(sut.to_chars(1), 1);
+        assert_eq!(sut.to_chars(7), 3);
+        assert_eq!(sut.to_chars(8), 4);
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+use redfa::Regex;
+
+/// Renders `regex` in the syntax accepted by the regex parser.
+///
+/// The rendering is not necessarily the same as the source of the regex since `regex` may
+/// have been normalized or be the derivative of another regex. A regex that matches nothing
+/// is rendered as `[]` and a regex that matches only the empty string as `()`.
+pub fn render(regex: &Regex<char>) -> String {
+    match regex {
+        &Regex::Null => "[]".to_string(),
+        &Regex::Empty => "()".to_string(),
+        &Regex::Except(ref ts) if ts.is_empty() => ".".to_string(),
+        &Regex::Except(ref ts) => format!("[^{}]", class(ts)),
+        &Regex::Alt(ref ts, ref res) => {
+            let mut parts = Vec::new();
+            match ts.len() {
+                0 => {}
+                1 => parts.push(literal(ts[0])),
+                _ => parts.push(format!("[{}]", class(ts))),
+            }
+            parts.extend(res.iter().map(render));
+
+            if parts.is_empty() {
+                "[]".to_string()
+            } else {
+                parts.join("|")
+            }
+        }
+        &Regex::Cat(ref res) if res.is_empty() => "()".to_string(),
+        &Regex::Cat(ref res) => res.iter().map(render_operand).collect(),
+        &Regex::And(ref res) => res.iter()
+            .map(render_operand)
+            .collect::<Vec<_>>()
+            .join("&"),
+        &Regex::Not(ref re) => format!("~{}", render_operand(re)),
+        &Regex::Kleene(ref re) => format!("{}*", render_operand(re)),
+    }
+}
+
+// Renders regex with parentheses if it is not a single unit.
+fn render_operand(regex: &Regex<char>) -> String {
+    let compound = match regex {
+        &Regex::Alt(ref ts, ref res) => res.len() + if ts.is_empty() { 0 } else { 1 } > 1,
+        &Regex::Cat(ref res) => res.len() > 1,
+        &Regex::And(_) | &Regex::Not(_) => true,
+        _ => false,
+    };
+
+    if compound {
+        format!("({})", render(regex))
+    } else {
+        render(regex)
+    }
+}
+
+fn class(ts: &[char]) -> String {
+    ts.iter()
+        .map(|&c| match c {
+            '\\' | ']' | '^' | '-' => format!("\\{}", c),
+            c => escape_control(c),
+        })
+        .collect()
+}
+
+/// Renders `c` so that it matches itself outside of a character class.
+pub fn literal(c: char) -> String {
+    match c {
+        '\\' | '|' | '&' | '~' | '*' | '+' | '?' | '(' | ')' | '[' | ']' | '.' | '{' | '}' | '^'
+        | '$' => {
+            format!("\\{}", c)
+        }
+        c => escape_control(c),
+    }
+}
+
+fn escape_control(c: char) -> String {
+    if c.is_control() {
+        c.escape_default().collect()
+    } else {
+        c.to_string()
+    }
+}
+
+/// A value that can be shown as a quoted literal in labels and messages.
+///
+/// The literal escapes every control and invisible character (as `\n`, `\t`, `\xNN`, or
+/// `\u{...}`) so that it can be written to a terminal or into a Graphviz label as is.
+pub trait Escape: Copy + PartialEq {
+    /// Renders the value as a quoted and escaped literal.
+    fn escaped(self) -> String;
+}
+
+impl Escape for char {
+    fn escaped(self) -> String {
+        let escaped = match self {
+            '\'' | '\\' => format!("\\{}", self),
+            '\n' => "\\n".to_string(),
+            '\r' => "\\r".to_string(),
+            '\t' => "\\t".to_string(),
+            '\0' => "\\0".to_string(),
+            c if c.is_ascii() && c.is_control() => format!("\\x{:02x}", c as u32),
+            c if c.is_control() || (c.is_whitespace() && c != ' ') || is_invisible(c) => {
+                format!("\\u{{{:x}}}", c as u32)
+            }
+            c => c.to_string(),
+        };
+        format!("'{}'", escaped)
+    }
+}
+
+impl Escape for u8 {
+    fn escaped(self) -> String {
+        let escaped = match self {
+            b'\'' | b'\\' => format!("\\{}", self as char),
+            b'\n' => "\\n".to_string(),
+            b'\r' => "\\r".to_string(),
+            b'\t' => "\\t".to_string(),
+            b'\0' => "\\0".to_string(),
+            b' '...b'~' => (self as char).to_string(),
+            b => format!("\\x{:02x}", b),
+        };
+        format!("b'{}'", escaped)
+    }
+}
+
+// Tests for c being a format char, a private use char, or a noncharacter (which a terminal
+// shows as nothing or as a placeholder).
+fn is_invisible(c: char) -> bool {
+    match c as u32 {
+        0x200B...0x200F | 0x2060...0x206F | 0xFEFF | 0xFFF9...0xFFFB => true,
+        0xE000...0xF8FF | 0xF_0000...0x10_FFFF => true,
+        n => n & 0xFFFE == 0xFFFE || (n >= 0xFDD0 && n <= 0xFDEF),
+    }
+}
+
+/// Renders the inclusive range from `first` to `last` as `'a'-'z'` (or as `'a'` for a range
+/// of one value), with the ends escaped.
+pub fn range<T: Escape>(first: T, last: T) -> String {
+    if first == last {
+        first.escaped()
+    } else {
+        format!("{}-{}", first.escaped(), last.escaped())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use redfa::dfa::Normalize;
+
+    fn round_trip(regex: &str) -> String {
+        let regex: Regex<char> = regex.parse().expect("unexpected regex parse error");
+        render(&vec![regex].normalize()[0])
+    }
+
+    #[test]
+    fn render_round_trips_simple_regexs() {
+        assert_eq!(round_trip("ab"), "ab");
+        assert_eq!(round_trip("a*"), "a*");
+        assert_eq!(round_trip("(ab)*"), "(ab)*");
+    }
+
+    #[test]
+    fn render_escapes_special_chars() {
+        assert_eq!(literal('*'), "\\*");
+        assert_eq!(literal('\n'), "\\n");
+    }
+
+    #[test]
+    fn range_escapes_control_and_invisible_chars() {
+        assert_eq!(range('a', 'z'), "'a'-'z'");
+        assert_eq!(range('\n', '\n'), "'\\n'");
+        assert_eq!(range('\x01', '\x1f'), "'\\x01'-'\\x1f'");
+        assert_eq!(range('\u{a0}', '\u{e9}'), "'\\u{a0}'-'\u{e9}'");
+        assert_eq!(range('\u{200b}', '\u{10ffff}'), "'\\u{200b}'-'\\u{10ffff}'");
+        assert_eq!(range(b'\t', b'\xff'), "b'\\t'-b'\\xff'");
+    }
+}
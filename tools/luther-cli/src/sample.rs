@@ -0,0 +1,168 @@
+// Copyright 2018 Steven Bosnick
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE-2.0 or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms
+
+//! Random sampling of the tokens accepted by a list of token definitions.
+//!
+//! Each sample is chosen uniformly from all of the strings (of the lengths asked for) that are
+//! a token, using the number of ways to complete a string from each state (as in the `count`
+//! module) to weight each `char`. The numbers of ways are kept as `f64`'s, so the samples are
+//! close to (but not exactly) uniform when there are a huge number of strings.
+
+use std::char;
+use std::ops::Range;
+
+use rand::Rng;
+
+use automaton::Automaton;
+use count::CHAR_COUNT;
+
+/// A token and its text chosen at random from the strings of `lengths` `char`'s that are a
+/// token in the default mode of `automaton`, or `None` if there are no such strings.
+///
+/// The text is lexically valid: the longest match at its start is the whole text.
+pub fn sample<R: Rng>(
+    automaton: &Automaton,
+    rng: &mut R,
+    lengths: Range<usize>,
+) -> Option<(usize, String)> {
+    let ways = completions(automaton, lengths.end);
+    let start = automaton.start_state;
+
+    let total: f64 = lengths.clone().map(|len| ways[len][start]).sum();
+    if total == 0.0 {
+        return None;
+    }
+    let mut pick = rng.gen::<f64>() * total;
+    let mut len = lengths.start;
+    for l in lengths.clone() {
+        len = l;
+        if pick < ways[l][start] {
+            break;
+        }
+        pick -= ways[l][start];
+    }
+
+    let mut state = start;
+    let mut text = String::new();
+    for remaining in (0..len).rev() {
+        let dfa_state = &automaton.dfa.states[state];
+        let others = (CHAR_COUNT - dfa_state.by_char.len() as u128) as f64;
+        let default_weight = others * ways[remaining][dfa_state.default as usize];
+        let total = dfa_state
+            .by_char
+            .values()
+            .map(|&to| ways[remaining][to as usize])
+            .sum::<f64>() + default_weight;
+
+        let mut pick = rng.gen::<f64>() * total;
+        let mut next = None;
+        for (&c, &to) in dfa_state.by_char.iter() {
+            let weight = ways[remaining][to as usize];
+            if pick < weight {
+                next = Some((c, to as usize));
+                break;
+            }
+            pick -= weight;
+        }
+        let (c, to) = match next {
+            Some(next) => next,
+            None if default_weight > 0.0 => (
+                other_char(rng, |c| dfa_state.by_char.contains_key(&c)),
+                dfa_state.default as usize,
+            ),
+            // rounding left pick just past the last weight
+            None => dfa_state
+                .by_char
+                .iter()
+                .rev()
+                .map(|(&c, &to)| (c, to as usize))
+                .find(|&(_, to)| ways[remaining][to] > 0.0)
+                .unwrap(),
+        };
+        text.push(c);
+        state = to;
+    }
+
+    automaton.dfa.states[state]
+        .value
+        .map(|token| (token, text))
+}
+
+// ways[n][state] is the number of strings of n chars that lead from state to an accepting
+// state, for n up to max_len.
+fn completions(automaton: &Automaton, max_len: usize) -> Vec<Vec<f64>> {
+    let states = &automaton.dfa.states;
+    let mut ways = vec![
+        states
+            .iter()
+            .map(|state| if state.value.is_some() { 1.0 } else { 0.0 })
+            .collect::<Vec<_>>(),
+    ];
+
+    for n in 1..max_len {
+        let next = states
+            .iter()
+            .map(|state| {
+                let others = (CHAR_COUNT - state.by_char.len() as u128) as f64;
+                state
+                    .by_char
+                    .values()
+                    .map(|&to| ways[n - 1][to as usize])
+                    .sum::<f64>() + others * ways[n - 1][state.default as usize]
+            })
+            .collect();
+        ways.push(next);
+    }
+
+    ways
+}
+
+// A random char for which listed is false.
+fn other_char<R: Rng, F: Fn(char) -> bool>(rng: &mut R, listed: F) -> char {
+    loop {
+        let n = rng.gen_range(0, CHAR_COUNT as u32);
+        let n = if n < 0xD800 { n } else { n + 0x800 };
+        let c = char::from_u32(n).unwrap();
+        if !listed(c) {
+            return c;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{SeedableRng, StdRng};
+    use tokens::TokenDef;
+
+    #[test]
+    fn sample_is_a_token_of_the_requested_length() {
+        let automaton = Automaton::new(vec![
+            TokenDef::new("Ident", "[a-z][a-z0-9]*"),
+            TokenDef::new("If", "if"),
+            TokenDef::new("Other", "[^a-z]"),
+        ]).unwrap();
+        let mut rng = StdRng::from_seed(&[1, 2, 3, 4][..]);
+
+        for _ in 0..100 {
+            let (token, text) = sample(&automaton, &mut rng, 1..5).expect("expected a sample");
+
+            let len = text.chars().count();
+            assert!(len >= 1 && len < 5);
+            assert_eq!(automaton.longest_match(&text), Some((token, text.len())));
+        }
+    }
+
+    #[test]
+    fn sample_is_none_without_strings_of_the_lengths() {
+        let automaton = Automaton::new(vec![TokenDef::new("Ab", "ab")]).unwrap();
+        let mut rng = StdRng::from_seed(&[1, 2, 3, 4][..]);
+
+        assert_eq!(sample(&automaton, &mut rng, 3..6), None);
+    }
+}